@@ -3,14 +3,15 @@
 
 use anyhow::{Result, anyhow};
 use dubhe_indexer::{IndexerBuilder, DubheIndexerArgs};
-use dubhe_indexer::proxy::ChannelHandler;
+use dubhe_indexer::proxy::{ChannelError, ChannelReply, ChannelRouter};
+use dubhe_indexer::GrpcSubscribers;
 use dubhe_common::Database;
-use dubhe_common::DubheConfig;
-use dubhe_db::{DubheDB, initialize_cache};
+use dubhe_common::{Dialect, DubheConfig};
+use dubhe_common::{UnknownTableError, UnknownTablePolicy};
+use dubhe_db::{CacheInitOptions, DubheDB, initialize_cache};
 use dubhe_db::{CacheDB, WrapDatabaseAsync};
 use dubhe_db::interface::Database as DBTrait;
-use hyper::{Body, Response, StatusCode};
-use http::header::CONTENT_TYPE;
+use hyper::{Body, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::str::FromStr;
@@ -19,10 +20,10 @@ use std::path::PathBuf;
 use sui_types::base_types::{ObjectID, SuiAddress};
 use sui_types::transaction::{CallArg, Command, ObjectArg, ProgrammableTransaction, ProgrammableMoveCall, Argument, Transaction, TransactionData};
 use sui_types::object::Object;
+use sui_types::Identifier;
 use tokio::sync::RwLock;
 use tokio::time::{interval, Duration};
 use bcs;
-use sui_sdk::SuiClientBuilder;
 use sui_sdk::rpc_types::SuiTransactionBlockResponseOptions;
 use sui_sdk::types::{
     programmable_transaction_builder::ProgrammableTransactionBuilder,
@@ -31,14 +32,20 @@ use sui_sdk::types::{
 };
 use sui_json_rpc_types::SuiObjectDataOptions;
 use shared_crypto::intent::Intent;
-use sui_types::crypto::SuiKeyPair;
+use sui_types::crypto::{SuiKeyPair, ToFromBytes};
+use sui_types::crypto::{Signature, SuiSignature};
+use sui_types::transaction::{TransactionDataAPI, TransactionKind};
+use shared_crypto::intent::IntentMessage;
+use sui_sdk::SuiClient;
+use std::future::Future;
 use sui_keys::keystore::{AccountKeystore, FileBasedKeystore, InMemKeystore};
 use clap::Parser;
 use sui_types::base_types::TransactionDigest;
-use hyper::body;
-use bytes::Buf;
 use bs58;
 use base64::{Engine as _, engine::general_purpose};
+use tracing::Instrument;
+
+mod signature;
 
 
 // Configuration struct
@@ -48,7 +55,31 @@ struct DubheChannelConfig {
     #[command(flatten)]
     indexer_args: DubheIndexerArgs,
     #[arg(long, default_value = "5")]
-    pub sync_time: u64
+    pub sync_time: u64,
+    /// Fetch PTB input objects missing from the cache from the RPC node instead of
+    /// rejecting the PTB
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub resolve_missing_objects: bool,
+    /// Bytes of objects the cache keeps in memory before spilling the least recently used
+    /// ones to `--cache-spill-dir`; unlimited when unset
+    #[arg(long)]
+    pub cache_memory_budget_bytes: Option<usize>,
+    /// Directory the cache spills objects to once over its memory budget, `dubhe-cache-spill`
+    /// in the system temp directory by default
+    #[arg(long)]
+    pub cache_spill_dir: Option<PathBuf>,
+    /// Largest number of inputs a PTB sent to /submit may have
+    #[arg(long, default_value = "1024")]
+    pub max_ptb_inputs: usize,
+    /// Largest number of commands a PTB sent to /submit may have
+    #[arg(long, default_value = "1024")]
+    pub max_ptb_commands: usize,
+    /// Largest /submit request body, in bytes
+    #[arg(long, default_value = "2097152")]
+    pub max_submit_body_bytes: usize,
+    /// Bearer token the /admin/* routes require; they are refused while it is unset
+    #[arg(long, env = "DUBHE_CHANNEL_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
 }
 
 // Submit Request struct
@@ -61,6 +92,21 @@ pub struct SubmitRequest {
     pub ptb: PtbJson,
     #[serde(default)]
     pub signature: Option<String>,
+    #[serde(default)]
+    pub mode: SubmitMode,
+    /// Base64 BCS `TransactionData` of the PTB, gas included, signed by the sender; required
+    /// in execute mode, where exactly these bytes are broadcast
+    #[serde(default)]
+    pub tx_bytes: Option<String>,
+}
+
+/// How `/submit` handles a PTB: `mock` runs it against the local cache, `execute` broadcasts it to Sui.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmitMode {
+    #[default]
+    Mock,
+    Execute,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -156,6 +202,50 @@ pub enum ArgumentJson {
     },
 }
 
+// Result of a set_storage transaction, exposed on /set_storage
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SetStorageResponse {
+    pub counter: u64,
+    pub digest: String,
+    #[serde(rename = "createdObjectIds")]
+    pub created_object_ids: Vec<String>,
+    #[serde(rename = "mutatedObjectIds")]
+    pub mutated_object_ids: Vec<String>,
+    #[serde(rename = "objectChanges")]
+    pub object_changes: Vec<serde_json::Value>,
+}
+
+impl SetStorageResponse {
+    fn from_transaction_response(counter: u64, response: &sui_json_rpc_types::SuiTransactionBlockResponse) -> Result<Self> {
+        Self::from_response_json(counter, &serde_json::to_value(response)?)
+    }
+
+    /// Build the response from the JSON-RPC representation of a `SuiTransactionBlockResponse`.
+    fn from_response_json(counter: u64, response: &serde_json::Value) -> Result<Self> {
+        let digest = response["digest"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Transaction response has no digest"))?
+            .to_string();
+        let object_ids = |kind: &str| -> Vec<String> {
+            response["effects"][kind]
+                .as_array()
+                .map(|refs| {
+                    refs.iter()
+                        .filter_map(|r| r["reference"]["objectId"].as_str().map(|id| id.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Ok(Self {
+            counter,
+            digest,
+            created_object_ids: object_ids("created"),
+            mutated_object_ids: object_ids("mutated"),
+            object_changes: response["objectChanges"].as_array().cloned().unwrap_or_default(),
+        })
+    }
+}
+
 // Storage state with FIFO queue and deduplication by key
 #[derive(Debug)]
 struct StorageState {
@@ -164,6 +254,8 @@ struct StorageState {
     // VecDeque to maintain insertion order
     order: std::collections::VecDeque<Vec<Vec<u8>>>,
     counter: u64,
+    // Response of the most recent successful set_storage transaction
+    last_response: Option<SetStorageResponse>,
 }
 
 impl StorageState {
@@ -172,6 +264,7 @@ impl StorageState {
             map: std::collections::HashMap::new(),
             order: std::collections::VecDeque::new(),
             counter: 1,
+            last_response: None,
         }
     }
 
@@ -217,13 +310,20 @@ impl StorageState {
 #[derive(Clone)]
 struct AppState<DB> {
     config: Arc<DubheChannelConfig>,
-    cache_db: Arc<RwLock<CacheDB<DB>>>
+    cache_db: Arc<RwLock<CacheDB<DB>>>,
+    sui_client: SuiClient,
+    /// Gains the tables `/submit` infers under the `auto_create` unknown table policy
+    dubhe_config: Arc<RwLock<DubheConfig>>,
+    grpc_subscribers: GrpcSubscribers,
+    temp_storage_state: Arc<RwLock<StorageState>>,
+    nonces: Arc<signature::NonceRegistry>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::init();
+    // Initialize logger. `log` records are forwarded to `tracing`, so lines written while a
+    // request is handled carry the request's trace id. The level is reloaded on SIGHUP.
+    let log_filter = dubhe_indexer::reload::init_logging()?;
 
     dotenvy::dotenv().ok();
     println!("🌟 Dubhe Channel Starting (with Indexer Integration) 🌟");
@@ -234,7 +334,8 @@ async fn main() -> Result<()> {
     let config: DubheChannelConfig = DubheChannelConfig::parse();
 
     // Build Indexer using IndexerBuilder
-    let mut builder = IndexerBuilder::new(config.indexer_args.clone());
+    let mut builder =
+        IndexerBuilder::new(config.indexer_args.clone()).with_log_filter(log_filter);
     builder.initialize().await?;
 
     // Get config for channel handlers
@@ -243,11 +344,17 @@ async fn main() -> Result<()> {
     
     // Create CacheDB
     println!("🔄 Initializing CacheDB...");
-    let client = SuiClientBuilder::default().build(&config.indexer_args.rpc_url).await?;
-    let dubhedb = DubheDB::new(client.clone());
+    let client = config.indexer_args.get_sui_client().await?;
+    let dubhedb = DubheDB::new(client.clone())
+        .with_resolve_missing_objects(config.resolve_missing_objects);
     let wrapped_dubhedb = WrapDatabaseAsync::new(dubhedb)
         .ok_or_else(|| anyhow::anyhow!("Failed to create WrapDatabaseAsync"))?;
     let mut cache_db = CacheDB::new(wrapped_dubhedb);
+    if let Some(budget_bytes) = config.cache_memory_budget_bytes {
+        let spill_dir = config.cache_spill_dir.clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("dubhe-cache-spill"));
+        cache_db = cache_db.with_memory_budget(budget_bytes, spill_dir)?;
+    }
     
     // Preload all required objects using initialize_cache
     initialize_cache(
@@ -255,8 +362,9 @@ async fn main() -> Result<()> {
         &client,
         &dubhe_config.dubhe_object_id,  // dubhe_hub_id
         &dubhe_config.original_dubhe_package_id,  // dubhe_package_id
-        &dubhe_config.original_package_id         // origin_package_id
-    ).await;
+        &dubhe_config.original_package_id,        // origin_package_id
+        &CacheInitOptions::default(),
+    ).await?;
     
     let cache_db = Arc::new(RwLock::new(cache_db));
     println!("✅ CacheDB initialization complete");
@@ -274,187 +382,212 @@ async fn main() -> Result<()> {
     let app_state = AppState {
         config: Arc::new(config.clone()),
         cache_db: cache_db.clone(),
+        sui_client: client.clone(),
+        dubhe_config: Arc::new(RwLock::new(dubhe_config.clone())),
+        grpc_subscribers: builder.grpc_subscribers(),
+        temp_storage_state: temp_storage_state.clone(),
+        nonces: Arc::new(signature::NonceRegistry::default()),
     };
 
-    // /submit route (only supports POST JSON)
-    let state_clone = app_state.clone();
-    let dubhe_config_clone = dubhe_config.clone();
-    let database_url_clone = config.indexer_args.database_url.clone();
-    let grpc_subscribers_clone = builder.grpc_subscribers();
-    let temp_storage_state_clone = temp_storage_state.clone();
-    let submit_handler: ChannelHandler = Arc::new(move |req| {
-        let state_clone = state_clone.clone();
-        let dubhe_config_clone = dubhe_config_clone.clone();
-        let database_url = database_url_clone.clone();
-        let grpc_subscribers = grpc_subscribers_clone.clone();
-        let temp_storage_state = temp_storage_state_clone.clone();
-        Box::pin(async move {
-            println!("🔍 Processing /submit request");
-            
-            // Handle OPTIONS preflight request (CORS)
-            if req.method() == hyper::Method::OPTIONS {
-                return Ok(Response::builder()
-                    .status(StatusCode::OK)
-                    .header("Access-Control-Allow-Origin", "*")
-                    .header("Access-Control-Allow-Methods", "POST, OPTIONS")
-                    .header("Access-Control-Allow-Headers", "Content-Type, Authorization")
-                    .header("Access-Control-Max-Age", "3600")
-                    .body(Body::empty())
-                    .unwrap());
-            }
-            
-            // Check request method
-            if req.method() != hyper::Method::POST {
-                return Ok(Response::builder()
-                    .status(StatusCode::METHOD_NOT_ALLOWED)
-                    .header(CONTENT_TYPE, "application/json")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(Body::from(json!({
-                        "success": false,
-                        "message": "Method not allowed. Only POST is supported",
-                        "data": null
-                    }).to_string()))
-                    .unwrap());
-            }
-            
-            // Read body
-            let whole_body = match body::aggregate(req.into_body()).await {
-                Ok(body) => body,
-                Err(e) => {
-                    return Ok(Response::builder()
-                        .status(StatusCode::BAD_REQUEST)
-                        .header(CONTENT_TYPE, "application/json")
-                        .header("Access-Control-Allow-Origin", "*")
-                        .body(Body::from(json!({
-                            "success": false,
-                            "message": format!("Failed to read body: {}", e),
-                            "data": null
-                        }).to_string()))
-                        .unwrap());
+    ChannelRouter::new(app_state)
+        // /submit route (only supports POST JSON)
+        .route("/submit", hyper::Method::POST, |state, req| {
+            // Every log line written while handling the request, including those of the
+            // indexer crates, is tagged with the trace id through this span
+            let trace_id = request_trace_id(&req);
+            let span = tracing::info_span!("submit", trace_id = %trace_id);
+            async move {
+                tracing::info!("🔍 Processing /submit request");
+
+                let req_data = read_submit_request(req, state.config.max_submit_body_bytes).await?;
+                log_submit_request(&req_data);
+
+                // Reject oversized PTBs before doing any work per input or command
+                check_ptb_limits(
+                    &req_data.ptb,
+                    state.config.max_ptb_inputs,
+                    state.config.max_ptb_commands,
+                )?;
+
+                let sender = sender_address(&req_data.chain, &req_data.sender)
+                    .map_err(|e| ChannelError::bad_request(e.to_string()))?;
+
+                // Every submission is signed. Mock submissions carry a native signature over
+                // submit_signing_message, which covers the nonce so it cannot be replayed;
+                // execute submissions carry the Sui signature of the transaction.
+                let signature = req_data.signature.as_deref().ok_or_else(|| {
+                    ChannelError::new(StatusCode::UNAUTHORIZED, "A signature is required")
+                })?;
+                if req_data.mode == SubmitMode::Mock {
+                    let nonce = req_data.nonce.ok_or_else(|| {
+                        ChannelError::bad_request("A nonce is required in mock mode")
+                    })?;
+                    let message = submit_signing_message(&req_data.ptb, &req_data.sender, nonce)
+                        .map_err(|e| ChannelError::bad_request(e.to_string()))?;
+                    if let Err(e) = signature::verify_signature(&req_data.chain, &req_data.sender, &message, signature) {
+                        tracing::error!("❌ Signature verification failed: {}", e);
+                        return Err(ChannelError::new(
+                            StatusCode::UNAUTHORIZED,
+                            format!("Invalid signature: {}", e),
+                        ));
+                    }
+                    state.nonces.consume(sender, nonce).map_err(|e| {
+                        tracing::error!("❌ Replayed submit request: {}", e);
+                        ChannelError::new(StatusCode::CONFLICT, e.to_string())
+                    })?;
                 }
-            };
-            
-            // Parse JSON
-            let submit_request: Result<SubmitRequest, _> = serde_json::from_reader(whole_body.reader());
-            
-            match submit_request {
-                Ok(req_data) => {
-                    println!("✅ Received submit request:");
-                    println!("  Chain: {}", req_data.chain);
-                    println!("  Sender: {}", req_data.sender);
-                    println!("  Nonce: {:?}", req_data.nonce);
-                    println!("  PTB inputs: {}, commands: {}", req_data.ptb.inputs.len(), req_data.ptb.commands.len());
-                    println!("  Signature: {:?}", req_data.signature);
-                    
-                    // TODO: Actual processing logic can be added here
-                    // Currently only returns success response
-                    let sender = match req_data.chain.as_str() {
-                        "sui" => SuiAddress::from_str(&req_data.sender).unwrap(),
-                        "evm" => evm_to_sui(&req_data.sender).unwrap(),
-                        "solana" => solana_to_sui(&req_data.sender).unwrap(),
-                        _ => panic!("Invalid chain: {}", req_data.chain),
-                    };
-
-                    let tx_digest = get_tx_digest_by_chain(req_data.chain.clone());
-
-                    // Build PTB
-                    let ptb = match convert_ptb_json_to_transaction(&req_data.ptb, &state_clone.cache_db).await {
-                        Ok(ptb) => ptb,
-                        Err(e) => {
-                            println!("❌ Failed to convert PTB: {}", e);
-                            return Ok(Response::builder()
-                                .status(StatusCode::BAD_REQUEST)
-                                .header(CONTENT_TYPE, "application/json")
-                                .header("Access-Control-Allow-Origin", "*")
-                                .body(Body::from(json!({
-                                    "success": false,
-                                    "message": format!("Failed to convert PTB: {}", e),
-                                    "data": null
-                                }).to_string()))
-                                .unwrap());
-                        }
-                    };
-                    
-                    // Execute PTB
-                    println!("🔄 Executing PTB transaction...");
-                    let value = {
-                        let mut cache_db_guard = state_clone.cache_db.write().await;
-                        mock_ptb_shared_sync(
-                            &state_clone.config, 
-                            &ptb, 
-                            &mut *cache_db_guard, 
-                            dubhe_config_clone, 
-                            sender, 
-                            tx_digest, 
-                            grpc_subscribers.clone(),
-                            &temp_storage_state
-                        ).await
-                    };
-                    
-                    match value {
-                        Ok(sqls) => {
-                            let database_channel = Database::new(&database_url).await.unwrap();
-                            for sql in &sqls {
-                                println!("📝 Executing SQL: {:?}", sql);
-                                database_channel.execute(&sql).await.unwrap();
-                            }
-                            
-                            println!("✅ PTB executed successfully, {} SQL statements", sqls.len());
-                            Ok(Response::builder()
-                                .status(StatusCode::OK)
-                                .header(CONTENT_TYPE, "application/json")
-                                .header("Access-Control-Allow-Origin", "*")
-                                .body(Body::from(json!({
-                                    "success": true,
-                                    "message": "Submit request processed successfully",
-                                    "data": {
-                                        "chain": req_data.chain,
-                                        "sender": req_data.sender,
-                                        "nonce": req_data.nonce,
-                                        "tx_digest": format!("{:?}", tx_digest),
-                                        "sql_count": sqls.len(),
-                                    }
-                                }).to_string()))
-                                .unwrap())
-                        },
-                        Err(e) => {
-                            println!("❌ Failed to execute PTB: {}", e);
-                            Ok(Response::builder()
-                                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                                .header(CONTENT_TYPE, "application/json")
-                                .header("Access-Control-Allow-Origin", "*")
-                                .body(Body::from(json!({
-                                    "success": false,
-                                    "message": format!("Failed to execute PTB: {}", e),
-                                    "data": null
-                                }).to_string()))
-                                .unwrap())
-                        }
+
+                let tx_digest = get_tx_digest_by_chain(req_data.chain.clone());
+
+                // Build PTB
+                let ptb = convert_ptb_json_to_transaction(&req_data.ptb, &state.cache_db)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("❌ Failed to convert PTB: {}", e);
+                        ChannelError::bad_request(format!("Failed to convert PTB: {}", e))
+                    })?;
+
+                // Broadcast to chain instead of mock executing
+                if req_data.mode == SubmitMode::Execute {
+                    tracing::info!("🔄 Broadcasting PTB transaction to chain...");
+                    let tx_bytes = req_data.tx_bytes.as_deref().ok_or_else(|| {
+                        ChannelError::bad_request("tx_bytes is required in execute mode")
+                    })?;
+                    let transaction = signed_transaction(tx_bytes, &ptb, sender, signature)
+                        .map_err(|e| {
+                            tracing::error!("❌ Rejected signed transaction: {}", e);
+                            ChannelError::new(StatusCode::UNAUTHORIZED, e.to_string())
+                        })?;
+                    let result = state.sui_client.execute_transaction(transaction).await;
+                    let digest = result.map_err(|e| {
+                        tracing::error!("❌ Failed to broadcast PTB: {}", e);
+                        ChannelError::internal(format!("Failed to execute transaction: {}", e))
+                    })?;
+                    tracing::info!("✅ PTB broadcast successfully: {}", digest);
+                    return Ok(ChannelReply::ok(
+                        "Transaction executed on chain",
+                        json!({
+                            "chain": req_data.chain,
+                            "sender": req_data.sender,
+                            "nonce": req_data.nonce,
+                            "tx_digest": digest.to_string(),
+                        }),
+                    ));
+                }
+
+                // Execute PTB, holding the cache lock until its SQL is written so the cache
+                // writes can still be rolled back
+                tracing::info!("🔄 Executing PTB transaction...");
+                let mut cache_db_guard = state.cache_db.write().await;
+                cache_db_guard.begin();
+                // Tables inferred while converting the events are kept once their SQL is written
+                let mut dubhe_config = state.dubhe_config.read().await.clone();
+                let value = mock_ptb_shared_sync(
+                    &state.config,
+                    &ptb,
+                    &mut *cache_db_guard,
+                    &mut dubhe_config,
+                    sender,
+                    tx_digest,
+                    &trace_id,
+                ).await;
+
+                let PtbSql { statements: sqls, table_changes, storage_entries } = match value {
+                    Ok(output) => output,
+                    Err(e) => {
+                        cache_db_guard.rollback();
+                        tracing::error!("❌ Failed to execute PTB: {}", e);
+                        return Err(ChannelError::internal(format!("Failed to execute PTB: {}", e)));
+                    }
+                };
+                tracing::debug!("📝 Executing SQL: {:?}", sqls);
+                // Statements that fail are kept for /admin/replay-dead-letters, subscribers only
+                // hear about the changes whose statements are written
+                let database_url = &state.config.indexer_args.database_url;
+                let written =
+                    apply_submit_sqls(&cache_db_guard, database_url, &sqls, table_changes).await;
+                // Only a fully written submit is set on chain by the storage monitor
+                if let Ok((0, _)) = written {
+                    *state.dubhe_config.write().await = dubhe_config;
+                    let mut storage_state = state.temp_storage_state.write().await;
+                    for (key, value) in storage_entries {
+                        storage_state.push(key, value);
+                    }
+                }
+                // Notify without the cache lock, so the next submit does not wait on subscribers
+                drop(cache_db_guard);
+                let written = written.map(|(saved, persisted)| {
+                    if !persisted.is_empty() {
+                        let subscribers = state.grpc_subscribers.clone();
+                        tokio::spawn(notify_subscribers(subscribers, persisted));
+                    }
+                    saved
+                });
+                match written {
+                    Ok(0) => {}
+                    Ok(saved) => {
+                        tracing::error!("❌ Failed to write PTB results, {} statement(s) saved for replay", saved);
+                        return Err(ChannelError::internal(format!(
+                            "Failed to write PTB results, {} statement(s) saved for replay",
+                            saved
+                        ))
+                        .with_data(json!({ "dead_lettered": saved })));
+                    }
+                    Err(e) => {
+                        tracing::error!("❌ Failed to write PTB results: {}", e);
+                        return Err(ChannelError::internal(format!("Failed to write PTB results: {}", e)));
                     }
-                },
-                Err(e) => {
-                    println!("❌ Failed to parse submit request: {}", e);
-                    Ok(Response::builder()
-                        .status(StatusCode::BAD_REQUEST)
-                        .header(CONTENT_TYPE, "application/json")
-                        .header("Access-Control-Allow-Origin", "*")
-                        .body(Body::from(json!({
-                            "success": false,
-                            "message": format!("Invalid JSON body: {}", e),
-                            "data": null
-                        }).to_string()))
-                        .unwrap())
                 }
+
+                tracing::info!("✅ PTB executed successfully, {} SQL statements", sqls.len());
+                Ok(submit_reply(&req_data, &tx_digest, sqls.len()))
             }
+            .instrument(span)
         })
-    });
-    proxy_server.register_channel_handler("/submit".to_string(), submit_handler).await;
+        // /set_storage route returns the result of the last set_storage transaction
+        .route("/set_storage", hyper::Method::GET, |state, _req| async move {
+            match state.temp_storage_state.read().await.last_response.clone() {
+                Some(response) => Ok(ChannelReply::ok("Last set_storage transaction", response)),
+                None => Err(ChannelError::new(
+                    StatusCode::NOT_FOUND,
+                    "No set_storage transaction executed yet",
+                )),
+            }
+        })
+        // /object/{id} returns an object from the cache, fetching it on a miss
+        .route("/object/", hyper::Method::GET, |state, req| async move {
+            let id = req.uri().path().trim_start_matches("/object/").to_string();
+            read_object(&state.cache_db, &id).await
+        })
+        // /admin/replay-dead-letters re-executes SQL statements that /submit failed to write
+        .route("/admin/replay-dead-letters", hyper::Method::POST, |state, req| async move {
+            authorize_admin(&req, state.config.admin_token.as_deref())?;
+            let database = Database::new(&state.config.indexer_args.database_url).await;
+            let report = match database {
+                Ok(database) => database.replay_dead_letters().await,
+                Err(e) => Err(e),
+            }
+            .map_err(|e| ChannelError::internal(format!("Failed to replay dead letters: {}", e)))?;
+            Ok(ChannelReply {
+                success: report.error.is_none(),
+                message: match &report.error {
+                    Some(e) => format!("Replay stopped: {}", e),
+                    None => "All dead-lettered statements replayed".to_string(),
+                },
+                data: json!({
+                    "replayed": report.replayed,
+                    "remaining": report.remaining,
+                }),
+            })
+        })
+        .register(&proxy_server)
+        .await;
 
     // Start periodic storage queue monitoring task (FIFO - one at a time)
     let temp_storage_state_monitor = temp_storage_state.clone();
     let sync_time = config.sync_time;
-    let config_monitor = Arc::new(config.clone());
     let dubhe_config_monitor = dubhe_config.clone();
+    let sui_client_monitor = client.clone();
     let monitor_handle = tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(sync_time));
         loop {
@@ -484,13 +617,16 @@ async fn main() -> Result<()> {
                     drop(storage_state);
                     
                     // Execute set_storage for this key-value pair
-                    match set_storage(&config_monitor, key.clone(), value.clone(), &dubhe_config_monitor, counter).await {
-                        Ok(_) => {
-                            println!("  ✅ Successfully executed set_storage");
+                    match set_storage(&sui_client_monitor, key.clone(), value.clone(), &dubhe_config_monitor, counter).await {
+                        Ok(response) => {
+                            println!("  ✅ Successfully executed set_storage: {}", response.digest);
+                            println!("  🆕 Created: {:?}", response.created_object_ids);
+                            println!("  ✏️  Mutated: {:?}", response.mutated_object_ids);
                             
                             // Reset counter after successful transaction
                             let mut storage_state = temp_storage_state_monitor.write().await;
                             storage_state.reset_counter();
+                            storage_state.last_response = Some(response);
                             println!("  🔄 Counter reset to 1");
                         },
                         Err(e) => {
@@ -516,6 +652,7 @@ async fn main() -> Result<()> {
     println!("💚 Health Check:     http://0.0.0.0:{}/health", config.indexer_args.port);
     println!("📋 Metadata:         http://0.0.0.0:{}/metadata", config.indexer_args.port);
     println!("🔍 Submit:           http://0.0.0.0:{}/submit", config.indexer_args.port);
+    println!("🗄️  Set Storage:      http://0.0.0.0:{}/set_storage", config.indexer_args.port);
     println!("⏱️  Monitor Interval: {} seconds", sync_time);
     println!("================================\n");
 
@@ -554,6 +691,195 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Header carrying a caller supplied trace id for `/submit`
+const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// Trace id of a request: its `x-trace-id` header if that is a plain token of at most 128
+/// characters, else a new random one
+fn request_trace_id(req: &hyper::Request<Body>) -> String {
+    req.headers()
+        .get(TRACE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 128
+                && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()))
+}
+
+fn log_submit_request(req_data: &SubmitRequest) {
+    tracing::info!("✅ Received submit request:");
+    tracing::info!("  Chain: {}", req_data.chain);
+    tracing::info!("  Sender: {}", req_data.sender);
+    tracing::info!("  Nonce: {:?}", req_data.nonce);
+    tracing::info!("  PTB inputs: {}, commands: {}", req_data.ptb.inputs.len(), req_data.ptb.commands.len());
+    tracing::info!("  Signature: {:?}", req_data.signature);
+}
+
+/// Bytes a mock-mode `/submit` signature covers: the BCS encoding of the PTB, the sender and
+/// the nonce, so clients can rebuild them exactly. In BCS terms the message is
+///
+/// ```text
+/// struct {
+///     inputs: vector<enum { Object(address), Pure(vector<u8>) }>,
+///     commands: vector<struct {
+///         package: address, module: string, function: string,
+///         type_arguments: vector<string>, arguments: vector<u16>,
+///     }>,
+///     sender: string,
+///     nonce: u64,
+/// }
+/// ```
+fn submit_signing_message(ptb: &PtbJson, sender: &str, nonce: u64) -> Result<Vec<u8>> {
+    #[derive(Serialize)]
+    enum SigningInput {
+        Object(ObjectID),
+        Pure(Vec<u8>),
+    }
+
+    #[derive(Serialize)]
+    struct SigningMoveCall<'a> {
+        package: ObjectID,
+        module: &'a str,
+        function: &'a str,
+        type_arguments: &'a [String],
+        arguments: Vec<u16>,
+    }
+
+    #[derive(Serialize)]
+    struct SigningMessage<'a> {
+        inputs: Vec<SigningInput>,
+        commands: Vec<SigningMoveCall<'a>>,
+        sender: &'a str,
+        nonce: u64,
+    }
+
+    let inputs = ptb
+        .inputs
+        .iter()
+        .map(|input| match input {
+            PtbInput::UnresolvedObject { data } => {
+                ObjectID::from_hex_literal(&data.unresolved_object.object_id)
+                    .map(SigningInput::Object)
+                    .map_err(|e| anyhow!("Invalid object id: {}", e))
+            }
+            PtbInput::Pure { data } => general_purpose::STANDARD
+                .decode(&data.pure.bytes)
+                .map(SigningInput::Pure)
+                .map_err(|e| anyhow!("Failed to decode base64: {}", e)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let commands = ptb
+        .commands
+        .iter()
+        .map(|command| match command {
+            PtbCommand::MoveCall { data } => {
+                let move_call = &data.move_call;
+                Ok(SigningMoveCall {
+                    package: ObjectID::from_hex_literal(&move_call.package)?,
+                    module: &move_call.module,
+                    function: &move_call.function,
+                    type_arguments: &move_call.type_arguments,
+                    arguments: move_call
+                        .arguments
+                        .iter()
+                        .map(|argument| match argument {
+                            ArgumentJson::Input { index, .. } => *index,
+                        })
+                        .collect(),
+                })
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(bcs::to_bytes(&SigningMessage {
+        inputs,
+        commands,
+        sender,
+        nonce,
+    })?)
+}
+
+/// Check the bearer token of an `/admin/*` request against `--admin-token`. Without a
+/// configured token the admin routes are refused outright.
+fn authorize_admin(
+    req: &hyper::Request<Body>,
+    admin_token: Option<&str>,
+) -> Result<(), ChannelError> {
+    let Some(expected) = admin_token else {
+        return Err(ChannelError::new(
+            StatusCode::FORBIDDEN,
+            "Admin routes are disabled, start the channel with --admin-token",
+        ));
+    };
+    let provided = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Compare every byte, so the time taken does not reveal how much of the token matched
+    let matches = provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if matches {
+        Ok(())
+    } else {
+        Err(ChannelError::new(StatusCode::UNAUTHORIZED, "Invalid admin token"))
+    }
+}
+
+/// Parse the JSON body of a `/submit` request, answering 413 as soon as it turns out to be
+/// longer than `max_bytes`: up front from Content-Length, or while reading a body without one
+async fn read_submit_request(
+    req: hyper::Request<Body>,
+    max_bytes: usize,
+) -> Result<SubmitRequest, ChannelError> {
+    let too_large = || {
+        ChannelError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Request body is larger than {} bytes", max_bytes),
+        )
+    };
+    let content_length = req
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if content_length.is_some_and(|length| length > max_bytes as u64) {
+        return Err(too_large());
+    }
+    let body = hyper::body::to_bytes(http_body::Limited::new(req.into_body(), max_bytes))
+        .await
+        .map_err(|e| {
+            if e.downcast_ref::<http_body::LengthLimitError>().is_some() {
+                too_large()
+            } else {
+                ChannelError::bad_request(format!("Failed to read body: {}", e))
+            }
+        })?;
+    serde_json::from_slice(&body).map_err(|e| {
+        tracing::error!("❌ Failed to parse submit request: {}", e);
+        ChannelError::bad_request(format!("Invalid JSON body: {}", e))
+    })
+}
+
+/// A 400 response if `ptb` has more inputs or commands than the channel accepts
+fn check_ptb_limits(ptb: &PtbJson, max_inputs: usize, max_commands: usize) -> Result<(), ChannelError> {
+    let message = if ptb.inputs.len() > max_inputs {
+        format!("PTB has {} inputs, at most {} are accepted", ptb.inputs.len(), max_inputs)
+    } else if ptb.commands.len() > max_commands {
+        format!("PTB has {} commands, at most {} are accepted", ptb.commands.len(), max_commands)
+    } else {
+        return Ok(());
+    };
+    tracing::error!("❌ {}", message);
+    Err(ChannelError::bad_request(message))
+}
 
 fn get_tx_digest_by_chain(chain: String) -> TransactionDigest {
     if chain == "evm" {
@@ -580,7 +906,10 @@ fn get_tx_digest_by_chain(chain: String) -> TransactionDigest {
 
 // ========== PTB Conversion Functions ==========
 
-/// Convert PtbJson to ProgrammableTransaction
+/// Convert PtbJson to ProgrammableTransaction.
+/// Input objects missing from the cache, e.g. created on chain after the cache was warmed up,
+/// are read through the cache's backing database, which fetches them from the RPC node
+/// unless `--resolve-missing-objects` is off.
 async fn convert_ptb_json_to_transaction<'a, DB>(
     ptb_json: &'a PtbJson,
     cache_db: &Arc<RwLock<CacheDB<DB>>>,
@@ -633,33 +962,7 @@ where
     for command in &ptb_json.commands {
         match command {
             PtbCommand::MoveCall { data } => {
-                let move_call = &data.move_call;
-                
-                // Parse package ID
-                let package = ObjectID::from_hex_literal(&move_call.package)?;
-                
-                // Parse module and function (directly use String)
-                let module = move_call.module.clone();
-                let function = move_call.function.clone();
-                
-                // Parse type_arguments - leave empty for now (can be extended later if needed)
-                let type_arguments = vec![];
-                
-                // Parse arguments
-                let arguments: Vec<Argument> = move_call.arguments
-                    .iter()
-                    .map(|arg| match arg {
-                        ArgumentJson::Input { index, .. } => Argument::Input(*index),
-                    })
-                    .collect();
-                
-                commands.push(Command::MoveCall(Box::new(ProgrammableMoveCall {
-                    package,
-                    module,
-                    function,
-                    type_arguments,
-                    arguments,
-                })));
+                commands.push(Command::MoveCall(Box::new(convert_move_call(&data.move_call)?)));
             },
         }
     }
@@ -670,6 +973,85 @@ where
     })
 }
 
+/// Answer of `GET /object/{id}`: the object from the cache, read from the RPC node and cached
+/// on a miss, with its Move fields decoded. `fields` is null when they cannot be decoded.
+async fn read_object<DB>(
+    cache_db: &Arc<RwLock<CacheDB<DB>>>,
+    id: &str,
+) -> Result<ChannelReply, ChannelError>
+where
+    DB: dubhe_db::interface::DatabaseRef + 'static,
+{
+    let object_id = ObjectID::from_hex_literal(id)
+        .map_err(|e| ChannelError::bad_request(format!("Invalid object id {:?}: {}", id, e)))?;
+    let cached = cache_db.read().await.cached_object(&object_id);
+    let object = match cached {
+        Some(object) => object,
+        // Only a miss takes the write lock, to fetch the object and cache it
+        None => DBTrait::object(&mut *cache_db.write().await, object_id)
+            .map_err(|e| {
+                ChannelError::internal(format!("Failed to read object {}: {}", object_id, e))
+            })?
+            .ok_or_else(|| {
+                ChannelError::new(StatusCode::NOT_FOUND, format!("Object {} not found", object_id))
+            })?,
+    };
+    let cache_db = cache_db.read().await;
+    let fields = dubhe_vm::move_object_fields(&object, &*cache_db).unwrap_or_else(|e| {
+        tracing::warn!("⚠️ Cannot decode the fields of object {}: {}", object_id, e);
+        None
+    });
+    drop(cache_db);
+    Ok(ChannelReply::ok(
+        "Object found",
+        json!({
+            "object_id": object_id.to_string(),
+            "version": object.version().value(),
+            "digest": object.digest().to_string(),
+            "type": object.type_().map(|type_| type_.to_string()),
+            "owner": object.owner.clone(),
+            "fields": fields,
+        }),
+    ))
+}
+
+/// Convert a MoveCall command, rejecting module and function names that are not
+/// valid Move identifiers before they reach the chain
+fn convert_move_call(move_call: &MoveCallInner) -> Result<ProgrammableMoveCall> {
+    // Parse package ID
+    let package = ObjectID::from_hex_literal(&move_call.package)?;
+    
+    // ProgrammableMoveCall keeps them as String, so only validate here
+    let module = Identifier::new(move_call.module.as_str())
+        .map_err(|_| anyhow!("Invalid Move module name: {:?}", move_call.module))?;
+    let function = Identifier::new(move_call.function.as_str())
+        .map_err(|_| anyhow!("Invalid Move function name: {:?}", move_call.function))?;
+    
+    let type_arguments = move_call.type_arguments
+        .iter()
+        .map(|type_argument| {
+            TypeTag::from_str(type_argument)
+                .map_err(|e| anyhow!("Invalid type argument {:?}: {}", type_argument, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    // Parse arguments
+    let arguments: Vec<Argument> = move_call.arguments
+        .iter()
+        .map(|arg| match arg {
+            ArgumentJson::Input { index, .. } => Argument::Input(*index),
+        })
+        .collect();
+    
+    Ok(ProgrammableMoveCall {
+        package,
+        module: module.to_string(),
+        function: function.to_string(),
+        type_arguments,
+        arguments,
+    })
+}
+
 // ========== Address Conversion Functions ==========
 
 /// Convert hex string to bytes
@@ -720,36 +1102,94 @@ pub fn solana_to_sui(solana_address_str: &str) -> Result<SuiAddress> {
     SuiAddress::from_bytes(&solana_bytes).map_err(|e| anyhow!("Failed to create SuiAddress: {}", e))
 }
 
+/// Sui address a `/submit` of `chain` runs as, an error for an unknown chain or a malformed
+/// sender
+fn sender_address(chain: &str, sender: &str) -> Result<SuiAddress> {
+    match chain {
+        "sui" => SuiAddress::from_str(sender).map_err(|e| anyhow!("Invalid Sui address: {}", e)),
+        "evm" => evm_to_sui(sender),
+        "solana" => solana_to_sui(sender),
+        _ => Err(anyhow!("Invalid chain: {}", chain)),
+    }
+}
+
 async fn mock_ptb_shared_sync<DB>(
-    _config: &Arc<DubheChannelConfig>, 
+    config: &Arc<DubheChannelConfig>, 
     ptb: &ProgrammableTransaction, 
     cache_db: &mut CacheDB<DB>,
-    dubhe_config: DubheConfig,
+    dubhe_config: &mut DubheConfig,
     sender: SuiAddress,
     tx_digest: TransactionDigest,
-    grpc_subscribers: Arc<RwLock<std::collections::HashMap<String, Vec<tokio::sync::mpsc::UnboundedSender<dubhe_indexer_grpc::types::TableChange>>>>>,
-    temp_storage_state: &Arc<RwLock<StorageState>>
-) -> Result<Vec<String>, anyhow::Error>
+    trace_id: &str,
+) -> Result<PtbSql, anyhow::Error>
 where
     DB: dubhe_db::interface::DatabaseRef
 {
-    println!("🔄 Starting PTB execution...");
-    println!("📝 Executing PTB transaction...");
-    let (store_set_records, current_checkpoint_timestamp_ms, current_digest) = dubhe_vm::execute_single_ptb_with_store_set_record(ptb, cache_db, sender, tx_digest)?;
-    println!("store_set_records: {:?}", store_set_records);
+    tracing::info!("🔄 Starting PTB execution...");
+    tracing::info!("📝 Executing PTB transaction...");
+    let result = dubhe_vm::execute_ptb(ptb, cache_db, sender, tx_digest)?;
+    // Apply store events in emission order, so a delete after a set of the same key wins
+    let mut store_events = result.events;
+    dubhe_common::sort_by_event_sequence(&mut store_events);
+    store_events_to_sql(
+        dubhe_config,
+        store_events,
+        result.timestamp_ms,
+        result.digest,
+        Dialect::from_database_url(&config.indexer_args.database_url),
+        trace_id,
+    ).await
+}
+
+/// SQL of the store events of one executed PTB, with what waits on it being written
+struct PtbSql {
+    statements: Vec<String>,
+    /// Subscriber changes, each sent once its statement is written
+    table_changes: Vec<PendingChange>,
+    /// Records for the storage monitor to set on chain, queued once every statement is written
+    storage_entries: Vec<(Vec<Vec<u8>>, Vec<Vec<u8>>)>,
+}
+
+/// SQL statements, subscriber changes and storage entries of the store events of one
+/// executed PTB. The changes are tagged with the trace id of the request that submitted it.
+async fn store_events_to_sql(
+    dubhe_config: &mut DubheConfig,
+    store_events: Vec<dubhe_common::SequencedEvent>,
+    current_checkpoint_timestamp_ms: u64,
+    current_digest: String,
+    dialect: Dialect,
+    trace_id: &str,
+) -> Result<PtbSql> {
+    tracing::debug!("store_events: {:?}", store_events);
     let mut sql_list = Vec::new();
-    for store_set_record in store_set_records {
-        if dubhe_config
-                            .can_convert_event_to_sql(&store_set_record)
-                            .is_ok() {
+    let mut table_changes = Vec::new();
+    let mut storage_entries = Vec::new();
+    for store_event in store_events {
+        let store_set_record = store_event.event;
+        let can_convert = match dubhe_config.can_convert_event_to_sql(&store_set_record) {
+            Err(e) if e.is::<UnknownTableError>() => {
+                if dubhe_config.on_unknown_table != UnknownTablePolicy::AutoCreate {
+                    return Err(e);
+                }
+                sql_list.push(dubhe_config.infer_table(&store_set_record)?);
+                true
+            }
+            Ok(true) => true,
+            _ => {
+                dubhe_common::record_skipped_event(dubhe_config, &store_set_record);
+                false
+            }
+        };
+        if can_convert {
             // Get table name
             let table_name = store_set_record.table_id().to_string();
+            let is_deleted = matches!(store_set_record, dubhe_common::Event::StoreDeleteRecord(_));
 
-            if table_name != "dapp_fee_state" {
-                temp_storage_state.write().await.push(
-                    store_set_record.key_tuple().clone(), 
-                    store_set_record.value_tuple().clone()
-                );
+            if table_name != "dapp_fee_state" && matches!(store_set_record, dubhe_common::Event::StoreSetRecord(_)) {
+                storage_entries.push((
+                    store_set_record.key_tuple().clone(),
+                    store_set_record.value_tuple().clone(),
+                ));
             }
             
             // Convert to proto_struct
@@ -775,52 +1215,184 @@ where
             proto_struct.fields.insert(
                 "is_deleted".to_string(),
                 prost_types::Value {
-                    kind: Some(prost_types::value::Kind::BoolValue(false)),
+                    kind: Some(prost_types::value::Kind::BoolValue(is_deleted)),
                 },
             );
 
-            println!("proto_struct: {:?}", proto_struct);
+            tracing::debug!("proto_struct: {:?}", proto_struct);
 
-            // Send to gRPC subscribers
-            let subscribers = grpc_subscribers.clone();
-            tokio::spawn(async move {
-                let table_change = dubhe_indexer_grpc::types::TableChange {
-                    table_id: table_name.clone(),
+            table_changes.push(PendingChange {
+                statement: sql_list.len(),
+                change: dubhe_indexer_grpc::types::TableChange {
+                    table_id: table_name,
                     data: Some(proto_struct),
-                };
-
-                // Send to GRPC subscribers
-                let subscribers = subscribers.read().await;
-                println!("📤 Subscribers: {:?}", subscribers);
-                if let Some(senders) = subscribers.get(&table_name) {
-                    for sender in senders {
-                        println!(
-                            "📤 Sending table change to GRPC subscriber: {:?}",
-                            table_name
-                        );
-                        let _ = sender.send(table_change.clone());
-                    }
-                }
+                    trace_id: trace_id.to_string(),
+                    cursor: 0,
+                    end_of_stream: false,
+                    heartbeat: false,
+                },
             });
 
-            let sql = dubhe_config.convert_event_to_sql(store_set_record, current_checkpoint_timestamp_ms, current_digest.clone())?;
-            println!("sql: {:?}", sql);
+            let sql = dubhe_config.convert_event_to_sql(store_set_record, current_checkpoint_timestamp_ms, current_digest.clone(), dialect)?;
+            tracing::debug!("sql: {:?}", sql);
             sql_list.push(sql);
         }
     }
-    Ok(sql_list)
+    Ok(PtbSql {
+        statements: sql_list,
+        table_changes,
+        storage_entries,
+    })
+}
+
+/// A subscriber change of a submitted PTB, waiting for the SQL statement at index
+/// `statement` that persists it
+struct PendingChange {
+    statement: usize,
+    change: dubhe_indexer_grpc::types::TableChange,
+}
+
+/// Write the SQL of a PTB executed against `cache_db` since [`CacheDB::begin`]. The cache
+/// writes are committed once every statement is written, and rolled back if the database
+/// could not take them or any statement was saved for replay, so the cache never runs ahead
+/// of the database. The submit fails in both cases.
+/// Returns the number of statements saved for replay, and the changes whose statements were
+/// written before the first failure. Subscribers never hear about a change that is not
+/// persisted; the caller sends these once it has released the cache.
+async fn apply_submit_sqls<DB>(
+    cache_db: &CacheDB<DB>,
+    database_url: &str,
+    sqls: &[String],
+    table_changes: Vec<PendingChange>,
+) -> Result<(usize, Vec<dubhe_indexer_grpc::types::TableChange>)> {
+    let result = match Database::new(database_url).await {
+        Ok(database) => database.execute_or_dead_letter(sqls).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(saved) => {
+            if saved == 0 {
+                cache_db.commit();
+            } else {
+                cache_db.rollback();
+            }
+            let written = sqls.len() - saved;
+            let persisted = table_changes
+                .into_iter()
+                .filter(|pending| pending.statement < written)
+                .map(|pending| pending.change)
+                .collect();
+            Ok((saved, persisted))
+        }
+        Err(e) => {
+            cache_db.rollback();
+            Err(e)
+        }
+    }
+}
+
+/// Deliver table changes in order, taking the subscribers read lock once for the whole batch.
+/// Changes go through the change archive, so resumed subscribers can replay them.
+async fn notify_subscribers(
+    grpc_subscribers: GrpcSubscribers,
+    table_changes: Vec<dubhe_indexer_grpc::types::TableChange>,
+) {
+    let subscribers = grpc_subscribers.read().await;
+    let archive = dubhe_indexer_grpc::change_archive();
+    for table_change in table_changes {
+        if let Some(senders) = subscribers.get(&table_change.table_id) {
+            tracing::info!(
+                "📤 Sending table change to {} GRPC subscriber(s): {:?}",
+                senders.len(),
+                table_change.table_id
+            );
+        }
+        archive.publish(&subscribers, table_change);
+    }
 }
 
 
+/// Reply to a `/submit` executed in mock mode. The digest is sent in its base58 form, the
+/// one stored in `last_update_digest`, never its `Debug` output.
+fn submit_reply(
+    req_data: &SubmitRequest,
+    tx_digest: &TransactionDigest,
+    sql_count: usize,
+) -> ChannelReply {
+    ChannelReply::ok(
+        "Submit request processed successfully",
+        json!({
+            "chain": req_data.chain,
+            "sender": req_data.sender,
+            "nonce": req_data.nonce,
+            "tx_digest": dubhe_common::digest_string(tx_digest),
+            "sql_count": sql_count,
+        }),
+    )
+}
+
+/// Chain access needed by the `execute` submit mode, kept behind a trait so it can be mocked.
+trait ChainSubmitter {
+    fn execute_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> impl Future<Output = Result<TransactionDigest>> + Send;
+}
+
+impl ChainSubmitter for SuiClient {
+    async fn execute_transaction(&self, transaction: Transaction) -> Result<TransactionDigest> {
+        let response = self
+            .quorum_driver_api()
+            .execute_transaction_block(
+                transaction,
+                SuiTransactionBlockResponseOptions::full_content(),
+                Some(ExecuteTransactionRequestType::WaitForEffectsCert),
+            )
+            .await?;
+        Ok(response.digest)
+    }
+}
+
+/// The transaction the client signed, ready to broadcast. `tx_bytes` is the base64 BCS
+/// `TransactionData` the client built, gas included, and `signature` its base64 Sui signature.
+/// The data has to be sent by `sender` and run the commands of `ptb`, and the signature has to
+/// match these exact bytes, since they are broadcast unchanged.
+fn signed_transaction(
+    tx_bytes: &str,
+    ptb: &ProgrammableTransaction,
+    sender: SuiAddress,
+    signature: &str,
+) -> Result<Transaction> {
+    let tx_bytes = general_purpose::STANDARD.decode(tx_bytes)
+        .map_err(|e| anyhow!("Failed to decode tx_bytes: {}", e))?;
+    let tx_data: TransactionData = bcs::from_bytes(&tx_bytes)
+        .map_err(|e| anyhow!("tx_bytes is not a TransactionData: {}", e))?;
+    if tx_data.sender() != sender {
+        return Err(anyhow!("Transaction is sent by {}, not {}", tx_data.sender(), sender));
+    }
+    match tx_data.kind() {
+        TransactionKind::ProgrammableTransaction(signed) if signed.commands == ptb.commands => {}
+        _ => return Err(anyhow!("Transaction does not run the commands of the submitted PTB")),
+    }
+
+    let signature_bytes = general_purpose::STANDARD.decode(signature)
+        .map_err(|e| anyhow!("Failed to decode signature: {}", e))?;
+    let signature = Signature::from_bytes(&signature_bytes)
+        .map_err(|e| anyhow!("Invalid signature: {}", e))?;
+    let intent_message = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
+    signature
+        .verify_secure(&intent_message, sender, signature.scheme())
+        .map_err(|e| anyhow!("Signature does not match the transaction: {}", e))?;
+    Ok(Transaction::from_data(tx_data, vec![signature]))
+}
+
 async fn set_storage(
-    config: &Arc<DubheChannelConfig>, 
+    sui_client: &SuiClient,
     key_tuple: Vec<Vec<u8>>,
     value_tuple: Vec<Vec<u8>>,
     dubhe_config: &DubheConfig,
     count: u64,
-) -> Result<(), anyhow::Error> { 
-    let sui_client = SuiClientBuilder::default().build(&config.indexer_args.rpc_url).await?;
-
+) -> Result<SetStorageResponse, anyhow::Error> { 
     let private_key = dotenvy::var("PRIVATE_KEY").unwrap();
     let keypair = SuiKeyPair::decode(&private_key).map_err(|e| anyhow!(e))?;
 
@@ -932,7 +1504,7 @@ async fn set_storage(
             .await?;
         println!("Successfully executed transaction: {}", transaction_response.digest);
 
-    Ok(())
+    SetStorageResponse::from_transaction_response(count, &transaction_response)
 }
 
 // ========== Tests ==========
@@ -941,6 +1513,264 @@ async fn set_storage(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_set_storage_response_from_effects() {
+        let fixture = json!({
+            "digest": "5hN7qfXJzLWXhYw3yJ5Tq3XJ9ZxT3hYpGGrDvzDHQm2w",
+            "effects": {
+                "messageVersion": "v1",
+                "status": { "status": "success" },
+                "created": [
+                    { "owner": { "ObjectOwner": "0x2" }, "reference": { "objectId": "0xaa", "version": 7, "digest": "11111111111111111111111111111111" } }
+                ],
+                "mutated": [
+                    { "owner": { "Shared": { "initial_shared_version": 3 } }, "reference": { "objectId": "0xbb", "version": 7, "digest": "11111111111111111111111111111111" } },
+                    { "owner": { "AddressOwner": "0xcc" }, "reference": { "objectId": "0xcd", "version": 7, "digest": "11111111111111111111111111111111" } }
+                ]
+            },
+            "objectChanges": [
+                { "type": "created", "objectId": "0xaa", "objectType": "0x2::dynamic_field::Field<u64, u64>" },
+                { "type": "mutated", "objectId": "0xbb", "objectType": "0xd::dapp_service::DappHub" }
+            ]
+        });
+
+        let response = SetStorageResponse::from_response_json(4, &fixture).unwrap();
+        assert_eq!(response.counter, 4);
+        assert_eq!(response.digest, "5hN7qfXJzLWXhYw3yJ5Tq3XJ9ZxT3hYpGGrDvzDHQm2w");
+        assert_eq!(response.created_object_ids, vec!["0xaa".to_string()]);
+        assert_eq!(response.mutated_object_ids, vec!["0xbb".to_string(), "0xcd".to_string()]);
+        assert_eq!(response.object_changes.len(), 2);
+        assert_eq!(response.object_changes[0]["type"], "created");
+
+        let serialized = serde_json::to_value(&response).unwrap();
+        assert_eq!(serialized["createdObjectIds"][0], "0xaa");
+
+        assert!(SetStorageResponse::from_response_json(4, &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_convert_move_call_rejects_invalid_identifier() {
+        let move_call = |module: &str, function: &str| MoveCallInner {
+            package: "0x2".to_string(),
+            module: module.to_string(),
+            function: function.to_string(),
+            type_arguments: vec![],
+            arguments: vec![ArgumentJson::Input { index: 0, arg_type: "pure".to_string() }],
+        };
+
+        let call = convert_move_call(&move_call("counter_system", "inc")).unwrap();
+        assert_eq!(call.module, "counter_system");
+        assert_eq!(call.arguments, vec![Argument::Input(0)]);
+
+        let err = convert_move_call(&move_call("counter system", "inc")).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid Move module name: \"counter system\"");
+        let err = convert_move_call(&move_call("counter_system", "in c")).unwrap_err();
+        assert!(err.to_string().contains("Invalid Move function name"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_sql_apply_rolls_back_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let object_id = ObjectID::from_hex_literal("0x1").unwrap();
+        let original = Object::immutable_with_id_for_testing(object_id);
+        let mut cache_db = CacheDB::new(dubhe_db::interface::EmptyDB::default());
+        cache_db.insert_object(original.clone()).unwrap();
+        let sqls = vec!["CREATE TABLE counter (value INTEGER)".to_string()];
+
+        // The database directory does not exist, so the SQL cannot be applied
+        cache_db.begin();
+        cache_db
+            .insert_object(Object::with_id_owner_for_testing(object_id, SuiAddress::ZERO))
+            .unwrap();
+        let missing_url = format!("sqlite:{}", dir.path().join("missing/channel.db").display());
+        let written = apply_submit_sqls(&cache_db, &missing_url, &sqls, Vec::new());
+        assert!(written.await.is_err());
+        assert_eq!(cache_db.cache.read().unwrap().objects.get(&object_id), Some(&original));
+
+        let database_url = format!("sqlite:{}", dir.path().join("channel.db").display());
+        cache_db.begin();
+        let updated = Object::with_id_owner_for_testing(object_id, SuiAddress::ZERO);
+        cache_db.insert_object(updated.clone()).unwrap();
+        let written = apply_submit_sqls(&cache_db, &database_url, &sqls, Vec::new());
+        assert_eq!(written.await.unwrap().0, 0);
+        assert_eq!(cache_db.cache.read().unwrap().objects.get(&object_id), Some(&updated));
+
+        // A statement saved for replay fails the submit, so its cache writes are dropped too
+        cache_db.begin();
+        cache_db.insert_object(original.clone()).unwrap();
+        let broken = vec!["INSERT INTO missing_table VALUES (1)".to_string()];
+        let written = apply_submit_sqls(&cache_db, &database_url, &broken, Vec::new());
+        assert_eq!(written.await.unwrap().0, 1);
+        assert_eq!(cache_db.cache.read().unwrap().objects.get(&object_id), Some(&updated));
+    }
+
+    #[tokio::test]
+    async fn test_only_persisted_changes_are_notified() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_db = CacheDB::new(dubhe_db::interface::EmptyDB::default());
+        let change = |statement: usize| PendingChange {
+            statement,
+            change: dubhe_indexer_grpc::types::TableChange {
+                table_id: format!("counter{}", statement),
+                data: Some(prost_types::Struct::default()),
+                trace_id: String::new(),
+                cursor: 0,
+                end_of_stream: false,
+                heartbeat: false,
+            },
+        };
+        let sqls = vec!["CREATE TABLE counter (value INTEGER)".to_string()];
+
+        // The database cannot be opened, so nothing is written and nobody is told
+        let missing_url = format!("sqlite:{}", dir.path().join("missing/channel.db").display());
+        cache_db.begin();
+        let written = apply_submit_sqls(&cache_db, &missing_url, &sqls, vec![change(0)]);
+        assert!(written.await.is_err());
+
+        // A statement saved for replay is not persisted yet, the ones before it are
+        let database_url = format!("sqlite:{}", dir.path().join("channel.db").display());
+        let partly_broken = vec![
+            "CREATE TABLE counter (value INTEGER)".to_string(),
+            "INSERT INTO missing_table VALUES (1)".to_string(),
+            "INSERT INTO counter VALUES (1)".to_string(),
+        ];
+        let changes = vec![change(0), change(1), change(2)];
+        cache_db.begin();
+        let written = apply_submit_sqls(&cache_db, &database_url, &partly_broken, changes);
+        let (saved, persisted) = written.await.unwrap();
+        assert_eq!(saved, 2);
+        let tables: Vec<_> = persisted.iter().map(|change| change.table_id.as_str()).collect();
+        assert_eq!(tables, vec!["counter0"]);
+
+        let sqls = vec!["INSERT INTO counter VALUES (2)".to_string()];
+        cache_db.begin();
+        let written = apply_submit_sqls(&cache_db, &database_url, &sqls, vec![change(0)]);
+        let (saved, persisted) = written.await.unwrap();
+        assert_eq!(saved, 0);
+        assert_eq!(persisted[0].table_id, "counter0");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_object_route_reads_the_cache() {
+        let object_id = ObjectID::from_hex_literal("0x42").unwrap();
+        let object = Object::immutable_with_id_for_testing(object_id);
+        let mut cache_db = CacheDB::new(dubhe_db::interface::EmptyDB::default());
+        // The framework packages the gas coin's layout is resolved from
+        for package in sui_framework::BuiltInFramework::genesis_objects() {
+            cache_db.insert_object(package).unwrap();
+        }
+        cache_db.insert_object(object.clone()).unwrap();
+        let cache_db = Arc::new(RwLock::new(cache_db));
+
+        let reply = read_object(&cache_db, "0x42").await.unwrap();
+        assert_eq!(reply.data["object_id"], object_id.to_string());
+        assert_eq!(reply.data["version"], object.version().value());
+        assert_eq!(reply.data["digest"], object.digest().to_string());
+        assert_eq!(reply.data["type"], object.type_().unwrap().to_string());
+        assert_eq!(reply.data["owner"], serde_json::to_value(&object.owner).unwrap());
+        assert!(reply.data["fields"].is_object(), "{}", reply.data);
+        assert!(reply.data["fields"].to_string().contains(&object_id.to_string()));
+
+        let missing = read_object(&cache_db, "0x43").await.unwrap_err();
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        let invalid = read_object(&cache_db, "not-an-id").await.unwrap_err();
+        assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    }
+
+    /// Serves `object` and fails every other call
+    struct SingleObjectReader {
+        object: Object,
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl dubhe_db::ObjectReader for SingleObjectReader {
+        async fn get_object_with_options(
+            &self,
+            object_id: ObjectID,
+            _options: SuiObjectDataOptions,
+        ) -> Result<sui_json_rpc_types::SuiObjectResponse, dubhe_db::DBTransportError> {
+            use sui_types::object::ObjectRead;
+
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            assert_eq!(object_id, self.object.id());
+            let read = ObjectRead::Exists(
+                self.object.compute_object_reference(),
+                self.object.clone(),
+                None,
+            );
+            // Without a layout the parsed content cannot be rendered, the BCS is enough
+            Ok((read, SuiObjectDataOptions::bcs_lossless()).try_into().unwrap())
+        }
+
+        async fn get_dynamic_fields(
+            &self,
+            _parent_object_id: ObjectID,
+            _cursor: Option<ObjectID>,
+            _limit: Option<usize>,
+        ) -> Result<sui_json_rpc_types::DynamicFieldPage, dubhe_db::DBTransportError> {
+            Err(unsupported_read("get_dynamic_fields"))
+        }
+
+        async fn multi_get_object_with_options(
+            &self,
+            _object_ids: Vec<ObjectID>,
+            _options: SuiObjectDataOptions,
+        ) -> Result<Vec<sui_json_rpc_types::SuiObjectResponse>, dubhe_db::DBTransportError> {
+            Err(unsupported_read("multi_get_object_with_options"))
+        }
+    }
+
+    fn unsupported_read(method: &str) -> dubhe_db::DBTransportError {
+        dubhe_db::DBTransportError(sui_sdk::error::Error::DataError(format!(
+            "SingleObjectReader does not serve {}",
+            method
+        )))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cache_miss_object_is_resolved_over_rpc() {
+        let object_id = ObjectID::from_hex_literal("0x42").unwrap();
+        let object = Object::immutable_with_id_for_testing(object_id);
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let cache_db_over = |resolve_missing_objects: bool| {
+            let reader = SingleObjectReader { object: object.clone(), calls: calls.clone() };
+            let dubhedb = DubheDB::new(reader).with_resolve_missing_objects(resolve_missing_objects);
+            Arc::new(RwLock::new(CacheDB::new(WrapDatabaseAsync::new(dubhedb).unwrap())))
+        };
+        let ptb: PtbJson = serde_json::from_value(json!({
+            "version": 1,
+            "inputs": [{ "$kind": "UnresolvedObject", "UnresolvedObject": { "objectId": "0x42" } }],
+            "commands": [{
+                "$kind": "MoveCall",
+                "MoveCall": {
+                    "package": "0x2",
+                    "module": "counter_system",
+                    "function": "inc",
+                    "typeArguments": [],
+                    "arguments": [{ "$kind": "Input", "Input": 0, "type": "object" }]
+                }
+            }]
+        })).unwrap();
+
+        // With resolution off a cache miss is rejected without asking the RPC node
+        let err = convert_ptb_json_to_transaction(&ptb, &cache_db_over(false)).await.unwrap_err();
+        assert_eq!(err.to_string(), format!("Object not found: {}", object_id));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let cache_db = cache_db_over(true);
+        let transaction = convert_ptb_json_to_transaction(&ptb, &cache_db).await.unwrap();
+        assert_eq!(
+            transaction.inputs,
+            vec![CallArg::Object(ObjectArg::ImmOrOwnedObject(object.compute_object_reference()))]
+        );
+        assert_eq!(transaction.commands.len(), 1);
+        assert_eq!(cache_db.read().await.cache.read().unwrap().object(&object_id), Some(object));
+
+        // Now cached, so no further RPC
+        convert_ptb_json_to_transaction(&ptb, &cache_db).await.unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_evm_to_sui() {
         // Test EVM address conversion
@@ -995,6 +1825,450 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_signing_message_covers_ptb_sender_and_nonce() {
+        use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519Signature};
+        use fastcrypto::traits::{KeyPair, Signer};
+
+        let ptb = |json: &str| -> PtbJson { serde_json::from_str(json).unwrap() };
+        let call = ptb(r#"{"version": 2,
+            "inputs": [{"$kind": "Pure", "Pure": {"bytes": "AQI="}}],
+            "commands": [{"$kind": "MoveCall", "MoveCall": {"package": "0x1", "module": "m",
+                "function": "f", "typeArguments": [], "arguments": [{"$kind": "Input", "Input": 0, "type": "pure"}]}}]}"#);
+        // The same PTB written with other whitespace and key order signs the same bytes
+        let reordered = ptb(r#"{"commands": [{"MoveCall": {"arguments": [{"type": "pure", "Input": 0, "$kind": "Input"}],
+            "typeArguments": [], "function": "f", "module": "m", "package": "0x0001"}, "$kind": "MoveCall"}],
+            "inputs": [{"Pure": {"bytes": "AQI="}, "$kind": "Pure"}], "version": 2}"#);
+
+        let message = submit_signing_message(&call, "sender", 7).unwrap();
+        assert_eq!(message, submit_signing_message(&reordered, "sender", 7).unwrap());
+        assert_ne!(message, submit_signing_message(&call, "sender", 8).unwrap());
+        assert_ne!(message, submit_signing_message(&call, "other", 7).unwrap());
+        // One input: variant 1 (Pure) with 2 bytes, then one command on package 0x1
+        assert_eq!(&message[..6], &[1, 1, 2, 1, 2, 1]);
+        assert_eq!(message[6..38], ObjectID::from_hex_literal("0x1").unwrap().into_bytes());
+        assert_eq!(&message[message.len() - 8..], &7u64.to_le_bytes());
+
+        let keypair = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let sender = bs58::encode(keypair.public().as_bytes()).into_string();
+        let message = submit_signing_message(&call, &sender, 7).unwrap();
+        let signature: Ed25519Signature = keypair.sign(&message);
+        let signature = bs58::encode(signature.as_bytes()).into_string();
+        assert!(signature::verify_signature("solana", &sender, &message, &signature).is_ok());
+        let replayed = submit_signing_message(&call, &sender, 8).unwrap();
+        assert!(signature::verify_signature("solana", &sender, &replayed, &signature).is_err());
+    }
+
+    #[test]
+    fn test_sender_address_rejects_bad_input() {
+        assert_eq!(sender_address("sui", "0x1").unwrap(), SuiAddress::from_str("0x1").unwrap());
+        assert!(sender_address("sui", "not an address").is_err());
+        assert!(sender_address("evm", "0x1234").is_err());
+        assert!(sender_address("solana", "0OIl").is_err());
+        let err = sender_address("bitcoin", "0x1").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid chain: bitcoin");
+    }
+
+    #[derive(Default)]
+    struct MockSubmitter {
+        executed: std::sync::Mutex<Vec<Transaction>>,
+    }
+
+    impl ChainSubmitter for MockSubmitter {
+        async fn execute_transaction(&self, transaction: Transaction) -> Result<TransactionDigest> {
+            let digest = *transaction.digest();
+            self.executed.lock().unwrap().push(transaction);
+            Ok(digest)
+        }
+    }
+
+    #[test]
+    fn test_submit_mode_defaults_to_mock() {
+        let request: SubmitRequest = serde_json::from_value(json!({
+            "chain": "sui",
+            "sender": "0x1",
+            "ptb": { "version": 1, "inputs": [], "commands": [] }
+        })).unwrap();
+        assert_eq!(request.mode, SubmitMode::Mock);
+
+        let mode: SubmitMode = serde_json::from_value(json!("execute")).unwrap();
+        assert_eq!(mode, SubmitMode::Execute);
+    }
+
+    #[tokio::test]
+    async fn test_execute_mode_broadcasts_the_signed_bytes() {
+        use sui_types::base_types::SequenceNumber;
+        use sui_types::crypto::{get_key_pair, Ed25519KeyPair};
+        use sui_types::digests::ObjectDigest;
+
+        let (sender, keypair): (SuiAddress, Ed25519KeyPair) = get_key_pair();
+        let gas_coin = (ObjectID::random(), SequenceNumber::from_u64(1), ObjectDigest::random());
+        let ptb = ProgrammableTransaction { inputs: vec![], commands: vec![] };
+
+        // The client picks the gas and signs the whole TransactionData
+        let tx_data = TransactionData::new_programmable(sender, vec![gas_coin], ptb.clone(), 5_000, 1000);
+        let sign = |tx_data: &TransactionData| {
+            let signature = Signature::new_secure(
+                &IntentMessage::new(Intent::sui_transaction(), tx_data.clone()),
+                &keypair,
+            );
+            general_purpose::STANDARD.encode(signature.as_ref())
+        };
+        let signature = sign(&tx_data);
+        let tx_bytes = general_purpose::STANDARD.encode(bcs::to_bytes(&tx_data).unwrap());
+
+        let transaction = signed_transaction(&tx_bytes, &ptb, sender, &signature).unwrap();
+        assert_eq!(transaction.data().transaction_data(), &tx_data);
+        let mock = MockSubmitter::default();
+        let digest = mock.execute_transaction(transaction).await.unwrap();
+        let executed = mock.executed.lock().unwrap();
+        assert_eq!(executed.len(), 1);
+        assert_eq!(*executed[0].digest(), digest);
+        assert_eq!(digest, tx_data.digest());
+
+        // A signature of other transaction data, e.g. with another gas budget, is rejected
+        let other = TransactionData::new_programmable(sender, vec![gas_coin], ptb.clone(), 6_000, 1000);
+        assert!(signed_transaction(&tx_bytes, &ptb, sender, &sign(&other)).is_err());
+        // So are data of another sender and data running other commands
+        assert!(signed_transaction(&tx_bytes, &ptb, SuiAddress::ZERO, &signature).is_err());
+        let mut builder = ProgrammableTransactionBuilder::new();
+        builder.pure(1u64).unwrap();
+        builder.programmable_move_call(
+            ObjectID::from_hex_literal("0x2").unwrap(),
+            Identifier::new("m").unwrap(),
+            Identifier::new("f").unwrap(),
+            vec![],
+            vec![Argument::Input(0)],
+        );
+        let other_ptb = builder.finish();
+        assert!(signed_transaction(&tx_bytes, &other_ptb, sender, &signature).is_err());
+    }
+
+    #[test]
+    fn test_execute_mode_rejects_bad_signature() {
+        let ptb = ProgrammableTransaction { inputs: vec![], commands: vec![] };
+        let tx_data = TransactionData::new_programmable(
+            SuiAddress::ZERO,
+            vec![(ObjectID::random(), Default::default(), sui_types::digests::ObjectDigest::random())],
+            ptb.clone(),
+            5_000,
+            1000,
+        );
+        let tx_bytes = general_purpose::STANDARD.encode(bcs::to_bytes(&tx_data).unwrap());
+        assert!(signed_transaction(&tx_bytes, &ptb, SuiAddress::ZERO, "not base64!").is_err());
+        assert!(signed_transaction("not base64!", &ptb, SuiAddress::ZERO, "AAAA").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notify_subscribers_preserves_order() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let (other_tx, mut other_rx) = tokio::sync::mpsc::unbounded_channel();
+        let subscribers: GrpcSubscribers = Arc::new(RwLock::new(std::collections::HashMap::from([
+            ("position".to_string(), vec![tx]),
+            ("counter".to_string(), vec![other_tx]),
+        ])));
+
+        let change = |table_id: &str, x: f64| dubhe_indexer_grpc::types::TableChange {
+            table_id: table_id.to_string(),
+            data: Some(prost_types::Struct {
+                fields: std::collections::BTreeMap::from([(
+                    "x".to_string(),
+                    prost_types::Value {
+                        kind: Some(prost_types::value::Kind::NumberValue(x)),
+                    },
+                )]),
+            }),
+            trace_id: String::new(),
+            cursor: 0,
+            end_of_stream: false,
+            heartbeat: false,
+        };
+        let changes = vec![change("position", 1.0), change("counter", 2.0), change("position", 3.0)];
+
+        notify_subscribers(subscribers.clone(), changes).await;
+
+        // The archive numbers the changes in the order they were published
+        let received: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let counter = other_rx.try_recv().unwrap();
+        assert!(other_rx.try_recv().is_err());
+        assert_eq!(received[0].cursor + 1, counter.cursor);
+        assert_eq!(counter.cursor + 1, received[1].cursor);
+
+        let without_cursor = |change: dubhe_indexer_grpc::types::TableChange| {
+            dubhe_indexer_grpc::types::TableChange { cursor: 0, ..change }
+        };
+        let received: Vec<_> = received.into_iter().map(without_cursor).collect();
+        assert_eq!(received, vec![change("position", 1.0), change("position", 3.0)]);
+        assert_eq!(without_cursor(counter), change("counter", 2.0));
+    }
+
+    #[test]
+    fn test_submit_reply_digest_is_base58() {
+        let submit_request: SubmitRequest = serde_json::from_value(json!({
+            "chain": "evm",
+            "sender": "0x1",
+            "nonce": 3,
+            "ptb": { "version": 2, "inputs": [], "commands": [] }
+        })).unwrap();
+        let tx_digest = get_tx_digest_by_chain(submit_request.chain.clone());
+
+        let reply = submit_reply(&submit_request, &tx_digest, 2);
+        let digest = reply.data["tx_digest"].as_str().unwrap();
+        assert_eq!(digest, tx_digest.to_string());
+        assert_ne!(digest, format!("{:?}", tx_digest));
+        assert!(bs58::decode(digest).into_vec().is_ok());
+        assert_eq!(TransactionDigest::from_str(digest).unwrap(), tx_digest);
+        assert_eq!(reply.data["nonce"], 3);
+        assert_eq!(reply.data["sql_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_ptb_is_rejected() {
+        let command = json!({
+            "$kind": "MoveCall",
+            "MoveCall": { "package": "0x1", "module": "counter", "function": "inc", "typeArguments": [], "arguments": [] }
+        });
+        let ptb: PtbJson = serde_json::from_value(json!({
+            "version": 2,
+            "inputs": [],
+            "commands": vec![command; 3]
+        })).unwrap();
+        assert!(check_ptb_limits(&ptb, 1024, 3).is_ok());
+
+        let response = check_ptb_limits(&ptb, 1024, 2).unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "PTB has 3 commands, at most 2 are accepted");
+    }
+
+    #[test]
+    fn test_admin_routes_require_the_admin_token() {
+        let request = |authorization: Option<&str>| {
+            let mut builder = hyper::Request::builder()
+                .method(hyper::Method::POST)
+                .uri("/admin/replay-dead-letters");
+            if let Some(authorization) = authorization {
+                builder = builder.header(hyper::header::AUTHORIZATION, authorization);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+        let status = |result: Result<(), ChannelError>| result.unwrap_err().status;
+
+        assert_eq!(status(authorize_admin(&request(None), None)), StatusCode::FORBIDDEN);
+        assert_eq!(
+            status(authorize_admin(&request(Some("Bearer secret")), None)),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(authorize_admin(&request(None), Some("secret"))),
+            StatusCode::UNAUTHORIZED
+        );
+        for wrong in ["Bearer secre", "Bearer secret2", "secret", "Basic secret"] {
+            assert_eq!(
+                status(authorize_admin(&request(Some(wrong)), Some("secret"))),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        assert!(authorize_admin(&request(Some("Bearer secret")), Some("secret")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_submit_body_is_capped_before_parsing() {
+        // The first steps of the /submit route, on a router that needs no chain access
+        let router = ChannelRouter::new(128usize).route(
+            "/submit",
+            hyper::Method::POST,
+            |max_bytes, req| async move {
+                let req_data = read_submit_request(req, max_bytes).await?;
+                check_ptb_limits(&req_data.ptb, 1024, 1024)?;
+                Ok(ChannelReply::ok("Parsed", json!({ "chain": req_data.chain })))
+            },
+        );
+        let (_, handler) = router.handlers().remove(0);
+        let call = |req: hyper::Request<Body>| {
+            let handler = handler.clone();
+            async move {
+                let response = handler(req).await.unwrap();
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        let post = || hyper::Request::builder().method(hyper::Method::POST).uri("/submit");
+
+        let small = json!({
+            "chain": "sui",
+            "sender": "0x1",
+            "ptb": { "version": 2, "inputs": [], "commands": [] }
+        })
+        .to_string();
+        assert!(small.len() <= 128);
+        let (status, body) = call(post().body(Body::from(small)).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["chain"], "sui");
+
+        // Rejected from the header, before the body is read
+        let declared = post()
+            .header(hyper::header::CONTENT_LENGTH, "1000000")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = call(declared).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["message"], "Request body is larger than 128 bytes");
+
+        // A streamed body without Content-Length is cut off at the limit
+        let (mut sender, streamed) = Body::channel();
+        tokio::spawn(async move {
+            while sender.send_data(bytes::Bytes::from(vec![b' '; 32])).await.is_ok() {}
+        });
+        let (status, _) = call(post().body(streamed).unwrap()).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    /// Log output written by a `tracing` subscriber
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_id_tags_submit_and_sql_logs() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let request = |trace_id: &str| {
+            hyper::Request::builder()
+                .uri("/submit")
+                .header(TRACE_ID_HEADER, trace_id)
+                .body(Body::empty())
+                .unwrap()
+        };
+        // Ids that are not a plain token are replaced rather than written to the logs
+        assert_eq!(request_trace_id(&request("not a token")).len(), 32);
+        let trace_id = request_trace_id(&request("trace-42"));
+        assert_eq!(trace_id, "trace-42");
+
+        let mut dubhe_config = DubheConfig::from_json(json!({
+            "components": [
+                { "counter": { "fields": [{ "entity_id": "address" }, { "value": "u32" }], "keys": ["entity_id"], "offchain": false } }
+            ],
+            "resources": [],
+            "enums": [],
+            "original_package_id": "0x1",
+            "dubhe_object_id": "0x2",
+            "original_dubhe_package_id": "0x3",
+            "start_checkpoint": "1"
+        })).unwrap();
+        let submit_request: SubmitRequest = serde_json::from_value(json!({
+            "chain": "sui",
+            "sender": "0x1",
+            "ptb": { "version": 2, "inputs": [], "commands": [] }
+        })).unwrap();
+        let event = dubhe_common::SequencedEvent {
+            event_seq: 0,
+            event: dubhe_common::Event::StoreSetRecord(dubhe_common::StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter".to_string(),
+                key_tuple: vec![bcs::to_bytes(&SuiAddress::ZERO).unwrap()],
+                value_tuple: vec![bcs::to_bytes(&7u32).unwrap()],
+            }),
+        };
+
+        let span = tracing::info_span!("submit", trace_id = %trace_id);
+        let output = async {
+            log_submit_request(&submit_request);
+            store_events_to_sql(
+                &mut dubhe_config,
+                vec![event],
+                0,
+                "digest".to_string(),
+                Dialect::Sqlite,
+                &trace_id,
+            ).await
+        }.instrument(span).await.unwrap();
+        assert_eq!(output.statements.len(), 1);
+        assert_eq!(output.table_changes[0].change.trace_id, "trace-42");
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = |message: &str| logs.lines().find(|line| line.contains(message)).unwrap().to_string();
+        assert!(line("Received submit request").contains("submit{trace_id=trace-42}"));
+        assert!(line("sql: ").contains("submit{trace_id=trace-42}"));
+    }
+
+    #[tokio::test]
+    async fn test_stored_digest_is_canonical() {
+        let mut dubhe_config = DubheConfig::from_json(json!({
+            "components": [
+                { "counter": { "fields": [{ "entity_id": "address" }, { "value": "u32" }], "keys": ["entity_id"], "offchain": false } }
+            ],
+            "resources": [],
+            "enums": [],
+            "original_package_id": "0x1",
+            "dubhe_object_id": "0x2",
+            "original_dubhe_package_id": "0x3",
+            "start_checkpoint": "1"
+        })).unwrap();
+        let event = dubhe_common::SequencedEvent {
+            event_seq: 0,
+            event: dubhe_common::Event::StoreSetRecord(dubhe_common::StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter".to_string(),
+                key_tuple: vec![bcs::to_bytes(&SuiAddress::ZERO).unwrap()],
+                value_tuple: vec![bcs::to_bytes(&7u32).unwrap()],
+            }),
+        };
+        let tx_digest = get_tx_digest_by_chain("evm".to_string());
+        let digest = dubhe_common::digest_string(&tx_digest);
+        assert_eq!(digest, tx_digest.to_string());
+        assert!(!digest.contains("TransactionDigest"));
+
+        let PtbSql { statements: sqls, table_changes: changes, storage_entries } =
+            store_events_to_sql(
+                &mut dubhe_config,
+                vec![event],
+                0,
+                digest,
+                Dialect::Sqlite,
+                "trace",
+            ).await.unwrap();
+        // Set on chain only once the caller has written the statements
+        assert_eq!(storage_entries.len(), 1);
+        let dir = tempfile::tempdir().unwrap();
+        let database_url = format!("sqlite:{}", dir.path().join("channel.db").display());
+        let database = Database::new(&database_url).await.unwrap();
+        database.execute(&dubhe_config.create_table_sql("counter").unwrap()).await.unwrap();
+        database.execute_or_dead_letter(&sqls).await.unwrap();
+
+        let table = dubhe_config.store_table_name(Some(&dubhe_config.original_package_id), "counter");
+        let rows = database
+            .query(&format!("SELECT last_update_digest FROM {}", table))
+            .await
+            .unwrap();
+        let stored = rows[0]["last_update_digest"].as_str().unwrap();
+        assert_eq!(stored, tx_digest.to_string());
+        assert_eq!(TransactionDigest::from_str(stored).unwrap(), tx_digest);
+        assert!(stored.len() <= dubhe_common::DIGEST_COLUMN_LENGTH);
+        let published = &changes[0].change.data.as_ref().unwrap().fields["last_update_digest"];
+        let expected = prost_types::value::Kind::StringValue(stored.to_string());
+        assert_eq!(published.kind, Some(expected));
+    }
+
     #[test]
     fn test_hex_string_to_bytes() {
         // Test with 0x prefix
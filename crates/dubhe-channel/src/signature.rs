@@ -0,0 +1,215 @@
+// Signature verification for the chains accepted by /submit.
+//
+// - sui:    base64 Sui `Signature` (flag || sig || pk) over a personal message
+// - evm:    hex 65-byte secp256k1 signature in `eth_sign` / `personal_sign` format
+// - solana: base58 64-byte ed25519 signature, the sender is the base58 public key
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use fastcrypto::ed25519::{Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::hash::{HashFunction, Keccak256};
+use fastcrypto::secp256k1::recoverable::Secp256k1RecoverableSignature;
+use fastcrypto::secp256k1::Secp256k1PublicKey;
+use fastcrypto::traits::{RecoverableSignature, ToFromBytes, VerifyingKey};
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{Signature, SuiSignature};
+
+/// Verify `signature` over `message` using the native scheme of `chain`.
+pub fn verify_signature(chain: &str, sender: &str, message: &[u8], signature: &str) -> Result<()> {
+    match chain {
+        "sui" => verify_sui_signature(sender, message, signature),
+        "evm" => verify_evm_signature(sender, message, signature),
+        "solana" => verify_solana_signature(sender, message, signature),
+        _ => Err(anyhow!("Unsupported chain for signature verification: {}", chain)),
+    }
+}
+
+/// Latest nonce each sender has used, so a signed request cannot be replayed. Nonces have to
+/// grow with every request of a sender; they are kept for the lifetime of the process.
+#[derive(Default)]
+pub struct NonceRegistry {
+    last: Mutex<HashMap<SuiAddress, u64>>,
+}
+
+impl NonceRegistry {
+    /// Record `nonce` for `sender`, the Sui address of the signer, failing if it is not above
+    /// every nonce the sender used before
+    pub fn consume(&self, sender: SuiAddress, nonce: u64) -> Result<()> {
+        let mut last = self.last.lock().unwrap();
+        match last.get(&sender) {
+            Some(&used) if nonce <= used => Err(anyhow!(
+                "Nonce {} was already used, the next nonce of {} must be above {}",
+                nonce,
+                sender,
+                used
+            )),
+            _ => {
+                last.insert(sender, nonce);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn verify_sui_signature(sender: &str, message: &[u8], signature: &str) -> Result<()> {
+    let sender = SuiAddress::from_str(sender)
+        .map_err(|e| anyhow!("sui: invalid sender address: {}", e))?;
+    let bytes = general_purpose::STANDARD
+        .decode(signature)
+        .map_err(|e| anyhow!("sui: signature is not valid base64: {}", e))?;
+    let signature = Signature::from_bytes(&bytes)
+        .map_err(|e| anyhow!("sui: malformed signature: {}", e))?;
+    let intent_message = IntentMessage::new(
+        Intent::personal_message(),
+        PersonalMessage {
+            message: message.to_vec(),
+        },
+    );
+    signature
+        .verify_secure(&intent_message, sender, signature.scheme())
+        .map_err(|e| anyhow!("sui: signature verification failed: {}", e))
+}
+
+/// Hash used by `eth_sign` / `personal_sign`.
+fn eth_message_hash_input(message: &[u8]) -> Vec<u8> {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    prefixed
+}
+
+/// Ethereum address of a secp256k1 public key: last 20 bytes of keccak256(uncompressed key).
+fn eth_address(public_key: &Secp256k1PublicKey) -> [u8; 20] {
+    let uncompressed = public_key.pubkey.serialize_uncompressed();
+    let digest = Keccak256::digest(&uncompressed[1..]).digest;
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&digest[12..]);
+    address
+}
+
+fn verify_evm_signature(sender: &str, message: &[u8], signature: &str) -> Result<()> {
+    let sender = hex::decode(sender.trim_start_matches("0x"))
+        .map_err(|e| anyhow!("evm: invalid sender address: {}", e))?;
+    if sender.len() != 20 {
+        return Err(anyhow!("evm: sender must be 20 bytes, got {}", sender.len()));
+    }
+
+    let mut bytes = hex::decode(signature.trim_start_matches("0x"))
+        .map_err(|e| anyhow!("evm: signature is not valid hex: {}", e))?;
+    if bytes.len() != 65 {
+        return Err(anyhow!("evm: signature must be 65 bytes, got {}", bytes.len()));
+    }
+    // eth_sign uses v = 27/28, fastcrypto expects the raw recovery id 0/1
+    bytes[64] = match bytes[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        v => return Err(anyhow!("evm: invalid recovery id {}", v)),
+    };
+    let signature = Secp256k1RecoverableSignature::from_bytes(&bytes)
+        .map_err(|e| anyhow!("evm: malformed signature: {}", e))?;
+    let public_key = signature
+        .recover_with_hash::<Keccak256>(&eth_message_hash_input(message))
+        .map_err(|e| anyhow!("evm: failed to recover public key: {}", e))?;
+
+    if eth_address(&public_key)[..] != sender[..] {
+        return Err(anyhow!("evm: signature was not produced by 0x{}", hex::encode(&sender)));
+    }
+    Ok(())
+}
+
+fn verify_solana_signature(sender: &str, message: &[u8], signature: &str) -> Result<()> {
+    let public_key = bs58::decode(sender)
+        .into_vec()
+        .map_err(|e| anyhow!("solana: invalid sender address: {}", e))?;
+    let public_key = Ed25519PublicKey::from_bytes(&public_key)
+        .map_err(|e| anyhow!("solana: sender is not an ed25519 public key: {}", e))?;
+    let signature = bs58::decode(signature)
+        .into_vec()
+        .map_err(|e| anyhow!("solana: signature is not valid base58: {}", e))?;
+    let signature = Ed25519Signature::from_bytes(&signature)
+        .map_err(|e| anyhow!("solana: malformed signature: {}", e))?;
+    public_key
+        .verify(message, &signature)
+        .map_err(|e| anyhow!("solana: signature verification failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::secp256k1::Secp256k1KeyPair;
+    use fastcrypto::traits::{KeyPair, RecoverableSigner, Signer};
+    use sui_types::crypto::get_key_pair;
+
+    const MESSAGE: &[u8] = b"dubhe submit";
+
+    #[test]
+    fn test_sui_signature() {
+        let (sender, keypair): (SuiAddress, sui_types::crypto::Ed25519KeyPair) = get_key_pair();
+        let intent_message = IntentMessage::new(
+            Intent::personal_message(),
+            PersonalMessage {
+                message: MESSAGE.to_vec(),
+            },
+        );
+        let signature = Signature::new_secure(&intent_message, &keypair);
+        let signature = general_purpose::STANDARD.encode(signature.as_ref());
+
+        assert!(verify_signature("sui", &sender.to_string(), MESSAGE, &signature).is_ok());
+        assert!(verify_signature("sui", &sender.to_string(), b"tampered", &signature).is_err());
+        let err = verify_signature("sui", &sender.to_string(), MESSAGE, "%%%").unwrap_err();
+        assert!(err.to_string().starts_with("sui:"));
+    }
+
+    #[test]
+    fn test_evm_signature() {
+        let keypair = Secp256k1KeyPair::generate(&mut rand::thread_rng());
+        let sender = format!("0x{}", hex::encode(eth_address(keypair.public())));
+        let mut signature = keypair
+            .sign_recoverable_with_hash::<Keccak256>(&eth_message_hash_input(MESSAGE))
+            .as_ref()
+            .to_vec();
+        signature[64] += 27;
+        let signature = format!("0x{}", hex::encode(&signature));
+
+        assert!(verify_signature("evm", &sender, MESSAGE, &signature).is_ok());
+        let err = verify_signature("evm", &sender, b"tampered", &signature).unwrap_err();
+        assert!(err.to_string().starts_with("evm:"));
+        let other = format!("0x{}", hex::encode([0x11u8; 20]));
+        assert!(verify_signature("evm", &other, MESSAGE, &signature).is_err());
+        assert!(verify_signature("evm", &sender, MESSAGE, "0x1234").is_err());
+    }
+
+    #[test]
+    fn test_solana_signature() {
+        let keypair = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let sender = bs58::encode(keypair.public().as_bytes()).into_string();
+        let signature: Ed25519Signature = keypair.sign(MESSAGE);
+        let signature = bs58::encode(signature.as_bytes()).into_string();
+
+        assert!(verify_signature("solana", &sender, MESSAGE, &signature).is_ok());
+        let err = verify_signature("solana", &sender, b"tampered", &signature).unwrap_err();
+        assert!(err.to_string().starts_with("solana:"));
+        assert!(verify_signature("solana", &sender, MESSAGE, "0OIl").is_err());
+    }
+
+    #[test]
+    fn test_unknown_chain() {
+        assert!(verify_signature("bitcoin", "x", MESSAGE, "y").is_err());
+    }
+
+    #[test]
+    fn test_nonce_cannot_be_reused() {
+        let nonces = NonceRegistry::default();
+        let sender = SuiAddress::random_for_testing_only();
+        assert!(nonces.consume(sender, 1).is_ok());
+        assert!(nonces.consume(sender, 1).is_err());
+        assert!(nonces.consume(sender, 0).is_err());
+        assert!(nonces.consume(sender, 2).is_ok());
+        // Every sender has its own sequence
+        assert!(nonces.consume(SuiAddress::random_for_testing_only(), 1).is_ok());
+    }
+}
@@ -0,0 +1,322 @@
+//! Benchmarks for turning store events into SQL and protobuf structs.
+//!
+//! Run with `cargo bench -p dubhe-common --bench convert_event`. To compare a change, save
+//! a baseline on the parent commit with `-- --save-baseline before`, then run the change
+//! with `-- --baseline before`; criterion reports the difference per case.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use dubhe_common::{
+    into_sql_string, write_sql_value, Dialect, DubheConfig, Event, StoreDeleteRecord,
+    StoreSetField, StoreSetRecord,
+};
+use serde_json::json;
+use sui_types::base_types::SuiAddress;
+
+const DAPP_KEY: &str = "1::dapp_key::DappKey";
+
+fn config() -> DubheConfig {
+    DubheConfig::from_json(json!({
+      "components": [
+        {
+          "scalar": {
+            "fields": [
+              { "id": "u64" },
+              { "owner": "address" },
+              { "amount": "u256" },
+              { "active": "bool" },
+              { "name": "String" }
+            ],
+            "keys": ["id"],
+            "offchain": false
+          }
+        },
+        {
+          "vectors": {
+            "fields": [
+              { "id": "u64" },
+              { "values": "vector<u64>" },
+              { "owners": "vector<address>" }
+            ],
+            "keys": ["id"],
+            "offchain": false
+          }
+        },
+        {
+          "enums": {
+            "fields": [
+              { "id": "u64" },
+              { "direction": "Direction" }
+            ],
+            "keys": ["id"],
+            "offchain": false
+          }
+        },
+        {
+          "composite": {
+            "fields": [
+              { "player": "address" },
+              { "monster": "address" },
+              { "value": "u32" }
+            ],
+            "keys": ["player", "monster"],
+            "offchain": false
+          }
+        }
+      ],
+      "resources": [],
+      "enums": [
+        { "Direction": ["Left", "Right"] }
+      ],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap()
+}
+
+/// 30 component tables of 6 fields each, the shape of a larger dapp
+fn wide_config() -> DubheConfig {
+    let components: Vec<_> = (0..30)
+        .map(|table| {
+            let mut component = serde_json::Map::new();
+            component.insert(
+                format!("table{}", table),
+                json!({
+                    "fields": [
+                        { "player": "address" },
+                        { "slot": "u64" },
+                        { "hp": "u32" },
+                        { "attack": "u32" },
+                        { "defense": "u32" },
+                        { "name": "String" }
+                    ],
+                    "keys": ["player", "slot"],
+                    "offchain": false
+                }),
+            );
+            component
+        })
+        .collect();
+    DubheConfig::from_json(json!({
+      "components": components,
+      "resources": [],
+      "enums": [],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap()
+}
+
+fn set_record(table_id: &str, key_tuple: Vec<Vec<u8>>, value_tuple: Vec<Vec<u8>>) -> Event {
+    Event::StoreSetRecord(StoreSetRecord {
+        dapp_key: DAPP_KEY.to_string(),
+        table_id: table_id.to_string(),
+        key_tuple,
+        value_tuple,
+    })
+}
+
+fn address(byte: u8) -> SuiAddress {
+    SuiAddress::from_bytes([byte; 32]).unwrap()
+}
+
+fn bcs_address(byte: u8) -> Vec<u8> {
+    bcs::to_bytes(&address(byte)).unwrap()
+}
+
+fn events() -> Vec<(&'static str, Event)> {
+    vec![
+        (
+            "scalar",
+            set_record(
+                "scalar",
+                vec![bcs::to_bytes(&42u64).unwrap()],
+                vec![
+                    bcs_address(7),
+                    bcs::to_bytes(&[u64::MAX; 4]).unwrap(),
+                    bcs::to_bytes(&true).unwrap(),
+                    bcs::to_bytes("benchmark").unwrap(),
+                ],
+            ),
+        ),
+        (
+            "vector",
+            set_record(
+                "vectors",
+                vec![bcs::to_bytes(&42u64).unwrap()],
+                vec![
+                    bcs::to_bytes(&(0..64u64).collect::<Vec<_>>()).unwrap(),
+                    bcs::to_bytes(&(0..16u8).map(address).collect::<Vec<_>>()).unwrap(),
+                ],
+            ),
+        ),
+        (
+            "enum",
+            set_record(
+                "enums",
+                vec![bcs::to_bytes(&42u64).unwrap()],
+                vec![bcs::to_bytes(&1u8).unwrap()],
+            ),
+        ),
+        (
+            "composite_key",
+            set_record(
+                "composite",
+                vec![bcs_address(1), bcs_address(2)],
+                vec![bcs::to_bytes(&7u32).unwrap()],
+            ),
+        ),
+        (
+            "set_field",
+            Event::StoreSetField(StoreSetField {
+                dapp_key: DAPP_KEY.to_string(),
+                table_id: "composite".to_string(),
+                key_tuple: vec![bcs_address(1), bcs_address(2)],
+                field_index: 0,
+                value: bcs::to_bytes(&8u32).unwrap(),
+            }),
+        ),
+        (
+            "delete_record",
+            Event::StoreDeleteRecord(StoreDeleteRecord {
+                dapp_key: DAPP_KEY.to_string(),
+                table_id: "composite".to_string(),
+                key_tuple: vec![bcs_address(1), bcs_address(2)],
+            }),
+        ),
+    ]
+}
+
+fn bench_convert_event_to_sql(c: &mut Criterion) {
+    let config = config();
+    let mut group = c.benchmark_group("convert_event_to_sql");
+    for (name, event) in events() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &event, |b, event| {
+            b.iter(|| {
+                config
+                    .convert_event_to_sql(
+                        black_box(event.clone()),
+                        1_700_000_000_000,
+                        "digest".to_string(),
+                        Dialect::Postgres,
+                    )
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_convert_event_to_proto_struct(c: &mut Criterion) {
+    let config = config();
+    let mut group = c.benchmark_group("convert_event_to_proto_struct");
+    for (name, event) in events() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &event, |b, event| {
+            b.iter(|| config.convert_event_to_proto_struct(black_box(event)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Per-event cost with many tables. Field lookups go through the per-table index, so the last
+/// table costs the same as the first instead of scanning the fields of the 29 before it.
+fn bench_wide_config(c: &mut Criterion) {
+    let config = wide_config();
+    let mut group = c.benchmark_group("convert_event_to_sql_30_tables");
+    for table in ["table0", "table29"] {
+        let event = set_record(
+            table,
+            vec![bcs_address(1), bcs::to_bytes(&3u64).unwrap()],
+            vec![
+                bcs::to_bytes(&100u32).unwrap(),
+                bcs::to_bytes(&10u32).unwrap(),
+                bcs::to_bytes(&5u32).unwrap(),
+                bcs::to_bytes("hero").unwrap(),
+            ],
+        );
+        group.bench_with_input(BenchmarkId::from_parameter(table), &event, |b, event| {
+            b.iter(|| {
+                config
+                    .convert_event_to_sql(
+                        black_box(event.clone()),
+                        1_700_000_000_000,
+                        "digest".to_string(),
+                        Dialect::Postgres,
+                    )
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The single pass `record_columns` against the per-list methods it replaced in
+/// `convert_event_to_sql`
+fn bench_record_columns(c: &mut Criterion) {
+    let config = config();
+    let key_tuple = vec![bcs_address(1), bcs_address(2)];
+    let value_tuple = vec![bcs::to_bytes(&7u32).unwrap()];
+    let mut group = c.benchmark_group("record_columns");
+    group.bench_function("single_pass", |b| {
+        b.iter(|| {
+            config.record_columns(
+                black_box("composite"),
+                &key_tuple,
+                &value_tuple,
+                Dialect::Postgres,
+            )
+            .unwrap()
+        })
+    });
+    group.bench_function("separate_lists", |b| {
+        b.iter(|| {
+            let table_id = black_box("composite");
+            (
+                config.field_names_by_table(table_id).join(","),
+                config.field_values_by_table(table_id, &key_tuple, &value_tuple).join(","),
+                config.field_names_by_table_and_primary_key(table_id).join(","),
+                config
+                    .field_values_with_set_by_table(table_id, &key_tuple, &value_tuple)
+                    .join(","),
+            )
+        })
+    });
+    group.finish();
+}
+
+/// Decoding one value into a fresh `String` against writing it into a reused buffer while
+/// borrowing strings and byte vectors from the BCS input
+fn bench_sql_value(c: &mut Criterion) {
+    let values = [
+        ("String", bcs::to_bytes("a fairly long player name").unwrap()),
+        ("vector<u8>", bcs::to_bytes(&vec![7u8; 64]).unwrap()),
+        ("vector<u64>", bcs::to_bytes(&(0..64u64).collect::<Vec<_>>()).unwrap()),
+    ];
+    let mut group = c.benchmark_group("sql_value");
+    for (type_, value) in &values {
+        group.bench_with_input(BenchmarkId::new("owning", type_), value, |b, value| {
+            b.iter(|| into_sql_string(black_box(type_), value).unwrap())
+        });
+        let mut out = String::with_capacity(1024);
+        group.bench_with_input(BenchmarkId::new("borrowing", type_), value, |b, value| {
+            b.iter(|| {
+                out.clear();
+                write_sql_value(&mut out, black_box(type_), value).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_convert_event_to_sql,
+    bench_convert_event_to_proto_struct,
+    bench_record_columns,
+    bench_wide_config,
+    bench_sql_value
+);
+criterion_main!(benches);
@@ -0,0 +1,266 @@
+use crate::db::Database;
+use crate::query::{BoundQuery, QueryValue};
+use crate::table::{quote_identifier, Dialect, DubheConfig, Field};
+use anyhow::Result;
+use std::path::Path;
+use std::str::FromStr;
+use sui_types::base_types::SuiAddress;
+
+impl DubheConfig {
+    /// Seed the store table of `table` from a CSV file, e.g. to try GraphQL or gRPC queries
+    /// without replaying chain events. The header names the table's field columns, in any
+    /// order, and every cell is converted according to its field's Move type. The whole file
+    /// is checked before anything is written, and the rows are inserted in one transaction.
+    /// Returns the number of rows inserted.
+    pub async fn import_csv(&self, db: &Database, table: &str, csv_path: &Path) -> Result<usize> {
+        if !self.tables.iter().any(|t| t.name == table) {
+            return Err(anyhow::anyhow!("Table '{}' is not in the config", table));
+        }
+        let content = std::fs::read_to_string(csv_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", csv_path.display(), e))?;
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} has no header", csv_path.display()))?;
+        let columns = self.csv_columns(table, &parse_csv_line(header)?)?;
+
+        let dialect = db.dialect();
+        let names: Vec<String> = columns
+            .iter()
+            .map(|field| quote_identifier(&field.column_name()))
+            .collect();
+        let placeholders: Vec<String> = (1..=columns.len())
+            .map(|n| match dialect {
+                Dialect::Postgres => format!("${}", n),
+                Dialect::Sqlite => "?".to_string(),
+            })
+            .collect();
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            self.store_table_name(Some(&self.original_package_id), table),
+            names.join(","),
+            placeholders.join(",")
+        );
+
+        let mut queries = Vec::new();
+        for (index, line) in lines {
+            let line_number = index + 1;
+            let cells = parse_csv_line(line)
+                .map_err(|e| anyhow::anyhow!("Line {}: {}", line_number, e))?;
+            if cells.len() != columns.len() {
+                return Err(anyhow::anyhow!(
+                    "Line {}: expected {} values, found {}",
+                    line_number,
+                    columns.len(),
+                    cells.len()
+                ));
+            }
+            let params = columns
+                .iter()
+                .zip(&cells)
+                .map(|(field, cell)| {
+                    self.csv_value(field, cell).map_err(|e| {
+                        anyhow::anyhow!("Line {}, column {}: {}", line_number, field.name, e)
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            queries.push(BoundQuery {
+                sql: sql.clone(),
+                params,
+                persistent: true,
+            });
+        }
+        db.query_batch(&queries).await?;
+        Ok(queries.len())
+    }
+
+    /// Fields of `table` in the order of a CSV header, which has to name each field once
+    fn csv_columns(&self, table: &str, header: &[String]) -> Result<Vec<&Field>> {
+        let fields: Vec<&Field> = self.table_fields(table).collect();
+        let mut columns = Vec::with_capacity(header.len());
+        for name in header {
+            let name = name.trim();
+            let field = fields
+                .iter()
+                .find(|field| field.name == name || field.column_name() == name)
+                .ok_or_else(|| anyhow::anyhow!("Table '{}' has no column '{}'", table, name))?;
+            if columns.iter().any(|column: &&Field| column.name == field.name) {
+                return Err(anyhow::anyhow!("Column '{}' appears twice in the header", name));
+            }
+            columns.push(*field);
+        }
+        if let Some(missing) = fields.iter().find(|f| !columns.iter().any(|c| c.name == f.name)) {
+            return Err(anyhow::anyhow!("Header is missing column '{}'", missing.name));
+        }
+        Ok(columns)
+    }
+
+    /// A CSV cell converted to what the indexer stores for a field of its Move type
+    fn csv_value(&self, field: &Field, cell: &str) -> Result<QueryValue> {
+        let invalid = || anyhow::anyhow!("'{}' is not a valid {}", cell, field.move_type);
+        if self.is_enum(&field.move_type) {
+            let is_variant = self
+                .enums
+                .iter()
+                .any(|enum_| enum_.name == field.move_type && enum_.value == cell);
+            return if is_variant {
+                Ok(QueryValue::String(cell.to_string()))
+            } else {
+                Err(invalid())
+            };
+        }
+        let value = match field.move_type.as_str() {
+            "String" => QueryValue::String(cell.to_string()),
+            "bool" => QueryValue::Boolean(bool::from_str(cell.trim()).map_err(|_| invalid())?),
+            "address" => QueryValue::String(
+                SuiAddress::from_str(cell.trim()).map_err(|_| invalid())?.to_string(),
+            ),
+            "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" => {
+                let value = i128::from_str(cell.trim()).map_err(|_| invalid())?;
+                let in_range = match field.move_type.as_str() {
+                    "u8" => u8::try_from(value).is_ok(),
+                    "u16" => u16::try_from(value).is_ok(),
+                    "u32" => u32::try_from(value).is_ok(),
+                    "u64" => u64::try_from(value).is_ok(),
+                    "i8" => i8::try_from(value).is_ok(),
+                    "i16" => i16::try_from(value).is_ok(),
+                    "i32" => i32::try_from(value).is_ok(),
+                    _ => i64::try_from(value).is_ok(),
+                };
+                if !in_range {
+                    return Err(invalid());
+                }
+                // BIGINT columns stop at i64::MAX
+                QueryValue::Integer(i64::try_from(value).map_err(|_| {
+                    anyhow::anyhow!("{} does not fit in a BIGINT column", cell.trim())
+                })?)
+            }
+            "u128" => {
+                let value = u128::from_str(cell.trim()).map_err(|_| invalid())?;
+                QueryValue::String(value.to_string())
+            }
+            "i128" => {
+                let value = i128::from_str(cell.trim()).map_err(|_| invalid())?;
+                QueryValue::String(value.to_string())
+            }
+            "u256" => {
+                // Decimal digits only, u256::MAX has 78 of them
+                let digits = cell.trim();
+                let is_number = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+                if !is_number || digits.len() > 78 {
+                    return Err(invalid());
+                }
+                QueryValue::String(digits.to_string())
+            }
+            other => {
+                return Err(anyhow::anyhow!("CSV import does not support {} columns", other));
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Cells of one CSV line. Cells may be quoted, with `""` standing for a quote inside them;
+/// a quoted cell cannot span lines.
+fn parse_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.is_empty() => quoted = true,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow::anyhow!("Unterminated quoted value"));
+    }
+    cells.push(cell);
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn counter3_config() -> DubheConfig {
+        DubheConfig::from_json(json!({
+          "components": [
+            { "counter3": {
+                "fields": [{ "entity_id": "address" }, { "hp": "u64" }, { "attack": "u64" }, { "defense": "u64" }],
+                "keys": ["entity_id"],
+                "offchain": false
+            } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_seeds_a_store_table() {
+        let config = counter3_config();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("seed.db").display());
+        let database = Database::new(&url).await.unwrap();
+        database
+            .execute(&config.create_table_sql("counter3").unwrap())
+            .await
+            .unwrap();
+
+        let csv_path = dir.path().join("counter3.csv");
+        std::fs::write(&csv_path, "hp,entity_id,attack,defense\n10,0x1,20,30\n\"7\",0x2,8,9\n")
+            .unwrap();
+        let imported = config.import_csv(&database, "counter3", &csv_path).await.unwrap();
+        assert_eq!(imported, 2);
+
+        let rows = database
+            .query("SELECT entity_id, hp, attack, defense FROM store_counter3 ORDER BY hp")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["entity_id"], SuiAddress::from_str("0x2").unwrap().to_string());
+        assert_eq!(rows[0]["hp"], 7);
+        assert_eq!(rows[1]["entity_id"], SuiAddress::from_str("0x1").unwrap().to_string());
+        assert_eq!(rows[1]["defense"], 30);
+
+        // A bad row anywhere rejects the whole file
+        let rejected = [
+            "entity_id,hp,attack,defense\n0x3,1,2,3\n0x4,1,2\n",
+            "entity_id,hp,attack,defense\n0x3,1,2,3\n0x4,-1,2,3\n",
+            "entity_id,hp,attack,defense\nnot an address,1,2,3\n",
+            "entity_id,hp,attack\n0x3,1,2\n",
+            "entity_id,hp,attack,defense,speed\n0x3,1,2,3,4\n",
+        ];
+        for csv in rejected {
+            std::fs::write(&csv_path, csv).unwrap();
+            let result = config.import_csv(&database, "counter3", &csv_path).await;
+            assert!(result.is_err(), "{}", csv);
+        }
+        let count = database.count_rows("store_counter3", "").await.unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_parse_csv_line() {
+        let cells = parse_csv_line("a,\"b,c\",\"say \"\"hi\"\"\",\r").unwrap();
+        assert_eq!(cells, vec!["a", "b,c", "say \"hi\"", ""]);
+        assert!(parse_csv_line("a,\"b").is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use super::Database;
+use crate::dead_letter::IndexedStatement;
+use crate::metrics::sql_buffer_fill;
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// Bounded queue of statement batches between event handling and a writer task. Once the
+/// queue is full, [`SqlWriteBuffer::send`] waits for the writer to catch up.
+#[derive(Clone)]
+pub struct SqlWriteBuffer {
+    tx: mpsc::Sender<PendingWrite>,
+    capacity: usize,
+    closing: Arc<watch::Sender<bool>>,
+}
+
+/// Executes the batches queued in a [`SqlWriteBuffer`], in order
+pub struct SqlWriter {
+    rx: mpsc::Receiver<PendingWrite>,
+    closing: watch::Receiver<bool>,
+    closed: bool,
+    commit_batch_size: usize,
+}
+
+/// A queued batch and where to report whether it was committed
+struct PendingWrite {
+    statements: Vec<IndexedStatement>,
+    committed: oneshot::Sender<Result<()>>,
+}
+
+impl SqlWriteBuffer {
+    /// A buffer holding up to `capacity` batches and the writer that drains it
+    pub fn new(capacity: usize) -> (Self, SqlWriter) {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let (closing, closing_rx) = watch::channel(false);
+        let buffer = Self {
+            tx,
+            capacity: capacity.max(1),
+            closing: Arc::new(closing),
+        };
+        let writer = SqlWriter {
+            rx,
+            closing: closing_rx,
+            closed: false,
+            commit_batch_size: 1,
+        };
+        (buffer, writer)
+    }
+
+    /// Stop taking batches. The writer commits the ones already queued, then returns.
+    pub fn close(&self) {
+        self.closing.send_replace(true);
+    }
+
+    /// Queue a batch, waiting while the buffer is full. The receiver tells once the writer
+    /// has committed the batch, or why it could not.
+    pub async fn send(
+        &self,
+        statements: Vec<IndexedStatement>,
+    ) -> Result<oneshot::Receiver<Result<()>>> {
+        let (committed, receiver) = oneshot::channel();
+        if statements.is_empty() {
+            let _ = committed.send(Ok(()));
+            return Ok(receiver);
+        }
+        self.tx
+            .send(PendingWrite {
+                statements,
+                committed,
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("SQL writer stopped, cannot queue statements"))?;
+        sql_buffer_fill().set(self.len() as i64);
+        Ok(receiver)
+    }
+
+    /// Queue `batches` and wait until the writer has committed all of them, so whatever
+    /// is recorded after this returns, like the checkpoint watermark, never gets ahead of
+    /// the data
+    pub async fn write(&self, batches: Vec<Vec<IndexedStatement>>) -> Result<()> {
+        let mut receivers = Vec::with_capacity(batches.len());
+        for statements in batches {
+            receivers.push(self.send(statements).await?);
+        }
+        for receiver in receivers {
+            receiver
+                .await
+                .map_err(|_| anyhow::anyhow!("SQL writer stopped before committing"))??;
+        }
+        Ok(())
+    }
+
+    /// Number of batches waiting for the writer
+    pub fn len(&self) -> usize {
+        self.capacity - self.tx.capacity()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl SqlWriter {
+    /// Commit up to `size` queued batches in one transaction. Only batches that are already
+    /// queued are combined, the writer never waits for more. With a size of 1, the default,
+    /// each batch is committed on its own.
+    pub fn with_commit_batch(mut self, size: usize) -> Self {
+        self.commit_batch_size = size.max(1);
+        self
+    }
+
+    /// Execute queued batches until the buffer is closed, or every [`SqlWriteBuffer`] is
+    /// dropped, and the queue is empty. Each commit is one transaction, see
+    /// [`Database::execute_in_transaction`] for statements that fail. When the transaction
+    /// fails, every batch in it is told so and the writer moves on.
+    pub async fn run(mut self, database: Arc<Database>) -> Result<()> {
+        while let Some(writes) = self.next_commit().await {
+            sql_buffer_fill().set(self.rx.len() as i64);
+            let statements: Vec<IndexedStatement> = writes
+                .iter()
+                .flat_map(|write| write.statements.iter().cloned())
+                .collect();
+            let result = database.execute_in_transaction(&statements).await;
+            if let Err(e) = &result {
+                log::error!("❌ Failed to commit {} batch(es): {}", writes.len(), e);
+            }
+            for write in writes {
+                let outcome = match &result {
+                    Ok(()) => Ok(()),
+                    Err(e) => Err(anyhow::anyhow!("{:#}", e)),
+                };
+                // The sender may have given up waiting, the batch is committed either way
+                let _ = write.committed.send(outcome);
+            }
+        }
+        log::info!("✅ SQL write buffer drained");
+        Ok(())
+    }
+
+    /// Batches of the next commit: the next queued batch and up to `commit_batch_size - 1`
+    /// more that are already waiting. `None` once nothing is left.
+    async fn next_commit(&mut self) -> Option<Vec<PendingWrite>> {
+        let mut writes = vec![self.recv().await?];
+        while writes.len() < self.commit_batch_size {
+            match self.rx.try_recv() {
+                Ok(write) => writes.push(write),
+                Err(_) => break,
+            }
+        }
+        Some(writes)
+    }
+
+    /// Next queued batch; once the buffer is closing, only the ones already queued
+    async fn recv(&mut self) -> Option<PendingWrite> {
+        if !self.closed && *self.closing.borrow() {
+            self.close();
+        }
+        if self.closed {
+            return self.rx.try_recv().ok();
+        }
+        tokio::select! {
+            write = self.rx.recv() => write,
+            Ok(()) = self.closing.changed() => {
+                self.close();
+                self.rx.try_recv().ok()
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.rx.close();
+        self.closed = true;
+    }
+
+    /// Run the writer on its own task
+    pub fn spawn(self, database: Arc<Database>) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(self.run(database))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn counter_database(dir: &tempfile::TempDir) -> Arc<Database> {
+        let url = format!("sqlite:{}", dir.path().join("buffer.db").display());
+        let database = Arc::new(Database::new(&url).await.unwrap());
+        database
+            .execute("CREATE TABLE counter (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        database
+    }
+
+    async fn counter_ids(database: &Database) -> Vec<i64> {
+        let rows = database.query("SELECT id FROM counter ORDER BY id").await.unwrap();
+        rows.iter().map(|row| row["id"].as_i64().unwrap()).collect()
+    }
+
+    fn insert(id: u32) -> Vec<IndexedStatement> {
+        vec![IndexedStatement {
+            sql: format!("INSERT INTO counter (id) VALUES ({})", id),
+            dead_letter: None,
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_writer_drains_buffer_and_full_buffer_waits() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = counter_database(&dir).await;
+
+        let (buffer, writer) = SqlWriteBuffer::new(2);
+        let first = buffer.send(insert(1)).await.unwrap();
+        let second = buffer.send(insert(2)).await.unwrap();
+        assert_eq!(buffer.len(), 2);
+
+        // Full, and no writer is running yet: the next batch has to wait
+        let blocked = tokio::time::timeout(Duration::from_millis(100), buffer.send(insert(3)));
+        assert!(blocked.await.is_err());
+        assert_eq!(buffer.len(), 2);
+
+        let handle = writer.spawn(database.clone());
+        let third = tokio::time::timeout(Duration::from_secs(5), buffer.send(insert(3)))
+            .await
+            .expect("writer did not free the buffer")
+            .unwrap();
+        for committed in [first, second, third] {
+            committed.await.unwrap().unwrap();
+        }
+        drop(buffer);
+        handle.await.unwrap().unwrap();
+        assert_eq!(counter_ids(&database).await, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_batched_checkpoints_commit_in_one_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = counter_database(&dir).await;
+
+        let (buffer, writer) = SqlWriteBuffer::new(8);
+        let writer = writer.with_commit_batch(3);
+        // One batch per checkpoint, the third one conflicts with the first
+        let failed = vec![
+            buffer.send(insert(1)).await.unwrap(),
+            buffer.send(insert(2)).await.unwrap(),
+            buffer.send(insert(1)).await.unwrap(),
+        ];
+        let handle = writer.spawn(database.clone());
+        for committed in failed {
+            assert!(committed.await.unwrap().is_err());
+        }
+        assert!(
+            counter_ids(&database).await.is_empty(),
+            "a failed batch must not commit any checkpoint"
+        );
+
+        let batches = vec![insert(3), insert(4), insert(5)];
+        tokio::time::timeout(Duration::from_secs(5), buffer.write(batches))
+            .await
+            .expect("writer did not commit the batches")
+            .unwrap();
+        assert_eq!(counter_ids(&database).await, vec![3, 4, 5]);
+        drop(buffer);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_close_commits_queued_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = counter_database(&dir).await;
+
+        let (buffer, writer) = SqlWriteBuffer::new(8);
+        let first = buffer.send(insert(1)).await.unwrap();
+        let second = buffer.send(insert(2)).await.unwrap();
+        buffer.close();
+
+        let handle = writer.spawn(database.clone());
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("close did not stop the writer")
+            .unwrap()
+            .unwrap();
+        first.await.unwrap().unwrap();
+        second.await.unwrap().unwrap();
+        assert_eq!(counter_ids(&database).await, vec![1, 2]);
+        assert!(buffer.send(insert(3)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_statement_with_dead_letter_is_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = counter_database(&dir).await;
+        database
+            .execute(&crate::DeadLetter::create_table_sql(crate::Dialect::Sqlite))
+            .await
+            .unwrap();
+
+        let (buffer, writer) = SqlWriteBuffer::new(8);
+        let handle = writer.with_commit_batch(2).spawn(database.clone());
+        let dead_letter = crate::DeadLetter::new("Dubhe_Store_SetRecord", &[0xab], "")
+            .with_transaction("digest", 7);
+        let mut second = insert(2);
+        second.push(IndexedStatement {
+            sql: "INSERT INTO counter (id) VALUES (1)".to_string(),
+            dead_letter: Some(dead_letter),
+        });
+        second.extend(insert(3));
+        buffer.write(vec![insert(1), second]).await.unwrap();
+        assert_eq!(counter_ids(&database).await, vec![1, 2, 3]);
+
+        let rows = database
+            .query("SELECT event_name, error, digest FROM dubhe_dead_letters")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["digest"], "digest");
+        assert!(rows[0]["error"].as_str().unwrap().contains("UNIQUE"));
+        drop(buffer);
+        handle.await.unwrap().unwrap();
+    }
+}
@@ -1,16 +1,98 @@
+mod buffer;
 mod postgres;
 mod sqlite;
 mod traits;
 
-pub use postgres::PostgresStorage;
+pub use buffer::{SqlWriteBuffer, SqlWriter};
+pub use postgres::{PostgresStorage, TABLE_CHANGES_CHANNEL};
 pub use sqlite::SqliteStorage;
 pub use traits::Storage;
 
+use crate::dead_letter::{DeadLetter, IndexedStatement, DEAD_LETTERS_TABLE};
+use crate::query::{BoundQuery, QueryValue};
 use crate::sql::DBData;
-use crate::table::DubheConfig;
+use crate::table::{Dialect, DubheConfig};
 use crate::table::TableMetadata;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+/// Retry settings for statements that failed with a transient database error
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Whether `error` is a transient failure (connection, deadlock, serialization) worth retrying.
+/// Syntax and constraint errors are permanent.
+pub fn is_retryable_error(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Io(_)) | Some(sqlx::Error::PoolTimedOut) | Some(sqlx::Error::WorkerCrashed) => true,
+        Some(sqlx::Error::Database(db_error)) => match db_error.code() {
+            // 40001 serialization_failure, 40P01 deadlock_detected, 08xxx connection exceptions,
+            // 57P01 admin_shutdown; 5/6 are SQLITE_BUSY/SQLITE_LOCKED
+            Some(code) => {
+                matches!(code.as_ref(), "40001" | "40P01" | "57P01" | "5" | "6")
+                    || code.starts_with("08")
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Run `operation` until it succeeds, fails permanently, or `policy.max_attempts` is reached.
+/// Only use this for idempotent statements.
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = policy.base_delay;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && is_retryable_error(&e) => {
+                log::warn!(
+                    "Transient database error (attempt {}/{}), retrying in {:?}: {}",
+                    attempt,
+                    policy.max_attempts,
+                    delay,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Outcome of [`Database::replay_dead_letters`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// Statements that executed and were removed from the dead letter table
+    pub replayed: usize,
+    /// Statements still waiting, starting with the one that failed again
+    pub remaining: usize,
+    /// Error of the statement that stopped the replay
+    pub error: Option<String>,
+}
 
 /// Database storage enum that supports both SQLite and PostgreSQL
 pub enum Database {
@@ -21,21 +103,113 @@ pub enum Database {
 impl Database {
     /// Create a new database instance based on the URL
     pub async fn new(db_url: &str) -> Result<Self> {
-        if db_url.starts_with("postgres://") || db_url.starts_with("postgresql://") {
-            let storage = PostgresStorage::new(db_url).await?;
-            Ok(Database::Postgres(storage))
-        } else {
-            let storage = SqliteStorage::new(db_url).await?;
-            Ok(Database::Sqlite(storage))
+        match Dialect::from_database_url(db_url) {
+            Dialect::Postgres => Ok(Database::Postgres(PostgresStorage::new(db_url).await?)),
+            Dialect::Sqlite => Ok(Database::Sqlite(SqliteStorage::new(db_url).await?)),
         }
     }
 
-    /// Execute SQL statement
+    /// Execute SQL statement, retrying transient failures with the default [`RetryPolicy`].
+    /// The generated statements are upserts, so running one twice is safe.
     pub async fn execute(&self, sql: &str) -> Result<()> {
-        match self {
-            Database::Sqlite(storage) => storage.execute(sql).await,
-            Database::Postgres(storage) => storage.execute(sql).await,
+        self.execute_with_retry(sql, &RetryPolicy::default()).await
+    }
+
+    /// Execute SQL statement, retrying transient failures according to `policy`
+    pub async fn execute_with_retry(&self, sql: &str, policy: &RetryPolicy) -> Result<()> {
+        with_retry(policy, || async move {
+            match self {
+                Database::Sqlite(storage) => storage.execute(sql).await,
+                Database::Postgres(storage) => storage.execute(sql).await,
+            }
+        })
+        .await
+    }
+
+    /// Execute statements in order, each with the default [`RetryPolicy`]
+    pub async fn execute_batch(&self, statements: &[String]) -> Result<()> {
+        for sql in statements {
+            self.execute(sql).await?;
         }
+        Ok(())
+    }
+
+    /// Execute statements in one transaction: either all of them are applied or none,
+    /// except that a statement with a dead letter that fails records its dead letter in
+    /// [`crate::DEAD_LETTERS_TABLE`] instead. Transient failures are retried with the default
+    /// [`RetryPolicy`], from the start.
+    pub async fn execute_in_transaction(&self, statements: &[IndexedStatement]) -> Result<()> {
+        with_retry(&RetryPolicy::default(), || async move {
+            match self {
+                Database::Sqlite(storage) => storage.execute_in_transaction(statements).await,
+                Database::Postgres(storage) => storage.execute_in_transaction(statements).await,
+            }
+        })
+        .await
+    }
+
+    /// Execute statements in order like [`Database::execute_batch`], but instead of failing,
+    /// save the statement that failed after retries and every statement after it to
+    /// [`DEAD_LETTERS_TABLE`], so they can be replayed in their original order.
+    /// Returns the number of statements saved.
+    pub async fn execute_or_dead_letter(&self, statements: &[String]) -> Result<usize> {
+        for (index, sql) in statements.iter().enumerate() {
+            if let Err(e) = self.execute(sql).await {
+                log::warn!("⚠️ Saving {} statement(s) for replay: {}", statements.len() - index, e);
+                self.create_dead_letter_table().await?;
+                for sql in &statements[index..] {
+                    self.execute(&DeadLetter::statement(sql, &e).insert_sql()).await?;
+                }
+                return Ok(statements.len() - index);
+            }
+        }
+        Ok(0)
+    }
+
+    /// Re-execute dead-lettered statements in the order they failed, removing each one that
+    /// succeeds in the same transaction, so a statement is never applied twice. Stops at the
+    /// first statement that fails again so later statements never overtake it. Dead-lettered
+    /// events, which have no statement, are left alone.
+    pub async fn replay_dead_letters(&self) -> Result<ReplayReport> {
+        self.create_dead_letter_table().await?;
+        let rows = self
+            .query(&format!(
+                "SELECT id, sql FROM {} WHERE sql IS NOT NULL ORDER BY id",
+                DEAD_LETTERS_TABLE
+            ))
+            .await?;
+        let mut report = ReplayReport {
+            replayed: 0,
+            remaining: rows.len(),
+            error: None,
+        };
+        for row in rows {
+            let id = row["id"]
+                .as_i64()
+                .ok_or_else(|| anyhow::anyhow!("Invalid dead letter id: {}", row["id"]))?;
+            let sql = row["sql"].as_str().unwrap_or_default();
+            let statements = [
+                IndexedStatement {
+                    sql: sql.to_string(),
+                    dead_letter: None,
+                },
+                IndexedStatement {
+                    sql: format!("DELETE FROM {} WHERE id = {}", DEAD_LETTERS_TABLE, id),
+                    dead_letter: None,
+                },
+            ];
+            if let Err(e) = self.execute_in_transaction(&statements).await {
+                report.error = Some(format!("{:#}", e));
+                break;
+            }
+            report.replayed += 1;
+            report.remaining -= 1;
+        }
+        Ok(report)
+    }
+
+    async fn create_dead_letter_table(&self) -> Result<()> {
+        self.execute(&DeadLetter::create_table_sql(self.dialect())).await
     }
 
     /// Create tables from configuration
@@ -51,17 +225,17 @@ impl Database {
         &self,
         table_name: &str,
         values: Vec<DBData>,
-        last_updated_checkpoint: u64,
+        updated_at_timestamp_ms: u64,
     ) -> Result<()> {
         match self {
             Database::Sqlite(storage) => {
                 storage
-                    .insert(table_name, values, last_updated_checkpoint)
+                    .insert(table_name, values, updated_at_timestamp_ms)
                     .await
             }
             Database::Postgres(storage) => {
                 storage
-                    .insert(table_name, values, last_updated_checkpoint)
+                    .insert(table_name, values, updated_at_timestamp_ms)
                     .await
             }
         }
@@ -83,6 +257,32 @@ impl Database {
         }
     }
 
+    /// Execute a SQL query with `params` bound to its placeholders, `?` on SQLite and `$n` on
+    /// PostgreSQL. A `persistent` statement stays prepared on each connection that runs it.
+    pub async fn query_with_params(
+        &self,
+        sql: &str,
+        params: &[QueryValue],
+        persistent: bool,
+    ) -> Result<Vec<serde_json::Value>> {
+        match self {
+            Database::Sqlite(storage) => storage.query_with_params(sql, params, persistent).await,
+            Database::Postgres(storage) => storage.query_with_params(sql, params, persistent).await,
+        }
+    }
+
+    /// Run `queries` in one read transaction and return the rows of each, in order. All of
+    /// them see the same snapshot, even while the indexer is writing.
+    pub async fn query_batch(
+        &self,
+        queries: &[BoundQuery],
+    ) -> Result<Vec<Vec<serde_json::Value>>> {
+        match self {
+            Database::Sqlite(storage) => storage.query_batch(queries).await,
+            Database::Postgres(storage) => storage.query_batch(queries).await,
+        }
+    }
+
     /// Count rows in a table with optional WHERE clause
     pub async fn count_rows(&self, table_name: &str, where_clause: &str) -> Result<u64> {
         let sql = if where_clause.is_empty() {
@@ -112,6 +312,30 @@ impl Database {
         }
     }
 
+    /// Run a trivial query once, without retries, to check that the database is reachable
+    pub async fn ping(&self) -> Result<()> {
+        match self {
+            Database::Sqlite(storage) => storage.execute("SELECT 1").await,
+            Database::Postgres(storage) => storage.execute("SELECT 1").await,
+        }
+    }
+
+    /// Close the connection pool; later queries fail instead of reconnecting
+    pub async fn close(&self) {
+        match self {
+            Database::Sqlite(storage) => storage.close().await,
+            Database::Postgres(storage) => storage.pool().close().await,
+        }
+    }
+
+    /// Dialect to generate statements for this database in
+    pub fn dialect(&self) -> Dialect {
+        match self {
+            Database::Sqlite(_) => Dialect::Sqlite,
+            Database::Postgres(_) => Dialect::Postgres,
+        }
+    }
+
     /// Get database type name
     pub fn db_type(&self) -> &'static str {
         match self {
@@ -147,3 +371,291 @@ impl Database {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const FAST_RETRY: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+    };
+
+    #[tokio::test]
+    async fn test_with_retry_recovers_from_transient_error() {
+        let calls = AtomicU32::new(0);
+        let calls_ref = &calls;
+        let result = with_retry(&FAST_RETRY, move || async move {
+            if calls_ref.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(anyhow::Error::from(sqlx::Error::PoolTimedOut))
+            } else {
+                Ok("upserted")
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "upserted");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_does_not_retry_permanent_errors() {
+        let calls = AtomicU32::new(0);
+        let calls_ref = &calls;
+        let result: Result<()> = with_retry(&FAST_RETRY, move || async move {
+            calls_ref.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow::Error::from(sqlx::Error::Protocol(
+                "syntax error at or near \"INSER\"".to_string(),
+            )))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Transient errors give up after max_attempts
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<()> = with_retry(&FAST_RETRY, move || async move {
+            calls_ref.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow::Error::from(sqlx::Error::PoolTimedOut))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_statements_are_dead_lettered_and_replayed() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("replay.db").display());
+        let database = Database::new(&url).await.unwrap();
+        database
+            .execute("CREATE TABLE store_a (id INTEGER PRIMARY KEY, name TEXT)")
+            .await
+            .unwrap();
+
+        // store_b does not exist yet, so the second statement fails and takes the third with it
+        let statements = vec![
+            "INSERT INTO store_a (id, name) VALUES (1, 'a')".to_string(),
+            "INSERT INTO store_b (id) VALUES (1)".to_string(),
+            "INSERT INTO store_a (id, name) VALUES (2, 'it''s')".to_string(),
+        ];
+        assert_eq!(database.execute_or_dead_letter(&statements).await.unwrap(), 2);
+        let dead_letters = database
+            .query("SELECT event_name, sql, error FROM dubhe_dead_letters ORDER BY id")
+            .await
+            .unwrap();
+        assert_eq!(dead_letters.len(), 2);
+        assert_eq!(dead_letters[0]["event_name"], "statement");
+        assert_eq!(dead_letters[0]["sql"], statements[1].as_str());
+        assert_eq!(dead_letters[1]["sql"], statements[2].as_str());
+        assert!(dead_letters[0]["error"].as_str().unwrap().contains("store_b"));
+        assert_eq!(database.count_rows("store_a", "").await.unwrap(), 1);
+
+        let report = database.replay_dead_letters().await.unwrap();
+        assert_eq!(report.replayed, 0);
+        assert_eq!(report.remaining, 2);
+        assert!(report.error.is_some());
+
+        database
+            .execute("CREATE TABLE store_b (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        let report = database.replay_dead_letters().await.unwrap();
+        assert_eq!(
+            report,
+            ReplayReport {
+                replayed: 2,
+                remaining: 0,
+                error: None
+            }
+        );
+        assert_eq!(database.count_rows("store_a", "").await.unwrap(), 2);
+        assert_eq!(database.count_rows("store_b", "").await.unwrap(), 1);
+        assert_eq!(database.count_rows("dubhe_dead_letters", "").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_statement_is_rolled_back_to_its_savepoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("savepoint.db").display());
+        let database = Database::new(&url).await.unwrap();
+        database
+            .execute("CREATE TABLE store_a (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        database
+            .execute(&DeadLetter::create_table_sql(Dialect::Sqlite))
+            .await
+            .unwrap();
+        let statement = |sql: &str, dead_letter: bool| IndexedStatement {
+            sql: sql.to_string(),
+            dead_letter: dead_letter.then(|| {
+                DeadLetter::new("Dubhe_Store_SetRecord", &[0x01], "").with_transaction("tx", 9)
+            }),
+        };
+
+        // The bad insert is undone on its own and recorded, the rest is committed
+        database
+            .execute_in_transaction(&[
+                statement("INSERT INTO store_a (id) VALUES (1)", true),
+                statement("INSERT INTO store_a (id, missing) VALUES (2)", true),
+                statement("INSERT INTO store_a (id) VALUES (3)", true),
+            ])
+            .await
+            .unwrap();
+        let ids = database.query("SELECT id FROM store_a ORDER BY id").await.unwrap();
+        assert_eq!(ids, vec![serde_json::json!({ "id": 1 }), serde_json::json!({ "id": 3 })]);
+        let dead_letters = database
+            .query("SELECT digest, checkpoint_timestamp_ms, error FROM dubhe_dead_letters")
+            .await
+            .unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0]["digest"], "tx");
+        assert_eq!(dead_letters[0]["checkpoint_timestamp_ms"], 9);
+        assert!(dead_letters[0]["error"].as_str().unwrap().contains("missing"));
+
+        // Without a dead letter the failure takes the whole transaction down
+        let result = database
+            .execute_in_transaction(&[
+                statement("INSERT INTO store_a (id) VALUES (4)", true),
+                statement("INSERT INTO store_a (id, missing) VALUES (5)", false),
+            ])
+            .await;
+        assert!(result.is_err());
+        assert_eq!(database.count_rows("store_a", "").await.unwrap(), 2);
+        assert_eq!(database.count_rows("dubhe_dead_letters", "").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_reads_do_not_lock_out_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal.db");
+        let url = format!("sqlite:{}?busy_timeout_ms=10000", path.display());
+        // Separate pools, like the indexer writing while GraphQL reads
+        let writer = Database::new(&url).await.unwrap();
+        let reader = std::sync::Arc::new(Database::new(&url).await.unwrap());
+        writer
+            .execute("CREATE TABLE store_counter (id INTEGER PRIMARY KEY, value INTEGER)")
+            .await
+            .unwrap();
+        let mode = reader.query("PRAGMA journal_mode").await.unwrap();
+        assert_eq!(mode[0]["journal_mode"], "wal");
+
+        let reads = tokio::spawn(async move {
+            for _ in 0..200 {
+                reader.query("SELECT COUNT(*) AS n FROM store_counter").await?;
+            }
+            anyhow::Ok(())
+        });
+        // A single attempt, so a lock error is not hidden by retries
+        let no_retry = RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::ZERO,
+        };
+        for id in 0..200 {
+            let sql = format!("INSERT INTO store_counter (id, value) VALUES ({}, {})", id, id);
+            writer.execute_with_retry(&sql, &no_retry).await.unwrap();
+        }
+        reads.await.unwrap().unwrap();
+        assert_eq!(writer.count_rows("store_counter", "").await.unwrap(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_concurrent_writes_queue_for_the_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}?busy_timeout_ms=0", dir.path().join("writer.db").display());
+        let database = std::sync::Arc::new(Database::new(&url).await.unwrap());
+        database
+            .execute("CREATE TABLE store_counter (id INTEGER PRIMARY KEY, value INTEGER)")
+            .await
+            .unwrap();
+
+        // Without a busy timeout or retries, any two writers holding the lock at once fail
+        let no_retry = RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::ZERO,
+        };
+        let writes: Vec<_> = (0..8)
+            .map(|task| {
+                let database = database.clone();
+                tokio::spawn(async move {
+                    for n in 0..25 {
+                        let statements = [task * 100 + n, task * 100 + n + 50].map(|id| {
+                            IndexedStatement {
+                                sql: format!("INSERT INTO store_counter VALUES ({}, 0)", id),
+                                dead_letter: None,
+                            }
+                        });
+                        database.execute_in_transaction(&statements).await?;
+                        let sql = format!("UPDATE store_counter SET value = 1 WHERE id = {}", n);
+                        database.execute_with_retry(&sql, &no_retry).await?;
+                    }
+                    anyhow::Ok(())
+                })
+            })
+            .collect();
+        for write in writes {
+            write.await.unwrap().unwrap();
+        }
+        assert_eq!(database.count_rows("store_counter", "").await.unwrap(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_legacy_schema_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("legacy.db").display());
+        let database = Database::new(&url).await.unwrap();
+
+        // A keyless resource as the old SQLite storage created it, without the store_ prefix
+        database
+            .execute(
+                "CREATE TABLE counter (value INTEGER, \
+                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP, \
+                 updated_at DATETIME DEFAULT CURRENT_TIMESTAMP, \
+                 last_updated_checkpoint INTEGER, is_deleted BOOLEAN DEFAULT 0, \
+                 PRIMARY KEY (value))",
+            )
+            .await
+            .unwrap();
+        database
+            .execute("INSERT INTO counter (value, last_updated_checkpoint) VALUES (7, 1)")
+            .await
+            .unwrap();
+
+        let table = TableMetadata {
+            name: "counter".to_string(),
+            table_type: "resource".to_string(),
+            fields: vec![crate::table::TableField {
+                field_name: "value".to_string(),
+                field_type: "u64".to_string(),
+                field_index: 0,
+                is_key: false,
+                is_enum: false,
+            }],
+            enums: HashMap::new(),
+            offchain: false,
+        };
+        let statements: Vec<IndexedStatement> = table
+            .legacy_schema_migration_sql(Dialect::Sqlite)
+            .into_iter()
+            .map(|sql| IndexedStatement {
+                sql,
+                dead_letter: None,
+            })
+            .collect();
+        database.execute_in_transaction(&statements).await.unwrap();
+
+        let rows = database
+            .query(
+                "SELECT unique_resource_id, value, created_at_timestamp_ms, \
+                 updated_at_timestamp_ms, last_update_digest FROM store_counter",
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["unique_resource_id"], 1);
+        assert_eq!(rows[0]["value"], 7);
+        assert!(rows[0]["created_at_timestamp_ms"].as_i64().unwrap() > 0);
+        assert!(database.query("SELECT value FROM counter").await.is_err());
+    }
+}
@@ -1,6 +1,8 @@
-use crate::db::Storage;
+use crate::db::{is_retryable_error, Storage};
+use crate::dead_letter::{DeadLetter, IndexedStatement};
+use crate::query::{BoundQuery, QueryValue};
 use crate::sql::{get_table_name, DBData};
-use crate::table::DubheConfig;
+use crate::table::{Dialect, DubheConfig};
 use crate::table::TableMetadata;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -10,113 +12,33 @@ pub struct PostgresStorage {
     pool: Pool<Postgres>,
 }
 
+/// NOTIFY channel carrying row changes of `store_*` tables, see `create_realtime_trigger`
+pub const TABLE_CHANGES_CHANNEL: &str = "dubhe_table_changes";
+
 impl PostgresStorage {
     pub async fn new(db_url: &str) -> Result<Self> {
         let pool = PgPool::connect(db_url).await?;
         Ok(Self { pool })
     }
 
+    /// Same statement as [`TableMetadata::generate_create_table_sql`]
     pub fn generate_create_table_sql(&self, table: &TableMetadata) -> String {
-        Self::generate_create_table_sql_static(table)
+        table.generate_create_table_sql()
     }
 
-    pub fn generate_create_table_sql_static(table: &TableMetadata) -> String {
-        let mut sql = String::new();
-        sql.push_str(&format!(
-            "CREATE TABLE IF NOT EXISTS store_{} (\n",
-            table.name
-        ));
-
-        let mut field_definitions = Vec::new();
-
-        // Add all fields
-        for field in &table.fields {
-            let sql_type = Self::get_sql_type_static(&field.field_type);
-            field_definitions.push(format!("    {} {}", field.field_name, sql_type));
-        }
-
-        // Add system fields for tracking
-        field_definitions.push("    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP".to_string());
-        field_definitions.push("    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP".to_string());
-        field_definitions.push("    last_updated_checkpoint BIGINT".to_string());
-        field_definitions.push("    is_deleted BOOLEAN DEFAULT FALSE".to_string());
-
-        // Join all field definitions
-        sql.push_str(&field_definitions.join(",\n"));
-
-        // Add primary key constraint
-        if table.table_type == "resource" && !table.fields.iter().any(|field| field.is_key) {
-            // Special case for resource type without key fields: set all fields as PRIMARY KEY
-            let all_field_names: Vec<String> = table
-                .fields
-                .iter()
-                .map(|field| field.field_name.clone())
-                .collect();
-
-            if !all_field_names.is_empty() {
-                sql.push_str(",\n    PRIMARY KEY (");
-                sql.push_str(&all_field_names.join(", "));
-                sql.push(')');
-            }
-        } else if table.fields.iter().any(|field| field.is_key) {
-            // Case with key fields: use key fields as PRIMARY KEY
-            let key_fields: Vec<_> = table
-                .fields
-                .iter()
-                .filter(|f| f.is_key)
-                .map(|f| f.field_name.clone())
-                .collect();
-
-            if !key_fields.is_empty() {
-                sql.push_str(",\n    PRIMARY KEY (");
-                sql.push_str(&key_fields.join(", "));
-                sql.push(')');
-            }
-        } else {
-            // Case without key fields: use non-key fields as PRIMARY KEY
-            let value_fields: Vec<_> = table
-                .fields
-                .iter()
-                .filter(|f| !f.is_key)
-                .map(|f| f.field_name.clone())
-                .collect();
-
-            if !value_fields.is_empty() {
-                sql.push_str(",\n    PRIMARY KEY (");
-                sql.push_str(&value_fields.join(", "));
-                sql.push(')');
+    /// Dry-run statements inside a transaction that is always rolled back.
+    ///
+    /// Returns an error naming the first statement the database rejects.
+    pub async fn validate_statements(&self, statements: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for sql in statements {
+            if let Err(e) = sqlx::query(sql).execute(&mut *tx).await {
+                tx.rollback().await?;
+                return Err(anyhow::anyhow!("Invalid statement `{}`: {}", sql, e));
             }
         }
-
-        sql.push_str("\n)");
-        sql
-    }
-
-    fn get_sql_type(&self, type_: &str) -> String {
-        Self::get_sql_type_static(type_)
-    }
-
-    fn get_sql_type_static(type_: &str) -> String {
-        match type_ {
-            "u8" => "SMALLINT",
-            "u16" => "INTEGER",
-            "u32" => "BIGINT",
-            "u64" => "BIGINT",
-            "u128" => "TEXT",
-            "u256" => "TEXT",
-            "vector<u8>" => "SMALLINT[]",
-            "vector<u16>" => "INTEGER[]",
-            "vector<u32>" => "BIGINT[]",
-            "vector<u64>" => "BIGINT[]",
-            "vector<u128>" => "TEXT[]",
-            "vector<u256>" => "TEXT[]",
-            "vector<bool>" => "BOOLEAN[]",
-            "vector<address>" => "TEXT[]",
-            "bool" => "BOOLEAN",
-            "address" => "TEXT",
-            _ => "TEXT", // Default for enums and other types
-        }
-        .to_string()
+        tx.rollback().await?;
+        Ok(())
     }
 
     // Setup simple logging function for debugging
@@ -217,6 +139,18 @@ impl PostgresStorage {
             
             -- 4. Send to table-specific channel
             PERFORM pg_notify('postgraphile:table:' || TG_TABLE_NAME, '{"op":"' || TG_OP || '"}');
+
+            -- 5. Send the changed row to the GraphQL subscription channel, the row is
+            --    dropped from the payload when it would exceed the NOTIFY size limit
+            payload_data := jsonb_build_object(
+                'table', table_name_without_prefix,
+                'op', TG_OP,
+                'data', CASE WHEN TG_OP = 'DELETE' THEN to_jsonb(OLD) ELSE to_jsonb(NEW) END
+            );
+            IF octet_length(payload_data::text) > 7900 THEN
+                payload_data := payload_data - 'data';
+            END IF;
+            PERFORM pg_notify('dubhe_table_changes', payload_data::text);
             
             -- Return appropriate record
             IF TG_OP = 'DELETE' THEN
@@ -255,7 +189,7 @@ impl PostgresStorage {
     pub fn generate_insert_sql_static(
         table_name: &str,
         values: &[DBData],
-        last_updated_checkpoint: u64,
+        updated_at_timestamp_ms: u64,
     ) -> String {
         // Add store_ prefix to table name for PostgreSQL
         let prefixed_table_name = format!("store_{}", table_name);
@@ -273,19 +207,13 @@ impl PostgresStorage {
 
         // Add system fields
         let mut final_column_names = column_names.clone();
-        final_column_names.push("updated_at".to_string());
-        final_column_names.push("last_updated_checkpoint".to_string());
+        final_column_names.push("updated_at_timestamp_ms".to_string());
 
         let mut final_column_values = column_values.clone();
-        final_column_values.push("CURRENT_TIMESTAMP".to_string());
-        final_column_values.push(last_updated_checkpoint.to_string());
+        final_column_values.push(updated_at_timestamp_ms.to_string());
 
         let mut final_set_clause = set_clause.clone();
-        final_set_clause.push("updated_at = CURRENT_TIMESTAMP".to_string());
-        final_set_clause.push(format!(
-            "last_updated_checkpoint = {}",
-            last_updated_checkpoint
-        ));
+        final_set_clause.push(format!("updated_at_timestamp_ms = {}", updated_at_timestamp_ms));
 
         // Build primary key columns
         let key_columns: Vec<String> = if values.iter().any(|d| d.is_primary_key) {
@@ -379,6 +307,119 @@ impl PostgresStorage {
         sql_statements
     }
 
+    /// Run `sql` with `params` bound to its `$n` placeholders. A `persistent` statement is
+    /// prepared once per connection and reused by later queries with the same SQL text.
+    pub async fn query_with_params(
+        &self,
+        sql: &str,
+        params: &[QueryValue],
+        persistent: bool,
+    ) -> Result<Vec<serde_json::Value>> {
+        let rows = Self::bind_params(sql, params, persistent)?
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(Self::rows_to_json(rows))
+    }
+
+    /// Run `queries` in one transaction, so they all read the same snapshot of the database
+    pub async fn query_batch(
+        &self,
+        queries: &[BoundQuery],
+    ) -> Result<Vec<Vec<serde_json::Value>>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries {
+            let rows = Self::bind_params(&query.sql, &query.params, query.persistent)?
+                .fetch_all(&mut *tx)
+                .await?;
+            results.push(Self::rows_to_json(rows));
+        }
+        tx.commit().await?;
+        Ok(results)
+    }
+
+    /// Execute `statements` in one transaction. A failing statement with a dead letter is
+    /// rolled back to a savepoint and its dead letter recorded instead; any other failure
+    /// rolls back the whole transaction.
+    pub async fn execute_in_transaction(&self, statements: &[IndexedStatement]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for statement in statements {
+            if statement.dead_letter.is_some() {
+                sqlx::query("SAVEPOINT dubhe_event").execute(&mut *tx).await?;
+            }
+            let error = match sqlx::query(&statement.sql).execute(&mut *tx).await {
+                Ok(_) => {
+                    if statement.dead_letter.is_some() {
+                        sqlx::query("RELEASE SAVEPOINT dubhe_event").execute(&mut *tx).await?;
+                    }
+                    continue;
+                }
+                Err(e) => anyhow::Error::from(e),
+            };
+            match &statement.dead_letter {
+                // Transient failures fail the transaction, so it is retried as a whole
+                Some(dead_letter) if !is_retryable_error(&error) => {
+                    sqlx::query("ROLLBACK TO SAVEPOINT dubhe_event").execute(&mut *tx).await?;
+                    log::warn!(
+                        "⚠️ Dead-lettering {} in tx {}: {}",
+                        dead_letter.event_name, dead_letter.digest, error
+                    );
+                    let mut dead_letter = dead_letter.clone();
+                    dead_letter.error = error.to_string();
+                    sqlx::query(&dead_letter.insert_sql()).execute(&mut *tx).await?;
+                }
+                _ => {
+                    tx.rollback().await?;
+                    // Keep the sqlx error reachable, so transient failures are still retried
+                    return Err(error.context(format!("Statement `{}` failed", statement.sql)));
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    fn bind_params<'q>(
+        sql: &'q str,
+        params: &[QueryValue],
+        persistent: bool,
+    ) -> Result<sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>> {
+        let mut query = sqlx::query(sql).persistent(persistent);
+        for param in params {
+            query = match param {
+                QueryValue::String(value) => query.bind(value.clone()),
+                QueryValue::Integer(value) => query.bind(*value),
+                QueryValue::Float(value) => query.bind(*value),
+                QueryValue::Boolean(value) => query.bind(*value),
+                QueryValue::Null => query.bind(None::<String>),
+                _ => return Err(anyhow::anyhow!("Cannot bind an array as one parameter")),
+            };
+        }
+        Ok(query)
+    }
+
+    fn rows_to_json(rows: Vec<sqlx::postgres::PgRow>) -> Vec<serde_json::Value> {
+        let mut results = Vec::new();
+        for row in rows {
+            let mut row_data = serde_json::Map::new();
+
+            // Get column names and values
+            for (i, column) in row.columns().iter().enumerate() {
+                let column_name = column.name();
+
+                // Use the new column_to_json_value method
+                let json_value = Self::column_to_json_value(&row, i);
+                row_data.insert(column_name.to_string(), json_value);
+            }
+
+            results.push(serde_json::Value::Object(row_data));
+        }
+        results
+    }
+
     fn column_to_json_value(row: &sqlx::postgres::PgRow, column_index: usize) -> serde_json::Value {
         if let Ok(value) = row.try_get::<serde_json::Value, _>(column_index) {
             return value;
@@ -407,6 +448,10 @@ impl PostgresStorage {
                 serde_json::Number::from_f64(value).unwrap_or(serde_json::Number::from(0)),
             );
         }
+        // NUMERIC columns of decimal fields, as exact text
+        if let Ok(value) = row.try_get::<sqlx::types::BigDecimal, _>(column_index) {
+            return serde_json::Value::String(value.to_string());
+        }
         if let Ok(value) = row.try_get::<String, _>(column_index) {
             return serde_json::Value::String(value);
         }
@@ -429,32 +474,34 @@ impl Storage for PostgresStorage {
     }
 
     async fn create_tables(&self, config: &DubheConfig) -> Result<()> {
-        self.execute(
-            &r#"CREATE TABLE IF NOT EXISTS table_fields (
+        let mut statements = vec![r#"CREATE TABLE IF NOT EXISTS table_fields (
             table_name VARCHAR(255),
             field_name VARCHAR(255),
             field_type VARCHAR(50),
             field_index INTEGER,
             is_key BOOLEAN,
             PRIMARY KEY (table_name, field_name)
-        )"#,
-        )
-        .await?;
+        )"#
+        .to_string(), DeadLetter::create_table_sql(Dialect::Postgres)];
 
-        for field in &config.fields {
-            self.execute(&format!(
+        for field in config.fields.iter().filter(|f| !config.is_excluded_table(&f.table)) {
+            statements.push(format!(
                 "INSERT INTO table_fields (table_name, field_name, field_type, field_index, is_key) VALUES ('{}', '{}', '{}', '{}', {})",
                 field.table, field.name, field.move_type, field.index, field.primary_key
-            )).await?;
+            ));
         }
 
-        let create_table_sqls = config.create_tables_sql();
-        for sql in create_table_sqls {
-            self.execute(&sql).await?;
+        statements.extend(config.create_tables_sql());
+
+        // Dry-run everything first so a bad config fails before any table is created
+        self.validate_statements(&statements).await?;
+
+        for sql in &statements {
+            self.execute(sql).await?;
         }
 
-        for table in &config.tables {
-            let table_name = format!("store_{}", table.name);
+        for table in config.tables.iter().filter(|t| !config.is_excluded_table(&t.name)) {
+            let table_name = config.store_table_name(Some(&config.original_package_id), &table.name);
             self.setup_simple_logging().await?;
             self.create_realtime_trigger(&table_name).await?;
         }
@@ -470,12 +517,12 @@ impl Storage for PostgresStorage {
         &self,
         table_name: &str,
         values: Vec<DBData>,
-        last_updated_checkpoint: u64,
+        updated_at_timestamp_ms: u64,
     ) -> Result<()> {
         let sql = PostgresStorage::generate_insert_sql_static(
             table_name,
             &values,
-            last_updated_checkpoint,
+            updated_at_timestamp_ms,
         );
         log::info!("Generated UPSERT SQL: {}", sql);
 
@@ -511,24 +558,7 @@ impl Storage for PostgresStorage {
 
     async fn query(&self, sql: &str) -> Result<Vec<serde_json::Value>> {
         let rows = sqlx::query(sql).fetch_all(&self.pool).await?;
-
-        let mut results = Vec::new();
-        for row in rows {
-            let mut row_data = serde_json::Map::new();
-
-            // Get column names and values
-            for (i, column) in row.columns().iter().enumerate() {
-                let column_name = column.name();
-
-                // Use the new column_to_json_value method
-                let json_value = Self::column_to_json_value(&row, i);
-                row_data.insert(column_name.to_string(), json_value);
-            }
-
-            results.push(serde_json::Value::Object(row_data));
-        }
-
-        Ok(results)
+        Ok(Self::rows_to_json(rows))
     }
 
     fn get_sql_type(&self, type_: &str) -> String {
@@ -639,18 +669,14 @@ mod tests {
             offchain: false,
         };
 
-        // Create a dummy storage instance for testing
-        // We'll use a mock approach since we can't easily create a real pool in tests
-        let sql = PostgresStorage::generate_create_table_sql_static(&table);
-
-        println!("Generated SQL: {}", sql);
+        let sql = table.generate_create_table_sql();
 
-        // Verify SQL contains store_ prefix and all fields as PRIMARY KEY
-        assert!(sql.contains("CREATE TABLE IF NOT EXISTS store_counter"));
-        assert!(sql.contains("PRIMARY KEY (value)"));
-        assert!(sql.contains("value BIGINT"));
-        assert!(sql.contains("created_at TIMESTAMP"));
-        assert!(sql.contains("updated_at TIMESTAMP"));
+        // A keyless onchain resource holds a single row, like in the indexer's schema
+        assert!(sql.starts_with("CREATE TABLE IF NOT EXISTS store_counter ("));
+        assert!(sql.contains("unique_resource_id INTEGER DEFAULT 1 PRIMARY KEY"));
+        assert!(sql.contains("\"value\" INTEGER"));
+        assert!(sql.contains("created_at_timestamp_ms BIGINT"));
+        assert!(!sql.contains("last_updated_checkpoint"));
     }
 
     #[test]
@@ -684,8 +710,7 @@ mod tests {
         assert!(sql.contains("ON CONFLICT (id)"));
         assert!(sql.contains("DO UPDATE SET"));
         assert!(sql.contains("name = 'test'"));
-        assert!(sql.contains("last_updated_checkpoint = 1000"));
-        assert!(sql.contains("updated_at = CURRENT_TIMESTAMP"));
+        assert!(sql.contains("updated_at_timestamp_ms = 1000"));
     }
 
     #[test]
@@ -711,7 +736,6 @@ mod tests {
         assert!(sql.contains("store_resource0"));
         assert!(sql.contains("1,"));
         assert!(sql.contains("4255"));
-        assert!(sql.contains("CURRENT_TIMESTAMP"));
 
         // Should NOT contain ON CONFLICT for resource tables without explicit keys
         assert!(!sql.contains("ON CONFLICT"));
@@ -769,4 +793,110 @@ mod tests {
         // 3. Each operation ensures only one record exists in the table
         // 4. The table will always have exactly one record, regardless of field values
     }
+
+    #[tokio::test]
+    #[ignore = "requires a running PostgreSQL instance"]
+    async fn test_create_tables_rejects_malformed_field_type() {
+        use crate::table::{Field, Table};
+
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or("postgres://postgres@localhost:5432/postgres".to_string());
+        let storage = PostgresStorage::new(&url).await.unwrap();
+        storage.clear().await.unwrap();
+
+        let mut config = DubheConfig::new(
+            "0x1".to_string(),
+            "0x2".to_string(),
+            "0x3".to_string(),
+            "0".to_string(),
+        );
+        for name in ["good", "broken"] {
+            config.push_table(Table {
+                name: name.to_string(),
+                offchain: false,
+                component: true,
+                push_min_interval_ms: None,
+                conflict_policy: Default::default(),
+            });
+        }
+        let mut id = Field::new("good".to_string(), "id".to_string());
+        id.index(0).move_type("u64".to_string()).db_type("BIGINT".to_string()).primary_key(true);
+        config.push_field(id);
+        let mut broken = Field::new("broken".to_string(), "id".to_string());
+        broken.index(0).move_type("u64".to_string()).db_type("NOT_A_TYPE".to_string()).primary_key(true);
+        config.push_field(broken);
+
+        let err = storage.create_tables(&config).await.unwrap_err();
+        assert!(err.to_string().contains("CREATE TABLE IF NOT EXISTS store_broken"));
+
+        // Nothing may have been created, not even the tables before the broken one
+        let created: Vec<String> = sqlx::query_scalar(
+            "SELECT tablename::TEXT FROM pg_tables WHERE schemaname = 'public' AND (tablename = 'table_fields' OR tablename LIKE 'store_%')",
+        )
+        .fetch_all(storage.pool())
+        .await
+        .unwrap();
+        assert!(created.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running PostgreSQL instance"]
+    async fn test_legacy_schema_migration() {
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or("postgres://postgres@localhost:5432/postgres".to_string());
+        let storage = PostgresStorage::new(&url).await.unwrap();
+        storage.clear().await.unwrap();
+
+        // A keyless resource as the old TableMetadata schema created it
+        storage
+            .execute(
+                "CREATE TABLE store_counter (value BIGINT, \
+                 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, \
+                 updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, \
+                 last_updated_checkpoint BIGINT, is_deleted BOOLEAN DEFAULT FALSE, \
+                 PRIMARY KEY (value))",
+            )
+            .await
+            .unwrap();
+        storage
+            .execute("INSERT INTO store_counter (value, last_updated_checkpoint) VALUES (7, 1)")
+            .await
+            .unwrap();
+
+        let table = TableMetadata {
+            name: "counter".to_string(),
+            table_type: "resource".to_string(),
+            fields: vec![TableField {
+                field_name: "value".to_string(),
+                field_type: "u64".to_string(),
+                field_index: 0,
+                is_key: false,
+                is_enum: false,
+            }],
+            enums: HashMap::new(),
+            offchain: false,
+        };
+        let statements: Vec<IndexedStatement> = table
+            .legacy_schema_migration_sql(Dialect::Postgres)
+            .into_iter()
+            .map(|sql| IndexedStatement {
+                sql,
+                dead_letter: None,
+            })
+            .collect();
+        storage.execute_in_transaction(&statements).await.unwrap();
+
+        let rows = storage
+            .query(
+                "SELECT unique_resource_id, value, created_at_timestamp_ms, \
+                 updated_at_timestamp_ms, last_update_digest, is_deleted FROM store_counter",
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["unique_resource_id"], 1);
+        assert_eq!(rows[0]["value"], 7);
+        assert!(rows[0]["created_at_timestamp_ms"].as_i64().unwrap() > 0);
+        assert!(storage.query("SELECT last_updated_checkpoint FROM store_counter").await.is_err());
+    }
 }
@@ -1,14 +1,52 @@
-use crate::db::Storage;
+use crate::db::{is_retryable_error, Storage};
+use crate::dead_letter::IndexedStatement;
+use crate::query::{BoundQuery, QueryValue};
 use crate::sql::DBData;
 use crate::table::DubheConfig;
 use crate::table::TableMetadata;
 use anyhow::Result;
 use async_trait::async_trait;
-use sqlx::{Column, Pool, Row, Sqlite, SqlitePool, TypeInfo};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{Column, Pool, Row, Sqlite, TypeInfo};
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long a connection waits for another connection's lock before failing with
+/// `database is locked`, unless the URL sets `busy_timeout_ms`
+pub const DEFAULT_SQLITE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Split the `busy_timeout_ms` query parameter off a SQLite URL, since sqlx does not know it
+fn take_busy_timeout(db_url: &str) -> Result<(String, Duration)> {
+    let Some((base, query)) = db_url.split_once('?') else {
+        return Ok((db_url.to_string(), DEFAULT_SQLITE_BUSY_TIMEOUT));
+    };
+    let mut busy_timeout = DEFAULT_SQLITE_BUSY_TIMEOUT;
+    let mut params = Vec::new();
+    for param in query.split('&') {
+        match param.strip_prefix("busy_timeout_ms=") {
+            Some(ms) => {
+                let ms = ms
+                    .parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("Invalid busy_timeout_ms: {}", ms))?;
+                busy_timeout = Duration::from_millis(ms);
+            }
+            None => params.push(param),
+        }
+    }
+    if params.is_empty() {
+        Ok((base.to_string(), busy_timeout))
+    } else {
+        Ok((format!("{}?{}", base, params.join("&")), busy_timeout))
+    }
+}
 
 pub struct SqliteStorage {
+    /// Connections for reads
     pool: Pool<Sqlite>,
+    /// The one connection every write goes through. SQLite allows a single writer at a
+    /// time, so writers queue here instead of contending for the database lock.
+    writer: Pool<Sqlite>,
 }
 
 impl SqliteStorage {
@@ -23,10 +61,13 @@ impl SqliteStorage {
                 "Database URL cannot be empty or must start with 'sqlite:'"
             ));
         }
+        let (db_url, busy_timeout) = take_busy_timeout(db_url)?;
+        let db_url = db_url.as_str();
         if db_url == "sqlite::memory:" {
             log::info!("Using in-memory SQLite database");
         } else {
             let db_file_path = db_url.strip_prefix("sqlite:").unwrap_or(db_url);
+            let db_file_path = db_file_path.split('?').next().unwrap_or(db_file_path);
             log::info!("Using SQLite database file at {}", db_file_path);
             // Check if the file exists, if not it will be created
             if !std::path::Path::new(db_file_path).exists() {
@@ -39,101 +80,134 @@ impl SqliteStorage {
                 log::info!("Created directory for SQLite database at {}", db_file_path);
             }
         }
-        let pool = SqlitePool::connect(db_url).await?;
-        Ok(Self { pool })
+        // WAL lets GraphQL readers run while the indexer writes. Writes of this storage take
+        // turns on its writer connection; other processes writing the same file are waited
+        // for up to busy_timeout instead of failing right away.
+        let options = SqliteConnectOptions::from_str(db_url)?
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(busy_timeout);
+        let writer = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options.clone())
+            .await?;
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        Ok(Self { pool, writer })
     }
 
-    fn generate_create_table_sql(&self, table: &TableMetadata) -> String {
-        let mut sql = String::new();
-        sql.push_str(&format!("CREATE TABLE IF NOT EXISTS {} (\n", table.name));
+    /// Close the reader and writer connections
+    pub async fn close(&self) {
+        self.pool.close().await;
+        self.writer.close().await;
+    }
 
-        let mut field_definitions = Vec::new();
+    /// Run `sql` with `params` bound to its `?` placeholders. A `persistent` statement is
+    /// prepared once per connection and reused by later queries with the same SQL text.
+    pub async fn query_with_params(
+        &self,
+        sql: &str,
+        params: &[QueryValue],
+        persistent: bool,
+    ) -> Result<Vec<serde_json::Value>> {
+        let rows = Self::bind_params(sql, params, persistent)?
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(self.rows_to_json(rows))
+    }
 
-        // Add all fields
-        for field in &table.fields {
-            let sql_type = self.get_sql_type(&field.field_type);
-            field_definitions.push(format!("    {} {}", field.field_name, sql_type));
+    /// Run `queries` in one transaction, so they all read the same snapshot of the database
+    pub async fn query_batch(
+        &self,
+        queries: &[BoundQuery],
+    ) -> Result<Vec<Vec<serde_json::Value>>> {
+        let mut tx = self.pool.begin().await?;
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries {
+            let rows = Self::bind_params(&query.sql, &query.params, query.persistent)?
+                .fetch_all(&mut *tx)
+                .await?;
+            results.push(self.rows_to_json(rows));
         }
+        tx.commit().await?;
+        Ok(results)
+    }
 
-        // Add system fields for tracking
-        field_definitions.push("    created_at DATETIME DEFAULT CURRENT_TIMESTAMP".to_string());
-        field_definitions.push("    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP".to_string());
-        field_definitions.push("    last_updated_checkpoint INTEGER".to_string());
-        field_definitions.push("    is_deleted BOOLEAN DEFAULT 0".to_string());
-
-        // Join all field definitions
-        sql.push_str(&field_definitions.join(",\n"));
-
-        // Add primary key constraint
-        if table.table_type == "resource" && !table.fields.iter().any(|field| field.is_key) {
-            // Special case for resource type without key fields: set all fields as PRIMARY KEY
-            let all_field_names: Vec<String> = table
-                .fields
-                .iter()
-                .map(|field| field.field_name.clone())
-                .collect();
-
-            if !all_field_names.is_empty() {
-                sql.push_str(",\n    PRIMARY KEY (");
-                sql.push_str(&all_field_names.join(", "));
-                sql.push(')');
-            }
-        } else if table.fields.iter().any(|field| field.is_key) {
-            // Case with key fields: use key fields as PRIMARY KEY
-            let key_fields: Vec<_> = table
-                .fields
-                .iter()
-                .filter(|f| f.is_key)
-                .map(|f| f.field_name.clone())
-                .collect();
-
-            if !key_fields.is_empty() {
-                sql.push_str(",\n    PRIMARY KEY (");
-                sql.push_str(&key_fields.join(", "));
-                sql.push(')');
+    /// Execute `statements` in one transaction. A failing statement with a dead letter is
+    /// rolled back to a savepoint and its dead letter recorded instead; any other failure
+    /// rolls back the whole transaction.
+    pub async fn execute_in_transaction(&self, statements: &[IndexedStatement]) -> Result<()> {
+        let mut tx = self.writer.begin().await?;
+        for statement in statements {
+            if statement.dead_letter.is_some() {
+                sqlx::query("SAVEPOINT dubhe_event").execute(&mut *tx).await?;
             }
-        } else {
-            // Case without key fields: use non-key fields as PRIMARY KEY
-            let value_fields: Vec<_> = table
-                .fields
-                .iter()
-                .filter(|f| !f.is_key)
-                .map(|f| f.field_name.clone())
-                .collect();
-
-            if !value_fields.is_empty() {
-                sql.push_str(",\n    PRIMARY KEY (");
-                sql.push_str(&value_fields.join(", "));
-                sql.push(')');
+            let error = match sqlx::query(&statement.sql).execute(&mut *tx).await {
+                Ok(_) => {
+                    if statement.dead_letter.is_some() {
+                        sqlx::query("RELEASE SAVEPOINT dubhe_event").execute(&mut *tx).await?;
+                    }
+                    continue;
+                }
+                Err(e) => anyhow::Error::from(e),
+            };
+            match &statement.dead_letter {
+                // Transient failures fail the transaction, so it is retried as a whole
+                Some(dead_letter) if !is_retryable_error(&error) => {
+                    sqlx::query("ROLLBACK TO SAVEPOINT dubhe_event").execute(&mut *tx).await?;
+                    log::warn!(
+                        "⚠️ Dead-lettering {} in tx {}: {}",
+                        dead_letter.event_name, dead_letter.digest, error
+                    );
+                    let mut dead_letter = dead_letter.clone();
+                    dead_letter.error = error.to_string();
+                    sqlx::query(&dead_letter.insert_sql()).execute(&mut *tx).await?;
+                }
+                _ => {
+                    tx.rollback().await?;
+                    // Keep the sqlx error reachable, so transient failures are still retried
+                    return Err(error.context(format!("Statement `{}` failed", statement.sql)));
+                }
             }
         }
+        tx.commit().await?;
+        Ok(())
+    }
 
-        sql.push_str("\n)");
-        sql
+    fn bind_params<'q>(
+        sql: &'q str,
+        params: &[QueryValue],
+        persistent: bool,
+    ) -> Result<sqlx::query::Query<'q, Sqlite, sqlx::sqlite::SqliteArguments<'q>>> {
+        let mut query = sqlx::query(sql).persistent(persistent);
+        for param in params {
+            query = match param {
+                QueryValue::String(value) => query.bind(value.clone()),
+                QueryValue::Integer(value) => query.bind(*value),
+                QueryValue::Float(value) => query.bind(*value),
+                QueryValue::Boolean(value) => query.bind(*value),
+                QueryValue::Null => query.bind(None::<String>),
+                _ => return Err(anyhow::anyhow!("Cannot bind an array as one parameter")),
+            };
+        }
+        Ok(query)
     }
 
-    fn get_sql_type(&self, type_: &str) -> String {
-        let sql_type = match type_ {
-            "u8" => "INTEGER",
-            "u16" => "INTEGER",
-            "u32" => "INTEGER",
-            "u64" => "TEXT",
-            "u128" => "TEXT",
-            "u256" => "TEXT",
-            "address" => "TEXT",
-            "String" => "TEXT",
-            "bool" => "BOOLEAN",
-            "vector<u8>" => "TEXT",
-            "vector<u16>" => "TEXT",
-            "vector<u32>" => "TEXT",
-            "vector<u64>" => "TEXT",
-            "vector<u128>" => "TEXT",
-            "vector<u256>" => "TEXT",
-            "vector<bool>" => "TEXT",
-            "vector<address>" => "TEXT",
-            _ => "TEXT",
-        };
-        sql_type.to_string()
+    fn rows_to_json(&self, rows: Vec<sqlx::sqlite::SqliteRow>) -> Vec<serde_json::Value> {
+        let mut results = Vec::new();
+        for row in rows {
+            let mut row_data = serde_json::Map::new();
+
+            // Get column names and values
+            for (i, column) in row.columns().iter().enumerate() {
+                let column_name = column.name();
+
+                // Use the new column_to_json_value method
+                let json_value = self.column_to_json_value(&row, i);
+                row_data.insert(column_name.to_string(), json_value);
+            }
+
+            results.push(serde_json::Value::Object(row_data));
+        }
+        results
     }
 
     fn column_to_json_value(
@@ -209,7 +283,7 @@ impl Storage for SqliteStorage {
     }
 
     async fn execute(&self, sql: &str) -> Result<()> {
-        sqlx::query(sql).execute(&self.pool).await?;
+        sqlx::query(sql).execute(&self.writer).await?;
         Ok(())
     }
 
@@ -218,15 +292,18 @@ impl Storage for SqliteStorage {
     }
 
     fn generate_create_table_sql(&self, table: &TableMetadata) -> String {
-        SqliteStorage::generate_create_table_sql(self, table)
+        table.generate_create_table_sql()
     }
 
     async fn insert(
         &self,
         table_name: &str,
         values: Vec<DBData>,
-        last_updated_checkpoint: u64,
+        updated_at_timestamp_ms: u64,
     ) -> Result<()> {
+        // Same store_ prefix as the tables `generate_create_table_sql` creates
+        let table_name = format!("store_{}", table_name);
+
         // Build column names and values
         let column_names: Vec<String> = values.iter().map(|d| d.column_name.clone()).collect();
         let column_values: Vec<String> =
@@ -253,19 +330,13 @@ impl Storage for SqliteStorage {
 
         // Add system fields
         let mut final_column_names = column_names.clone();
-        final_column_names.push("updated_at".to_string());
-        final_column_names.push("last_updated_checkpoint".to_string());
+        final_column_names.push("updated_at_timestamp_ms".to_string());
 
         let mut final_column_values = column_values.clone();
-        final_column_values.push("CURRENT_TIMESTAMP".to_string());
-        final_column_values.push(last_updated_checkpoint.to_string());
+        final_column_values.push(updated_at_timestamp_ms.to_string());
 
         let mut final_set_clause = set_clause.clone();
-        final_set_clause.push("updated_at = CURRENT_TIMESTAMP".to_string());
-        final_set_clause.push(format!(
-            "last_updated_checkpoint = {}",
-            last_updated_checkpoint
-        ));
+        final_set_clause.push(format!("updated_at_timestamp_ms = {}", updated_at_timestamp_ms));
 
         // Build UPSERT SQL statement
         let sql = if !key_columns.is_empty() {
@@ -294,24 +365,7 @@ impl Storage for SqliteStorage {
 
     async fn query(&self, sql: &str) -> Result<Vec<serde_json::Value>> {
         let rows = sqlx::query(sql).fetch_all(&self.pool).await?;
-
-        let mut results = Vec::new();
-        for row in rows {
-            let mut row_data = serde_json::Map::new();
-
-            // Get column names and values
-            for (i, column) in row.columns().iter().enumerate() {
-                let column_name = column.name();
-
-                // Use the new column_to_json_value method
-                let json_value = self.column_to_json_value(&row, i);
-                row_data.insert(column_name.to_string(), json_value);
-            }
-
-            results.push(serde_json::Value::Object(row_data));
-        }
-
-        Ok(results)
+        Ok(self.rows_to_json(rows))
     }
 
     fn get_sql_type(&self, type_: &str) -> String {
@@ -25,7 +25,7 @@ pub trait Storage: Send + Sync {
         &self,
         table_name: &str,
         values: Vec<DBData>,
-        last_updated_checkpoint: u64,
+        updated_at_timestamp_ms: u64,
     ) -> Result<()>;
 
     /// Execute raw SQL query and return results
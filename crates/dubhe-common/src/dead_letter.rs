@@ -0,0 +1,149 @@
+use crate::table::Dialect;
+use anyhow::Result;
+use std::str::FromStr;
+
+/// Table receiving events that could not be indexed and generated SQL statements that failed
+/// to execute. Rows with a `sql` statement are replayed in `id` order.
+pub const DEAD_LETTERS_TABLE: &str = "dubhe_dead_letters";
+
+/// `event_name` of a dead letter holding a failed statement rather than an event
+pub const STATEMENT_EVENT_NAME: &str = "statement";
+
+/// What the indexer does with an event it cannot convert or store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeadLetterPolicy {
+    /// Record the event in [`DEAD_LETTERS_TABLE`] and keep indexing
+    #[default]
+    Skip,
+    /// Stop indexing with the error
+    Halt,
+}
+
+impl FromStr for DeadLetterPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(DeadLetterPolicy::Skip),
+            "halt" => Ok(DeadLetterPolicy::Halt),
+            _ => Err(anyhow::anyhow!(
+                "Invalid dead letter policy '{}', expected 'skip' or 'halt'",
+                s
+            )),
+        }
+    }
+}
+
+/// An event that failed conversion or SQL execution, kept with its raw payload for replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub event_name: String,
+    /// BCS contents of the event as emitted on chain
+    pub payload: Vec<u8>,
+    pub error: String,
+    pub digest: String,
+    pub checkpoint_timestamp_ms: u64,
+    /// Statement to re-execute on replay, see [`DeadLetter::statement`]
+    pub sql: Option<String>,
+}
+
+impl DeadLetter {
+    pub fn new(event_name: &str, payload: &[u8], error: impl ToString) -> Self {
+        Self {
+            event_name: event_name.to_string(),
+            payload: payload.to_vec(),
+            error: error.to_string(),
+            digest: String::new(),
+            checkpoint_timestamp_ms: 0,
+            sql: None,
+        }
+    }
+
+    /// A generated statement that failed to execute, kept so it can be replayed
+    pub fn statement(sql: &str, error: impl ToString) -> Self {
+        let mut dead_letter = Self::new(STATEMENT_EVENT_NAME, &[], error);
+        dead_letter.sql = Some(sql.to_string());
+        dead_letter
+    }
+
+    pub fn with_transaction(mut self, digest: &str, checkpoint_timestamp_ms: u64) -> Self {
+        self.digest = digest.to_string();
+        self.checkpoint_timestamp_ms = checkpoint_timestamp_ms;
+        self
+    }
+
+    /// Create the dead letter table
+    pub fn create_table_sql(dialect: Dialect) -> String {
+        let id_column = match dialect {
+            Dialect::Sqlite => "id INTEGER PRIMARY KEY AUTOINCREMENT",
+            Dialect::Postgres => "id BIGSERIAL PRIMARY KEY",
+        };
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             {},\
+             event_name TEXT NOT NULL,\
+             payload TEXT NOT NULL,\
+             error TEXT NOT NULL,\
+             digest TEXT NOT NULL,\
+             checkpoint_timestamp_ms BIGINT NOT NULL,\
+             replayed BOOLEAN DEFAULT FALSE,\
+             sql TEXT);",
+            DEAD_LETTERS_TABLE, id_column
+        )
+    }
+
+    /// Insert this dead letter; the payload is stored as `0x`-prefixed hex
+    pub fn insert_sql(&self) -> String {
+        format!(
+            "INSERT INTO {} (event_name, payload, error, digest, checkpoint_timestamp_ms, sql) VALUES ('{}', '0x{}', '{}', '{}', {}, {});",
+            DEAD_LETTERS_TABLE,
+            escape(&self.event_name),
+            hex::encode(&self.payload),
+            escape(&self.error),
+            escape(&self.digest),
+            self.checkpoint_timestamp_ms,
+            match &self.sql {
+                Some(sql) => format!("'{}'", escape(sql)),
+                None => "NULL".to_string(),
+            }
+        )
+    }
+}
+
+/// SQL for one event, with the dead letter to record instead if executing it fails
+#[derive(Debug, Clone)]
+pub struct IndexedStatement {
+    pub sql: String,
+    /// `None` when a failure should halt indexing
+    pub dead_letter: Option<DeadLetter>,
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('\'', "''")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_letter_policy_from_str() {
+        assert_eq!("skip".parse::<DeadLetterPolicy>().unwrap(), DeadLetterPolicy::Skip);
+        assert_eq!("HALT".parse::<DeadLetterPolicy>().unwrap(), DeadLetterPolicy::Halt);
+        assert!("retry".parse::<DeadLetterPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_insert_sql_escapes_error() {
+        let sql = DeadLetter::new("Dubhe_Store_SetRecord", &[0xab, 0x01], "can't parse")
+            .with_transaction("digest", 7)
+            .insert_sql();
+        assert!(sql.contains("'0xab01'"));
+        assert!(sql.contains("'can''t parse'"));
+        assert!(sql.ends_with(", 7, NULL);"));
+
+        let sql = DeadLetter::statement("UPDATE t SET a = 'b' WHERE c = 1", "locked").insert_sql();
+        assert!(sql.contains("'statement', '0x', 'locked'"));
+        assert!(sql.ends_with(", 0, 'UPDATE t SET a = ''b'' WHERE c = 1');"));
+    }
+}
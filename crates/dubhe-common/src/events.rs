@@ -129,3 +129,15 @@ impl Event {
         }
     }
 }
+
+/// A store event tagged with its sequence number among the events of its transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencedEvent {
+    pub event_seq: u64,
+    pub event: Event,
+}
+
+/// Order events by emission, so e.g. a delete emitted after a set of the same key is applied last
+pub fn sort_by_event_sequence(events: &mut [SequencedEvent]) {
+    events.sort_by_key(|event| event.event_seq);
+}
@@ -1,14 +1,21 @@
+mod csv_import;
 mod db;
+mod dead_letter;
 mod events;
+mod metrics;
 mod primitives;
 mod proto_converter;
 mod query;
 mod sql;
 mod table;
+mod throttle;
 
 pub use db::*;
+pub use dead_letter::*;
 pub use events::*;
+pub use metrics::*;
 pub use proto_converter::*;
 pub use query::*;
 pub use sql::*;
 pub use table::*;
+pub use throttle::*;
@@ -0,0 +1,62 @@
+use crate::events::Event;
+use crate::table::{DubheConfig, SkipReason};
+use anyhow::Result;
+use prometheus::{Encoder, IntCounterVec, IntGauge, Opts, TextEncoder};
+use std::sync::OnceLock;
+
+static SKIPPED_EVENTS: OnceLock<IntCounterVec> = OnceLock::new();
+static SQL_BUFFER_FILL: OnceLock<IntGauge> = OnceLock::new();
+
+/// `dubhe_skipped_events_total{reason}`, registered in the default prometheus registry
+pub fn skipped_events() -> &'static IntCounterVec {
+    SKIPPED_EVENTS.get_or_init(|| {
+        let counter = IntCounterVec::new(
+            Opts::new(
+                "dubhe_skipped_events_total",
+                "Store events dropped before indexing because they do not match the config",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(counter.clone())).unwrap();
+        counter
+    })
+}
+
+/// `dubhe_sql_buffer_fill`, the statement batches waiting in the [`crate::SqlWriteBuffer`]
+pub fn sql_buffer_fill() -> &'static IntGauge {
+    SQL_BUFFER_FILL.get_or_init(|| {
+        let gauge = IntGauge::new(
+            "dubhe_sql_buffer_fill",
+            "Statement batches waiting in the SQL write buffer for the database writer",
+        )
+        .unwrap();
+        prometheus::register(Box::new(gauge.clone())).unwrap();
+        gauge
+    })
+}
+
+/// Count `event` as skipped if `config` drops it for a [`SkipReason`]. Call this where an event
+/// rejected by [`DubheConfig::can_convert_event_to_sql`] is dropped.
+pub fn record_skipped_event(config: &DubheConfig, event: &Event) {
+    if let Some(reason) = config.skip_reason(event) {
+        log::debug!(
+            "Skipped event of table {} ({}): {}",
+            event.table_id(),
+            event.dapp_key(),
+            reason.as_str()
+        );
+        skipped_events().with_label_values(&[reason.as_str()]).inc();
+    }
+}
+
+/// Metrics of the default registry in the prometheus text format
+pub fn encode_metrics() -> Result<String> {
+    // Register the counters even before the first skip so they are always exported
+    skipped_events();
+    sql_buffer_fill();
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
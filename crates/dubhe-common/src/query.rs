@@ -1,6 +1,10 @@
+use crate::table::quote_identifier;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Condition hiding soft-deleted rows, appended to every query unless deleted rows are requested
+pub const SOFT_DELETE_CONDITION: &str = "is_deleted = FALSE";
+
 /// Query operator types for filtering data
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum QueryOperator {
@@ -37,6 +41,63 @@ pub enum SortDirection {
     Desc,
 }
 
+/// Aggregate function computed per group of a grouped query
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Number of rows
+    Count,
+    /// Sum of a numeric column
+    Sum,
+    /// Average of a numeric column
+    Avg,
+    /// Smallest value of a numeric column
+    Min,
+    /// Largest value of a numeric column
+    Max,
+}
+
+impl Aggregate {
+    /// Whether the aggregate reads a column, which then has to be numeric
+    pub fn needs_column(&self) -> bool {
+        !matches!(self, Aggregate::Count)
+    }
+
+    /// SQL expression of the aggregate over `column`
+    pub fn to_sql(&self, column: Option<&str>) -> anyhow::Result<String> {
+        let function = match self {
+            Aggregate::Count => return Ok("COUNT(*)".to_string()),
+            Aggregate::Sum => "SUM",
+            Aggregate::Avg => "AVG",
+            Aggregate::Min => "MIN",
+            Aggregate::Max => "MAX",
+        };
+        let column =
+            column.ok_or_else(|| anyhow::anyhow!("{} needs a column to aggregate", function))?;
+        Ok(format!("{}({})", function, column))
+    }
+}
+
+/// `field` as it is written in SQL: a plain column name is quoted with [`quote_identifier`],
+/// so columns named after keywords such as `from` work, and an expression such as
+/// `COUNT(*)` is kept as it is
+fn column_sql(field: &str) -> String {
+    let is_column = field
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_column {
+        quote_identifier(field)
+    } else {
+        field.to_string()
+    }
+}
+
+/// Column holding the group of each row of [`QueryBuilder::aggregate_by`]
+pub const GROUP_KEY_COLUMN: &str = "group_key";
+/// Column holding the aggregate of each row of [`QueryBuilder::aggregate_by`]
+pub const GROUP_VALUE_COLUMN: &str = "group_value";
+
 /// Filter condition for querying data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilterCondition {
@@ -77,6 +138,15 @@ pub enum QueryValue {
     Null,
 }
 
+/// A SQL query with the parameters bound to its placeholders
+#[derive(Debug, Clone)]
+pub struct BoundQuery {
+    pub sql: String,
+    pub params: Vec<QueryValue>,
+    /// Keep the statement prepared on the connections that run it
+    pub persistent: bool,
+}
+
 /// Comprehensive query builder for database operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryBuilder {
@@ -96,6 +166,9 @@ pub struct QueryBuilder {
     pub group_by: Vec<String>,
     /// Having conditions (for GROUP BY)
     pub having: Vec<FilterCondition>,
+    /// Include rows marked `is_deleted = TRUE`
+    #[serde(default)]
+    pub include_deleted: bool,
 }
 
 /// Query result with metadata
@@ -144,15 +217,62 @@ impl QueryBuilder {
             offset: None,
             group_by: Vec::new(),
             having: Vec::new(),
+            include_deleted: false,
         }
     }
 
+    /// Include soft-deleted rows in the results
+    pub fn include_deleted(mut self, include_deleted: bool) -> Self {
+        self.include_deleted = include_deleted;
+        self
+    }
+
     /// Select specific fields
     pub fn select(mut self, fields: Vec<&str>) -> Self {
         self.select_fields = fields.into_iter().map(|f| f.to_string()).collect();
         self
     }
 
+    /// Select only `fields`, each of which must be one of the table's `columns`. An empty
+    /// projection selects every column.
+    pub fn project(mut self, fields: &[String], columns: &[String]) -> anyhow::Result<Self> {
+        if let Some(unknown) = fields.iter().find(|field| !columns.contains(field)) {
+            return Err(anyhow::anyhow!(
+                "Unknown column '{}' in table {}",
+                unknown,
+                self.table
+            ));
+        }
+        self.select_fields = fields.to_vec();
+        Ok(self)
+    }
+
+    /// Group rows by `column` and select one row per group holding the group as
+    /// [`GROUP_KEY_COLUMN`] and `aggregate` over `aggregate_column` as [`GROUP_VALUE_COLUMN`],
+    /// ordered by group
+    pub fn aggregate_by(
+        mut self,
+        column: &str,
+        aggregate: Aggregate,
+        aggregate_column: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let aggregate_column = aggregate_column.map(column_sql);
+        self.select_fields = vec![
+            format!("{} AS {}", column_sql(column), GROUP_KEY_COLUMN),
+            format!(
+                "{} AS {}",
+                aggregate.to_sql(aggregate_column.as_deref())?,
+                GROUP_VALUE_COLUMN
+            ),
+        ];
+        self.group_by = vec![column.to_string()];
+        self.sorts = vec![SortOrder {
+            field: column.to_string(),
+            direction: SortDirection::Asc,
+        }];
+        Ok(self)
+    }
+
     /// Add a filter condition
     pub fn filter(mut self, field: &str, operator: QueryOperator, value: QueryValue) -> Self {
         self.filters.push(FilterCondition {
@@ -215,51 +335,138 @@ impl QueryBuilder {
 
     /// Convert to SQL WHERE clause
     pub fn to_where_clause(&self) -> String {
-        if self.filters.is_empty() {
-            return String::new();
-        }
-
-        let conditions: Vec<String> = self
+        let mut conditions: Vec<String> = self
             .filters
             .iter()
-            .map(|f| match &f.operator {
-                QueryOperator::Eq => format!("{} = {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::Ne => format!("{} != {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::Gt => format!("{} > {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::Gte => format!("{} >= {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::Lt => format!("{} < {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::Lte => format!("{} <= {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::Like => format!("{} LIKE {}", f.field, self.value_to_sql(&f.value)),
-                QueryOperator::In => match &f.value {
-                    QueryValue::StringArray(arr) => {
-                        let values: Vec<String> = arr.iter().map(|v| format!("'{}'", v)).collect();
-                        format!("{} IN ({})", f.field, values.join(", "))
-                    }
-                    QueryValue::IntegerArray(arr) => {
-                        let values: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
-                        format!("{} IN ({})", f.field, values.join(", "))
-                    }
-                    _ => format!("{} IN ({})", f.field, self.value_to_sql(&f.value)),
-                },
-                QueryOperator::NotIn => match &f.value {
-                    QueryValue::StringArray(arr) => {
-                        let values: Vec<String> = arr.iter().map(|v| format!("'{}'", v)).collect();
-                        format!("{} NOT IN ({})", f.field, values.join(", "))
+            .map(|f| {
+                let field = column_sql(&f.field);
+                match &f.operator {
+                    QueryOperator::Eq => format!("{} = {}", field, self.value_to_sql(&f.value)),
+                    QueryOperator::Ne => format!("{} != {}", field, self.value_to_sql(&f.value)),
+                    QueryOperator::Gt => format!("{} > {}", field, self.value_to_sql(&f.value)),
+                    QueryOperator::Gte => format!("{} >= {}", field, self.value_to_sql(&f.value)),
+                    QueryOperator::Lt => format!("{} < {}", field, self.value_to_sql(&f.value)),
+                    QueryOperator::Lte => format!("{} <= {}", field, self.value_to_sql(&f.value)),
+                    QueryOperator::Like => {
+                        format!("{} LIKE {}", field, self.value_to_sql(&f.value))
                     }
-                    QueryValue::IntegerArray(arr) => {
-                        let values: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
-                        format!("{} NOT IN ({})", f.field, values.join(", "))
-                    }
-                    _ => format!("{} NOT IN ({})", f.field, self.value_to_sql(&f.value)),
-                },
-                QueryOperator::IsNull => format!("{} IS NULL", f.field),
-                QueryOperator::IsNotNull => format!("{} IS NOT NULL", f.field),
+                    QueryOperator::In => match &f.value {
+                        QueryValue::StringArray(arr) => {
+                            let values: Vec<String> =
+                                arr.iter().map(|v| format!("'{}'", v)).collect();
+                            format!("{} IN ({})", field, values.join(", "))
+                        }
+                        QueryValue::IntegerArray(arr) => {
+                            let values: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
+                            format!("{} IN ({})", field, values.join(", "))
+                        }
+                        _ => format!("{} IN ({})", field, self.value_to_sql(&f.value)),
+                    },
+                    QueryOperator::NotIn => match &f.value {
+                        QueryValue::StringArray(arr) => {
+                            let values: Vec<String> =
+                                arr.iter().map(|v| format!("'{}'", v)).collect();
+                            format!("{} NOT IN ({})", field, values.join(", "))
+                        }
+                        QueryValue::IntegerArray(arr) => {
+                            let values: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
+                            format!("{} NOT IN ({})", field, values.join(", "))
+                        }
+                        _ => format!("{} NOT IN ({})", field, self.value_to_sql(&f.value)),
+                    },
+                    QueryOperator::IsNull => format!("{} IS NULL", field),
+                    QueryOperator::IsNotNull => format!("{} IS NOT NULL", field),
+                }
             })
             .collect();
 
+        if !self.include_deleted {
+            conditions.push(SOFT_DELETE_CONDITION.to_string());
+        }
+
+        if conditions.is_empty() {
+            return String::new();
+        }
+
         format!(" WHERE {}", conditions.join(" AND "))
     }
 
+    /// Convert to a SQL WHERE clause whose values are bound parameters instead of literals,
+    /// so queries that only differ in their values share one SQL text and prepared statement.
+    /// `placeholder` renders the parameter with the given 1-based index compared to `field`.
+    pub fn to_bound_where_clause(
+        &self,
+        placeholder: impl Fn(usize, &str) -> String,
+    ) -> (String, Vec<QueryValue>) {
+        let mut params = Vec::new();
+        let mut bind = |field: &str, value: QueryValue| {
+            params.push(value);
+            placeholder(params.len(), field)
+        };
+        let mut conditions: Vec<String> = Vec::with_capacity(self.filters.len() + 1);
+        for f in &self.filters {
+            let operator = match &f.operator {
+                QueryOperator::Eq => "=",
+                QueryOperator::Ne => "!=",
+                QueryOperator::Gt => ">",
+                QueryOperator::Gte => ">=",
+                QueryOperator::Lt => "<",
+                QueryOperator::Lte => "<=",
+                QueryOperator::Like => "LIKE",
+                QueryOperator::In => "IN",
+                QueryOperator::NotIn => "NOT IN",
+                QueryOperator::IsNull => {
+                    conditions.push(format!("{} IS NULL", column_sql(&f.field)));
+                    continue;
+                }
+                QueryOperator::IsNotNull => {
+                    conditions.push(format!("{} IS NOT NULL", column_sql(&f.field)));
+                    continue;
+                }
+            };
+            let values: Vec<QueryValue> = match &f.value {
+                QueryValue::StringArray(arr) => {
+                    arr.iter().cloned().map(QueryValue::String).collect()
+                }
+                QueryValue::IntegerArray(arr) => {
+                    arr.iter().copied().map(QueryValue::Integer).collect()
+                }
+                value => vec![value.clone()],
+            };
+            let rendered: Vec<String> = values
+                .into_iter()
+                .map(|value| match value {
+                    QueryValue::Null => "NULL".to_string(),
+                    value => bind(&f.field, value),
+                })
+                .collect();
+            match &f.operator {
+                QueryOperator::In | QueryOperator::NotIn => conditions.push(format!(
+                    "{} {} ({})",
+                    column_sql(&f.field),
+                    operator,
+                    rendered.join(", ")
+                )),
+                _ => conditions.push(format!(
+                    "{} {} {}",
+                    column_sql(&f.field),
+                    operator,
+                    rendered.join(", ")
+                )),
+            }
+        }
+
+        if !self.include_deleted {
+            conditions.push(SOFT_DELETE_CONDITION.to_string());
+        }
+
+        if conditions.is_empty() {
+            return (String::new(), params);
+        }
+
+        (format!(" WHERE {}", conditions.join(" AND ")), params)
+    }
+
     /// Convert to SQL ORDER BY clause
     pub fn to_order_clause(&self) -> String {
         if self.sorts.is_empty() {
@@ -274,7 +481,7 @@ impl QueryBuilder {
                     SortDirection::Asc => "ASC",
                     SortDirection::Desc => "DESC",
                 };
-                format!("{} {}", s.field, direction)
+                format!("{} {}", column_sql(&s.field), direction)
             })
             .collect();
 
@@ -295,18 +502,35 @@ impl QueryBuilder {
 
     /// Build complete SQL query
     pub fn to_sql(&self) -> String {
+        self.to_sql_with_where_clause(&self.to_where_clause())
+    }
+
+    /// Build the complete SQL query with bound filter values, see [`Self::to_bound_where_clause`]
+    pub fn to_bound_sql(
+        &self,
+        placeholder: impl Fn(usize, &str) -> String,
+    ) -> (String, Vec<QueryValue>) {
+        let (where_clause, params) = self.to_bound_where_clause(placeholder);
+        (self.to_sql_with_where_clause(&where_clause), params)
+    }
+
+    /// Build the complete SQL query around a WHERE clause rendered by the caller, ignoring
+    /// this builder's own filters
+    pub fn to_sql_with_where_clause(&self, where_clause: &str) -> String {
         let select_clause = if self.select_fields.is_empty() {
             "SELECT *".to_string()
         } else {
-            format!("SELECT {}", self.select_fields.join(", "))
+            let fields: Vec<String> = self.select_fields.iter().map(|f| column_sql(f)).collect();
+            format!("SELECT {}", fields.join(", "))
         };
 
         let mut sql = format!("{} FROM {}", select_clause, self.table);
 
-        sql.push_str(&self.to_where_clause());
+        sql.push_str(where_clause);
 
         if !self.group_by.is_empty() {
-            sql.push_str(&format!(" GROUP BY {}", self.group_by.join(", ")));
+            let columns: Vec<String> = self.group_by.iter().map(|f| column_sql(f)).collect();
+            sql.push_str(&format!(" GROUP BY {}", columns.join(", ")));
         }
 
         sql.push_str(&self.to_order_clause());
@@ -382,6 +606,78 @@ impl QueryResult {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_aggregate_by_groups_and_orders_by_column() {
+        let sql = QueryBuilder::new("store_position")
+            .aggregate_by("facing", Aggregate::Sum, Some("x"))
+            .unwrap()
+            .to_sql();
+        assert_eq!(
+            sql,
+            r#"SELECT "facing" AS group_key, SUM("x") AS group_value FROM store_position WHERE is_deleted = FALSE GROUP BY "facing" ORDER BY "facing" ASC"#
+        );
+        assert!(QueryBuilder::new("store_position")
+            .aggregate_by("facing", Aggregate::Avg, None)
+            .is_err());
+        assert_eq!(Aggregate::Count.to_sql(Some("x")).unwrap(), "COUNT(*)");
+    }
+
+    #[test]
+    fn test_projection_selects_only_requested_columns() {
+        let columns: Vec<String> = ["player", "x", "y", "is_deleted"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let query = QueryBuilder::new("store_position")
+            .project(&["x".to_string(), "player".to_string()], &columns)
+            .unwrap()
+            .filter("y", QueryOperator::Gt, QueryValue::Integer(3))
+            .limit(10);
+        let (sql, params) = query.to_bound_sql(|index, _| format!("${}", index));
+        assert_eq!(
+            sql,
+            r#"SELECT "x", "player" FROM store_position WHERE "y" > $1 AND is_deleted = FALSE LIMIT 10"#
+        );
+        assert_eq!(params.len(), 1);
+
+        let err = QueryBuilder::new("store_position")
+            .project(&["x; DROP TABLE store_position".to_string()], &columns)
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown column"));
+        assert!(QueryBuilder::new("store_position")
+            .project(&[], &columns)
+            .unwrap()
+            .to_sql()
+            .starts_with("SELECT * FROM"));
+    }
+
+    #[test]
+    fn test_bound_where_clause_shares_sql_across_values() {
+        let query = |age: i64, name: &str| {
+            QueryBuilder::new("users")
+                .filter("age", QueryOperator::Gte, QueryValue::Integer(age))
+                .filter("name", QueryOperator::Eq, QueryValue::String(name.to_string()))
+                .filter(
+                    "status",
+                    QueryOperator::In,
+                    QueryValue::StringArray(vec!["active".to_string(), "verified".to_string()]),
+                )
+                .filter("email", QueryOperator::IsNull, QueryValue::Null)
+        };
+        let placeholder = |index: usize, _field: &str| format!("${}", index);
+
+        let (sql, params) = query(18, "o'brien").to_bound_where_clause(placeholder);
+        assert_eq!(
+            sql,
+            r#" WHERE "age" >= $1 AND "name" = $2 AND "status" IN ($3, $4) AND "email" IS NULL AND is_deleted = FALSE"#
+        );
+        assert_eq!(params.len(), 4);
+        assert!(matches!(&params[1], QueryValue::String(name) if name == "o'brien"));
+
+        let (other_sql, _) = query(30, "alice").to_bound_where_clause(placeholder);
+        assert_eq!(sql, other_sql);
+    }
+
     #[test]
     fn test_query_builder() {
         let query = QueryBuilder::new("users")
@@ -399,12 +695,38 @@ mod tests {
         let sql = query.to_sql();
         println!("Generated SQL: {}", sql);
 
-        assert!(sql.contains("SELECT id, name, email"));
+        assert!(sql.contains(r#"SELECT "id", "name", "email""#));
         assert!(sql.contains("FROM users"));
         assert!(sql.contains("WHERE"));
-        assert!(sql.contains("age >= 18"));
-        assert!(sql.contains("ORDER BY created_at DESC"));
+        assert!(sql.contains(r#""age" >= 18"#));
+        assert!(sql.contains(r#"ORDER BY "created_at" DESC"#));
         assert!(sql.contains("LIMIT 10"));
         assert!(sql.contains("OFFSET 20"));
     }
+
+    #[tokio::test]
+    async fn test_soft_deleted_rows_are_hidden_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("query.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        database
+            .execute("CREATE TABLE store_counter1 (entity_id TEXT PRIMARY KEY, value INTEGER, is_deleted BOOLEAN DEFAULT FALSE)")
+            .await
+            .unwrap();
+        database
+            .execute("INSERT INTO store_counter1 (entity_id, value, is_deleted) VALUES ('0x1', 1, FALSE), ('0x2', 2, TRUE)")
+            .await
+            .unwrap();
+
+        let query = QueryBuilder::new("store_counter1");
+        assert_eq!(query.to_where_clause(), " WHERE is_deleted = FALSE");
+        let rows = database.query(&query.to_sql()).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["entity_id"], "0x1");
+
+        let query = QueryBuilder::new("store_counter1").include_deleted(true);
+        assert_eq!(query.to_where_clause(), "");
+        let rows = database.query(&query.to_sql()).await.unwrap();
+        assert_eq!(rows.len(), 2);
+    }
 }
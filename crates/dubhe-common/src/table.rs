@@ -1,6 +1,8 @@
+use crate::dead_letter::escape;
 use crate::events::Event;
 use crate::events::StoreSetRecord;
 use crate::primitives::{MoveTypeParser, ParsedMoveValue};
+use crate::query::Aggregate;
 use crate::sql::DBData;
 use anyhow::Result;
 use bcs;
@@ -8,19 +10,56 @@ use move_core_types::u256::U256;
 use prost_types::compiler::code_generator_response::Feature;
 use prost_types::ListValue;
 use prost_types::{Struct, Value as ProtoValue};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json;
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::format;
+use std::fmt::Write;
 use std::str::FromStr;
-use sui_types::base_types::SuiAddress;
+use sui_types::base_types::{ObjectID, SuiAddress, TransactionDigest};
 
 pub const ONCHAIN_TABLE: &str = "ont";
 pub const OFFCHAIN_TABLE: &str = "oft";
 
-#[derive(Debug, Deserialize, Default, Clone)]
+/// Bookkeeping columns every store table has besides its fields
+pub const SYSTEM_COLUMNS: [&str; 4] = [
+    "created_at_timestamp_ms",
+    "updated_at_timestamp_ms",
+    "last_update_digest",
+    "is_deleted",
+];
+
+/// Columns besides [`SYSTEM_COLUMNS`] that a store table may have, which no field can use
+const RESERVED_COLUMNS: [&str; 2] = ["unique_resource_id", RAW_KEY_BCS_COLUMN];
+
+/// Longest [`digest_string`], 32 bytes in base58
+pub const DIGEST_COLUMN_LENGTH: usize = 44;
+
+/// Column types and defaults of [`SYSTEM_COLUMNS`], in the same order. `last_update_digest`
+/// is [`DIGEST_COLUMN_LENGTH`] wide.
+pub const SYSTEM_COLUMN_TYPES: [&str; 4] = [
+    "BIGINT DEFAULT 0",
+    "BIGINT DEFAULT 0",
+    "VARCHAR(44) DEFAULT ''",
+    "BOOLEAN DEFAULT FALSE",
+];
+
+/// Canonical base58 form of a transaction digest, the one written to `last_update_digest`
+/// by the indexer and the channel alike
+pub fn digest_string(digest: &TransactionDigest) -> String {
+    digest.base58_encode()
+}
+
+/// Column holding the BCS of a record's key tuple when [`DubheConfig::store_raw_bcs`] is
+/// set, see [`DubheConfig::raw_bcs_column`] for the value fields
+pub const RAW_KEY_BCS_COLUMN: &str = "raw_key_bcs";
+
+/// SQL type of the raw BCS columns. SQLite keeps the blobs written to it as they are.
+pub const RAW_BCS_SQL_TYPE: &str = "BYTEA";
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct Field {
     pub table: String,
     pub name: String,
@@ -28,6 +67,11 @@ pub struct Field {
     pub move_type: String,
     pub db_type: String,
     pub primary_key: bool,
+    /// SQL column name to use instead of the Move field name
+    pub column_alias: Option<String>,
+    /// Implied decimal scale of an integer amount, exposed as a scaled decimal next to the
+    /// raw integer
+    pub decimals: Option<u8>,
 }
 
 impl Field {
@@ -59,8 +103,27 @@ impl Field {
         self
     }
 
-    pub fn proto_value(&self, value: &[u8]) -> ProtoValue {
-        match self.move_type.as_str() {
+    pub fn column_alias(&mut self, column_alias: Option<String>) -> &mut Self {
+        self.column_alias = column_alias;
+        self
+    }
+
+    pub fn decimals(&mut self, decimals: Option<u8>) -> &mut Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// SQL column of this field: the configured alias, else the Move field name. Keywords
+    /// such as `from` stay column names, SQL built from them goes through
+    /// [`quote_identifier`]. BCS decoding keeps using `name`/`index`.
+    pub fn column_name(&self) -> String {
+        self.column_alias.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// Decode `value` for subscribers. Fails for move types outside [`SUPPORTED_MOVE_TYPES`],
+    /// which [`DubheConfig::validate`] rejects up front.
+    pub fn proto_value(&self, value: &[u8]) -> Result<ProtoValue> {
+        let proto_value = match self.move_type.as_str() {
             "bool" => {
                 let parsed_value: bool = bcs::from_bytes(value).unwrap();
                 ProtoValue {
@@ -99,6 +162,16 @@ impl Field {
                     )),
                 }
             }
+            "i8" | "i16" | "i32" | "i64" => ProtoValue {
+                kind: Some(prost_types::value::Kind::NumberValue(
+                    decode_signed(&self.move_type, value)? as f64,
+                )),
+            },
+            "i128" => ProtoValue {
+                kind: Some(prost_types::value::Kind::StringValue(
+                    decode_signed(&self.move_type, value)?.to_string(),
+                )),
+            },
             "u256" => {
                 let parsed_value: U256 = bcs::from_bytes(value).unwrap();
                 ProtoValue {
@@ -107,14 +180,16 @@ impl Field {
                     )),
                 }
             }
-            "address" => {
-                let parsed_value: SuiAddress = bcs::from_bytes(value).unwrap();
-                ProtoValue {
+            "address" => match parse_address(value) {
+                Ok(parsed_value) => ProtoValue {
                     kind: Some(prost_types::value::Kind::StringValue(
                         parsed_value.to_string(),
                     )),
-                }
-            }
+                },
+                Err(_) => ProtoValue {
+                    kind: Some(prost_types::value::Kind::NullValue(0)),
+                },
+            },
             "String" => {
                 let parsed_value: String = bcs::from_bytes(value).unwrap();
                 ProtoValue {
@@ -215,8 +290,11 @@ impl Field {
                 }
             }
             "vector<address>" => {
-                let parsed_value: Vec<SuiAddress> = bcs::from_bytes(value).unwrap();
-                println!("parsed_value: {:?}", parsed_value);
+                let Ok(parsed_value) = parse_address_vector(value) else {
+                    return Ok(ProtoValue {
+                        kind: Some(prost_types::value::Kind::NullValue(0)),
+                    });
+                };
                 ProtoValue {
                     kind: Some(prost_types::value::Kind::ListValue(ListValue {
                         values: parsed_value
@@ -264,42 +342,382 @@ impl Field {
                     })),
                 }
             }
-            // String
-            _ => {
-                let parsed_value: String = bcs::from_bytes(value).unwrap();
-                ProtoValue {
-                    kind: Some(prost_types::value::Kind::StringValue(
-                        parsed_value.to_string(),
-                    )),
+            RAW_BCS_TYPE => ProtoValue {
+                kind: Some(prost_types::value::Kind::StringValue(format!(
+                    "0x{}",
+                    hex::encode(value)
+                ))),
+            },
+            move_type if is_object_id_type(move_type) => ProtoValue {
+                kind: Some(prost_types::value::Kind::StringValue(
+                    parse_object_id(value)?.to_string(),
+                )),
+            },
+            move_type => match DecimalType::parse(move_type) {
+                Some(decimal) => ProtoValue {
+                    kind: Some(prost_types::value::Kind::StringValue(decimal.decode(value)?)),
+                },
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Unsupported move type {} of field {}.{}",
+                        move_type,
+                        self.table,
+                        self.name
+                    ))
                 }
-            }
-        }
+            },
+        };
+        Ok(proto_value)
     }
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct Enum {
     pub name: String,
-    pub index: u8,
+    pub index: u64,
     pub value: String,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+/// On-chain width of an enum discriminant stored as a plain integer. Enums without one are
+/// Move enums, whose variant tag is ULEB128 encoded.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnumWidth {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct Table {
     pub name: String,
     pub offchain: bool,
     pub component: bool,
+    /// Coalesce subscriber pushes so each key is pushed at most once per interval
+    pub push_min_interval_ms: Option<u64>,
+    /// What a SetRecord does to an existing row with the same key
+    pub conflict_policy: ConflictPolicy,
+}
+
+/// Tables whose rows describe the same entity, matched on key fields both tables have
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Relation {
+    pub table: String,
+    pub related_table: String,
+    /// Move fields that must be equal in both tables
+    pub on: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+/// Deserializing goes through the derived `DubheConfig::deserialize` and then rebuilds the
+/// per-table field index, which is not part of the serialized form.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[serde(remote = "Self")]
 pub struct DubheConfig {
+    /// Add fields with [`DubheConfig::push_field`], which keeps the per-table index in sync, or
+    /// call [`DubheConfig::rebuild_field_index`] after adding or removing them directly.
     pub fields: Vec<Field>,
     pub enums: Vec<Enum>,
+    /// Discriminant widths of enums stored as plain integers, by enum name
+    #[serde(default)]
+    pub enum_widths: BTreeMap<String, EnumWidth>,
     pub tables: Vec<Table>,
+    pub relations: Vec<Relation>,
     pub original_package_id: String,
     pub dubhe_object_id: String,
     pub original_dubhe_package_id: String,
     pub start_checkpoint: String,
+    /// System tables whose events are accepted regardless of the emitting package.
+    pub system_tables: Vec<String>,
+    /// Tables that get no storage table and whose events are skipped.
+    pub exclude_tables: Vec<String>,
+    /// Name storage tables `store_<pkgshort>_<table>` so same-named tables of different
+    /// packages do not collide. System tables keep their flat name.
+    pub package_qualified_tables: bool,
+    /// What to do with events of tables missing from the config.
+    pub on_unknown_table: UnknownTablePolicy,
+    /// Keep the BCS of each record's key tuple in [`RAW_KEY_BCS_COLUMN`] and the BCS of each
+    /// value field in its [`DubheConfig::raw_bcs_column`], so fields can be re-derived after a
+    /// config change without re-indexing from chain.
+    pub store_raw_bcs: bool,
+    /// Positions in `fields` by table, so lookups do not scan every field of every table.
+    #[serde(skip)]
+    field_index: HashMap<String, TableFieldIndex>,
+}
+
+impl<'de> Deserialize<'de> for DubheConfig {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut config = DubheConfig::deserialize(deserializer)?;
+        config.rebuild_field_index();
+        Ok(config)
+    }
+}
+
+/// Positions in [`DubheConfig::fields`] of the fields of one table
+#[derive(Debug, Default, Clone, PartialEq)]
+struct TableFieldIndex {
+    /// All fields, in config order
+    all: Vec<usize>,
+    /// Key fields, in key tuple order
+    keys: Vec<usize>,
+    /// Value fields, in config order
+    values: Vec<usize>,
+}
+
+/// System tables accepted by default, kept for backward compatibility.
+pub const DEFAULT_SYSTEM_TABLES: &[&str] = &["dapp_fee_state"];
+
+/// Dubhe framework package. System table events it emits itself are not accepted for a dapp.
+pub const DUBHE_FRAMEWORK_PACKAGE_ID: &str =
+    "0xa337791835d15223727ace33cce17ea0901c094c8cfbe34d089c1a18c2df7a15";
+
+/// Initial capacity of a generated statement, enough for most upserts to avoid regrowing.
+const SQL_CAPACITY: usize = 512;
+
+/// Suffix of the column holding the scaled decimal value of a field with `decimals`
+pub const DECIMAL_COLUMN_SUFFIX: &str = "_decimal";
+
+/// Move type of columns holding raw BCS bytes, stored as `0x`-prefixed hex.
+pub const RAW_BCS_TYPE: &str = "bcs";
+
+/// Fixed-point amount declared as `decimal<uN>(p,s)`: the field holds an unsigned integer
+/// `uN` with `s` implied decimals, stored scaled in a `NUMERIC(p,s)` column. Unlike the
+/// `decimals` setting, which adds a scaled copy next to the raw integer, the column holds
+/// the scaled value itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecimalType {
+    /// Unsigned integer type of the on-chain value
+    pub integer: String,
+    pub precision: u16,
+    pub scale: u8,
+}
+
+impl DecimalType {
+    /// `None` unless `type_` has the form `decimal<uN>(p,s)`; see [`DubheConfig::validate`]
+    /// for the accepted integers and bounds
+    pub fn parse(type_: &str) -> Option<Self> {
+        let (integer, rest) = type_.strip_prefix("decimal<")?.split_once(">(")?;
+        let (precision, scale) = rest.strip_suffix(')')?.split_once(',')?;
+        Some(Self {
+            integer: integer.to_string(),
+            precision: precision.trim().parse().ok()?,
+            scale: scale.trim().parse().ok()?,
+        })
+    }
+
+    pub fn sql_type(&self) -> String {
+        format!("NUMERIC({},{})", self.precision, self.scale)
+    }
+
+    /// Decimal digits of the largest value of the integer type, `None` for an unsupported
+    /// integer
+    pub fn integer_digits(&self) -> Option<u16> {
+        match self.integer.as_str() {
+            "u8" => Some(3),
+            "u16" => Some(5),
+            "u32" => Some(10),
+            "u64" => Some(20),
+            "u128" => Some(39),
+            "u256" => Some(78),
+            _ => None,
+        }
+    }
+
+    /// Exact decimal text of the BCS integer `value` divided by `10^scale`
+    pub fn decode(&self, value: &[u8]) -> Result<String> {
+        let raw = into_sql_string(&self.integer, value)?;
+        scale_decimal(raw.trim_matches('\''), self.scale)
+            .ok_or_else(|| anyhow::anyhow!("{} is not an unsigned integer", self.integer))
+    }
+}
+
+/// Move types fields can be declared with, besides the enums of the config, [`DecimalType`]s
+/// and object ids (see [`is_object_id_type`]).
+///
+/// Move has no signed integers, so `i8` to `i128` are a convention: the field holds the
+/// two's complement of the value in the unsigned integer of the same width, which BCS
+/// encodes as the same little-endian bytes a Rust `i8` to `i128` would have. `i8` to `i64`
+/// get signed integer columns, `i128` is stored as decimal text like `u128`.
+pub const SUPPORTED_MOVE_TYPES: [&str; 25] = [
+    "bool",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "u128",
+    "u256",
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "i128",
+    "address",
+    "String",
+    "vector<bool>",
+    "vector<u8>",
+    "vector<u16>",
+    "vector<u32>",
+    "vector<u64>",
+    "vector<u128>",
+    "vector<u256>",
+    "vector<address>",
+    "vector<String>",
+    "vector<vector<u8>>",
+    RAW_BCS_TYPE,
+];
+
+/// What a SetRecord of a keyed or singleton table does when its row already exists
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Overwrite the row with the new values (`ON CONFLICT ... DO UPDATE`).
+    #[default]
+    Update,
+    /// Keep the existing row, the first write wins. A soft-deleted row is overwritten, as if
+    /// it were absent (`ON CONFLICT ... DO UPDATE ... WHERE is_deleted`).
+    Ignore,
+    /// Leave out the conflict clause, so the database rejects the insert.
+    Error,
+}
+
+/// Handling of events whose table is not in the config.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownTablePolicy {
+    /// Drop the event silently.
+    #[default]
+    Skip,
+    /// Drop the event and log a warning.
+    Warn,
+    /// Fail with [`UnknownTableError`].
+    Error,
+    /// Infer a table from the event's tuple shape, see [`DubheConfig::infer_table`].
+    AutoCreate,
+}
+
+/// Returned by [`DubheConfig::can_convert_event_to_sql`] for an event of a table that is not
+/// in the config, under the `error` and `auto_create` policies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTableError {
+    pub table_id: String,
+}
+
+impl std::fmt::Display for UnknownTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Event table id does not match the table id: {}", self.table_id)
+    }
+}
+
+impl std::error::Error for UnknownTableError {}
+
+/// Why [`DubheConfig::can_convert_event_to_sql`] drops an event that was not dropped on
+/// purpose, usually a sign the config does not match the deployed package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// The event was emitted by another package than `original_package_id`
+    PackageMismatch,
+    /// The event's table is not in the config
+    UnknownTable,
+    /// The event's tuples are too short for the fields of its table
+    ArityMismatch,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::PackageMismatch => "package_mismatch",
+            SkipReason::UnknownTable => "unknown_table",
+            SkipReason::ArityMismatch => "arity_mismatch",
+        }
+    }
+}
+
+/// The comma separated column lists of one record, see [`DubheConfig::record_columns`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordColumns {
+    /// Same as [`DubheConfig::field_names_by_table`]
+    pub names: String,
+    /// Same as [`DubheConfig::field_values_by_table`]
+    pub values: String,
+    /// Same as [`DubheConfig::field_names_by_table_and_primary_key`]
+    pub key_names: String,
+    /// Same as [`DubheConfig::field_values_with_set_by_table`]
+    pub assignments: String,
+    /// Same as [`DubheConfig::field_values_by_table_and_non_primary_key`]
+    pub value_assignments: String,
+}
+
+/// How the rows of a store table are identified, shared by [`DubheConfig::create_table_sql`]
+/// and [`DubheConfig::convert_event_to_sql`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableLayout {
+    /// One row per key tuple, upserted on the key columns
+    Keyed,
+    /// An onchain table without keys holds a single row, pinned by `unique_resource_id = 1`
+    Singleton,
+    /// An offchain table without keys is an append-only log with no `unique_resource_id`.
+    /// Every SetRecord inserts a row; SetField and DeleteRecord are rejected, since nothing
+    /// in them identifies the row.
+    AppendOnly,
+}
+
+/// SQL dialect the generated statements are written for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dialect {
+    #[default]
+    Postgres,
+    Sqlite,
+}
+
+impl Dialect {
+    /// Dialect of the database `db_url` points to, the same choice [`crate::Database::new`]
+    /// makes
+    pub fn from_database_url(db_url: &str) -> Self {
+        if db_url.starts_with("postgres://") || db_url.starts_with("postgresql://") {
+            Dialect::Postgres
+        } else {
+            Dialect::Sqlite
+        }
+    }
+
+    pub fn bool_literal(self, value: bool) -> &'static str {
+        match (self, value) {
+            (Dialect::Postgres, true) => "TRUE",
+            (Dialect::Postgres, false) => "FALSE",
+            (Dialect::Sqlite, true) => "1",
+            (Dialect::Sqlite, false) => "0",
+        }
+    }
+
+    /// SQL literal of a value column, see [`into_sql_string`] and [`sqlite_sql_string`]
+    pub fn sql_string(self, type_: &str, value: &[u8]) -> Result<String> {
+        match self {
+            Dialect::Postgres => into_sql_string(type_, value),
+            Dialect::Sqlite => sqlite_sql_string(type_, value),
+        }
+    }
+
+    /// SQL literal of raw bytes written to a BYTEA column
+    pub fn bytes_literal(self, bytes: &[u8]) -> String {
+        match self {
+            Dialect::Postgres => format!("'\\x{}'::bytea", hex::encode(bytes)),
+            Dialect::Sqlite => format!("X'{}'", hex::encode(bytes)),
+        }
+    }
+
+    /// SQL literal of one key tuple component, see [`key_sql_string`]
+    pub fn key_sql_string(self, type_: &str, value: &[u8]) -> Result<String> {
+        match self {
+            Dialect::Sqlite if type_ == "bool" || get_sql_type(type_).ends_with("[]") => {
+                sqlite_sql_string(type_, value)
+            }
+            _ => key_sql_string(type_, value),
+        }
+    }
 }
 
 impl DubheConfig {
@@ -307,19 +725,162 @@ impl DubheConfig {
         Self {
             fields: Vec::new(),
             enums: Vec::new(),
+            enum_widths: BTreeMap::new(),
             tables: Vec::new(),
+            relations: Vec::new(),
             original_package_id,
             dubhe_object_id,
             original_dubhe_package_id,
             start_checkpoint,
+            system_tables: DEFAULT_SYSTEM_TABLES.iter().map(|t| t.to_string()).collect(),
+            exclude_tables: Vec::new(),
+            package_qualified_tables: false,
+            on_unknown_table: UnknownTablePolicy::default(),
+            store_raw_bcs: false,
+            field_index: HashMap::new(),
+        }
+    }
+
+    pub fn set_system_tables(&mut self, system_tables: Vec<String>) -> &mut Self {
+        self.system_tables = system_tables;
+        self
+    }
+
+    pub fn is_system_table(&self, table_id: &str) -> bool {
+        self.system_tables.iter().any(|table| table == table_id)
+    }
+
+    /// Whether `event` is a system table event accepted whatever its origin package, which
+    /// holds unless the Dubhe framework package emitted it
+    fn is_accepted_system_event(&self, event: &Event) -> bool {
+        self.is_system_table(event.table_id())
+            && event.origin_package_id().as_deref() != Some(DUBHE_FRAMEWORK_PACKAGE_ID)
+    }
+
+    pub fn set_exclude_tables(&mut self, exclude_tables: Vec<String>) -> &mut Self {
+        self.exclude_tables = exclude_tables;
+        self
+    }
+
+    /// Hex SHA-256 of the normalized config, for checking that replicas loaded the same one.
+    /// Tables, enums, relations and table lists are sorted and fields are grouped by table in
+    /// their BCS order, so configs that only differ in JSON ordering hash the same. The
+    /// normalized config is BCS encoded, which is canonical and stable across builds.
+    pub fn config_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut fields: Vec<&Field> = self.fields.iter().collect();
+        fields.sort_by(|a, b| a.table.cmp(&b.table));
+        let mut tables: Vec<&Table> = self.tables.iter().collect();
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut enums: Vec<&Enum> = self.enums.iter().collect();
+        enums.sort_by(|a, b| (&a.name, a.index).cmp(&(&b.name, b.index)));
+        let mut relations: Vec<&Relation> = self.relations.iter().collect();
+        relations.sort_by(|a, b| (&a.table, &a.related_table).cmp(&(&b.table, &b.related_table)));
+        let mut system_tables = self.system_tables.clone();
+        system_tables.sort();
+        let mut exclude_tables = self.exclude_tables.clone();
+        exclude_tables.sort();
+
+        let normalized = bcs::to_bytes(&(
+            fields,
+            tables,
+            enums,
+            relations,
+            (&self.original_package_id, &self.dubhe_object_id),
+            (&self.original_dubhe_package_id, &self.start_checkpoint),
+            (system_tables, exclude_tables),
+            (self.package_qualified_tables, &self.on_unknown_table, self.store_raw_bcs),
+            &self.enum_widths,
+        ))
+        .expect("the config is BCS encodable");
+        hex::encode(Sha256::digest(normalized))
+    }
+
+    pub fn is_excluded_table(&self, table_id: &str) -> bool {
+        self.exclude_tables.iter().any(|table| table == table_id)
+    }
+
+    pub fn set_on_unknown_table(&mut self, on_unknown_table: UnknownTablePolicy) -> &mut Self {
+        self.on_unknown_table = on_unknown_table;
+        self
+    }
+
+    pub fn set_package_qualified_tables(&mut self, package_qualified_tables: bool) -> &mut Self {
+        self.package_qualified_tables = package_qualified_tables;
+        self
+    }
+
+    pub fn set_store_raw_bcs(&mut self, store_raw_bcs: bool) -> &mut Self {
+        self.store_raw_bcs = store_raw_bcs;
+        self
+    }
+
+    pub fn set_enum_width(&mut self, enum_name: &str, width: EnumWidth) -> &mut Self {
+        self.enum_widths.insert(enum_name.to_string(), width);
+        self
+    }
+
+    /// Storage table of `table_id` as emitted by `package_id`
+    pub fn store_table_name(&self, package_id: Option<&str>, table_id: &str) -> String {
+        match package_id {
+            Some(package_id)
+                if self.package_qualified_tables
+                    && table_id != "storage_submit"
+                    && !self.is_system_table(table_id) =>
+            {
+                format!("store_{}_{}", package_short_id(package_id), table_id)
+            }
+            _ => format!("store_{}", table_id),
         }
     }
 
+    /// Id of the configured table `table` names, either by its id or by its store table
+    pub fn table_id_of<'a>(&'a self, table: &str) -> Option<&'a str> {
+        self.tables
+            .iter()
+            .find(|t| {
+                t.name == table
+                    || self.store_table_name(Some(&self.original_package_id), &t.name) == table
+            })
+            .map(|t| t.name.as_str())
+    }
+
+    /// Storage table an event is written to
+    pub fn event_store_table_name(&self, event: &Event) -> String {
+        self.store_table_name(event.origin_package_id().as_deref(), event.table_id())
+    }
+
     pub fn push_field(&mut self, field: Field) -> &mut Self {
         self.fields.push(field);
+        self.index_field(self.fields.len() - 1);
         self
     }
 
+    /// Add the field at `position` in `fields` to the index of its table
+    fn index_field(&mut self, position: usize) {
+        let field = &self.fields[position];
+        let index = self.field_index.entry(field.table.clone()).or_default();
+        index.all.push(position);
+        if field.primary_key {
+            let at = index
+                .keys
+                .partition_point(|&key| self.fields[key].index <= field.index);
+            index.keys.insert(at, position);
+        } else {
+            index.values.push(position);
+        }
+    }
+
+    /// Index `fields` from scratch, for configs whose fields were not added with
+    /// [`DubheConfig::push_field`]
+    pub fn rebuild_field_index(&mut self) {
+        self.field_index.clear();
+        for position in 0..self.fields.len() {
+            self.index_field(position);
+        }
+    }
+
     pub fn push_enum(&mut self, enum_: Enum) -> &mut Self {
         self.enums.push(enum_);
         self
@@ -330,51 +891,387 @@ impl DubheConfig {
         self
     }
 
-    pub fn field_names_by_table_and_primary_key(&self, table_id: &str) -> Vec<String> {
-        self.fields
+    /// Declare `relation`, whose `on` fields must exist in both tables
+    pub fn push_relation(&mut self, relation: Relation) -> Result<&mut Self> {
+        if relation.on.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Relation {} -> {} has no fields to join on",
+                relation.table,
+                relation.related_table
+            ));
+        }
+        for table in [&relation.table, &relation.related_table] {
+            if !self.tables.iter().any(|t| &t.name == table) {
+                return Err(anyhow::anyhow!("Unknown table in relation: {}", table));
+            }
+            if let Some(field) = relation
+                .on
+                .iter()
+                .find(|field| !self.table_fields(table).any(|f| &f.name == *field))
+            {
+                return Err(anyhow::anyhow!(
+                    "Relation field {} is not a field of table {}",
+                    field,
+                    table
+                ));
+            }
+        }
+        // Object ids and addresses are stored alike, so either side may be an id, but an id
+        // never equals a value of any other type
+        let holds_address = |table: &str, name: &str| {
+            self.table_fields(table)
+                .find(|field| field.name == name)
+                .is_some_and(|field| {
+                    field.move_type == "address" || is_object_id_type(&field.move_type)
+                })
+        };
+        for field in &relation.on {
+            if holds_address(&relation.table, field)
+                != holds_address(&relation.related_table, field)
+            {
+                return Err(anyhow::anyhow!(
+                    "Relation field {} holds an address or object id in only one of {} and {}",
+                    field,
+                    relation.table,
+                    relation.related_table
+                ));
+            }
+        }
+        self.relations.push(relation);
+        Ok(self)
+    }
+
+    /// Relations declared from `table_id` to other tables
+    pub fn relations_of<'a>(&'a self, table_id: &'a str) -> impl Iterator<Item = &'a Relation> {
+        self.relations
             .iter()
-            .filter(|field| field.table == table_id && field.primary_key)
-            .map(|field| format!("\"{}\"", field.name))
+            .filter(move |relation| relation.table == table_id)
+    }
+
+    /// SQL column of a Move field, or `field_name` unchanged when it is not a configured field
+    pub fn column_name(&self, table_id: &str, field_name: &str) -> String {
+        self.table_fields(table_id)
+            .find(|field| field.name == field_name)
+            .map(|field| field.column_name())
+            .unwrap_or_else(|| field_name.to_string())
+    }
+
+    /// Columns a query on a table may select: its fields in config order, the raw BCS
+    /// columns if they are stored, then the bookkeeping columns every store table has
+    pub fn selectable_columns(&self, table_id: &str) -> Vec<String> {
+        self.table_fields(table_id)
+            .map(|field| field.column_name())
+            .chain(self.raw_bcs_columns(table_id))
+            .chain(SYSTEM_COLUMNS.iter().map(|column| column.to_string()))
+            .collect()
+    }
+
+    /// Column holding the scaled value of `column` when that is a field with decimals,
+    /// see [`DubheConfig::with_scaled_decimals`]
+    pub fn decimal_column(column: &str) -> String {
+        format!("{}{}", column, DECIMAL_COLUMN_SUFFIX)
+    }
+
+    /// Field column a `<column>_decimal` column is derived from, `None` for any other column
+    pub fn decimal_source_column(&self, table_id: &str, column: &str) -> Option<String> {
+        let source = column.strip_suffix(DECIMAL_COLUMN_SUFFIX)?;
+        self.table_fields(table_id)
+            .find(|field| field.decimals.is_some() && field.column_name() == source)
+            .map(|field| field.column_name())
+    }
+
+    /// Add a `<column>_decimal` string holding the scaled value of every field with
+    /// decimals present in a JSON row of `table_id`. The raw integer is kept as is.
+    pub fn with_scaled_decimals(&self, table_id: &str, row: &mut serde_json::Value) {
+        let Some(row) = row.as_object_mut() else {
+            return;
+        };
+        for field in self.table_fields(table_id) {
+            let Some(decimals) = field.decimals else {
+                continue;
+            };
+            let column = field.column_name();
+            let raw = match row.get(&column) {
+                Some(serde_json::Value::Number(number)) => number.to_string(),
+                Some(serde_json::Value::String(raw)) => raw.clone(),
+                _ => continue,
+            };
+            if let Some(scaled) = scale_decimal(&raw, decimals) {
+                row.insert(Self::decimal_column(&column), serde_json::Value::String(scaled));
+            }
+        }
+    }
+
+    /// Column holding the BCS bytes of value field `column` when
+    /// [`DubheConfig::store_raw_bcs`] is set. Each value field has its own, so a single field
+    /// update keeps the raw bytes of the record in step with its columns.
+    pub fn raw_bcs_column(column: &str) -> String {
+        format!("raw_{}_bcs", column)
+    }
+
+    /// Raw BCS columns of `table_id`: the key tuple, then each value field. Empty unless
+    /// [`DubheConfig::store_raw_bcs`] is set.
+    pub fn raw_bcs_columns(&self, table_id: &str) -> Vec<String> {
+        if !self.store_raw_bcs {
+            return Vec::new();
+        }
+        std::iter::once(RAW_KEY_BCS_COLUMN.to_string())
+            .chain(
+                self.value_fields(table_id)
+                    .map(|field| Self::raw_bcs_column(&field.column_name())),
+            )
+            .collect()
+    }
+
+    /// Append the raw key tuple and value fields of a record to its column lists
+    fn push_raw_bcs_columns(
+        &self,
+        columns: &mut RecordColumns,
+        table_id: &str,
+        key_tuple: &[Vec<u8>],
+        value_tuple: &[Vec<u8>],
+        dialect: Dialect,
+    ) -> Result<()> {
+        fn push(list: &mut String, part: &str) {
+            if !list.is_empty() {
+                list.push(',');
+            }
+            list.push_str(part);
+        }
+
+        let key = dialect.bytes_literal(&bcs::to_bytes(key_tuple)?);
+        push(&mut columns.names, &format!("\"{}\"", RAW_KEY_BCS_COLUMN));
+        push(&mut columns.values, &key);
+        push(&mut columns.assignments, &format!("\"{}\" = {}", RAW_KEY_BCS_COLUMN, key));
+        for field in self.value_fields(table_id) {
+            let column = Self::raw_bcs_column(&field.column_name());
+            let bytes = value_tuple.get(field.index as usize).ok_or_else(|| {
+                anyhow::anyhow!("Value tuple of {} has no field {}", table_id, field.index)
+            })?;
+            let value = dialect.bytes_literal(bytes);
+            let assignment = format!("\"{}\" = {}", column, value);
+            push(&mut columns.names, &format!("\"{}\"", column));
+            push(&mut columns.values, &value);
+            push(&mut columns.assignments, &assignment);
+            push(&mut columns.value_assignments, &assignment);
+        }
+        Ok(())
+    }
+
+    /// `"raw_<field>_bcs" = <bytes>,` for a single field update of `table_id`, empty unless
+    /// [`DubheConfig::store_raw_bcs`] is set
+    fn raw_bcs_field_assignment(
+        &self,
+        table_id: &str,
+        field_index: u8,
+        value: &[u8],
+        dialect: Dialect,
+    ) -> String {
+        if !self.store_raw_bcs {
+            return String::new();
+        }
+        self.value_fields(table_id)
+            .find(|field| field.index == field_index)
+            .map(|field| {
+                format!(
+                    "\"{}\" = {},",
+                    Self::raw_bcs_column(&field.column_name()),
+                    dialect.bytes_literal(value)
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// SQL columns of a query grouping a table by `column` and aggregating `aggregate` over
+    /// `aggregate_column`, both given as a Move field or a column name. Fails unless both are
+    /// columns of the table and the aggregated one is stored as an SQL integer. The aggregated
+    /// column is `None` when `aggregate` needs none.
+    pub fn group_by_columns(
+        &self,
+        table_id: &str,
+        column: &str,
+        aggregate: Aggregate,
+        aggregate_column: Option<&str>,
+    ) -> Result<(String, Option<String>)> {
+        let columns = self.selectable_columns(table_id);
+        let known = |column: &str| columns.iter().any(|c| c == column);
+        let column = self.column_name(table_id, column);
+        if !known(&column) {
+            return Err(anyhow::anyhow!("Unknown column '{}' in table {}", column, table_id));
+        }
+        if !aggregate.needs_column() {
+            return Ok((column, None));
+        }
+        let aggregate_column = aggregate_column
+            .map(|field| self.column_name(table_id, field))
+            .ok_or_else(|| anyhow::anyhow!("{:?} needs a column to aggregate", aggregate))?;
+        let aggregate_column = aggregate_column.as_str();
+        if !known(aggregate_column) {
+            return Err(anyhow::anyhow!(
+                "Unknown column '{}' in table {}",
+                aggregate_column,
+                table_id
+            ));
+        }
+        let numeric = self
+            .table_fields(table_id)
+            .find(|field| field.column_name() == aggregate_column)
+            .map(|field| matches!(field.db_type.as_str(), "INTEGER" | "BIGINT"))
+            .unwrap_or_else(|| {
+                ["created_at_timestamp_ms", "updated_at_timestamp_ms"].contains(&aggregate_column)
+            });
+        if !numeric {
+            return Err(anyhow::anyhow!(
+                "Cannot compute {:?} of column '{}' in table {}, it is not numeric",
+                aggregate,
+                aggregate_column,
+                table_id
+            ));
+        }
+        Ok((column, Some(aggregate_column.to_string())))
+    }
+
+    fn indexed_fields<'a>(
+        &'a self,
+        table_id: &str,
+        positions: fn(&TableFieldIndex) -> &Vec<usize>,
+    ) -> impl Iterator<Item = &'a Field> + 'a {
+        self.field_index
+            .get(table_id)
+            .map(positions)
+            .into_iter()
+            .flatten()
+            .map(move |&position| &self.fields[position])
+    }
+
+    /// Fields of a table in config order
+    pub(crate) fn table_fields<'a>(
+        &'a self,
+        table_id: &str,
+    ) -> impl Iterator<Item = &'a Field> + 'a {
+        self.indexed_fields(table_id, |index| &index.all)
+    }
+
+    /// Key fields of a table in key tuple order
+    fn key_fields<'a>(&'a self, table_id: &str) -> impl Iterator<Item = &'a Field> + 'a {
+        self.indexed_fields(table_id, |index| &index.keys)
+    }
+
+    /// Value fields of a table in config order
+    fn value_fields<'a>(&'a self, table_id: &str) -> impl Iterator<Item = &'a Field> + 'a {
+        self.indexed_fields(table_id, |index| &index.values)
+    }
+
+    pub fn field_names_by_table_and_primary_key(&self, table_id: &str) -> Vec<String> {
+        self.key_fields(table_id)
+            .map(|field| format!("\"{}\"", field.column_name()))
             .collect()
     }
 
     pub fn field_names_by_table(&self, table_id: &str) -> Vec<String> {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id)
-            .map(|field| format!("\"{}\"", field.name))
+        self.table_fields(table_id)
+            .map(|field| format!("\"{}\"", field.column_name()))
             .collect()
     }
 
     pub fn field_names_and_db_types_by_table(&self, table_id: &str) -> Vec<String> {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id)
-            .map(|field| format!("\"{}\" {}", field.name, field.db_type))
+        self.table_fields(table_id)
+            .map(|field| format!("\"{}\" {}", field.column_name(), field.db_type))
             .collect()
     }
 
+    /// Write the SQL literal of `field` in `dialect` to `out`, read from the key or value tuple
+    /// depending on the field. Non-enum value fields are written without intermediate copies
+    /// through [`write_sql_value`] for Postgres; keys, enums and SQLite values are formatted
+    /// first and then appended.
+    fn write_field_sql_value(
+        &self,
+        out: &mut String,
+        field: &Field,
+        key_tuple: &[Vec<u8>],
+        value_tuple: &[Vec<u8>],
+        dialect: Dialect,
+    ) -> Result<()> {
+        let tuple = if field.primary_key { key_tuple } else { value_tuple };
+        let bytes = tuple.get(field.index as usize).ok_or_else(|| {
+            anyhow::anyhow!("{}.{} is missing from the record", field.table, field.name)
+        })?;
+        if self.is_enum(&field.move_type) {
+            let index = self.enum_index(&field.move_type, bytes)?;
+            out.push_str(&self.enum_value(&field.move_type, index));
+        } else if field.primary_key {
+            out.push_str(&dialect.key_sql_string(&field.move_type, bytes)?);
+        } else if dialect == Dialect::Sqlite {
+            out.push_str(&sqlite_sql_string(&field.move_type, bytes)?);
+        } else {
+            write_sql_value(out, &field.move_type, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Build every column list an upsert of the record needs in one pass over the table's
+    /// fields. For Postgres the lists are identical to the joined output of the per-list
+    /// methods.
+    pub fn record_columns(
+        &self,
+        table_id: &str,
+        key_tuple: &[Vec<u8>],
+        value_tuple: &[Vec<u8>],
+        dialect: Dialect,
+    ) -> Result<RecordColumns> {
+        fn append(list: &mut String, parts: &[&str]) {
+            if !list.is_empty() {
+                list.push(',');
+            }
+            parts.iter().for_each(|part| list.push_str(part));
+        }
+
+        let mut columns = RecordColumns::default();
+        let mut keys = Vec::new();
+        // One scratch buffer for the values of every field of the record
+        let mut value = String::new();
+        for field in self.table_fields(table_id) {
+            let name = field.column_name();
+            value.clear();
+            self.write_field_sql_value(&mut value, field, key_tuple, value_tuple, dialect)?;
+            append(&mut columns.names, &["\"", &name, "\""]);
+            append(&mut columns.values, &[&value]);
+            append(&mut columns.assignments, &["\"", &name, "\" = ", &value]);
+            if field.primary_key {
+                keys.push((field.index, name));
+            } else {
+                append(&mut columns.value_assignments, &["\"", &name, "\" = ", &value]);
+            }
+        }
+        keys.sort_by_key(|(index, _)| *index);
+        for (_, name) in keys {
+            append(&mut columns.key_names, &["\"", &name, "\""]);
+        }
+        Ok(columns)
+    }
+
     pub fn field_values_by_table(
         &self,
         table_id: &str,
         key_tuple: &Vec<Vec<u8>>,
         value_tuple: &Vec<Vec<u8>>,
     ) -> Vec<String> {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id)
+        self.table_fields(table_id)
             .map(|field| {
                 if field.primary_key {
                     if self.is_enum(&field.move_type) {
-                        let enum_index = bcs::from_bytes(&key_tuple[field.index as usize]).unwrap();
+                        let enum_index = self
+                            .enum_index(&field.move_type, &key_tuple[field.index as usize])
+                            .unwrap();
                         self.enum_value(&field.move_type, enum_index)
                     } else {
-                        into_sql_string(&field.move_type, &key_tuple[field.index as usize]).unwrap()
+                        key_sql_string(&field.move_type, &key_tuple[field.index as usize]).unwrap()
                     }
                 } else {
                     if self.is_enum(&field.move_type) {
-                        let enum_index =
-                            bcs::from_bytes(&value_tuple[field.index as usize]).unwrap();
+                        let enum_index = self
+                            .enum_index(&field.move_type, &value_tuple[field.index as usize])
+                            .unwrap();
                         self.enum_value(&field.move_type, enum_index)
                     } else {
                         into_sql_string(&field.move_type, &value_tuple[field.index as usize])
@@ -390,17 +1287,17 @@ impl DubheConfig {
         table_id: &str,
         key_tuple: &Vec<Vec<u8>>,
         value_tuple: &Vec<Vec<u8>>,
-    ) -> BTreeMap<String, ProtoValue> {
+    ) -> Result<BTreeMap<String, ProtoValue>> {
         let mut fields = BTreeMap::new();
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id)
-            .for_each(|field| {
+        self.table_fields(table_id)
+            .try_for_each(|field| {
                 if field.primary_key {
                     if self.is_enum(&field.move_type) {
-                        let enum_index = bcs::from_bytes(&key_tuple[field.index as usize]).unwrap();
+                        let enum_index = self
+                            .enum_index(&field.move_type, &key_tuple[field.index as usize])
+                            .unwrap();
                         fields.insert(
-                            field.name.clone(),
+                            field.column_name(),
                             ProtoValue {
                                 kind: Some(prost_types::value::Kind::StringValue(
                                     self.enum_value_string(&field.move_type, enum_index),
@@ -409,16 +1306,17 @@ impl DubheConfig {
                         );
                     } else {
                         fields.insert(
-                            field.name.clone(),
-                            field.proto_value(&key_tuple[field.index as usize]),
+                            field.column_name(),
+                            field.proto_value(&key_tuple[field.index as usize])?,
                         );
                     }
                 } else {
                     if self.is_enum(&field.move_type) {
-                        let enum_index =
-                            bcs::from_bytes(&value_tuple[field.index as usize]).unwrap();
+                        let enum_index = self
+                            .enum_index(&field.move_type, &value_tuple[field.index as usize])
+                            .unwrap();
                         fields.insert(
-                            field.name.clone(),
+                            field.column_name(),
                             ProtoValue {
                                 kind: Some(prost_types::value::Kind::StringValue(
                                     self.enum_value_string(&field.move_type, enum_index),
@@ -427,13 +1325,14 @@ impl DubheConfig {
                         );
                     } else {
                         fields.insert(
-                            field.name.clone(),
-                            field.proto_value(&value_tuple[field.index as usize]),
+                            field.column_name(),
+                            field.proto_value(&value_tuple[field.index as usize])?,
                         );
                     }
                 }
-            });
-        fields
+                Ok::<_, anyhow::Error>(())
+            })?;
+        Ok(fields)
     }
 
     pub fn field_proto_value_by_table_and_index(
@@ -441,16 +1340,17 @@ impl DubheConfig {
         table_id: &str,
         index: u8,
         value: &[u8],
-    ) -> BTreeMap<String, ProtoValue> {
+    ) -> Result<BTreeMap<String, ProtoValue>> {
         let mut fields = BTreeMap::new();
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id && field.index == index)
-            .for_each(|field| {
+        self.table_fields(table_id)
+            .filter(|field| field.index == index)
+            .try_for_each(|field| {
                 if self.is_enum(&field.move_type) {
-                    let enum_index = bcs::from_bytes(&value).unwrap();
+                    let enum_index = self
+                        .enum_index(&field.move_type, value)
+                        .unwrap();
                     fields.insert(
-                        field.name.clone(),
+                        field.column_name(),
                         ProtoValue {
                             kind: Some(prost_types::value::Kind::StringValue(
                                 self.enum_value_string(&field.move_type, enum_index),
@@ -458,10 +1358,11 @@ impl DubheConfig {
                         },
                     );
                 } else {
-                    fields.insert(field.name.clone(), field.proto_value(value));
+                    fields.insert(field.column_name(), field.proto_value(value)?);
                 }
-            });
-        fields
+                Ok::<_, anyhow::Error>(())
+            })?;
+        Ok(fields)
     }
 
     pub fn field_values_with_set_by_table(
@@ -470,39 +1371,40 @@ impl DubheConfig {
         key_tuple: &Vec<Vec<u8>>,
         value_tuple: &Vec<Vec<u8>>,
     ) -> Vec<String> {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id)
+        self.table_fields(table_id)
             .map(|field| {
                 if field.primary_key {
                     if self.is_enum(&field.move_type) {
-                        let enum_index = bcs::from_bytes(&key_tuple[field.index as usize]).unwrap();
+                        let enum_index = self
+                            .enum_index(&field.move_type, &key_tuple[field.index as usize])
+                            .unwrap();
                         format!(
                             "\"{}\" = {}",
-                            field.name,
+                            field.column_name(),
                             self.enum_value(&field.move_type, enum_index)
                         )
                     } else {
                         format!(
                             "\"{}\" = {}",
-                            field.name,
-                            into_sql_string(&field.move_type, &key_tuple[field.index as usize])
+                            field.column_name(),
+                            key_sql_string(&field.move_type, &key_tuple[field.index as usize])
                                 .unwrap()
                         )
                     }
                 } else {
                     if self.is_enum(&field.move_type) {
-                        let enum_index =
-                            bcs::from_bytes(&value_tuple[field.index as usize]).unwrap();
+                        let enum_index = self
+                            .enum_index(&field.move_type, &value_tuple[field.index as usize])
+                            .unwrap();
                         format!(
                             "\"{}\" = {}",
-                            field.name,
+                            field.column_name(),
                             self.enum_value(&field.move_type, enum_index)
                         )
                     } else {
                         format!(
                             "\"{}\" = {}",
-                            field.name,
+                            field.column_name(),
                             into_sql_string(&field.move_type, &value_tuple[field.index as usize])
                                 .unwrap()
                         )
@@ -517,21 +1419,21 @@ impl DubheConfig {
         table_id: &str,
         value_tuple: &Vec<Vec<u8>>,
     ) -> Vec<String> {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id && !field.primary_key)
+        self.value_fields(table_id)
             .map(|field| {
                 if self.is_enum(&field.move_type) {
-                    let enum_index = bcs::from_bytes(&value_tuple[field.index as usize]).unwrap();
+                    let enum_index = self
+                        .enum_index(&field.move_type, &value_tuple[field.index as usize])
+                        .unwrap();
                     format!(
                         "\"{}\" = {}",
-                        field.name,
+                        field.column_name(),
                         self.enum_value(&field.move_type, enum_index)
                     )
                 } else {
                     format!(
                         "\"{}\" = {}",
-                        field.name,
+                        field.column_name(),
                         into_sql_string(&field.move_type, &value_tuple[field.index as usize])
                             .unwrap()
                     )
@@ -544,23 +1446,25 @@ impl DubheConfig {
         &self,
         table_id: &str,
         key_tuple: &Vec<Vec<u8>>,
+        dialect: Dialect,
     ) -> Vec<String> {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id && field.primary_key)
+        self.key_fields(table_id)
             .map(|field| {
                 if self.is_enum(&field.move_type) {
-                    let enum_index = bcs::from_bytes(&key_tuple[field.index as usize]).unwrap();
+                    let enum_index = self
+                        .enum_index(&field.move_type, &key_tuple[field.index as usize])
+                        .unwrap();
                     format!(
                         "\"{}\" = {}",
-                        field.name,
+                        field.column_name(),
                         self.enum_value(&field.move_type, enum_index)
                     )
                 } else {
                     format!(
                         "\"{}\" = {}",
-                        field.name,
-                        into_sql_string(&field.move_type, &key_tuple[field.index as usize])
+                        field.column_name(),
+                        dialect
+                            .key_sql_string(&field.move_type, &key_tuple[field.index as usize])
                             .unwrap()
                     )
                 }
@@ -573,23 +1477,25 @@ impl DubheConfig {
         table_id: &str,
         index: u8,
         value: &[u8],
+        dialect: Dialect,
     ) -> String {
-        self.fields
-            .iter()
-            .filter(|field| field.table == table_id && field.index == index && !field.primary_key)
+        self.value_fields(table_id)
+            .filter(|field| field.index == index)
             .map(|field| {
                 if self.is_enum(&field.move_type) {
-                    let enum_index = bcs::from_bytes(&value).unwrap();
+                    let enum_index = self
+                        .enum_index(&field.move_type, value)
+                        .unwrap();
                     format!(
                         "\"{}\" = {}",
-                        field.name,
+                        field.column_name(),
                         self.enum_value(&field.move_type, enum_index)
                     )
                 } else {
                     format!(
                         "\"{}\" = {}",
-                        field.name,
-                        into_sql_string(&field.move_type, &value).unwrap()
+                        field.column_name(),
+                        dialect.sql_string(&field.move_type, value).unwrap()
                     )
                 }
             })
@@ -597,17 +1503,55 @@ impl DubheConfig {
             .join(",")
     }
 
-    pub fn is_exist_primary_key(&self, table_id: &str) -> bool {
-        self.fields
+    /// Minimum time between two subscriber pushes of the same key of `table_id`, if throttled
+    pub fn push_min_interval(&self, table_id: &str) -> Option<std::time::Duration> {
+        self.tables
+            .iter()
+            .find(|table| table.name == table_id)
+            .and_then(|table| table.push_min_interval_ms)
+            .filter(|interval_ms| *interval_ms > 0)
+            .map(std::time::Duration::from_millis)
+    }
+
+    /// Conflict policy of `table_id`, [`ConflictPolicy::Update`] for unknown tables
+    pub fn conflict_policy(&self, table_id: &str) -> ConflictPolicy {
+        self.tables
+            .iter()
+            .find(|table| table.name == table_id)
+            .map(|table| table.conflict_policy)
+            .unwrap_or_default()
+    }
+
+    pub fn is_offchain_table(&self, table_id: &str) -> bool {
+        self.tables
             .iter()
-            .any(|field| field.table == table_id && field.primary_key)
+            .any(|table| table.name == table_id && table.offchain)
+    }
+
+    pub fn is_exist_primary_key(&self, table_id: &str) -> bool {
+        self.key_fields(table_id).next().is_some()
+    }
+
+    pub fn table_layout(&self, table_id: &str) -> TableLayout {
+        if self.is_exist_primary_key(table_id) {
+            TableLayout::Keyed
+        } else if self.is_offchain_table(table_id) {
+            TableLayout::AppendOnly
+        } else {
+            TableLayout::Singleton
+        }
     }
 
     pub fn is_enum(&self, field_type: &str) -> bool {
         self.enums.iter().any(|enum_| enum_.name == field_type)
     }
 
-    pub fn enum_value(&self, field_type: &str, index: u8) -> String {
+    /// Discriminant of a value of enum `field_type`, decoded with the enum's configured width
+    pub fn enum_index(&self, field_type: &str, value: &[u8]) -> Result<u64> {
+        decode_enum_index(value, self.enum_widths.get(field_type).copied())
+    }
+
+    pub fn enum_value(&self, field_type: &str, index: u64) -> String {
         self.enums
             .iter()
             .find(|enum_| enum_.name == field_type && enum_.index == index)
@@ -615,7 +1559,7 @@ impl DubheConfig {
             .unwrap_or_default()
     }
 
-    pub fn enum_value_string(&self, field_type: &str, index: u8) -> String {
+    pub fn enum_value_string(&self, field_type: &str, index: u64) -> String {
         self.enums
             .iter()
             .find(|enum_| enum_.name == field_type && enum_.index == index)
@@ -624,8 +1568,69 @@ impl DubheConfig {
     }
 
     pub fn from_json(json: Value) -> Result<Self> {
-        let dubhe_config_json: DubheConfigJson = serde_json::from_value(json)?;
+        let dubhe_config_json: DubheConfigJson = serde_json::from_value(json)
+            .map_err(|e| anyhow::anyhow!("JSON parsing error: {}", e))?;
+        Self::from_config_json(dubhe_config_json)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let dubhe_config_json: DubheConfigJson = toml::from_str(content)
+            .map_err(|e| anyhow::anyhow!("TOML parsing error: {}", e))?;
+        Self::from_config_json(dubhe_config_json)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let dubhe_config_json: DubheConfigJson = serde_yaml::from_str(content)
+            .map_err(|e| anyhow::anyhow!("YAML parsing error: {}", e))?;
+        Self::from_config_json(dubhe_config_json)
+    }
+
+    /// Load the config from a `.json`, `.toml`, `.yaml` or `.yml` file.
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e)
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let json: Value = serde_json::from_str(&content)
+                    .map_err(|e| anyhow::anyhow!("JSON parsing error: {}", e))?;
+                Self::from_json(json)
+            }
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&content),
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => Self::from_yaml(&content),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported config file format: {}",
+                path.display()
+            )),
+        }
+    }
+
+    /// Read a `.json`, `.toml`, `.yaml` or `.yml` config file as JSON, for consumers that
+    /// keep the raw config around (metadata endpoint, GraphQL schema). Other extensions are
+    /// read as JSON.
+    pub fn read_config_value(path: impl AsRef<std::path::Path>) -> Result<Value> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e)
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => toml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("TOML parsing error: {}", e)),
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("YAML parsing error: {}", e)),
+            _ => serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("JSON parsing error: {}", e)),
+        }
+    }
 
+    fn from_config_json(dubhe_config_json: DubheConfigJson) -> Result<Self> {
         let original_package_id = dubhe_config_json
             .original_package_id
             .ok_or(anyhow::anyhow!("No package id found in config file"))?;
@@ -640,6 +1645,13 @@ impl DubheConfig {
             .ok_or(anyhow::anyhow!("No start checkpoint found in config file"))?;
 
         let mut dubhe_config = Self::new(original_package_id, dubhe_object_id, original_dubhe_package_id, start_checkpoint);
+        if let Some(system_tables) = dubhe_config_json.system_tables {
+            dubhe_config.set_system_tables(system_tables);
+        }
+        dubhe_config.set_exclude_tables(dubhe_config_json.exclude_tables);
+        dubhe_config.set_package_qualified_tables(dubhe_config_json.package_qualified_tables);
+        dubhe_config.set_on_unknown_table(dubhe_config_json.on_unknown_table);
+        dubhe_config.set_store_raw_bcs(dubhe_config_json.store_raw_bcs);
 
         /// handle enums
         for enum_ in dubhe_config_json.enums {
@@ -647,12 +1659,18 @@ impl DubheConfig {
                 values.iter().enumerate().for_each(|(index, value)| {
                     dubhe_config.push_enum(Enum {
                         name: name.clone(),
-                        index: index as u8,
+                        index: index as u64,
                         value: value.clone(),
                     });
                 });
             });
         }
+        for (enum_name, width) in &dubhe_config_json.enum_widths {
+            if !dubhe_config.is_enum(enum_name) {
+                return Err(anyhow::anyhow!("Unknown enum {} in enum_widths", enum_name));
+            }
+            dubhe_config.set_enum_width(enum_name, *width);
+        }
 
         // handle components
         for tables in dubhe_config_json.components {
@@ -661,9 +1679,10 @@ impl DubheConfig {
                     name: table_name.clone(),
                     offchain: table_info.offchain,
                     component: true,
+                    push_min_interval_ms: table_info.push_min_interval_ms,
+                    conflict_policy: table_info.conflict_policy,
                 });
 
-                let mut key_field_index = 0;
                 let mut value_field_index = 0;
                 for field in table_info.fields {
                     field.into_iter().for_each(|(field_name, field_type)| {
@@ -675,10 +1694,11 @@ impl DubheConfig {
                             f.move_type(field_type.clone());
                             f.db_type(get_sql_type(&field_type));
                         }
-                        if table_info.keys.contains(&field_name) {
+                        // Key tuples are emitted in `keys` order, which may differ from `fields`
+                        let key_index = table_info.keys.iter().position(|key| key == &field_name);
+                        if let Some(key_index) = key_index {
                             f.primary_key(true);
-                            f.index(key_field_index);
-                            key_field_index += 1;
+                            f.index(key_index as u8);
                         } else {
                             f.index(value_field_index);
                             f.primary_key(false);
@@ -697,9 +1717,10 @@ impl DubheConfig {
                     name: table_name.clone(),
                     offchain: table_info.offchain,
                     component: false,
+                    push_min_interval_ms: table_info.push_min_interval_ms,
+                    conflict_policy: table_info.conflict_policy,
                 });
 
-                let mut key_field_index = 0;
                 let mut value_field_index = 0;
                 for field in table_info.fields {
                     field.into_iter().for_each(|(field_name, field_type)| {
@@ -711,10 +1732,11 @@ impl DubheConfig {
                             f.move_type(field_type.clone());
                             f.db_type(get_sql_type(&field_type));
                         }
-                        if table_info.keys.contains(&field_name) {
+                        // Key tuples are emitted in `keys` order, which may differ from `fields`
+                        let key_index = table_info.keys.iter().position(|key| key == &field_name);
+                        if let Some(key_index) = key_index {
                             f.primary_key(true);
-                            f.index(key_field_index);
-                            key_field_index += 1;
+                            f.index(key_index as u8);
                         } else {
                             f.index(value_field_index);
                             f.primary_key(false);
@@ -726,127 +1748,359 @@ impl DubheConfig {
             }
         }
 
+        for (table, aliases) in &dubhe_config_json.column_aliases {
+            for (field_name, alias) in aliases {
+                let field = dubhe_config
+                    .fields
+                    .iter_mut()
+                    .find(|field| &field.table == table && &field.name == field_name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Unknown field {}.{} in column_aliases", table, field_name)
+                    })?;
+                field.column_alias(Some(alias.clone()));
+            }
+        }
+        for (table, decimals) in &dubhe_config_json.decimals {
+            for (field_name, decimals) in decimals {
+                let field = dubhe_config
+                    .fields
+                    .iter_mut()
+                    .find(|field| &field.table == table && &field.name == field_name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Unknown field {}.{} in decimals", table, field_name)
+                    })?;
+                field.decimals(Some(*decimals));
+            }
+        }
+
+        dubhe_config.validate()?;
+
+        for (table, related) in dubhe_config_json.relations {
+            for (related_table, on) in related {
+                dubhe_config.push_relation(Relation {
+                    table: table.clone(),
+                    related_table,
+                    on,
+                })?;
+            }
+        }
+
         Ok(dubhe_config)
     }
 
     pub fn create_tables_sql(&self) -> Vec<String> {
         self.tables
             .iter()
-            .map(|table| {
-                if self.is_exist_primary_key(&table.name) {
-                    let mut sql = String::new();
-                    sql.push_str(&format!(
-                        "CREATE TABLE IF NOT EXISTS {} (",
-                        format!("store_{}", table.name)
-                    ));
-                    sql.push_str(
-                        &self
-                            .field_names_and_db_types_by_table(&table.name)
-                            .join(","),
-                    );
-                    sql.push_str(",");
-                    sql.push_str("created_at_timestamp_ms BIGINT DEFAULT 0,");
-                    sql.push_str("updated_at_timestamp_ms BIGINT DEFAULT 0,");
-                    sql.push_str("last_update_digest VARCHAR(255) DEFAULT '',");
-                    sql.push_str("is_deleted BOOLEAN DEFAULT FALSE,");
-                    sql.push_str("PRIMARY KEY (");
-                    sql.push_str(
-                        &self
-                            .field_names_by_table_and_primary_key(&table.name)
-                            .join(","),
-                    );
-                    sql.push_str("));");
-                    sql
-                } else if !table.offchain {
-                    let mut sql = String::new();
-                    sql.push_str(&format!(
-                        "CREATE TABLE IF NOT EXISTS {} (",
-                        format!("store_{}", table.name)
-                    ));
-                    sql.push_str(
-                        "unique_resource_id INTEGER PRIMARY KEY CHECK (unique_resource_id = 1),",
-                    );
-                    sql.push_str(
-                        &self
-                            .field_names_and_db_types_by_table(&table.name)
-                            .join(","),
-                    );
-                    sql.push_str(",");
-                    sql.push_str("created_at_timestamp_ms BIGINT DEFAULT 0,");
-                    sql.push_str("updated_at_timestamp_ms BIGINT DEFAULT 0,");
-                    sql.push_str("last_update_digest VARCHAR(255) DEFAULT '',");
-                    sql.push_str("is_deleted BOOLEAN DEFAULT FALSE");
-                    sql.push_str(");");
-                    sql
-                } else {
-                    let mut sql = String::new();
-                    sql.push_str(&format!(
-                        "CREATE TABLE IF NOT EXISTS {} (",
-                        format!("store_{}", table.name)
-                    ));
-                    sql.push_str(
-                        &self
-                            .field_names_and_db_types_by_table(&table.name)
-                            .join(","),
-                    );
-                    sql.push_str(",");
-                    sql.push_str("created_at_timestamp_ms BIGINT DEFAULT 0,");
-                    sql.push_str("updated_at_timestamp_ms BIGINT DEFAULT 0,");
-                    sql.push_str("last_update_digest VARCHAR(255) DEFAULT '',");
-                    sql.push_str("is_deleted BOOLEAN DEFAULT FALSE");
-                    sql.push_str(");");
-                    sql
-                }
-            })
+            .filter_map(|table| self.create_table_sql(&table.name))
             .collect()
     }
 
-    pub fn can_convert_event_to_sql(&self, event: &Event) -> Result<()> {
+    /// CREATE TABLE statement of `table_name`, `None` for unknown or excluded tables
+    pub fn create_table_sql(&self, table_name: &str) -> Option<String> {
+        let table = self.tables.iter().find(|table| table.name == table_name)?;
+        if self.is_excluded_table(&table.name) {
+            return None;
+        }
+        let sql = create_store_table_sql(
+            &self.store_table_name(Some(&self.original_package_id), &table.name),
+            self.table_layout(&table.name),
+            &self.field_names_and_db_types_by_table(&table.name),
+            &self.raw_bcs_columns(&table.name),
+            &self.field_names_by_table_and_primary_key(&table.name),
+        );
+        Some(sql)
+    }
+
+    /// Returns `Ok(false)` for events of excluded tables, which should be dropped silently.
+    /// Events of tables missing from the config are handled per `on_unknown_table`.
+    pub fn can_convert_event_to_sql(&self, event: &Event) -> Result<bool> {
       if event.table_id() == "storage_submit" {
-            return Ok(());
+            return Ok(true);
         }
 
-        if event.table_id() == "dapp_fee_state"
-            && event.origin_package_id()
-                != Some(
-                    "0xa337791835d15223727ace33cce17ea0901c094c8cfbe34d089c1a18c2df7a15"
-                        .to_string(),
-                )
-        {
-            return Ok(());
+        if self.is_excluded_table(event.table_id()) {
+            return Ok(false);
         }
 
-        println!("event.origin_package_id(): {:?}", event.origin_package_id());
-        println!("self.original_package_id: {:?}", self.original_package_id);
-        if event.origin_package_id() != Some(self.original_package_id.clone()) {
-            return Err(anyhow::anyhow!(
+        if self.is_accepted_system_event(event) {
+            return Ok(true);
+        }
+
+        match self.skip_reason(event) {
+            None => Ok(true),
+            Some(SkipReason::PackageMismatch) => Err(anyhow::anyhow!(
                 "Event origin package id does not match the package id"
-            ));
+            )),
+            Some(SkipReason::UnknownTable) => match self.on_unknown_table {
+                UnknownTablePolicy::Skip => Ok(false),
+                UnknownTablePolicy::Warn => {
+                    log::warn!("Skipping event of unknown table: {}", event.table_id());
+                    Ok(false)
+                }
+                UnknownTablePolicy::Error | UnknownTablePolicy::AutoCreate => {
+                    Err(UnknownTableError {
+                        table_id: event.table_id().to_string(),
+                    }
+                    .into())
+                }
+            },
+            Some(SkipReason::ArityMismatch) => {
+                log::debug!(
+                    "Skipping event of table {}: tuples too short for its fields",
+                    event.table_id()
+                );
+                Ok(false)
+            }
         }
-        if !self
-            .fields
-            .iter()
-            .any(|field| field.table == event.table_id())
+    }
+
+    /// Why [`DubheConfig::can_convert_event_to_sql`] drops `event` or rejects it as unknown,
+    /// `None` if it is converted or belongs to an excluded table.
+    pub fn skip_reason(&self, event: &Event) -> Option<SkipReason> {
+        let table_id = event.table_id();
+        if table_id == "storage_submit"
+            || self.is_excluded_table(table_id)
+            || self.is_accepted_system_event(event)
         {
+            return None;
+        }
+        if event.origin_package_id() != Some(self.original_package_id.clone()) {
+            return Some(SkipReason::PackageMismatch);
+        }
+        if self.table_fields(table_id).next().is_none() {
+            return Some(SkipReason::UnknownTable);
+        }
+        let keys_fit = self
+            .key_fields(table_id)
+            .all(|field| (field.index as usize) < event.key_tuple().len());
+        let mut values = self.value_fields(table_id);
+        let values_fit = match event {
+            Event::StoreSetRecord(event) => {
+                values.all(|field| (field.index as usize) < event.value_tuple.len())
+            }
+            Event::StoreSetField(event) => values.any(|field| field.index == event.field_index),
+            Event::StoreDeleteRecord(_) => true,
+        };
+        if !keys_fit || !values_fit {
+            return Some(SkipReason::ArityMismatch);
+        }
+        None
+    }
+
+    /// Register a table for the unknown table of `event`, inferred from its tuple shape, and
+    /// return its CREATE TABLE statement.
+    ///
+    /// Columns are named `key_<i>` and `value_<i>` and hold the raw BCS bytes of each tuple
+    /// element. Only a `SetRecord` carries the full shape, so other events are rejected.
+    pub fn infer_table(&mut self, event: &Event) -> Result<String> {
+        let Event::StoreSetRecord(record) = event else {
             return Err(anyhow::anyhow!(
-                "Event table id does not match the table id: {}",
+                "Cannot infer table {} before a SetRecord event of it",
                 event.table_id()
             ));
+        };
+        self.push_table(Table {
+            name: record.table_id.clone(),
+            offchain: false,
+            component: !record.key_tuple.is_empty(),
+            push_min_interval_ms: None,
+            conflict_policy: ConflictPolicy::default(),
+        });
+        for index in 0..record.key_tuple.len() {
+            let mut field = Field::new(record.table_id.clone(), format!("key_{}", index));
+            field.move_type(RAW_BCS_TYPE.to_string());
+            field.db_type(get_sql_type(RAW_BCS_TYPE));
+            field.primary_key(true);
+            field.index(index as u8);
+            self.push_field(field);
+        }
+        for index in 0..record.value_tuple.len() {
+            let mut field = Field::new(record.table_id.clone(), format!("value_{}", index));
+            field.move_type(RAW_BCS_TYPE.to_string());
+            field.db_type(get_sql_type(RAW_BCS_TYPE));
+            field.primary_key(false);
+            field.index(index as u8);
+            self.push_field(field);
+        }
+        self.create_table_sql(&record.table_id)
+            .ok_or_else(|| anyhow::anyhow!("Table {} is excluded", record.table_id))
+    }
+
+    /// Check the table declarations: every component has fields, and no field name is
+    /// declared twice in a table, which would make a key field a value field as well and
+    /// shift the value tuple indexes. No two fields of a table may share a column, and no
+    /// field may take a column every store table has. Scaled fields must be unsigned
+    /// integers. Append-only tables take no conflict policy, since their rows never conflict.
+    pub fn validate(&self) -> Result<()> {
+        for table in &self.tables {
+            let mut names = std::collections::HashSet::new();
+            for field in self.table_fields(&table.name) {
+                if !names.insert(field.name.as_str()) {
+                    return Err(anyhow::anyhow!(
+                        "Field {} of table {} is declared more than once; a key field cannot also be a value field",
+                        field.name,
+                        table.name
+                    ));
+                }
+            }
+            if table.component && names.is_empty() {
+                return Err(anyhow::anyhow!("Component {} has no fields", table.name));
+            }
+            // Column aliases may not collide with another field or a column every table has
+            let mut columns = std::collections::HashSet::new();
+            for field in self.table_fields(&table.name) {
+                let column = field.column_name();
+                if RESERVED_COLUMNS.contains(&column.as_str())
+                    || SYSTEM_COLUMNS.contains(&column.as_str())
+                {
+                    return Err(anyhow::anyhow!(
+                        "Column {} of field {}.{} is reserved",
+                        column,
+                        table.name,
+                        field.name
+                    ));
+                }
+                if !columns.insert(column) {
+                    return Err(anyhow::anyhow!(
+                        "Column {} of field {}.{} is already used by another field",
+                        field.column_name(),
+                        table.name,
+                        field.name
+                    ));
+                }
+            }
+            if table.conflict_policy != ConflictPolicy::default()
+                && self.table_layout(&table.name) == TableLayout::AppendOnly
+            {
+                return Err(anyhow::anyhow!(
+                    "Table {} is append-only, every SetRecord inserts a row, so it cannot have \
+                     a conflict_policy",
+                    table.name
+                ));
+            }
+        }
+        for field in &self.fields {
+            if let Some(decimal) = DecimalType::parse(&field.move_type) {
+                // NUMERIC precision is at most 1000 in PostgreSQL, and the column needs room
+                // for every integer digit of uN::MAX
+                let fits = decimal.integer_digits().is_some_and(|digits| {
+                    decimal.precision <= 1000
+                        && u16::from(decimal.scale) <= decimal.precision
+                        && decimal.precision - u16::from(decimal.scale) >= digits
+                });
+                if !fits {
+                    return Err(anyhow::anyhow!(
+                        "Field {}.{} has invalid decimal type {}, expected decimal<uN>(p,s) \
+                         with p <= 1000 and p - s at least the digit count of uN::MAX",
+                        field.table,
+                        field.name,
+                        field.move_type
+                    ));
+                }
+                continue;
+            }
+            if !SUPPORTED_MOVE_TYPES.contains(&field.move_type.as_str())
+                && !self.is_enum(&field.move_type)
+                && !is_object_id_type(&field.move_type)
+            {
+                return Err(anyhow::anyhow!(
+                    "Field {}.{} has unsupported move type {}",
+                    field.table,
+                    field.name,
+                    field.move_type
+                ));
+            }
+        }
+        for field in self.fields.iter().filter(|field| field.decimals.is_some()) {
+            if !matches!(field.move_type.as_str(), "u8" | "u16" | "u32" | "u64" | "u128" | "u256") {
+                return Err(anyhow::anyhow!(
+                    "Field {}.{} has decimals but is not an unsigned integer",
+                    field.table,
+                    field.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every field value carried by the event decodes to exactly its move type,
+    /// so a corrupt or misaligned event is rejected up front rather than panicking or
+    /// writing a questionable literal halfway through building SQL. Enum values must name
+    /// one of the enum's variants, and every key field and value field the event carries
+    /// must be in its tuples.
+    pub fn validate_values(&self, event: &Event) -> Result<()> {
+        let mut scratch = String::new();
+        for field in self.table_fields(event.table_id()) {
+            // Every event carries the whole key tuple, only value fields may be absent
+            let value = if field.primary_key {
+                event.key_tuple().get(field.index as usize)
+            } else {
+                match event {
+                    Event::StoreSetRecord(event) => event.value_tuple.get(field.index as usize),
+                    Event::StoreSetField(event) if field.index == event.field_index => {
+                        Some(&event.value)
+                    }
+                    _ => continue,
+                }
+            };
+            let value = value.ok_or_else(|| {
+                anyhow::anyhow!("{}.{} is missing from the event", field.table, field.name)
+            })?;
+            scratch.clear();
+            let result = if self.is_enum(&field.move_type) {
+                self.enum_index(&field.move_type, value).and_then(|index| {
+                    if self.enum_value_string(&field.move_type, index).is_empty() {
+                        Err(anyhow::anyhow!("{} has no variant {}", field.move_type, index))
+                    } else {
+                        Ok(())
+                    }
+                })
+            } else if field.primary_key {
+                key_sql_string(&field.move_type, value).map(|_| ())
+            } else {
+                write_sql_value(&mut scratch, &field.move_type, value)
+            };
+            result.map_err(|e| {
+                anyhow::anyhow!("Invalid value for {}.{}: {}", field.table, field.name, e)
+            })?;
         }
         Ok(())
     }
 
+    /// Upsert, update or soft delete statement of a store event, written for `dialect`
     pub fn convert_event_to_sql(
         &self,
         event: Event,
         current_checkpoint_timestamp_ms: u64,
         current_digest: String,
+        dialect: Dialect,
     ) -> Result<String> {
-        self.can_convert_event_to_sql(&event)?;
+        if !self.can_convert_event_to_sql(&event)? {
+            return Ok(String::new());
+        }
+        self.validate_values(&event)?;
+        let store_table = self.event_store_table_name(&event);
+        let layout = self.table_layout(event.table_id());
+        let conflict_policy = self.conflict_policy(event.table_id());
         match event {
             Event::StoreSetRecord(event) => {
-                let mut sql = String::new();
-                if self.is_exist_primary_key(&event.table_id) {
+                let mut sql = String::with_capacity(SQL_CAPACITY);
+                let mut columns = self.record_columns(
+                    &event.table_id,
+                    &event.key_tuple,
+                    &event.value_tuple,
+                    dialect,
+                )?;
+                if self.store_raw_bcs {
+                    self.push_raw_bcs_columns(
+                        &mut columns,
+                        &event.table_id,
+                        &event.key_tuple,
+                        &event.value_tuple,
+                        dialect,
+                    )?;
+                }
+                if layout == TableLayout::Keyed {
                     // insert or update the record
                     // INSERT INTO config (id, database_url, port, log_level, created_at_timestamp_ms, updated_at_timestamp_ms)
                     //    VALUES (1, 'postgres://localhost:5432', 3000, 'debug', 0, 0)
@@ -857,195 +2111,176 @@ impl DubheConfig {
                     //        log_level = EXCLUDED.log_level,
                     //        created_at_timestamp_ms = EXCLUDED.created_at_timestamp_ms,
                     //        updated_at_timestamp_ms = EXCLUDED.updated_at_timestamp_ms
-                    sql.push_str(&format!("INSERT INTO store_{} (", event.table_id));
-                    sql = format!(
-                        "{} {}, created_at_timestamp_ms, updated_at_timestamp_ms, last_update_digest",
-                        sql,
-                        self.field_names_by_table(&event.table_id).join(",")
-                    );
-                    sql.push_str(") VALUES (");
+                    write!(sql, "INSERT INTO {} ( ", store_table)?;
+                    sql.push_str(&columns.names);
                     sql.push_str(
-                        &self
-                            .field_values_by_table(
-                                &event.table_id,
-                                &event.key_tuple,
-                                &event.value_tuple,
-                            )
-                            .join(","),
+                        ", created_at_timestamp_ms, updated_at_timestamp_ms, last_update_digest",
                     );
+                    sql.push_str(") VALUES (");
+                    sql.push_str(&columns.values);
                     sql.push_str(",");
-                    sql.push_str(current_checkpoint_timestamp_ms.to_string().as_str());
-                    sql.push_str(",");
-                    sql.push_str(current_checkpoint_timestamp_ms.to_string().as_str());
-                    sql.push_str(",");
-                    sql.push_str(format!("'{}'", current_digest).as_str());
-                    sql.push_str(") ON CONFLICT (");
-
-                    // Add primary key field names for conflict detection
-                    sql.push_str(
-                        &self
-                            .field_names_by_table_and_primary_key(&event.table_id)
-                            .join(","),
-                    );
-                    sql.push_str(") DO UPDATE SET ");
-
-                    // Add update fields
-                    sql.push_str(
-                        &self
-                            .field_values_with_set_by_table(
-                                &event.table_id,
-                                &event.key_tuple,
-                                &event.value_tuple,
-                            )
-                            .join(","),
-                    );
+                    write!(sql, "{}", current_checkpoint_timestamp_ms)?;
                     sql.push_str(",");
-                    sql.push_str(
-                        format!(
-                            "updated_at_timestamp_ms = {}",
-                            current_checkpoint_timestamp_ms
-                        )
-                        .as_str(),
-                    );
+                    write!(sql, "{}", current_checkpoint_timestamp_ms)?;
                     sql.push_str(",");
-                    sql.push_str(format!("last_update_digest = '{}'", current_digest).as_str());
-                    sql.push_str(";");
-                } else if !self
-                    .tables
-                    .iter()
-                    .any(|table| table.name == event.table_id && table.offchain)
-                {
-                    sql.push_str(&format!("INSERT INTO store_{} (", event.table_id));
+                    write!(sql, "'{}'", current_digest)?;
+                    sql.push_str(")");
+
+                    // Add primary key field names for conflict detection, and update fields
+                    push_conflict_clause(
+                        &mut sql,
+                        conflict_policy,
+                        &store_table,
+                        &columns.key_names,
+                        &columns.assignments,
+                        current_checkpoint_timestamp_ms,
+                        &current_digest,
+                        dialect,
+                    )?;
+                } else if layout == TableLayout::Singleton {
+                    write!(sql, "INSERT INTO {} (", store_table)?;
                     sql.push_str("unique_resource_id,");
-                    sql.push_str(&self.field_names_by_table(&event.table_id).join(","));
+                    sql.push_str(&columns.names);
                     sql.push_str(",");
                     sql.push_str(
                         "created_at_timestamp_ms, updated_at_timestamp_ms, last_update_digest",
                     );
                     sql.push_str(") VALUES (1,");
-                    sql.push_str(
-                        &self
-                            .field_values_by_table(
-                                &event.table_id,
-                                &event.key_tuple,
-                                &event.value_tuple,
-                            )
-                            .join(","),
-                    );
-                    sql.push_str(",");
-                    sql.push_str(current_checkpoint_timestamp_ms.to_string().as_str());
+                    sql.push_str(&columns.values);
                     sql.push_str(",");
-                    sql.push_str(current_checkpoint_timestamp_ms.to_string().as_str());
+                    write!(sql, "{}", current_checkpoint_timestamp_ms)?;
                     sql.push_str(",");
-                    sql.push_str(format!("'{}'", current_digest).as_str());
-                    sql.push_str(") ON CONFLICT (unique_resource_id) DO UPDATE SET ");
-                    sql.push_str(
-                        &self
-                            .field_values_by_table_and_non_primary_key(
-                                &event.table_id,
-                                &event.value_tuple,
-                            )
-                            .join(","),
-                    );
-                    sql.push_str(",");
-                    sql.push_str(
-                        format!(
-                            "updated_at_timestamp_ms = {}",
-                            current_checkpoint_timestamp_ms
-                        )
-                        .as_str(),
-                    );
+                    write!(sql, "{}", current_checkpoint_timestamp_ms)?;
                     sql.push_str(",");
-                    sql.push_str(format!("last_update_digest = '{}'", current_digest).as_str());
-                    sql.push_str(";");
+                    write!(sql, "'{}'", current_digest)?;
+                    sql.push_str(")");
+                    push_conflict_clause(
+                        &mut sql,
+                        conflict_policy,
+                        &store_table,
+                        "unique_resource_id",
+                        &columns.value_assignments,
+                        current_checkpoint_timestamp_ms,
+                        &current_digest,
+                        dialect,
+                    )?;
                 } else {
-                    sql.push_str(&format!("INSERT INTO store_{} (", event.table_id));
-                    sql.push_str(&self.field_names_by_table(&event.table_id).join(","));
+                    write!(sql, "INSERT INTO {} (", store_table)?;
+                    sql.push_str(&columns.names);
                     sql.push_str(",");
                     sql.push_str(
                         "created_at_timestamp_ms, updated_at_timestamp_ms, last_update_digest",
                     );
                     sql.push_str(") VALUES (");
-                    sql.push_str(
-                        &self
-                            .field_values_by_table(
-                                &event.table_id,
-                                &event.key_tuple,
-                                &event.value_tuple,
-                            )
-                            .join(","),
-                    );
+                    sql.push_str(&columns.values);
                     sql.push_str(",");
-                    sql.push_str(current_checkpoint_timestamp_ms.to_string().as_str());
+                    write!(sql, "{}", current_checkpoint_timestamp_ms)?;
                     sql.push_str(",");
-                    sql.push_str(current_checkpoint_timestamp_ms.to_string().as_str());
+                    write!(sql, "{}", current_checkpoint_timestamp_ms)?;
                     sql.push_str(",");
-                    sql.push_str(format!("'{}'", current_digest).as_str());
+                    write!(sql, "'{}'", current_digest)?;
                     sql.push_str(");");
                 };
                 Ok(sql)
             }
             Event::StoreSetField(event) => {
-                let mut sql = String::new();
-                if self.is_exist_primary_key(&event.table_id) {
-                    sql.push_str(&format!("UPDATE store_{} SET ", event.table_id));
+                if layout == TableLayout::AppendOnly {
+                    return Err(anyhow::anyhow!(
+                        "Cannot set a field of offchain table {} because it has no keys",
+                        event.table_id
+                    ));
+                }
+                let mut sql = String::with_capacity(SQL_CAPACITY);
+                if layout == TableLayout::Keyed {
+                    write!(sql, "UPDATE {} SET ", store_table)?;
                     sql.push_str(&self.field_value_by_table_and_index(
                         &event.table_id,
                         event.field_index,
                         &event.value,
+                        dialect,
                     ));
                     sql.push_str(",");
-                    sql.push_str(
-                        format!(
-                            "updated_at_timestamp_ms = {}",
-                            current_checkpoint_timestamp_ms
-                        )
-                        .as_str(),
-                    );
+                    sql.push_str(&self.raw_bcs_field_assignment(
+                        &event.table_id,
+                        event.field_index,
+                        &event.value,
+                        dialect,
+                    ));
+                    write!(
+                        sql,
+                        "updated_at_timestamp_ms = {}",
+                        current_checkpoint_timestamp_ms
+                    )?;
                     sql.push_str(" WHERE ");
                     sql.push_str(
                         &self
                             .field_values_by_table_and_primary_key(
                                 &event.table_id,
                                 &event.key_tuple,
+                                dialect,
                             )
                             .join(" AND "),
                     );
                     sql.push_str(";");
                 } else {
-                    sql.push_str(&format!("UPDATE store_{} SET ", event.table_id));
+                    write!(sql, "UPDATE {} SET ", store_table)?;
                     sql.push_str(&self.field_value_by_table_and_index(
                         &event.table_id,
                         event.field_index,
                         &event.value,
+                        dialect,
                     ));
                     sql.push_str(",");
-                    sql.push_str(
-                        format!(
-                            "updated_at_timestamp_ms = {}",
-                            current_checkpoint_timestamp_ms
-                        )
-                        .as_str(),
-                    );
+                    sql.push_str(&self.raw_bcs_field_assignment(
+                        &event.table_id,
+                        event.field_index,
+                        &event.value,
+                        dialect,
+                    ));
+                    write!(
+                        sql,
+                        "updated_at_timestamp_ms = {}",
+                        current_checkpoint_timestamp_ms
+                    )?;
                     sql.push_str(" WHERE unique_resource_id = 1;");
                 }
                 Ok(sql)
             }
             Event::StoreDeleteRecord(event) => {
-                let mut sql = String::new();
-                if self.is_exist_primary_key(&event.table_id) {
-                    sql.push_str(&format!("UPDATE store_{} SET is_deleted = TRUE, updated_at_timestamp_ms = {}, last_update_digest = '{}' WHERE ", event.table_id, current_checkpoint_timestamp_ms, current_digest));
+                let mut sql = String::with_capacity(SQL_CAPACITY);
+                if layout == TableLayout::Keyed {
+                    write!(
+                        sql,
+                        "UPDATE {} SET is_deleted = {}, updated_at_timestamp_ms = {}, last_update_digest = '{}' WHERE ",
+                        store_table,
+                        dialect.bool_literal(true),
+                        current_checkpoint_timestamp_ms,
+                        current_digest
+                    )?;
                     sql.push_str(
                         &self
                             .field_values_by_table_and_primary_key(
                                 &event.table_id,
                                 &event.key_tuple,
+                                dialect,
                             )
                             .join(" AND "),
                     );
                     sql.push_str(";");
+                } else if layout == TableLayout::AppendOnly {
+                    return Err(anyhow::anyhow!(
+                        "Cannot delete from offchain table {} because it has no keys",
+                        event.table_id
+                    ));
                 } else {
-                    sql.push_str(&format!("UPDATE store_{} SET is_deleted = TRUE, updated_at_timestamp_ms = {}, last_update_digest = '{}' WHERE unique_resource_id = 1;", event.table_id, current_checkpoint_timestamp_ms, current_digest));
+                    write!(
+                        sql,
+                        "UPDATE {} SET is_deleted = {}, updated_at_timestamp_ms = {}, last_update_digest = '{}' WHERE unique_resource_id = 1;",
+                        store_table,
+                        dialect.bool_literal(true),
+                        current_checkpoint_timestamp_ms,
+                        current_digest
+                    )?;
                 }
                 Ok(sql)
             }
@@ -1053,13 +2288,14 @@ impl DubheConfig {
     }
 
     pub fn convert_event_to_proto_struct(&self, event: &Event) -> Result<Struct> {
+        self.validate_values(event)?;
         match event {
             Event::StoreSetRecord(event) => {
                 let fields = self.field_proto_values_by_table(
                     &event.table_id,
                     &event.key_tuple,
                     &event.value_tuple,
-                );
+                )?;
                 Ok(Struct { fields })
             }
             Event::StoreSetField(event) => {
@@ -1067,7 +2303,7 @@ impl DubheConfig {
                     &event.table_id,
                     event.field_index,
                     &event.value,
-                );
+                )?;
                 Ok(Struct { fields })
             }
             _ => Ok(Struct {
@@ -1082,6 +2318,12 @@ pub struct TableJsonInfo {
     pub fields: Vec<HashMap<String, String>>,
     pub keys: Vec<String>,
     pub offchain: bool,
+    /// Minimum time between two subscriber pushes of the same key, unset to push every change
+    #[serde(default)]
+    pub push_min_interval_ms: Option<u64>,
+    /// `update` (default), `ignore` or `error`, see [`ConflictPolicy`]
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1093,6 +2335,28 @@ pub struct DubheConfigJson {
     pub dubhe_object_id: Option<String>,
     pub original_dubhe_package_id: Option<String>,
     pub start_checkpoint: Option<String>,
+    #[serde(default)]
+    pub system_tables: Option<Vec<String>>,
+    #[serde(default)]
+    pub exclude_tables: Vec<String>,
+    #[serde(default)]
+    pub package_qualified_tables: bool,
+    #[serde(default)]
+    pub on_unknown_table: UnknownTablePolicy,
+    #[serde(default)]
+    pub store_raw_bcs: bool,
+    /// `table -> move field -> SQL column` renames
+    #[serde(default)]
+    pub column_aliases: HashMap<String, HashMap<String, String>>,
+    /// `table -> related table -> move fields` both tables are keyed by
+    #[serde(default)]
+    pub relations: HashMap<String, HashMap<String, Vec<String>>>,
+    /// `table -> move field -> decimals` of integer amounts with an implied decimal scale
+    #[serde(default)]
+    pub decimals: HashMap<String, HashMap<String, u8>>,
+    /// `enum -> width` of enums stored as plain integers instead of Move enums
+    #[serde(default)]
+    pub enum_widths: HashMap<String, EnumWidth>,
 }
 
 #[derive(Debug, Clone)]
@@ -1124,7 +2388,6 @@ impl TableMetadata {
                 let mut fields = Vec::new();
                 let offchain = table_info.offchain;
                 let mut enums = HashMap::new();
-                let mut key_field_index = 0;
                 let mut value_field_index = 0;
                 for field in table_info.fields {
                     field.into_iter().for_each(|(field_name, field_type)| {
@@ -1139,15 +2402,16 @@ impl TableMetadata {
                                 enums.insert(field_type.clone(), enum_value.clone());
                             }
                         }
-                        if table_info.keys.contains(&field_name) {
+                        // Key tuples are emitted in `keys` order, which may differ from `fields`
+                        let key_index = table_info.keys.iter().position(|key| key == &field_name);
+                        if let Some(key_index) = key_index {
                             fields.push(TableField {
                                 field_name,
                                 field_type,
-                                field_index: key_field_index,
+                                field_index: key_index as u8,
                                 is_key: true,
                                 is_enum,
                             });
-                            key_field_index += 1;
                         } else {
                             fields.push(TableField {
                                 field_name,
@@ -1177,7 +2441,6 @@ impl TableMetadata {
                 let mut fields = Vec::new();
                 let mut enums = HashMap::new();
                 let offchain = table_info.offchain;
-                let mut key_field_index = 0;
                 let mut value_field_index = 0;
                 for field in table_info.fields {
                     field.into_iter().for_each(|(field_name, field_type)| {
@@ -1192,15 +2455,16 @@ impl TableMetadata {
                                 enums.insert(field_type.clone(), enum_value.clone());
                             }
                         }
-                        if table_info.keys.contains(&field_name) {
+                        // Key tuples are emitted in `keys` order, which may differ from `fields`
+                        let key_index = table_info.keys.iter().position(|key| key == &field_name);
+                        if let Some(key_index) = key_index {
                             fields.push(TableField {
                                 field_name,
                                 field_type,
-                                field_index: key_field_index,
+                                field_index: key_index as u8,
                                 is_key: true,
                                 is_enum,
                             });
-                            key_field_index += 1;
                         } else {
                             fields.push(TableField {
                                 field_name,
@@ -1259,6 +2523,11 @@ impl TableMetadata {
             | "u64"
             | "u128"
             | "u256"
+            | "i8"
+            | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
             | "bool"
             | "address"
             | "String"
@@ -1288,84 +2557,132 @@ impl TableMetadata {
         enum_[index as usize].clone()
     }
 
-    pub fn generate_create_table_sql(&self) -> String {
-        let mut fields = Vec::new();
-
-        // Add debug information
-        println!("DEBUG: table_type = '{}'", self.table_type);
-        println!("DEBUG: fields count = {}", self.fields.len());
-        for (i, field) in self.fields.iter().enumerate() {
-            println!(
-                "DEBUG: field[{}] = {{ name: '{}', is_key: {} }}",
-                i, field.field_name, field.is_key
-            );
-        }
-        println!(
-            "DEBUG: has_key_fields = {}",
-            self.fields.iter().any(|field| field.is_key)
-        );
+    /// Key column names in key tuple order, the order of the PRIMARY KEY and ON CONFLICT lists
+    pub fn key_field_names(&self) -> Vec<String> {
+        let mut keys: Vec<&TableField> = self.fields.iter().filter(|field| field.is_key).collect();
+        keys.sort_by_key(|field| field.field_index);
+        keys.into_iter().map(|field| field.field_name.clone()).collect()
+    }
 
-        // Add key fields
-        for field in &self.fields {
-            fields.push(format!(
-                "{} {}",
-                field.field_name,
-                self.get_sql_type(&field.field_type)
-            ));
+    /// How rows of this table are stored, the same choice as [`DubheConfig::table_layout`]
+    pub fn layout(&self) -> TableLayout {
+        if self.fields.iter().any(|field| field.is_key) {
+            TableLayout::Keyed
+        } else if self.offchain {
+            TableLayout::AppendOnly
+        } else {
+            TableLayout::Singleton
         }
+    }
 
-        // Always add created_at and updated_at fields
-        fields.push("created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP".to_string());
-        fields.push("updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP".to_string());
-        fields.push("last_updated_checkpoint BIGINT DEFAULT 0".to_string());
-        fields.push("is_deleted BOOLEAN DEFAULT FALSE".to_string());
-
-        // Add primary key constraint
-        if self.table_type == "resource" && !self.fields.iter().any(|field| field.is_key) {
-            println!("DEBUG: Entering special case for resource without key fields");
-            // Special case for resource type without key fields: set all fields as PRIMARY KEY
-            let all_field_names: Vec<String> = self
-                .fields
-                .iter()
-                .map(|field| field.field_name.clone())
-                .collect();
+    /// Store table this table is written to, the unqualified name [`DubheConfig`] uses
+    pub fn store_table_name(&self) -> String {
+        format!("store_{}", self.name)
+    }
 
-            println!("all_field_names: {:?}", all_field_names);
+    /// Column definitions of the fields, with the types [`DubheConfig`] gives them
+    pub fn column_definitions(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .map(|field| {
+                let db_type = if self.enums.contains_key(&field.field_type) {
+                    "TEXT".to_string()
+                } else {
+                    get_sql_type(&field.field_type)
+                };
+                format!("{} {}", quote_identifier(&field.field_name), db_type)
+            })
+            .collect()
+    }
 
-            if !all_field_names.is_empty() {
-                fields.push(format!("PRIMARY KEY ({})", all_field_names.join(", ")));
-            }
-        } else if self.fields.iter().any(|field| field.is_key) {
-            println!("DEBUG: Entering case with key fields");
-            // Case with key fields: use key fields as PRIMARY KEY
-            let key_names: Vec<String> = self
-                .fields
-                .iter()
-                .filter(|field| field.is_key)
-                .map(|field| field.field_name.clone())
-                .collect();
-
-            fields.push(format!("PRIMARY KEY ({})", key_names.join(", ")));
-        } else {
-            println!("DEBUG: Entering case without key fields");
-            // Case without key fields: use non-key fields as PRIMARY KEY
-            let value_names: Vec<String> = self
-                .fields
-                .iter()
-                .filter(|field| !field.is_key)
-                .map(|field| field.field_name.clone())
-                .collect();
+    /// Same statement as [`DubheConfig::create_table_sql`] for a config without column
+    /// aliases, package qualified tables or raw BCS columns
+    pub fn generate_create_table_sql(&self) -> String {
+        let keys: Vec<String> = self
+            .key_field_names()
+            .iter()
+            .map(|name| quote_identifier(name))
+            .collect();
+        create_store_table_sql(
+            &self.store_table_name(),
+            self.layout(),
+            &self.column_definitions(),
+            &[],
+            &keys,
+        )
+    }
 
-            if !value_names.is_empty() {
-                fields.push(format!("PRIMARY KEY ({})", value_names.join(", ")));
-            }
+    /// Statements moving a table created by the old `TableMetadata` schema
+    /// (`created_at`/`updated_at` timestamps and `last_updated_checkpoint`) to the one
+    /// [`TableMetadata::generate_create_table_sql`] creates now. Run them in one transaction.
+    ///
+    /// Postgres tables are altered in place. The old SQLite tables were named after the table
+    /// without the `store_` prefix and SQLite cannot drop their primary key, so their rows are
+    /// copied into a new `store_` table and the old table is dropped.
+    pub fn legacy_schema_migration_sql(&self, dialect: Dialect) -> Vec<String> {
+        if dialect == Dialect::Sqlite {
+            return self.legacy_sqlite_migration_sql();
+        }
+        let table = self.store_table_name();
+        let mut statements = vec![
+            format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}, \
+                 ADD COLUMN IF NOT EXISTS {} {}, ADD COLUMN IF NOT EXISTS {} {}",
+                table,
+                SYSTEM_COLUMNS[0],
+                SYSTEM_COLUMN_TYPES[0],
+                SYSTEM_COLUMNS[1],
+                SYSTEM_COLUMN_TYPES[1],
+                SYSTEM_COLUMNS[2],
+                SYSTEM_COLUMN_TYPES[2]
+            ),
+            format!(
+                "UPDATE {} SET \
+                 created_at_timestamp_ms = (EXTRACT(EPOCH FROM created_at) * 1000)::BIGINT, \
+                 updated_at_timestamp_ms = (EXTRACT(EPOCH FROM updated_at) * 1000)::BIGINT",
+                table
+            ),
+            format!(
+                "ALTER TABLE {} DROP COLUMN created_at, DROP COLUMN updated_at, \
+                 DROP COLUMN last_updated_checkpoint",
+                table
+            ),
+        ];
+        // The old schema made every field of a keyless table its primary key
+        if self.layout() != TableLayout::Keyed {
+            statements.push(format!(
+                "ALTER TABLE {} DROP CONSTRAINT IF EXISTS {}_pkey",
+                table, table
+            ));
         }
+        if self.layout() == TableLayout::Singleton {
+            statements.push(format!(
+                "ALTER TABLE {} ADD COLUMN unique_resource_id INTEGER DEFAULT 1 \
+                 PRIMARY KEY CHECK (unique_resource_id = 1)",
+                table
+            ));
+        }
+        statements
+    }
 
-        format!(
-            "CREATE TABLE IF NOT EXISTS {} ({})",
-            self.name,
-            fields.join(", ")
-        )
+    fn legacy_sqlite_migration_sql(&self) -> Vec<String> {
+        let columns: Vec<String> =
+            self.fields.iter().map(|field| quote_identifier(&field.field_name)).collect();
+        let to_ms = |column: &str| format!("CAST(strftime('%s', {}) AS INTEGER) * 1000", column);
+        vec![
+            self.generate_create_table_sql(),
+            format!(
+                "INSERT INTO {} ({},created_at_timestamp_ms,updated_at_timestamp_ms,is_deleted) \
+                 SELECT {},{},{},is_deleted FROM \"{}\"",
+                self.store_table_name(),
+                columns.join(","),
+                columns.join(","),
+                to_ms("created_at"),
+                to_ms("updated_at"),
+                self.name
+            ),
+            format!("DROP TABLE \"{}\"", self.name),
+        ]
     }
 
     pub fn generate_insert_table_fields_sql(&self) -> Vec<String> {
@@ -1390,22 +2707,6 @@ impl TableMetadata {
         )
     }
 
-    fn get_sql_type(&self, type_: &str) -> String {
-        match type_ {
-            "u8" => "INTEGER",
-            "u16" => "INTEGER",
-            "u32" => "INTEGER",
-            "u64" => "INTEGER",
-            "u128" => "TEXT",
-            "u256" => "TEXT",
-            "address" => "TEXT",
-            "String" => "TEXT",
-            "bool" => "BOOLEAN",
-            _ => "TEXT",
-        }
-        .to_string()
-    }
-
     pub fn parse_table_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<DBData>> {
         let mut result = Vec::new();
         for (key_index, field) in self.fields.iter().filter(|field| field.is_key).enumerate() {
@@ -1449,65 +2750,68 @@ impl TableMetadata {
 }
 
 pub fn into_sql_string(type_: &str, value: &[u8]) -> Result<String> {
+    check_width(type_, value)?;
     match type_ {
         "u8" => {
-            let v: u8 = bcs::from_bytes(value).unwrap();
+            let v: u8 = bcs::from_bytes(value)?;
             Ok(v.to_string())
         }
         "u16" => {
-            let v: u16 = bcs::from_bytes(value).unwrap();
+            let v: u16 = bcs::from_bytes(value)?;
             Ok(v.to_string())
         }
         "u32" => {
-            let v: u32 = bcs::from_bytes(value).unwrap();
+            let v: u32 = bcs::from_bytes(value)?;
             Ok(v.to_string())
         }
         "u64" => {
-            let v: u64 = bcs::from_bytes(value).unwrap();
+            let v: u64 = bcs::from_bytes(value)?;
             Ok(v.to_string())
         }
         "u128" => {
-            let v: u128 = bcs::from_bytes(value).unwrap();
+            let v: u128 = bcs::from_bytes(value)?;
             Ok(format!("'{}'", v.to_string()))
         }
         "u256" => {
-            let v: U256 = bcs::from_bytes(value).unwrap();
+            let v: U256 = bcs::from_bytes(value)?;
             Ok(format!("'{}'", v.to_string()))
         }
+        "i8" | "i16" | "i32" | "i64" => Ok(decode_signed(type_, value)?.to_string()),
+        "i128" => Ok(format!("'{}'", decode_signed(type_, value)?)),
         "String" => {
-            let v: String = bcs::from_bytes(value).unwrap();
-            Ok(format!("'{}'", v))
+            let v: String = bcs::from_bytes(value)?;
+            Ok(format!("'{}'", escape(&v)))
         }
         "bool" => {
-            let v: bool = bcs::from_bytes(value).unwrap();
-            Ok(v.to_string())
+            let v: bool = bcs::from_bytes(value)?;
+            Ok(Dialect::Postgres.bool_literal(v).to_string())
         }
         "address" => {
-            let v: SuiAddress = bcs::from_bytes(value).unwrap();
+            let v = parse_address(value)?;
             Ok(format!("'{}'", v.to_string()))
         }
         "vector<u8>" => {
-            let v: Vec<u8> = bcs::from_bytes(value).unwrap();
+            let v: Vec<u8> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| v.to_string()).collect();
-            Ok(format!("ARRAY[{}]", values.join(", ")))
+            Ok(untyped_array_literal(type_, &values))
         }
         "vector<u16>" => {
-            let v: Vec<u16> = bcs::from_bytes(value).unwrap();
+            let v: Vec<u16> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| v.to_string()).collect();
-            Ok(format!("ARRAY[{}]", values.join(", ")))
+            Ok(untyped_array_literal(type_, &values))
         }
         "vector<u32>" => {
-            let v: Vec<u32> = bcs::from_bytes(value).unwrap();
+            let v: Vec<u32> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| v.to_string()).collect();
-            Ok(format!("ARRAY[{}]", values.join(", ")))
+            Ok(untyped_array_literal(type_, &values))
         }
         "vector<u64>" => {
-            let v: Vec<u64> = bcs::from_bytes(value).unwrap();
+            let v: Vec<u64> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| v.to_string()).collect();
-            Ok(format!("ARRAY[{}]", values.join(", ")))
+            Ok(untyped_array_literal(type_, &values))
         }
         "vector<u128>" => {
-            let v: Vec<u128> = bcs::from_bytes(value).unwrap();
+            let v: Vec<u128> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| format!("'{}'", v.to_string())).collect();
             if values.is_empty() {
                 Ok("ARRAY[]::TEXT[]".to_string())
@@ -1516,7 +2820,7 @@ pub fn into_sql_string(type_: &str, value: &[u8]) -> Result<String> {
             }
         }
         "vector<u256>" => {
-            let v: Vec<U256> = bcs::from_bytes(value).unwrap();
+            let v: Vec<U256> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| format!("'{}'", v.to_string())).collect();
             if values.is_empty() {
                 Ok("ARRAY[]::TEXT[]".to_string())
@@ -1525,7 +2829,7 @@ pub fn into_sql_string(type_: &str, value: &[u8]) -> Result<String> {
             }
         }
         "vector<address>" => {
-            let v: Vec<SuiAddress> = bcs::from_bytes(value).unwrap();
+            let v = parse_address_vector(value)?;
             let values: Vec<String> = v.iter().map(|v| format!("'{}'", v.to_string())).collect();
             if values.is_empty() {
                 Ok("ARRAY[]::TEXT[]".to_string())
@@ -1534,13 +2838,16 @@ pub fn into_sql_string(type_: &str, value: &[u8]) -> Result<String> {
             }
         }
         "vector<bool>" => {
-            let v: Vec<bool> = bcs::from_bytes(value).unwrap();
-            let values: Vec<String> = v.iter().map(|v| v.to_string()).collect();
-            Ok(format!("ARRAY[{}]", values.join(", ")))
+            let v: Vec<bool> = bcs::from_bytes(value)?;
+            let values: Vec<String> = v
+                .iter()
+                .map(|v| Dialect::Postgres.bool_literal(*v).to_string())
+                .collect();
+            Ok(untyped_array_literal(type_, &values))
         }
         "vector<String>" => {
-            let v: Vec<String> = bcs::from_bytes(value).unwrap();
-            let values: Vec<String> = v.iter().map(|v| format!("'{}'", v)).collect();
+            let v: Vec<String> = bcs::from_bytes(value)?;
+            let values: Vec<String> = v.iter().map(|v| format!("'{}'", escape(v))).collect();
             if values.is_empty() {
                 Ok("ARRAY[]::TEXT[]".to_string())
             } else {
@@ -1548,19 +2855,339 @@ pub fn into_sql_string(type_: &str, value: &[u8]) -> Result<String> {
             }
         }
         "vector<vector<u8>>" => {
-            let v: Vec<Vec<u8>> = bcs::from_bytes(value).unwrap();
+            let v: Vec<Vec<u8>> = bcs::from_bytes(value)?;
             let values: Vec<String> = v.iter().map(|v| format!("ARRAY{:?}", v)).collect();
             Ok(format!("ARRAY[{}]", values.join(", ")))
         }
-        _ => Err(anyhow::anyhow!("Invalid move type: {}", type_)),
+        RAW_BCS_TYPE => Ok(format!("'0x{}'", hex::encode(value))),
+        _ if is_object_id_type(type_) => Ok(format!("'{}'", parse_object_id(value)?)),
+        _ => match DecimalType::parse(type_) {
+            Some(decimal) => decimal.decode(value),
+            None => Err(anyhow::anyhow!("Invalid move type: {}", type_)),
+        },
+    }
+}
+
+/// BCS size of the fixed width integer and boolean move types. Addresses are checked by
+/// [`parse_address`].
+fn fixed_width(type_: &str) -> Option<usize> {
+    match type_ {
+        "u8" | "i8" | "bool" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" => Some(4),
+        "u64" | "i64" => Some(8),
+        "u128" | "i128" => Some(16),
+        "u256" => Some(32),
+        _ => None,
+    }
+}
+
+/// Value of a signed field, see [`SUPPORTED_MOVE_TYPES`] for the encoding
+fn decode_signed(type_: &str, value: &[u8]) -> Result<i128> {
+    check_width(type_, value)?;
+    Ok(match type_ {
+        "i8" => bcs::from_bytes::<i8>(value)?.into(),
+        "i16" => bcs::from_bytes::<i16>(value)?.into(),
+        "i32" => bcs::from_bytes::<i32>(value)?.into(),
+        "i64" => bcs::from_bytes::<i64>(value)?.into(),
+        "i128" => bcs::from_bytes::<i128>(value)?,
+        _ => return Err(anyhow::anyhow!("{} is not a signed integer type", type_)),
+    })
+}
+
+/// Reject a fixed width value of the wrong size, the usual sign of a tuple element decoded
+/// as the field at another index
+fn check_width(type_: &str, value: &[u8]) -> Result<()> {
+    match fixed_width(type_) {
+        Some(width) if value.len() != width => Err(anyhow::anyhow!(
+            "Expected {} bytes for {}, got {} (0x{})",
+            width,
+            type_,
+            value.len(),
+            hex::encode(value)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// `ARRAY[..]` literal of numeric or boolean elements. Postgres cannot infer the element type
+/// of an empty `ARRAY[]`, so that one is cast to the column type of `type_`.
+fn untyped_array_literal(type_: &str, values: &[String]) -> String {
+    if values.is_empty() {
+        format!("ARRAY[]::{}", get_sql_type(type_))
+    } else {
+        format!("ARRAY[{}]", values.join(", "))
+    }
+}
+
+/// SQLite literal of a BCS value. SQLite has no arrays, so vectors are stored as a JSON array
+/// in a TEXT column, with 128 and 256 bit integers and addresses as JSON strings. Booleans
+/// are written as 0 and 1, other types as in [`into_sql_string`].
+pub fn sqlite_sql_string(type_: &str, value: &[u8]) -> Result<String> {
+    fn strings<T: ToString>(items: &[T]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    let json = match type_ {
+        "bool" => {
+            return Ok(Dialect::Sqlite.bool_literal(bcs::from_bytes(value)?).to_string());
+        }
+        "vector<u8>" => serde_json::json!(bcs::from_bytes::<Vec<u8>>(value)?),
+        "vector<u16>" => serde_json::json!(bcs::from_bytes::<Vec<u16>>(value)?),
+        "vector<u32>" => serde_json::json!(bcs::from_bytes::<Vec<u32>>(value)?),
+        "vector<u64>" => serde_json::json!(bcs::from_bytes::<Vec<u64>>(value)?),
+        "vector<bool>" => serde_json::json!(bcs::from_bytes::<Vec<bool>>(value)?),
+        "vector<u128>" => serde_json::json!(strings(&bcs::from_bytes::<Vec<u128>>(value)?)),
+        "vector<u256>" => serde_json::json!(strings(&bcs::from_bytes::<Vec<U256>>(value)?)),
+        "vector<address>" => serde_json::json!(strings(&parse_address_vector(value)?)),
+        "vector<String>" => serde_json::json!(bcs::from_bytes::<Vec<String>>(value)?),
+        "vector<vector<u8>>" => serde_json::json!(bcs::from_bytes::<Vec<Vec<u8>>>(value)?),
+        _ => return into_sql_string(type_, value),
+    };
+    Ok(format!("'{}'", json.to_string().replace('\'', "''")))
+}
+
+/// Append the SQL literal of a BCS value to `out`, producing the same text as
+/// [`into_sql_string`] without its intermediate allocations: strings and byte vectors are
+/// borrowed from `value` instead of copied, and vector elements are written in place.
+/// Malformed values are reported as errors.
+pub fn write_sql_value(out: &mut String, type_: &str, value: &[u8]) -> Result<()> {
+    use std::fmt::Display;
+
+    check_width(type_, value)?;
+
+    /// Writes through to the inner string, doubling single quotes like [`escape`]
+    struct Escaped<'a>(&'a mut String);
+
+    impl Write for Escaped<'_> {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            for (index, part) in s.split('\'').enumerate() {
+                if index > 0 {
+                    self.0.push_str("''");
+                }
+                self.0.push_str(part);
+            }
+            Ok(())
+        }
+    }
+
+    fn write_array<T: Display>(
+        out: &mut String,
+        items: impl IntoIterator<Item = T>,
+        quoted: bool,
+        cast: &str,
+    ) -> Result<()> {
+        out.push_str("ARRAY[");
+        for (index, item) in items.into_iter().enumerate() {
+            if index > 0 {
+                out.push_str(", ");
+            }
+            if quoted {
+                out.push('\'');
+                write!(Escaped(out), "{}", item)?;
+                out.push('\'');
+            } else {
+                write!(out, "{}", item)?;
+            }
+        }
+        out.push(']');
+        out.push_str(cast);
+        Ok(())
+    }
+
+    /// Like [`untyped_array_literal`], casting only the empty array
+    fn write_untyped_array<T: Display>(
+        out: &mut String,
+        type_: &str,
+        items: impl ExactSizeIterator<Item = T>,
+    ) -> Result<()> {
+        if items.len() == 0 {
+            write!(out, "ARRAY[]::{}", get_sql_type(type_))?;
+            return Ok(());
+        }
+        write_array(out, items, false, "")
+    }
+
+    match type_ {
+        "u8" => write!(out, "{}", bcs::from_bytes::<u8>(value)?)?,
+        "u16" => write!(out, "{}", bcs::from_bytes::<u16>(value)?)?,
+        "u32" => write!(out, "{}", bcs::from_bytes::<u32>(value)?)?,
+        "u64" => write!(out, "{}", bcs::from_bytes::<u64>(value)?)?,
+        "u128" => write!(out, "'{}'", bcs::from_bytes::<u128>(value)?)?,
+        "u256" => write!(out, "'{}'", bcs::from_bytes::<U256>(value)?)?,
+        "bool" => out.push_str(Dialect::Postgres.bool_literal(bcs::from_bytes(value)?)),
+        "String" => {
+            out.push('\'');
+            Escaped(out).write_str(bcs::from_bytes::<&str>(value)?)?;
+            out.push('\'');
+        }
+        "address" => write!(out, "'{}'", parse_address(value)?)?,
+        "vector<u8>" => {
+            write_untyped_array(out, type_, bcs::from_bytes::<&[u8]>(value)?.iter())?
+        }
+        "vector<u16>" => {
+            write_untyped_array(out, type_, bcs::from_bytes::<Vec<u16>>(value)?.into_iter())?
+        }
+        "vector<u32>" => {
+            write_untyped_array(out, type_, bcs::from_bytes::<Vec<u32>>(value)?.into_iter())?
+        }
+        "vector<u64>" => {
+            write_untyped_array(out, type_, bcs::from_bytes::<Vec<u64>>(value)?.into_iter())?
+        }
+        "vector<bool>" => {
+            let items = bcs::from_bytes::<Vec<bool>>(value)?.into_iter();
+            write_untyped_array(out, type_, items.map(|v| Dialect::Postgres.bool_literal(v)))?
+        }
+        "vector<u128>" => {
+            write_array(out, bcs::from_bytes::<Vec<u128>>(value)?, true, "::TEXT[]")?
+        }
+        "vector<u256>" => {
+            write_array(out, bcs::from_bytes::<Vec<U256>>(value)?, true, "::TEXT[]")?
+        }
+        "vector<address>" => write_array(out, parse_address_vector(value)?, true, "::TEXT[]")?,
+        "vector<String>" => {
+            write_array(out, bcs::from_bytes::<Vec<&str>>(value)?, true, "::TEXT[]")?
+        }
+        "vector<vector<u8>>" => {
+            let items = bcs::from_bytes::<Vec<&[u8]>>(value)?;
+            let items = items.iter().map(|item| format!("ARRAY{:?}", item));
+            write_array(out, items, false, "")?
+        }
+        _ => out.push_str(&into_sql_string(type_, value)?),
+    }
+    Ok(())
+}
+
+/// SQL literal of one key tuple component.
+///
+/// Flat vector keys carry an explicit array cast so an empty key still compares against its
+/// column. Keys without a native column type (nested vectors, structs) are stored in a TEXT
+/// column as the hex of their BCS bytes, which is canonical for any Move value.
+pub fn key_sql_string(type_: &str, value: &[u8]) -> Result<String> {
+    let sql_type = get_sql_type(type_);
+    match type_ {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "u256" | "i8" | "i16" | "i32" | "i64"
+        | "i128" | "String" | "bool" | "address" => into_sql_string(type_, value),
+        _ if DecimalType::parse(type_).is_some() || is_object_id_type(type_) => {
+            into_sql_string(type_, value)
+        }
+        _ if sql_type.ends_with("[]") => {
+            let literal = into_sql_string(type_, value)?;
+            if literal.ends_with(&format!("::{}", sql_type)) {
+                Ok(literal)
+            } else {
+                Ok(format!("{}::{}", literal, sql_type))
+            }
+        }
+        _ => Ok(format!("'0x{}'", hex::encode(value))),
+    }
+}
+
+/// First 8 hex digits of a package id, used to qualify storage table names
+pub fn package_short_id(package_id: &str) -> String {
+    package_id
+        .trim_start_matches("0x")
+        .chars()
+        .take(8)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Decode a BCS encoded `address`, logging the raw bytes instead of panicking on corrupt data.
+pub fn parse_address(value: &[u8]) -> Result<SuiAddress> {
+    bcs::from_bytes::<SuiAddress>(value).map_err(|e| {
+        log::warn!("Malformed address bytes 0x{} ({} bytes): {}", hex::encode(value), value.len(), e);
+        anyhow::anyhow!("Malformed address bytes 0x{}: {}", hex::encode(value), e)
+    })
+}
+
+/// Whether `type_` is `0x2::object::ID` or `UID`, written out or by its short name. Both hold
+/// the address of an object and are stored like an `address`, so they join with address
+/// fields in relations.
+pub fn is_object_id_type(type_: &str) -> bool {
+    let name = match type_.rsplit_once("::object::") {
+        Some((package, name)) => {
+            if package.trim_start_matches("0x").trim_start_matches('0') != "2" {
+                return false;
+            }
+            name
+        }
+        None => type_,
+    };
+    matches!(name, "ID" | "UID")
+}
+
+/// Decode an object id, whose BCS encoding is exactly the 32 bytes of the object's address
+pub fn parse_object_id(value: &[u8]) -> Result<ObjectID> {
+    ObjectID::from_bytes(value).map_err(|_| {
+        anyhow::anyhow!(
+            "Expected 32 bytes for an object id, got {} (0x{})",
+            value.len(),
+            hex::encode(value)
+        )
+    })
+}
+
+/// Decode a BCS encoded `vector<address>`, see [`parse_address`].
+pub fn parse_address_vector(value: &[u8]) -> Result<Vec<SuiAddress>> {
+    bcs::from_bytes::<Vec<SuiAddress>>(value).map_err(|e| {
+        log::warn!("Malformed vector<address> bytes 0x{} ({} bytes): {}", hex::encode(value), value.len(), e);
+        anyhow::anyhow!("Malformed vector<address> bytes 0x{}: {}", hex::encode(value), e)
+    })
+}
+
+/// Decode an enum discriminant of `width`, or the ULEB128 variant tag of a Move enum when
+/// no width is given. The value has to be exactly one discriminant, so a value of another
+/// width is rejected instead of being misread.
+pub fn decode_enum_index(value: &[u8], width: Option<EnumWidth>) -> Result<u64> {
+    let index = match width {
+        None => decode_uleb128(value)?,
+        Some(EnumWidth::U8) => bcs::from_bytes::<u8>(value)? as u64,
+        Some(EnumWidth::U16) => bcs::from_bytes::<u16>(value)? as u64,
+        Some(EnumWidth::U32) => bcs::from_bytes::<u32>(value)? as u64,
+        Some(EnumWidth::U64) => bcs::from_bytes::<u64>(value)?,
+        Some(EnumWidth::U128) => {
+            let index = bcs::from_bytes::<u128>(value)?;
+            u64::try_from(index)
+                .map_err(|_| anyhow::anyhow!("Enum index {} does not fit in u64", index))?
+        }
+    };
+    Ok(index)
+}
+
+/// Canonical ULEB128 integer taking up all of `value`
+fn decode_uleb128(value: &[u8]) -> Result<u64> {
+    let invalid = |reason: &str| {
+        anyhow::anyhow!("Invalid ULEB128 enum tag 0x{}: {}", hex::encode(value), reason)
+    };
+    let mut index: u64 = 0;
+    for (position, byte) in value.iter().enumerate() {
+        let bits = u64::from(byte & 0x7f);
+        let shift = 7 * position as u32;
+        if shift >= u64::BITS || (bits << shift) >> shift != bits {
+            return Err(invalid("does not fit in u64"));
+        }
+        index |= bits << shift;
+        if byte & 0x80 == 0 {
+            if position + 1 != value.len() {
+                return Err(invalid("trailing bytes"));
+            }
+            if position > 0 && *byte == 0 {
+                return Err(invalid("not canonical"));
+            }
+            return Ok(index);
+        }
     }
+    Err(invalid("unterminated"))
 }
 
-pub fn format_sql_value(value: &Value, field_type: &str) -> String {
+pub fn format_sql_value(value: &Value, field_type: &str, dialect: Dialect) -> String {
     match field_type {
-        "bool" => value.as_bool().unwrap().to_string(),
-        "u8" | "u16" | "u32" | "u64" | "u128" => value.to_string(),
-        "u256" => {
+        "bool" => dialect.bool_literal(value.as_bool().unwrap()).to_string(),
+        "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" => {
+            value.to_string()
+        }
+        "u256" | "i128" => {
             format!("'{}'", value.as_str().unwrap_or(""))
         }
         "vector<u8>" | "vector<u16>" | "vector<u32>" | "vector<u64>" => {
@@ -1600,7 +3227,7 @@ pub fn format_sql_value(value: &Value, field_type: &str) -> String {
                 } else {
                     let values: Vec<String> = array
                         .iter()
-                        .map(|v| v.as_bool().unwrap_or(false).to_string())
+                        .map(|v| dialect.bool_literal(v.as_bool().unwrap_or(false)).to_string())
                         .collect();
                     format!("ARRAY[{}]", values.join(", "))
                 }
@@ -1647,7 +3274,86 @@ pub fn format_sql_value(value: &Value, field_type: &str) -> String {
     }
 }
 
+/// Exact decimal string of the unsigned integer `raw` divided by `10^decimals`, with
+/// trailing fractional zeros trimmed. `None` if `raw` is not an unsigned integer.
+pub fn scale_decimal(raw: &str, decimals: u8) -> Option<String> {
+    if raw.is_empty() || !raw.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let raw = raw.trim_start_matches('0');
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", raw, width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        Some(integer.to_string())
+    } else {
+        Some(format!("{}.{}", integer, fraction))
+    }
+}
+
+/// End an INSERT of a record into `store_table` with the clause `policy` asks for: an
+/// upsert applying `assignments` on a conflict over `conflict_target`, the same upsert
+/// limited to soft-deleted rows, or no clause at all. An applied upsert undeletes the row.
+#[allow(clippy::too_many_arguments)]
+fn push_conflict_clause(
+    sql: &mut String,
+    policy: ConflictPolicy,
+    store_table: &str,
+    conflict_target: &str,
+    assignments: &str,
+    updated_at_timestamp_ms: u64,
+    digest: &str,
+    dialect: Dialect,
+) -> std::fmt::Result {
+    if policy != ConflictPolicy::Error {
+        write!(sql, " ON CONFLICT ({}) DO UPDATE SET ", conflict_target)?;
+        sql.push_str(assignments);
+        write!(sql, ",is_deleted = {}", dialect.bool_literal(false))?;
+        write!(sql, ",updated_at_timestamp_ms = {}", updated_at_timestamp_ms)?;
+        write!(sql, ",last_update_digest = '{}'", digest)?;
+    }
+    if policy == ConflictPolicy::Ignore {
+        write!(sql, " WHERE {}.is_deleted", store_table)?;
+    }
+    sql.push(';');
+    Ok(())
+}
+
+/// CREATE TABLE statement of a store table, shared by [`DubheConfig::create_table_sql`] and
+/// [`TableMetadata::generate_create_table_sql`] so both create the same columns. `columns`
+/// are the field definitions and `keys` the key columns in key tuple order, both quoted.
+pub fn create_store_table_sql(
+    table_name: &str,
+    layout: TableLayout,
+    columns: &[String],
+    raw_bcs_columns: &[String],
+    keys: &[String],
+) -> String {
+    let mut definitions = Vec::new();
+    if layout == TableLayout::Singleton {
+        definitions.push(
+            "unique_resource_id INTEGER DEFAULT 1 PRIMARY KEY CHECK (unique_resource_id = 1)"
+                .to_string(),
+        );
+    }
+    definitions.push(columns.join(","));
+    for column in raw_bcs_columns {
+        definitions.push(format!("{} {}", quote_identifier(column), RAW_BCS_SQL_TYPE));
+    }
+    for (column, type_) in SYSTEM_COLUMNS.iter().zip(SYSTEM_COLUMN_TYPES) {
+        definitions.push(format!("{} {}", column, type_));
+    }
+    if layout == TableLayout::Keyed {
+        definitions.push(format!("PRIMARY KEY ({})", keys.join(",")));
+    }
+    format!("CREATE TABLE IF NOT EXISTS {} ({});", table_name, definitions.join(","))
+}
+
 pub fn get_sql_type(type_: &str) -> String {
+    if let Some(decimal) = DecimalType::parse(type_) {
+        return decimal.sql_type();
+    }
     match type_ {
         "u8" => "INTEGER",
         "u16" => "INTEGER",
@@ -1655,6 +3361,11 @@ pub fn get_sql_type(type_: &str) -> String {
         "u64" => "BIGINT",
         "u128" => "TEXT",
         "u256" => "TEXT",
+        "i8" => "SMALLINT",
+        "i16" => "SMALLINT",
+        "i32" => "INTEGER",
+        "i64" => "BIGINT",
+        "i128" => "TEXT",
         "address" => "TEXT",
         "String" => "TEXT",
         "bool" => "BOOLEAN",
@@ -1672,6 +3383,12 @@ pub fn get_sql_type(type_: &str) -> String {
     .to_string()
 }
 
+/// `name` as a quoted SQL identifier, so columns named after keywords like `from` or `to`
+/// can be used as they are
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
 pub fn is_sql_keyword(name: &str) -> bool {
     let sql_keywords = [
         "from",
@@ -1730,6 +3447,7 @@ pub fn is_sql_keyword(name: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::events::{StoreDeleteRecord, StoreSetField};
     use serde_json::json;
 
     fn get_test_json() -> Value {
@@ -2478,86 +4196,2057 @@ mod tests {
         assert_eq!(result.enums[4].value, "Missed");
     }
 
-    #[test]
-    fn test_dubhe_config_fields_from_json() {
-        let test_json = get_test_json();
-        let result = DubheConfig::from_json(test_json).unwrap();
-        assert_eq!(result.fields.len(), 15);
-        assert_eq!(result.fields[0].name, "entity_id");
-        assert_eq!(result.fields[1].name, "entity_id");
-        assert_eq!(result.fields[2].name, "value");
-        assert_eq!(result.fields[3].name, "entity_id");
-        assert_eq!(result.fields[4].name, "value");
-
-        assert_eq!(result.fields[5].table, "counter3");
-        assert_eq!(result.fields[5].name, "entity_id");
-        assert_eq!(result.fields[5].index, 0);
-        assert_eq!(result.fields[5].primary_key, true);
-        assert_eq!(result.fields[5].move_type, "address");
-        assert_eq!(result.fields[5].db_type, "TEXT");
+    fn get_status_enum_config() -> DubheConfig {
+        let mut config = DubheConfig::new(
+            "0x1".to_string(),
+            "0x2".to_string(),
+            "0x3".to_string(),
+            "1".to_string(),
+        );
+        for (index, value) in ["Caught", "Fled", "Missed"].iter().enumerate() {
+            config.push_enum(Enum {
+                name: "Status".to_string(),
+                index: index as u64,
+                value: value.to_string(),
+            });
+        }
+        let mut field = Field::new("counter2".to_string(), "value".to_string());
+        field
+            .index(0)
+            .move_type("Status".to_string())
+            .db_type("TEXT".to_string())
+            .primary_key(false);
+        config.push_field(field);
+        config
+    }
 
-        assert_eq!(result.fields[6].name, "hp");
-        assert_eq!(result.fields[6].index, 0);
-        assert_eq!(result.fields[6].primary_key, false);
-        assert_eq!(result.fields[6].move_type, "u64");
-        assert_eq!(result.fields[6].db_type, "INTEGER");
+    #[test]
+    fn test_u8_backed_enum_index() {
+        let mut config = get_status_enum_config();
+        config.set_enum_width("Status", EnumWidth::U8);
+        let value_tuple = vec![bcs::to_bytes(&2u8).unwrap()];
+        assert_eq!(decode_enum_index(&value_tuple[0], Some(EnumWidth::U8)).unwrap(), 2);
+        assert_eq!(
+            config.field_values_by_table("counter2", &vec![], &value_tuple),
+            vec!["'Missed'".to_string()]
+        );
+    }
 
-        assert_eq!(result.fields[7].name, "attack");
-        assert_eq!(result.fields[7].index, 1);
-        assert_eq!(result.fields[7].primary_key, false);
-        assert_eq!(result.fields[7].move_type, "u64");
-        assert_eq!(result.fields[7].db_type, "INTEGER");
+    #[test]
+    fn test_u64_backed_enum_index() {
+        let mut config = get_status_enum_config();
+        config.set_enum_width("Status", EnumWidth::U64);
+        let value_tuple = vec![bcs::to_bytes(&1u64).unwrap()];
+        assert_eq!(decode_enum_index(&value_tuple[0], Some(EnumWidth::U64)).unwrap(), 1);
+        // Read as a Move enum tag, the trailing bytes of the u64 are rejected
+        assert!(decode_enum_index(&value_tuple[0], None).is_err());
+        assert_eq!(
+            config.field_values_by_table("counter2", &vec![], &value_tuple),
+            vec!["'Fled'".to_string()]
+        );
+        assert_eq!(
+            config.field_value_by_table_and_index(
+                "counter2",
+                0,
+                &value_tuple[0],
+                Dialect::Postgres
+            ),
+            "\"value\" = 'Fled'"
+        );
+    }
 
-        assert_eq!(result.fields[8].name, "defense");
-        assert_eq!(result.fields[8].index, 2);
-        assert_eq!(result.fields[8].primary_key, false);
-        assert_eq!(result.fields[8].move_type, "u64");
-        assert_eq!(result.fields[8].db_type, "INTEGER");
+    #[test]
+    fn test_enum_index_rejects_other_widths() {
+        assert!(decode_enum_index(&[0u8, 1, 2], Some(EnumWidth::U16)).is_err());
+        assert!(decode_enum_index(&bcs::to_bytes(&7u32).unwrap(), Some(EnumWidth::U64)).is_err());
+        let too_large = bcs::to_bytes(&(u64::MAX as u128 + 1)).unwrap();
+        assert!(decode_enum_index(&too_large, Some(EnumWidth::U128)).is_err());
+        // Unterminated, not canonical and overlong tags
+        assert!(decode_enum_index(&[0x80], None).is_err());
+        assert!(decode_enum_index(&[0x80, 0x00], None).is_err());
+        assert!(decode_enum_index(&[0xff; 11], None).is_err());
+    }
 
-        assert_eq!(result.fields[9].name, "value");
-        assert_eq!(result.fields[9].index, 0);
-        assert_eq!(result.fields[9].primary_key, false);
+    #[test]
+    fn test_move_enum_tag_is_uleb128() {
+        let mut config = get_status_enum_config();
+        config.enums.clear();
+        for index in 0..130u64 {
+            config.push_enum(Enum {
+                name: "Status".to_string(),
+                index,
+                value: format!("Variant{}", index),
+            });
+        }
+        // Variant 128 spans two bytes, which a fixed u16 would read as 384
+        let value_tuple = vec![vec![0x80, 0x01]];
+        assert_eq!(decode_enum_index(&value_tuple[0], None).unwrap(), 128);
+        assert_eq!(decode_enum_index(&value_tuple[0], Some(EnumWidth::U16)).unwrap(), 384);
+        assert_eq!(
+            config.field_values_by_table("counter2", &vec![], &value_tuple),
+            vec!["'Variant128'".to_string()]
+        );
+        assert_eq!(decode_enum_index(&[0x05], None).unwrap(), 5);
+    }
 
-        assert_eq!(result.fields[10].name, "player");
-        assert_eq!(result.fields[10].index, 0);
-        assert_eq!(result.fields[10].primary_key, false);
-        assert_eq!(result.fields[11].name, "value");
-        assert_eq!(result.fields[11].index, 1);
-        assert_eq!(result.fields[11].primary_key, false);
+    #[test]
+    fn test_enum_widths_from_json() {
+        let json = |enum_widths| {
+            json!({
+              "components": [
+                { "hero": { "fields": [{ "id": "u64" }, { "class": "Class" }], "keys": ["id"], "offchain": false } }
+              ],
+              "resources": [],
+              "enums": [{ "Class": ["Knight", "Mage"] }],
+              "original_package_id": "0x1",
+              "dubhe_object_id": "0x2",
+              "original_dubhe_package_id": "0x3",
+              "start_checkpoint": "1",
+              "enum_widths": enum_widths
+            })
+        };
+        let config = DubheConfig::from_json(json(json!({ "Class": "u64" }))).unwrap();
+        assert_eq!(config.enum_widths.get("Class"), Some(&EnumWidth::U64));
+        assert_eq!(config.enum_index("Class", &bcs::to_bytes(&1u64).unwrap()).unwrap(), 1);
 
-        assert_eq!(result.fields[12].name, "player");
-        assert_eq!(result.fields[12].index, 0);
-        assert_eq!(result.fields[12].primary_key, true);
-        assert_eq!(result.fields[13].name, "monster");
-        assert_eq!(result.fields[13].index, 1);
-        assert_eq!(result.fields[13].primary_key, true);
-        assert_eq!(result.fields[14].name, "value");
-        assert_eq!(result.fields[14].index, 0);
-        assert_eq!(result.fields[14].primary_key, false);
+        let err = DubheConfig::from_json(json(json!({ "Race": "u8" }))).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown enum Race in enum_widths");
+    }
 
-        println!("fields: {:?}", result.fields);
+    #[cfg(all(feature = "toml", feature = "yaml"))]
+    #[test]
+    fn test_dubhe_config_from_all_formats() {
+        let json = json!({
+          "components": [
+            {
+              "position": {
+                "fields": [
+                  { "entity_id": "address" },
+                  { "x": "u64" },
+                  { "status": "Status" }
+                ],
+                "keys": ["entity_id"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [
+            {
+              "counter": {
+                "fields": [
+                  { "value": "u32" }
+                ],
+                "keys": [],
+                "offchain": false
+              }
+            }
+          ],
+          "enums": [
+            { "Status": ["Caught", "Fled"] }
+          ],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let toml = r#"
+original_package_id = "0x1"
+dubhe_object_id = "0x2"
+original_dubhe_package_id = "0x3"
+start_checkpoint = "1"
+
+[[components]]
+[components.position]
+fields = [{ entity_id = "address" }, { x = "u64" }, { status = "Status" }]
+keys = ["entity_id"]
+offchain = false
+
+[[resources]]
+[resources.counter]
+fields = [{ value = "u32" }]
+keys = []
+offchain = false
+
+[[enums]]
+Status = ["Caught", "Fled"]
+"#;
+        let yaml = r#"
+original_package_id: "0x1"
+dubhe_object_id: "0x2"
+original_dubhe_package_id: "0x3"
+start_checkpoint: "1"
+components:
+  - position:
+      fields:
+        - entity_id: address
+        - x: u64
+        - status: Status
+      keys: [entity_id]
+      offchain: false
+resources:
+  - counter:
+      fields:
+        - value: u32
+      keys: []
+      offchain: false
+enums:
+  - Status: [Caught, Fled]
+"#;
+
+        let from_json = DubheConfig::from_json(json.clone()).unwrap();
+        let from_toml = DubheConfig::from_toml(toml).unwrap();
+        let from_yaml = DubheConfig::from_yaml(yaml).unwrap();
+        assert_eq!(from_json.fields.len(), 4);
+        assert_eq!(from_json, from_toml);
+        assert_eq!(from_json, from_yaml);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dubhe.config.yaml");
+        std::fs::write(&path, yaml).unwrap();
+        assert_eq!(DubheConfig::from_path(&path).unwrap(), from_json);
+
+        // The raw config kept by the indexer reads the same from every format
+        let toml_path = dir.path().join("dubhe.config.toml");
+        std::fs::write(&toml_path, toml).unwrap();
+        let json_path = dir.path().join("dubhe.config.json");
+        std::fs::write(&json_path, json.to_string()).unwrap();
+        let json_value = DubheConfig::read_config_value(&json_path).unwrap();
+        assert_eq!(json_value, json);
+        assert_eq!(DubheConfig::read_config_value(&toml_path).unwrap(), json_value);
+        assert_eq!(DubheConfig::read_config_value(&path).unwrap(), json_value);
+        assert_eq!(DubheConfig::from_json(json_value).unwrap(), from_json);
+
+        let err = DubheConfig::from_toml("original_package_id = ").unwrap_err();
+        assert!(err.to_string().starts_with("TOML parsing error"));
+        assert!(DubheConfig::from_path(dir.path().join("dubhe.config.ini")).is_err());
     }
 
     #[test]
-    fn test_can_convert_event_to_sql() {
-        let test_json = get_test_json();
-        let result = DubheConfig::from_json(test_json).unwrap();
+    fn test_truncated_address_does_not_panic() {
+        let mut config = DubheConfig::new(
+            "0x1".to_string(),
+            "0x2".to_string(),
+            "0x3".to_string(),
+            "1".to_string(),
+        );
+        config.push_table(Table {
+            name: "player".to_string(),
+            offchain: false,
+            component: true,
+            push_min_interval_ms: None,
+            conflict_policy: ConflictPolicy::default(),
+        });
+        let mut key = Field::new("player".to_string(), "entity_id".to_string());
+        key.index(0)
+            .move_type("address".to_string())
+            .db_type("TEXT".to_string())
+            .primary_key(true);
+        config.push_field(key.clone());
+        let mut owners = Field::new("player".to_string(), "owners".to_string());
+        owners
+            .index(0)
+            .move_type("vector<address>".to_string())
+            .db_type("TEXT[]".to_string())
+            .primary_key(false);
+        config.push_field(owners.clone());
+
+        let truncated = vec![0xab; 20];
+        assert!(into_sql_string("address", &truncated).is_err());
+        assert!(into_sql_string("vector<address>", &[1u8, 0xab, 0xcd]).is_err());
+        assert_eq!(
+            key.proto_value(&truncated).unwrap().kind,
+            Some(prost_types::value::Kind::NullValue(0))
+        );
+
         let event = Event::StoreSetRecord(StoreSetRecord {
             dapp_key: "1::dapp_key::DappKey".to_string(),
-            table_id: "counter0".to_string(),
-            key_tuple: Vec::new(),
-            value_tuple: Vec::new(),
+            table_id: "player".to_string(),
+            key_tuple: vec![truncated],
+            value_tuple: vec![bcs::to_bytes(&Vec::<SuiAddress>::new()).unwrap()],
         });
-        assert!(result.can_convert_event_to_sql(&event).is_ok());
+        let err = config.convert_event_to_proto_struct(&event).unwrap_err();
+        assert!(err.to_string().contains("player.entity_id"));
+        assert!(config
+            .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Postgres)
+            .is_err());
     }
 
     #[test]
-    fn test_convert_event_to_sql() {
-        let test_json = get_test_json();
-        let config = DubheConfig::from_json(test_json).unwrap();
-        let event = Event::StoreSetRecord(StoreSetRecord {
-            dapp_key: "1::dapp_key::DappKey".to_string(),
-            table_id: "counter3".to_string(),
+    fn test_system_tables_are_configurable() {
+        let mut json = json!({
+          "components": [],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let event = |table_id: &str| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "99::dapp_key::DappKey".to_string(),
+                table_id: table_id.to_string(),
+                key_tuple: vec![],
+                value_tuple: vec![],
+            })
+        };
+
+        // dapp_fee_state stays accepted when nothing is configured
+        let config = DubheConfig::from_json(json.clone()).unwrap();
+        assert_eq!(config.system_tables, vec!["dapp_fee_state".to_string()]);
+        assert!(config.can_convert_event_to_sql(&event("dapp_fee_state")).is_ok());
+        // but not when the framework package emits it
+        let framework_event = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: format!(
+                "{}::dapp_key::DappKey",
+                DUBHE_FRAMEWORK_PACKAGE_ID.trim_start_matches("0x")
+            ),
+            table_id: "dapp_fee_state".to_string(),
+            key_tuple: vec![],
+            value_tuple: vec![],
+        });
+        assert!(config.can_convert_event_to_sql(&framework_event).is_err());
+
+        json["system_tables"] = json!(["dapp_metadata"]);
+        let config = DubheConfig::from_json(json).unwrap();
+        assert!(config.can_convert_event_to_sql(&event("dapp_metadata")).is_ok());
+        assert!(config.can_convert_event_to_sql(&event("dapp_fee_state")).is_err());
+    }
+
+    #[test]
+    fn test_excluded_tables_are_skipped() {
+        let json = json!({
+          "components": [
+            { "counter0": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } },
+            { "counter1": { "fields": [{ "value": "u32" }], "keys": [], "offchain": true } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "exclude_tables": ["counter1"]
+        });
+        let config = DubheConfig::from_json(json).unwrap();
+        assert!(config.is_excluded_table("counter1"));
+
+        let sql = config.create_tables_sql();
+        assert_eq!(sql.len(), 1);
+        assert!(sql[0].contains("store_counter0 ("));
+
+        let event = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "counter1".to_string(),
+            key_tuple: vec![],
+            value_tuple: vec![],
+        });
+        assert!(!config.can_convert_event_to_sql(&event).unwrap());
+        assert_eq!(
+            config
+                .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Postgres)
+                .unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_empty_numeric_vectors_are_typed() {
+        let cases = [
+            ("vector<u8>", bcs::to_bytes(&Vec::<u8>::new()).unwrap(), "ARRAY[]::INTEGER[]"),
+            ("vector<u16>", bcs::to_bytes(&Vec::<u16>::new()).unwrap(), "ARRAY[]::INTEGER[]"),
+            ("vector<u32>", bcs::to_bytes(&Vec::<u32>::new()).unwrap(), "ARRAY[]::INTEGER[]"),
+            ("vector<u64>", bcs::to_bytes(&Vec::<u64>::new()).unwrap(), "ARRAY[]::BIGINT[]"),
+            ("vector<bool>", bcs::to_bytes(&Vec::<bool>::new()).unwrap(), "ARRAY[]::BOOLEAN[]"),
+        ];
+        for (type_, value, expected) in cases {
+            assert_eq!(into_sql_string(type_, &value).unwrap(), expected);
+            assert_eq!(key_sql_string(type_, &value).unwrap(), expected);
+        }
+
+        // Non-empty arrays keep their inferred element type
+        let bytes = bcs::to_bytes(&vec![1u64, 2]).unwrap();
+        assert_eq!(into_sql_string("vector<u64>", &bytes).unwrap(), "ARRAY[1, 2]");
+        let flags = bcs::to_bytes(&vec![true]).unwrap();
+        assert_eq!(into_sql_string("vector<bool>", &flags).unwrap(), "ARRAY[TRUE]");
+    }
+
+    #[test]
+    fn test_boolean_literals_per_dialect() {
+        let yes = bcs::to_bytes(&true).unwrap();
+        let no = bcs::to_bytes(&false).unwrap();
+        assert_eq!(into_sql_string("bool", &yes).unwrap(), "TRUE");
+        assert_eq!(Dialect::Postgres.sql_string("bool", &no).unwrap(), "FALSE");
+        assert_eq!(Dialect::Sqlite.sql_string("bool", &yes).unwrap(), "1");
+        assert_eq!(Dialect::Sqlite.key_sql_string("bool", &no).unwrap(), "0");
+        assert_eq!(format_sql_value(&json!(true), "bool", Dialect::Postgres), "TRUE");
+        assert_eq!(format_sql_value(&json!(false), "bool", Dialect::Sqlite), "0");
+        assert_eq!(
+            format_sql_value(&json!([true, false]), "vector<bool>", Dialect::Postgres),
+            "ARRAY[TRUE, FALSE]"
+        );
+
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "flag": { "fields": [{ "id": "u64" }, { "on": "bool" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let set = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "flag".to_string(),
+            key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+            value_tuple: vec![yes],
+        });
+        let delete = Event::StoreDeleteRecord(crate::events::StoreDeleteRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "flag".to_string(),
+            key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+        });
+        for (dialect, literal) in [(Dialect::Postgres, "TRUE"), (Dialect::Sqlite, "1")] {
+            let sql = config
+                .convert_event_to_sql(set.clone(), 0, "digest".to_string(), dialect)
+                .unwrap();
+            assert!(sql.contains(&format!("\"on\" = {},", literal)), "{}", sql);
+            let sql = config
+                .convert_event_to_sql(delete.clone(), 0, "digest".to_string(), dialect)
+                .unwrap();
+            assert!(sql.contains(&format!("SET is_deleted = {},", literal)), "{}", sql);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signed_integers_round_trip_through_sql() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "ledger": {
+                "fields": [
+                  { "id": "i32" },
+                  { "tick": "i8" },
+                  { "step": "i16" },
+                  { "delta": "i64" },
+                  { "total": "i128" }
+                ],
+                "keys": ["id"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        assert_eq!(get_sql_type("i64"), "BIGINT");
+        assert_eq!(into_sql_string("i64", &bcs::to_bytes(&-5i64).unwrap()).unwrap(), "-5");
+        assert_eq!(
+            into_sql_string("i128", &bcs::to_bytes(&i128::MIN).unwrap()).unwrap(),
+            format!("'{}'", i128::MIN)
+        );
+        // A u64 field holding the two's complement of -1 decodes as -1
+        assert_eq!(into_sql_string("i64", &bcs::to_bytes(&u64::MAX).unwrap()).unwrap(), "-1");
+        assert!(into_sql_string("i32", &bcs::to_bytes(&-1i64).unwrap()).is_err());
+        let delta = config.fields.iter().find(|field| field.name == "delta").unwrap();
+        assert_eq!(
+            delta.proto_value(&bcs::to_bytes(&-7i64).unwrap()).unwrap().kind,
+            Some(prost_types::value::Kind::NumberValue(-7.0))
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("signed.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for sql in config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        let rows = [(-1i32, i8::MIN, -300i16, i64::MIN, i128::MIN), (2, 7, 300, 42, i128::MAX)];
+        for (id, tick, step, delta, total) in rows {
+            let event = Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "ledger".to_string(),
+                key_tuple: vec![bcs::to_bytes(&id).unwrap()],
+                value_tuple: vec![
+                    bcs::to_bytes(&tick).unwrap(),
+                    bcs::to_bytes(&step).unwrap(),
+                    bcs::to_bytes(&delta).unwrap(),
+                    bcs::to_bytes(&total).unwrap(),
+                ],
+            });
+            let sql = config
+                .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Sqlite)
+                .unwrap();
+            database.execute(&sql).await.unwrap();
+        }
+
+        let stored = database
+            .query("SELECT * FROM store_ledger ORDER BY id")
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 2);
+        for (row, (id, tick, step, delta, total)) in stored.iter().zip(rows) {
+            assert_eq!(row["id"], id);
+            assert_eq!(row["tick"], tick);
+            assert_eq!(row["step"], step);
+            assert_eq!(row["delta"], delta);
+            assert_eq!(row["total"], total.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decimal_fields_store_the_scaled_value() {
+        let config_json = |price_type: &str| {
+            json!({
+              "components": [
+                {
+                  "trade": {
+                    "fields": [{ "id": "u64" }, { "price": price_type }],
+                    "keys": ["id"],
+                    "offchain": false
+                  }
+                }
+              ],
+              "resources": [],
+              "enums": [],
+              "original_package_id": "0x1",
+              "dubhe_object_id": "0x2",
+              "original_dubhe_package_id": "0x3",
+              "start_checkpoint": "1"
+            })
+        };
+        for invalid in [
+            "decimal<u512>(90,6)",
+            "decimal<u64>(0,0)",
+            "decimal<u64>(4,6)",
+            "decimal<u64>(20,6)",
+            "decimal<u8>(2,0)",
+            "decimal<u256>(1001,0)",
+        ] {
+            let err = DubheConfig::from_json(config_json(invalid)).unwrap_err();
+            assert!(err.to_string().contains("invalid decimal type"), "{}", err);
+        }
+
+        assert!(DubheConfig::from_json(config_json("decimal<u8>(3,0)")).is_ok());
+        let config = DubheConfig::from_json(config_json("decimal<u64>(26,6)")).unwrap();
+        let price = config.fields.iter().find(|field| field.name == "price").unwrap();
+        assert_eq!(price.db_type, "NUMERIC(26,6)");
+        let amount = bcs::to_bytes(&1_250_000u64).unwrap();
+        assert_eq!(into_sql_string(&price.move_type, &amount).unwrap(), "1.25");
+        assert_eq!(
+            price.proto_value(&amount).unwrap().kind,
+            Some(prost_types::value::Kind::StringValue("1.25".to_string()))
+        );
+        assert!(into_sql_string(&price.move_type, &[1, 2, 3]).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("decimal.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for sql in config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        for (id, amount) in [(1u64, 1_250_000u64), (2, 3_000_000)] {
+            let event = Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "trade".to_string(),
+                key_tuple: vec![bcs::to_bytes(&id).unwrap()],
+                value_tuple: vec![bcs::to_bytes(&amount).unwrap()],
+            });
+            let sql = config
+                .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Sqlite)
+                .unwrap();
+            database.execute(&sql).await.unwrap();
+        }
+        let rows = database
+            .query("SELECT price FROM store_trade ORDER BY id")
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["price"], 1.25);
+        assert_eq!(rows[1]["price"], 3);
+    }
+
+    #[test]
+    fn test_misaligned_value_tuple_is_rejected() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "hero": {
+                "fields": [{ "id": "u64" }, { "hp": "u32" }, { "level": "u64" }, { "class": "Class" }],
+                "keys": ["id"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [{ "Class": ["Knight", "Mage"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let set = |value_tuple: Vec<Vec<u8>>| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "hero".to_string(),
+                key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+                value_tuple,
+            })
+        };
+        let hp = bcs::to_bytes(&100u32).unwrap();
+        let level = bcs::to_bytes(&3u64).unwrap();
+        let class = bcs::to_bytes(&1u8).unwrap();
+        let convert = |event| config.convert_event_to_sql(event, 0, "d".to_string(), Dialect::Postgres);
+
+        assert!(convert(set(vec![hp.clone(), level.clone(), class.clone()])).is_ok());
+        // hp and level swapped: each is decoded as the field at the other index
+        let err = convert(set(vec![level.clone(), hp.clone(), class.clone()])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value for hero.hp: Expected 4 bytes for u32, got 8 (0x0300000000000000)"
+        );
+        // A discriminant outside the enum would be written as an empty literal
+        let err = convert(set(vec![hp.clone(), level, bcs::to_bytes(&7u8).unwrap()])).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value for hero.class: Class has no variant 7");
+        assert!(into_sql_string("u8", &[1, 0]).is_err());
+        assert!(into_sql_string("bool", &[2]).is_err());
+    }
+
+    #[test]
+    fn test_short_system_table_tuple_is_rejected() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "dapp_fee_state": {
+                "fields": [{ "dapp_key": "String" }, { "base_fee": "u64" }, { "total_paid": "u64" }],
+                "keys": ["dapp_key"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        // System table events of other packages skip the arity check
+        let set = |key_tuple: Vec<Vec<u8>>, value_tuple: Vec<Vec<u8>>| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "99::dapp_key::DappKey".to_string(),
+                table_id: "dapp_fee_state".to_string(),
+                key_tuple,
+                value_tuple,
+            })
+        };
+        let dapp_key = bcs::to_bytes(&"99::dapp_key::DappKey".to_string()).unwrap();
+        let fee = bcs::to_bytes(&10u64).unwrap();
+        let convert = |event| config.convert_event_to_sql(event, 0, "d".to_string(), Dialect::Postgres);
+
+        assert!(convert(set(vec![dapp_key.clone()], vec![fee.clone(), fee.clone()])).is_ok());
+        let err = convert(set(vec![], vec![fee.clone(), fee.clone()])).unwrap_err();
+        assert_eq!(err.to_string(), "dapp_fee_state.dapp_key is missing from the event");
+        let err = convert(set(vec![dapp_key.clone()], vec![fee.clone()])).unwrap_err();
+        assert_eq!(err.to_string(), "dapp_fee_state.total_paid is missing from the event");
+        assert!(config
+            .record_columns("dapp_fee_state", &[dapp_key], &[fee], Dialect::Sqlite)
+            .is_err());
+    }
+
+    #[test]
+    fn test_write_sql_value_matches_into_sql_string() {
+        let address = SuiAddress::from_bytes([7; 32]).unwrap();
+        let cases: Vec<(&str, Vec<u8>)> = vec![
+            ("u8", bcs::to_bytes(&7u8).unwrap()),
+            ("u64", bcs::to_bytes(&u64::MAX).unwrap()),
+            ("u128", bcs::to_bytes(&u128::MAX).unwrap()),
+            ("u256", bcs::to_bytes(&U256::max_value()).unwrap()),
+            ("bool", bcs::to_bytes(&true).unwrap()),
+            ("String", bcs::to_bytes("hello").unwrap()),
+            ("address", bcs::to_bytes(&address).unwrap()),
+            ("vector<u8>", bcs::to_bytes(&vec![1u8, 2, 3]).unwrap()),
+            ("vector<u8>", bcs::to_bytes(&Vec::<u8>::new()).unwrap()),
+            ("vector<u32>", bcs::to_bytes(&vec![1u32, 2]).unwrap()),
+            ("vector<u64>", bcs::to_bytes(&vec![u64::MAX]).unwrap()),
+            ("vector<bool>", bcs::to_bytes(&vec![true, false]).unwrap()),
+            ("vector<u128>", bcs::to_bytes(&vec![1u128, 2]).unwrap()),
+            ("vector<u128>", bcs::to_bytes(&Vec::<u128>::new()).unwrap()),
+            ("vector<u256>", bcs::to_bytes(&vec![U256::one()]).unwrap()),
+            ("vector<address>", bcs::to_bytes(&vec![address, address]).unwrap()),
+            ("vector<String>", bcs::to_bytes(&vec!["a", "b"]).unwrap()),
+            ("vector<String>", bcs::to_bytes(&Vec::<String>::new()).unwrap()),
+            ("vector<u16>", bcs::to_bytes(&Vec::<u16>::new()).unwrap()),
+            ("vector<u64>", bcs::to_bytes(&Vec::<u64>::new()).unwrap()),
+            ("vector<bool>", bcs::to_bytes(&Vec::<bool>::new()).unwrap()),
+            ("vector<vector<u8>>", bcs::to_bytes(&vec![vec![1u8, 2], vec![]]).unwrap()),
+            (RAW_BCS_TYPE, vec![0xab, 0xcd]),
+        ];
+        let mut out = String::new();
+        for (type_, value) in cases {
+            out.clear();
+            write_sql_value(&mut out, type_, &value).unwrap();
+            assert_eq!(out, into_sql_string(type_, &value).unwrap(), "{}", type_);
+        }
+
+        assert!(write_sql_value(&mut out, "String", &[5, b'a']).is_err());
+        assert!(write_sql_value(&mut out, "vector<u8>", &[]).is_err());
+    }
+
+    /// Panic unless every string literal in `sql` is closed, with the quotes inside doubled,
+    /// and nothing outside the literals could end the statement or start a comment
+    fn assert_escaped_literal(sql: &str) {
+        let mut chars = sql.chars().peekable();
+        let mut in_literal = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' if in_literal && chars.peek() == Some(&'\'') => {
+                    chars.next();
+                }
+                '\'' => in_literal = !in_literal,
+                ';' if !in_literal => panic!("statement separator outside a literal: {}", sql),
+                '-' if !in_literal && chars.peek() == Some(&'-') => {
+                    panic!("comment outside a literal: {}", sql)
+                }
+                _ => {}
+            }
+        }
+        assert!(!in_literal, "unterminated literal: {}", sql);
+    }
+
+    fn fuzzed_types() -> Vec<&'static str> {
+        let mut types = SUPPORTED_MOVE_TYPES.to_vec();
+        types.extend(["decimal<u64>(26,6)", "0x2::object::ID"]);
+        types
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_random_bytes_give_a_literal_or_an_error(
+            type_ in proptest::sample::select(fuzzed_types()),
+            value in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..80),
+        ) {
+            if let Ok(sql) = into_sql_string(type_, &value) {
+                assert_escaped_literal(&sql);
+            }
+            let mut out = String::new();
+            if write_sql_value(&mut out, type_, &value).is_ok() {
+                assert_escaped_literal(&out);
+            }
+        }
+
+        #[test]
+        fn prop_strings_are_escaped(
+            text in "[a-z'; -]{0,16}",
+            items in proptest::collection::vec("[a-z'; -]{0,16}", 0..4),
+        ) {
+            let sql = into_sql_string("String", &bcs::to_bytes(&text).unwrap()).unwrap();
+            assert_escaped_literal(&sql);
+            proptest::prop_assert_eq!(sql[1..sql.len() - 1].replace("''", "'"), text);
+
+            let value = bcs::to_bytes(&items).unwrap();
+            let sql = into_sql_string("vector<String>", &value).unwrap();
+            assert_escaped_literal(&sql);
+            let mut out = String::new();
+            write_sql_value(&mut out, "vector<String>", &value).unwrap();
+            proptest::prop_assert_eq!(out, sql);
+        }
+    }
+
+    #[test]
+    fn test_delete_from_offchain_table() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "log": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": true } },
+            { "feed": { "fields": [{ "value": "u32" }], "keys": [], "offchain": true } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let delete = |table_id: &str, key_tuple: Vec<Vec<u8>>| {
+            Event::StoreDeleteRecord(StoreDeleteRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: table_id.to_string(),
+                key_tuple,
+            })
+        };
+
+        let event = delete("log", vec![bcs::to_bytes(&7u64).unwrap()]);
+        let sql = config
+            .convert_event_to_sql(event, 5, "digest".to_string(), Dialect::Postgres)
+            .unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE store_log SET is_deleted = TRUE, updated_at_timestamp_ms = 5, last_update_digest = 'digest' WHERE \"id\" = 7;"
+        );
+
+        let err = config
+            .convert_event_to_sql(
+                delete("feed", vec![]),
+                5,
+                "digest".to_string(),
+                Dialect::Postgres,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("offchain table feed"));
+    }
+
+    #[tokio::test]
+    async fn test_keyless_offchain_resource_is_append_only() {
+        let config = DubheConfig::from_json(json!({
+          "components": [],
+          "resources": [
+            { "notice": { "fields": [{ "value": "u32" }], "keys": [], "offchain": true } },
+            { "total": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+          ],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        assert_eq!(config.table_layout("notice"), TableLayout::AppendOnly);
+        assert_eq!(config.table_layout("total"), TableLayout::Singleton);
+
+        let create_sql = config.create_table_sql("notice").unwrap();
+        assert!(!create_sql.contains("unique_resource_id"));
+        assert!(config
+            .create_table_sql("total")
+            .unwrap()
+            .contains("unique_resource_id"));
+
+        let set_record = |table_id: &str| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: table_id.to_string(),
+                key_tuple: vec![],
+                value_tuple: vec![bcs::to_bytes(&3u32).unwrap()],
+            })
+        };
+        let set_field = |table_id: &str| {
+            Event::StoreSetField(StoreSetField {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: table_id.to_string(),
+                key_tuple: vec![],
+                field_index: 0,
+                value: bcs::to_bytes(&4u32).unwrap(),
+            })
+        };
+        let convert =
+            |event| config.convert_event_to_sql(event, 5, "digest".to_string(), Dialect::Sqlite);
+
+        let insert = convert(set_record("notice")).unwrap();
+        assert!(insert.starts_with("INSERT INTO store_notice"));
+        assert!(!insert.contains("ON CONFLICT"));
+        assert!(!insert.contains("unique_resource_id"));
+        assert!(convert(set_record("total"))
+            .unwrap()
+            .contains("ON CONFLICT (unique_resource_id)"));
+
+        let err = convert(set_field("notice")).unwrap_err();
+        assert!(err.to_string().contains("offchain table notice"));
+        assert!(convert(set_field("total"))
+            .unwrap()
+            .contains("WHERE unique_resource_id = 1"));
+
+        // Every SetRecord of the append-only table lands as its own row
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("append.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        database.execute(&create_sql).await.unwrap();
+        database.execute(&insert).await.unwrap();
+        database.execute(&insert).await.unwrap();
+        let rows = database.query("SELECT value FROM store_notice").await.unwrap();
+        assert_eq!(rows.len(), 2);
+    }
+
+    fn unknown_table_config(on_unknown_table: &str) -> DubheConfig {
+        DubheConfig::from_json(json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "on_unknown_table": on_unknown_table
+        }))
+        .unwrap()
+    }
+
+    fn unknown_table_event() -> Event {
+        Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "player".to_string(),
+            key_tuple: vec![bcs::to_bytes(&7u64).unwrap()],
+            value_tuple: vec![bcs::to_bytes(&"alice".to_string()).unwrap()],
+        })
+    }
+
+    #[test]
+    fn test_unknown_table_is_skipped_by_default() {
+        let config = DubheConfig::from_json(get_test_json()).unwrap();
+        assert_eq!(config.on_unknown_table, UnknownTablePolicy::Skip);
+
+        let config = unknown_table_config("skip");
+        assert!(!config.can_convert_event_to_sql(&unknown_table_event()).unwrap());
+        assert_eq!(
+            config
+                .convert_event_to_sql(
+                    unknown_table_event(),
+                    0,
+                    "digest".to_string(),
+                    Dialect::Postgres,
+                )
+                .unwrap(),
+            ""
+        );
+        let config = unknown_table_config("warn");
+        assert!(!config.can_convert_event_to_sql(&unknown_table_event()).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_table_errors() {
+        let config = unknown_table_config("error");
+        let err = config.can_convert_event_to_sql(&unknown_table_event()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<UnknownTableError>(),
+            Some(&UnknownTableError {
+                table_id: "player".to_string()
+            })
+        );
+        assert!(config
+            .convert_event_to_sql(unknown_table_event(), 0, "digest".to_string(), Dialect::Postgres)
+            .is_err());
+        assert!(DubheConfig::from_json(json!({
+          "components": [],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "on_unknown_table": "ignore"
+        }))
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_table_is_auto_created() {
+        let mut config = unknown_table_config("auto_create");
+        let event = unknown_table_event();
+        assert!(config
+            .can_convert_event_to_sql(&event)
+            .unwrap_err()
+            .is::<UnknownTableError>());
+
+        let create_sql = config.infer_table(&event).unwrap();
+        assert!(create_sql.contains("store_player ("));
+        assert!(create_sql.contains("PRIMARY KEY (\"key_0\")"));
+        assert!(config.can_convert_event_to_sql(&event).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("inferred.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        database.execute(&create_sql).await.unwrap();
+        let sql = config
+            .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Sqlite)
+            .unwrap();
+        database.execute(&sql).await.unwrap();
+
+        let rows = database
+            .query("SELECT key_0, value_0 FROM store_player")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["key_0"], "0x0700000000000000");
+        assert_eq!(rows[0]["value_0"], "0x05616c696365");
+
+        let delete = Event::StoreDeleteRecord(crate::events::StoreDeleteRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "monster".to_string(),
+            key_tuple: vec![],
+        });
+        assert!(config.infer_table(&delete).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_composite_key_declared_in_reverse_order_upserts() {
+        let json = json!({
+          "components": [
+            {
+              "counter6": {
+                "fields": [{ "monster": "u64" }, { "player": "address" }, { "value": "u32" }],
+                "keys": ["player", "monster"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let config = DubheConfig::from_json(json.clone()).unwrap();
+        let player = SuiAddress::from_bytes([7; 32]).unwrap();
+        let set = |value: u32| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter6".to_string(),
+                key_tuple: vec![bcs::to_bytes(&player).unwrap(), bcs::to_bytes(&9u64).unwrap()],
+                value_tuple: vec![bcs::to_bytes(&value).unwrap()],
+            })
+        };
+
+        // The conflict target, the primary key and the key tuple all follow `keys`
+        let create_sql = config.create_table_sql("counter6").unwrap();
+        assert!(create_sql.ends_with("PRIMARY KEY (\"player\",\"monster\"));"));
+        let sql = config
+            .convert_event_to_sql(set(1), 0, "digest".to_string(), Dialect::Sqlite)
+            .unwrap();
+        assert!(sql.contains("ON CONFLICT (\"player\",\"monster\")"));
+
+        let (_, _, _, _, tables) = TableMetadata::from_json(json).unwrap();
+        assert_eq!(tables[0].key_field_names(), vec!["player", "monster"]);
+        assert_eq!(tables[0].generate_create_table_sql(), create_sql);
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("composite.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        database.execute(&create_sql).await.unwrap();
+        database.execute(&sql).await.unwrap();
+        let update = config
+            .convert_event_to_sql(set(2), 1, "digest".to_string(), Dialect::Sqlite)
+            .unwrap();
+        database.execute(&update).await.unwrap();
+
+        let rows = database
+            .query("SELECT monster, player, value FROM store_counter6")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["monster"], 9);
+        assert_eq!(rows[0]["player"], player.to_string());
+        assert_eq!(rows[0]["value"], 2);
+    }
+
+    #[test]
+    fn test_digest_column_fits_every_digest() {
+        let digest = TransactionDigest::new([0xff; 32]);
+        assert_eq!(digest_string(&digest), digest.to_string());
+        assert_eq!(digest_string(&digest).len(), DIGEST_COLUMN_LENGTH);
+        assert_eq!(
+            SYSTEM_COLUMN_TYPES[2],
+            format!("VARCHAR({}) DEFAULT ''", DIGEST_COLUMN_LENGTH)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_table_metadata_and_config_create_the_same_tables() {
+        let json = json!({
+          "components": [
+            { "position": {
+                "fields": [{ "player": "address" }, { "order": "u32" }, { "facing": "Direction" }],
+                "keys": ["player"],
+                "offchain": false
+            } }
+          ],
+          "resources": [
+            { "total": { "fields": [{ "value": "u64" }], "keys": [], "offchain": false } },
+            { "log": { "fields": [{ "message": "String" }], "keys": [], "offchain": true } }
+          ],
+          "enums": [{ "Direction": ["North", "South"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let config = DubheConfig::from_json(json.clone()).unwrap();
+        let (_, _, _, _, tables) = TableMetadata::from_json(json).unwrap();
+        assert_eq!(tables.len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("schema.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for table in &tables {
+            assert_eq!(table.layout(), config.table_layout(&table.name));
+            let sql = table.generate_create_table_sql();
+            assert_eq!(Some(&sql), config.create_table_sql(&table.name).as_ref());
+            assert_eq!(database.generate_create_table_sql(table), sql);
+            database.execute(&sql).await.unwrap();
+        }
+
+        // Every column the indexer selects exists in the tables either caller created
+        for table in &tables {
+            let columns = config.selectable_columns(&table.name).join(", ");
+            database
+                .query(&format!("SELECT {} FROM {}", columns, table.store_table_name()))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_upserts_scalar_and_vector_fields() {
+        let mut config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "inventory": {
+                "fields": [
+                  { "id": "u64" },
+                  { "flag": "bool" },
+                  { "hash": "vector<u8>" },
+                  { "amounts": "vector<u128>" },
+                  { "names": "vector<String>" }
+                ],
+                "keys": ["id"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [
+            { "settings": { "fields": [{ "admins": "vector<address>" }], "keys": [], "offchain": false } }
+          ],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        // SQLite has no array columns, see the indexer's print-schema
+        for field in config.fields.iter_mut() {
+            if field.db_type.ends_with("[]") {
+                field.db_type = "TEXT".to_string();
+            }
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("dialect.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for sql in config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+
+        let set = |hash: Vec<u8>| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "inventory".to_string(),
+                key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+                value_tuple: vec![
+                    bcs::to_bytes(&true).unwrap(),
+                    bcs::to_bytes(&hash).unwrap(),
+                    bcs::to_bytes(&vec![u128::MAX]).unwrap(),
+                    bcs::to_bytes(&vec!["potion".to_string(), "it's".to_string()]).unwrap(),
+                ],
+            })
+        };
+        let postgres = config
+            .convert_event_to_sql(set(vec![]), 0, "digest".to_string(), Dialect::Postgres)
+            .unwrap();
+        assert!(postgres.contains("ARRAY[]::INTEGER[]"));
+        // The second set conflicts with the first and updates the row
+        for (checkpoint, hash) in [(0, vec![]), (1, vec![1, 2, 3])] {
+            let sql = config
+                .convert_event_to_sql(set(hash), checkpoint, "digest".to_string(), Dialect::Sqlite)
+                .unwrap();
+            assert!(!sql.contains("ARRAY"));
+            database.execute(&sql).await.unwrap();
+        }
+        let admin = SuiAddress::from_bytes([7; 32]).unwrap();
+        let sql = config
+            .convert_event_to_sql(
+                Event::StoreSetRecord(StoreSetRecord {
+                    dapp_key: "1::dapp_key::DappKey".to_string(),
+                    table_id: "settings".to_string(),
+                    key_tuple: vec![],
+                    value_tuple: vec![bcs::to_bytes(&vec![admin]).unwrap()],
+                }),
+                0,
+                "digest".to_string(),
+                Dialect::Sqlite,
+            )
+            .unwrap();
+        database.execute(&sql).await.unwrap();
+        database.execute(&sql).await.unwrap();
+
+        let rows = database
+            .query(
+                "SELECT id FROM store_inventory WHERE flag = 1 AND is_deleted = 0 \
+                 AND json_array_length(hash) = 3 AND json_extract(hash, '$[2]') = 3 \
+                 AND json_extract(amounts, '$[0]') = '340282366920938463463374607431768211455' \
+                 AND json_extract(names, '$[1]') = 'it''s' AND updated_at_timestamp_ms = 1",
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        let rows = database
+            .query(&format!(
+                "SELECT unique_resource_id FROM store_settings \
+                 WHERE json_extract(admins, '$[0]') = '{}'",
+                admin
+            ))
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+
+        let delete = Event::StoreDeleteRecord(crate::events::StoreDeleteRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "inventory".to_string(),
+            key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+        });
+        let sql = config
+            .convert_event_to_sql(delete, 2, "digest".to_string(), Dialect::Sqlite)
+            .unwrap();
+        assert!(sql.contains("SET is_deleted = 1,"));
+        database.execute(&sql).await.unwrap();
+        let rows = database
+            .query("SELECT id FROM store_inventory WHERE is_deleted = 1")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_and_missing_fields() {
+        let config = |components: Value| {
+            DubheConfig::from_json(json!({
+              "components": components,
+              "resources": [],
+              "enums": [],
+              "original_package_id": "0x1",
+              "dubhe_object_id": "0x2",
+              "original_dubhe_package_id": "0x3",
+              "start_checkpoint": "1"
+            }))
+        };
+        let err = config(json!([
+            { "counter": { "fields": [{ "player": "address" }, { "value": "u32" }, { "player": "address" }], "keys": ["player"], "offchain": false } }
+        ]))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Field player of table counter is declared more than once; a key field cannot also be a value field"
+        );
+
+        let err = config(json!([
+            { "counter": { "fields": [], "keys": [], "offchain": false } }
+        ]))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Component counter has no fields");
+
+        assert!(config(json!([
+            { "counter": { "fields": [{ "player": "address" }, { "value": "u32" }], "keys": ["player"], "offchain": false } }
+        ]))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_unsupported_move_types() {
+        let json = json!({
+          "components": [
+            { "counter": { "fields": [{ "player": "address" }, { "value": "u512" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let err = DubheConfig::from_json(json).unwrap_err();
+        assert_eq!(err.to_string(), "Field counter.value has unsupported move type u512");
+
+        // Fields added after loading are caught by validate, and never guessed to be a String
+        let mut config = DubheConfig::from_json(json!({
+          "components": [
+            { "counter": { "fields": [{ "player": "address" }, { "status": "Status" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [{ "Status": ["Idle", "Busy"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let mut field = Field::new("counter".to_string(), "value".to_string());
+        field.index(1).move_type("u512".to_string()).db_type("TEXT".to_string());
+        config.push_field(field.clone());
+        assert!(config.validate().is_err());
+        assert!(field.proto_value(&[0; 64]).is_err());
+    }
+
+    #[test]
+    fn test_relations_must_join_on_shared_fields() {
+        let config = |relations: Value| {
+            DubheConfig::from_json(json!({
+              "components": [
+                { "position": { "fields": [{ "entity_id": "address" }, { "x": "u64" }], "keys": ["entity_id"], "offchain": false } },
+                { "health": { "fields": [{ "entity_id": "address" }, { "value": "u32" }], "keys": ["entity_id"], "offchain": false } }
+              ],
+              "resources": [],
+              "enums": [],
+              "original_package_id": "0x1",
+              "dubhe_object_id": "0x2",
+              "original_dubhe_package_id": "0x3",
+              "start_checkpoint": "1",
+              "relations": relations
+            }))
+        };
+        let related = config(json!({ "position": { "health": ["entity_id"] } })).unwrap();
+        let relations: Vec<_> = related.relations_of("position").collect();
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].related_table, "health");
+        assert_eq!(related.relations_of("health").count(), 0);
+
+        assert!(config(json!({ "position": { "health": ["x"] } })).is_err());
+        assert!(config(json!({ "position": { "missing": ["entity_id"] } })).is_err());
+        assert!(config(json!({ "position": { "health": [] } })).is_err());
+    }
+
+    #[test]
+    fn test_object_id_fields() {
+        let long_uid = format!("0x{:0>64}::object::UID", 2);
+        for type_ in ["ID", "UID", "0x2::object::ID", long_uid.as_str()] {
+            assert!(is_object_id_type(type_), "{}", type_);
+        }
+        assert!(!is_object_id_type("0x3::object::ID"));
+        assert!(!is_object_id_type("address"));
+
+        let config = |pet_owner: &str| {
+            DubheConfig::from_json(json!({
+              "components": [
+                { "pet": { "fields": [{ "id": "0x2::object::ID" }, { "owner": pet_owner }], "keys": ["id"], "offchain": false } },
+                { "profile": { "fields": [{ "owner": "address" }, { "name": "String" }], "keys": ["owner"], "offchain": false } }
+              ],
+              "resources": [],
+              "enums": [],
+              "original_package_id": "0x1",
+              "dubhe_object_id": "0x2",
+              "original_dubhe_package_id": "0x3",
+              "start_checkpoint": "1",
+              "relations": { "pet": { "profile": ["owner"] } }
+            }))
+        };
+        // An ID resolves the object at the same address
+        let err = config("u64").unwrap_err();
+        assert!(err.to_string().contains("only one of pet and profile"), "{}", err);
+        let config = config("ID").unwrap();
+        let id = config.fields.iter().find(|field| field.name == "id").unwrap();
+        assert_eq!(id.db_type, "TEXT");
+
+        let object = ObjectID::from_bytes([9; 32]).unwrap();
+        let bytes = bcs::to_bytes(&object).unwrap();
+        assert_eq!(bytes.len(), 32);
+        let literal = format!("'0x{}'", "09".repeat(32));
+        assert_eq!(into_sql_string("ID", &bytes).unwrap(), literal);
+        assert_eq!(key_sql_string(&id.move_type, &bytes).unwrap(), literal);
+        assert_eq!(
+            into_sql_string("address", &bcs::to_bytes(&SuiAddress::from(object)).unwrap())
+                .unwrap(),
+            literal
+        );
+        assert_eq!(
+            id.proto_value(&bytes).unwrap().kind,
+            Some(prost_types::value::Kind::StringValue(object.to_string()))
+        );
+
+        let err = into_sql_string("ID", &bytes[..31]).unwrap_err();
+        assert!(err.to_string().contains("Expected 32 bytes for an object id"));
+        assert!(id.proto_value(&[bytes.clone(), vec![0]].concat()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keyword_fields_are_quoted() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "transfer": { "fields": [{ "id": "u64" }, { "from": "address" }, { "to": "address" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "column_aliases": { "transfer": { "to": "recipient" } }
+        }))
+        .unwrap();
+        assert_eq!(config.column_name("transfer", "from"), "from");
+        assert_eq!(config.column_name("transfer", "to"), "recipient");
+        assert_eq!(config.column_name("transfer", "id"), "id");
+        assert_eq!(
+            config.field_names_by_table("transfer"),
+            vec!["\"id\"", "\"from\"", "\"recipient\""]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("alias.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for sql in config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+
+        // Values are still decoded by Move field position
+        let sender = SuiAddress::from_str(&format!("0x{}", "a".repeat(64))).unwrap();
+        let receiver = SuiAddress::from_str(&format!("0x{}", "b".repeat(64))).unwrap();
+        let event = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "transfer".to_string(),
+            key_tuple: vec![bcs::to_bytes(&7u64).unwrap()],
+            value_tuple: vec![bcs::to_bytes(&sender).unwrap(), bcs::to_bytes(&receiver).unwrap()],
+        });
+        let sql = config
+            .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Sqlite)
+            .unwrap();
+        database.execute(&sql).await.unwrap();
+
+        let rows = database
+            .query(r#"SELECT "from", recipient FROM store_transfer WHERE recipient IS NOT NULL"#)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["from"], sender.to_string());
+        assert_eq!(rows[0]["recipient"], receiver.to_string());
+
+        // Query builders quote the keyword column too
+        let query = crate::QueryBuilder::new("store_transfer")
+            .select(vec!["from"])
+            .filter("from", crate::QueryOperator::Eq, crate::QueryValue::String(sender.to_string()))
+            .sort("from", crate::SortDirection::Asc);
+        let rows = database.query(&query.to_sql()).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["from"], sender.to_string());
+    }
+
+    #[test]
+    fn test_column_aliases_are_checked() {
+        let config = |column_aliases: Value| {
+            DubheConfig::from_json(json!({
+              "components": [
+                { "transfer": { "fields": [{ "id": "u64" }, { "from": "address" }, { "to": "address" }], "keys": ["id"], "offchain": false } }
+              ],
+              "resources": [],
+              "enums": [],
+              "original_package_id": "0x1",
+              "dubhe_object_id": "0x2",
+              "original_dubhe_package_id": "0x3",
+              "start_checkpoint": "1",
+              "column_aliases": column_aliases
+            }))
+        };
+        assert!(config(json!({ "transfer": { "to": "recipient" } })).is_ok());
+
+        let err = config(json!({ "transfers": { "to": "recipient" } })).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown field transfers.to in column_aliases");
+        let err = config(json!({ "transfer": { "amount": "value" } })).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown field transfer.amount in column_aliases");
+
+        let err = config(json!({ "transfer": { "to": "from" } })).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column from of field transfer.to is already used by another field"
+        );
+
+        let err = config(json!({ "transfer": { "to": "is_deleted" } })).unwrap_err();
+        assert_eq!(err.to_string(), "Column is_deleted of field transfer.to is reserved");
+        let err = config(json!({ "transfer": { "to": "raw_key_bcs" } })).unwrap_err();
+        assert_eq!(err.to_string(), "Column raw_key_bcs of field transfer.to is reserved");
+    }
+
+    #[tokio::test]
+    async fn test_delete_after_set_in_same_transaction() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("sequence.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for sql in config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+
+        let key_tuple = vec![bcs::to_bytes(&1u64).unwrap()];
+        // Events arrive out of order; the set was emitted first
+        let mut events = vec![
+            crate::SequencedEvent {
+                event_seq: 1,
+                event: Event::StoreDeleteRecord(crate::events::StoreDeleteRecord {
+                    dapp_key: "1::dapp_key::DappKey".to_string(),
+                    table_id: "counter".to_string(),
+                    key_tuple: key_tuple.clone(),
+                }),
+            },
+            crate::SequencedEvent {
+                event_seq: 0,
+                event: Event::StoreSetRecord(StoreSetRecord {
+                    dapp_key: "1::dapp_key::DappKey".to_string(),
+                    table_id: "counter".to_string(),
+                    key_tuple,
+                    value_tuple: vec![bcs::to_bytes(&5u32).unwrap()],
+                }),
+            },
+        ];
+        crate::sort_by_event_sequence(&mut events);
+        for sequenced in events {
+            let sql = config
+                .convert_event_to_sql(sequenced.event, 0, "digest".to_string(), Dialect::Sqlite)
+                .unwrap();
+            database.execute(&sql).await.unwrap();
+        }
+
+        let rows = database
+            .query("SELECT id, is_deleted FROM store_counter")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["is_deleted"], true);
+    }
+
+    #[test]
+    fn test_package_qualified_table_names() {
+        let json = json!({
+          "components": [
+            { "counter0": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0xaaaaaaaa01",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "package_qualified_tables": true
+        });
+        let config = DubheConfig::from_json(json).unwrap();
+        let event = |package: &str| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: format!("{}::dapp_key::DappKey", package),
+                table_id: "counter0".to_string(),
+                key_tuple: vec![],
+                value_tuple: vec![bcs::to_bytes(&7u32).unwrap()],
+            })
+        };
+
+        // Two packages defining counter0 get distinct storage tables
+        assert_eq!(config.event_store_table_name(&event("aaaaaaaa01")), "store_aaaaaaaa_counter0");
+        assert_eq!(config.event_store_table_name(&event("bbbbbbbb02")), "store_bbbbbbbb_counter0");
+        assert!(config.create_tables_sql()[0].contains("store_aaaaaaaa_counter0 ("));
+        let sql = config
+            .convert_event_to_sql(event("aaaaaaaa01"), 0, "digest".to_string(), Dialect::Postgres)
+            .unwrap();
+        assert!(sql.starts_with("INSERT INTO store_aaaaaaaa_counter0 ("));
+
+        // System tables and the default flat naming are unchanged
+        assert_eq!(config.store_table_name(Some("0xaaaaaaaa01"), "dapp_fee_state"), "store_dapp_fee_state");
+        let mut flat = config.clone();
+        flat.set_package_qualified_tables(false);
+        assert_eq!(flat.event_store_table_name(&event("bbbbbbbb02")), "store_counter0");
+    }
+
+    #[test]
+    fn test_composite_key_follows_keys_order() {
+        let json = json!({
+          "components": [
+            {
+              "position": {
+                "fields": [{ "x": "u32" }, { "y": "u64" }, { "value": "bool" }],
+                "keys": ["y", "x"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let config = DubheConfig::from_json(json).unwrap();
+        // The key tuple is (y, x), as emitted by the Move codegen
+        let key_tuple = vec![bcs::to_bytes(&20u64).unwrap(), bcs::to_bytes(&10u32).unwrap()];
+
+        assert_eq!(
+            config.field_names_by_table_and_primary_key("position"),
+            vec!["\"y\"".to_string(), "\"x\"".to_string()]
+        );
+        assert!(config.create_tables_sql()[0].ends_with("PRIMARY KEY (\"y\",\"x\"));"));
+        assert_eq!(
+            config.field_values_by_table_and_primary_key("position", &key_tuple, Dialect::Postgres),
+            vec!["\"y\" = 20".to_string(), "\"x\" = 10".to_string()]
+        );
+        assert_eq!(
+            config.field_values_by_table(
+                "position",
+                &key_tuple,
+                &vec![bcs::to_bytes(&true).unwrap()]
+            ),
+            vec!["10".to_string(), "20".to_string(), "true".to_string()]
+        );
+
+        let sql = config
+            .convert_event_to_sql(
+                Event::StoreDeleteRecord(crate::events::StoreDeleteRecord {
+                    dapp_key: "1::dapp_key::DappKey".to_string(),
+                    table_id: "position".to_string(),
+                    key_tuple,
+                }),
+                0,
+                "digest".to_string(),
+                Dialect::Postgres,
+            )
+            .unwrap();
+        assert!(sql.ends_with("WHERE \"y\" = 20 AND \"x\" = 10;"));
+    }
+
+    #[test]
+    fn test_deserialized_config_indexes_its_fields() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "position": {
+                "fields": [{ "x": "u32" }, { "y": "u64" }, { "value": "bool" }],
+                "keys": ["y", "x"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let deserialized: DubheConfig = serde_json::from_value(json!({
+          "fields": config.fields,
+          "enums": [],
+          "tables": [],
+          "relations": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "system_tables": [],
+          "exclude_tables": [],
+          "package_qualified_tables": false,
+          "on_unknown_table": "skip",
+          "store_raw_bcs": false
+        }))
+        .unwrap();
+
+        assert_eq!(
+            deserialized.field_names_by_table_and_primary_key("position"),
+            vec!["\"y\"".to_string(), "\"x\"".to_string()]
+        );
+        assert_eq!(
+            deserialized.field_names_by_table("position"),
+            config.field_names_by_table("position")
+        );
+
+        // Fields changed directly are picked up once the index is rebuilt
+        let mut edited = deserialized.clone();
+        edited.fields.retain(|field| field.name != "x");
+        edited.rebuild_field_index();
+        assert_eq!(
+            edited.field_names_by_table_and_primary_key("position"),
+            vec!["\"y\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_vector_keys() {
+        let json = json!({
+          "components": [
+            {
+              "blob": {
+                "fields": [
+                  { "hash": "vector<u8>" },
+                  { "path": "vector<vector<u8>>" },
+                  { "size": "u64" }
+                ],
+                "keys": ["hash", "path"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let config = DubheConfig::from_json(json).unwrap();
+        let path = bcs::to_bytes(&vec![vec![1u8], vec![2u8, 3u8]]).unwrap();
+        let key_tuple = vec![bcs::to_bytes(&vec![0xabu8, 0xcd]).unwrap(), path.clone()];
+
+        // Nested vectors have no array column type and are keyed by their BCS hex
+        let create_sql = &config.create_tables_sql()[0];
+        assert!(create_sql.contains("\"hash\" INTEGER[]"));
+        assert!(create_sql.contains("\"path\" TEXT"));
+        assert_eq!(
+            config.field_values_by_table_and_primary_key("blob", &key_tuple, Dialect::Postgres),
+            vec![
+                "\"hash\" = ARRAY[171, 205]::INTEGER[]".to_string(),
+                format!("\"path\" = '0x{}'", hex::encode(&path)),
+            ]
+        );
+
+        // An empty vector key still has a typed literal
+        let empty_key = vec![bcs::to_bytes(&Vec::<u8>::new()).unwrap(), path];
+        assert_eq!(
+            config.field_values_by_table_and_primary_key("blob", &empty_key, Dialect::Postgres)[0],
+            "\"hash\" = ARRAY[]::INTEGER[]"
+        );
+        let empty_strings = bcs::to_bytes(&Vec::<String>::new()).unwrap();
+        assert_eq!(key_sql_string("vector<String>", &empty_strings).unwrap(), "ARRAY[]::TEXT[]");
+    }
+
+    #[test]
+    fn test_config_hash_ignores_json_order() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "y": "u64" }], "keys": ["player"], "offchain": false } },
+            { "counter": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [{ "Direction": ["North", "South"] }, { "Status": ["Active", "Closed"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let reordered = DubheConfig::from_json(json!({
+          "start_checkpoint": "1",
+          "original_dubhe_package_id": "0x3",
+          "dubhe_object_id": "0x2",
+          "original_package_id": "0x1",
+          "enums": [{ "Status": ["Active", "Closed"] }, { "Direction": ["North", "South"] }],
+          "resources": [],
+          "components": [
+            { "counter": { "offchain": false, "keys": [], "fields": [{ "value": "u32" }] } },
+            { "position": { "offchain": false, "keys": ["player"], "fields": [{ "player": "address" }, { "x": "u64" }, { "y": "u64" }] } }
+          ]
+        }))
+        .unwrap();
+        assert_eq!(config.config_hash().len(), 64);
+        assert_eq!(config.config_hash(), reordered.config_hash());
+
+        // Field order is the BCS order, so swapping fields is a different config
+        let swapped = DubheConfig::from_json(json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "y": "u64" }, { "x": "u64" }], "keys": ["player"], "offchain": false } },
+            { "counter": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [{ "Direction": ["North", "South"] }, { "Status": ["Active", "Closed"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        assert_ne!(config.config_hash(), swapped.config_hash());
+    }
+
+    #[test]
+    fn test_dubhe_config_fields_from_json() {
+        let test_json = get_test_json();
+        let result = DubheConfig::from_json(test_json).unwrap();
+        assert_eq!(result.fields.len(), 15);
+        assert_eq!(result.fields[0].name, "entity_id");
+        assert_eq!(result.fields[1].name, "entity_id");
+        assert_eq!(result.fields[2].name, "value");
+        assert_eq!(result.fields[3].name, "entity_id");
+        assert_eq!(result.fields[4].name, "value");
+
+        assert_eq!(result.fields[5].table, "counter3");
+        assert_eq!(result.fields[5].name, "entity_id");
+        assert_eq!(result.fields[5].index, 0);
+        assert_eq!(result.fields[5].primary_key, true);
+        assert_eq!(result.fields[5].move_type, "address");
+        assert_eq!(result.fields[5].db_type, "TEXT");
+
+        assert_eq!(result.fields[6].name, "hp");
+        assert_eq!(result.fields[6].index, 0);
+        assert_eq!(result.fields[6].primary_key, false);
+        assert_eq!(result.fields[6].move_type, "u64");
+        assert_eq!(result.fields[6].db_type, "INTEGER");
+
+        assert_eq!(result.fields[7].name, "attack");
+        assert_eq!(result.fields[7].index, 1);
+        assert_eq!(result.fields[7].primary_key, false);
+        assert_eq!(result.fields[7].move_type, "u64");
+        assert_eq!(result.fields[7].db_type, "INTEGER");
+
+        assert_eq!(result.fields[8].name, "defense");
+        assert_eq!(result.fields[8].index, 2);
+        assert_eq!(result.fields[8].primary_key, false);
+        assert_eq!(result.fields[8].move_type, "u64");
+        assert_eq!(result.fields[8].db_type, "INTEGER");
+
+        assert_eq!(result.fields[9].name, "value");
+        assert_eq!(result.fields[9].index, 0);
+        assert_eq!(result.fields[9].primary_key, false);
+
+        assert_eq!(result.fields[10].name, "player");
+        assert_eq!(result.fields[10].index, 0);
+        assert_eq!(result.fields[10].primary_key, false);
+        assert_eq!(result.fields[11].name, "value");
+        assert_eq!(result.fields[11].index, 1);
+        assert_eq!(result.fields[11].primary_key, false);
+
+        assert_eq!(result.fields[12].name, "player");
+        assert_eq!(result.fields[12].index, 0);
+        assert_eq!(result.fields[12].primary_key, true);
+        assert_eq!(result.fields[13].name, "monster");
+        assert_eq!(result.fields[13].index, 1);
+        assert_eq!(result.fields[13].primary_key, true);
+        assert_eq!(result.fields[14].name, "value");
+        assert_eq!(result.fields[14].index, 0);
+        assert_eq!(result.fields[14].primary_key, false);
+
+        println!("fields: {:?}", result.fields);
+    }
+
+    #[test]
+    fn test_record_columns_match_separate_lists() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "encounter": {
+                "fields": [
+                  { "value": "u32" },
+                  { "monster": "address" },
+                  { "player": "address" },
+                  { "direction": "Direction" }
+                ],
+                "keys": ["player", "monster"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [{ "Direction": ["Left", "Right"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let key_tuple = vec![
+            bcs::to_bytes(&SuiAddress::from_bytes([1; 32]).unwrap()).unwrap(),
+            bcs::to_bytes(&SuiAddress::from_bytes([2; 32]).unwrap()).unwrap(),
+        ];
+        let value_tuple = vec![bcs::to_bytes(&7u32).unwrap(), bcs::to_bytes(&1u8).unwrap()];
+
+        let columns = config
+            .record_columns("encounter", &key_tuple, &value_tuple, Dialect::Postgres)
+            .unwrap();
+        assert_eq!(
+            columns,
+            RecordColumns {
+                names: config.field_names_by_table("encounter").join(","),
+                values: config
+                    .field_values_by_table("encounter", &key_tuple, &value_tuple)
+                    .join(","),
+                key_names: config
+                    .field_names_by_table_and_primary_key("encounter")
+                    .join(","),
+                assignments: config
+                    .field_values_with_set_by_table("encounter", &key_tuple, &value_tuple)
+                    .join(","),
+                value_assignments: config
+                    .field_values_by_table_and_non_primary_key("encounter", &value_tuple)
+                    .join(","),
+            }
+        );
+        assert!(columns.key_names.starts_with("\"player\""));
+    }
+
+    #[test]
+    fn test_scale_decimal_is_exact() {
+        assert_eq!(scale_decimal("1500000000", 9).as_deref(), Some("1.5"));
+        assert_eq!(scale_decimal("42", 9).as_deref(), Some("0.000000042"));
+        assert_eq!(scale_decimal("0", 9).as_deref(), Some("0"));
+        assert_eq!(scale_decimal("7", 0).as_deref(), Some("7"));
+        assert_eq!(
+            scale_decimal(&u128::MAX.to_string(), 18).as_deref(),
+            Some("340282366920938463463.374607431768211455")
+        );
+        assert!(scale_decimal("-1", 9).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_store_raw_bcs_keeps_record_tuples() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            {
+              "position": {
+                "fields": [
+                  { "player": "address" },
+                  { "x": "u32" },
+                  { "y": "u32" }
+                ],
+                "keys": ["player"],
+                "offchain": false
+              }
+            }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "store_raw_bcs": true
+        }))
+        .unwrap();
+        let create_sql = config.create_table_sql("position").unwrap();
+        assert!(create_sql
+            .contains("\"raw_key_bcs\" BYTEA,\"raw_x_bcs\" BYTEA,\"raw_y_bcs\" BYTEA,"));
+        assert!(config.selectable_columns("position").contains(&"raw_y_bcs".to_string()));
+
+        let key_tuple = vec![bcs::to_bytes(&SuiAddress::from_bytes([1; 32]).unwrap()).unwrap()];
+        let set_record = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "position".to_string(),
+            key_tuple: key_tuple.clone(),
+            value_tuple: vec![bcs::to_bytes(&7u32).unwrap(), bcs::to_bytes(&9u32).unwrap()],
+        });
+        let set_field = Event::StoreSetField(StoreSetField {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "position".to_string(),
+            key_tuple: key_tuple.clone(),
+            field_index: 1,
+            value: bcs::to_bytes(&3u32).unwrap(),
+        });
+
+        let sql = config
+            .convert_event_to_sql(set_record.clone(), 0, "digest".to_string(), Dialect::Postgres)
+            .unwrap();
+        // The key tuple is BCS encoded as a whole, so its components keep their boundaries
+        let raw_key = format!("'\\x0120{}'::bytea", "01".repeat(32));
+        assert!(sql.contains("\"y\",\"raw_key_bcs\",\"raw_x_bcs\",\"raw_y_bcs\", created_at"));
+        let raw_values = "'\\x07000000'::bytea,'\\x09000000'::bytea,";
+        assert!(sql.contains(&format!("9,{},{}", raw_key, raw_values)));
+        assert!(sql.contains("\"raw_y_bcs\" = '\\x09000000'::bytea"));
+
+        // A single field update rewrites that field's raw bytes along with its column
+        let sql = config
+            .convert_event_to_sql(set_field.clone(), 0, "digest".to_string(), Dialect::Postgres)
+            .unwrap();
+        assert!(sql.contains("\"y\" = 3,\"raw_y_bcs\" = '\\x03000000'::bytea,"));
+        assert!(!sql.contains("raw_x_bcs"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("raw_bcs.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        database.execute(&create_sql).await.unwrap();
+        for event in [set_record, set_field] {
+            let sql = config
+                .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Sqlite)
+                .unwrap();
+            database.execute(&sql).await.unwrap();
+        }
+        let stored = database
+            .query(
+                "SELECT y, hex(raw_key_bcs) AS raw_key, hex(raw_x_bcs) AS raw_x, \
+                 hex(raw_y_bcs) AS raw_y, typeof(raw_y_bcs) AS raw_type FROM store_position",
+            )
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0]["y"], 3);
+        assert_eq!(stored[0]["raw_key"], format!("0120{}", "01".repeat(32)));
+        assert_eq!(stored[0]["raw_x"], "07000000");
+        assert_eq!(stored[0]["raw_y"], "03000000");
+        assert_eq!(stored[0]["raw_type"], "blob");
+    }
+
+    #[test]
+    fn test_raw_bcs_columns_keep_the_case_of_their_field() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "hero": { "fields": [{ "id": "u64" }, { "maxHp": "u32" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "store_raw_bcs": true
+        }))
+        .unwrap();
+        // Unquoted, Postgres would create raw_maxhp_bcs and every insert would miss it
+        let create_sql = config.create_table_sql("hero").unwrap();
+        assert!(create_sql.contains("\"raw_maxHp_bcs\" BYTEA"));
+        let sql = config
+            .convert_event_to_sql(
+                Event::StoreSetRecord(StoreSetRecord {
+                    dapp_key: "1::dapp_key::DappKey".to_string(),
+                    table_id: "hero".to_string(),
+                    key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+                    value_tuple: vec![bcs::to_bytes(&10u32).unwrap()],
+                }),
+                0,
+                "digest".to_string(),
+                Dialect::Postgres,
+            )
+            .unwrap();
+        assert!(sql.contains("\"raw_maxHp_bcs\""));
+    }
+
+    #[test]
+    fn test_can_convert_event_to_sql() {
+        let test_json = get_test_json();
+        let result = DubheConfig::from_json(test_json).unwrap();
+        let event = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "counter0".to_string(),
+            key_tuple: Vec::new(),
+            value_tuple: Vec::new(),
+        });
+        assert!(result.can_convert_event_to_sql(&event).is_ok());
+    }
+
+    #[test]
+    fn test_convert_event_to_sql() {
+        let test_json = get_test_json();
+        let config = DubheConfig::from_json(test_json).unwrap();
+        let event = Event::StoreSetRecord(StoreSetRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "counter3".to_string(),
             key_tuple: vec![bcs::to_bytes(
                 &SuiAddress::from_str(
                     "0xd8f042479dcb0028d868051bd53f0d3a41c600db7b14241674db1c2e60124975",
@@ -2593,6 +6282,128 @@ mod tests {
         assert_eq!(result, "INSERT INTO store_counter5 (unique_resource_id,player,value) VALUES (1,'0xd8f042479dcb0028d868051bd53f0d3a41c600db7b14241674db1c2e60124975',10) ON CONFLICT (unique_resource_id) DO UPDATE SET player = '0xd8f042479dcb0028d868051bd53f0d3a41c600db7b14241674db1c2e60124975',value = 10;");
     }
 
+    #[test]
+    fn test_conflict_policy_clause() {
+        let event = || {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter3".to_string(),
+                key_tuple: vec![bcs::to_bytes(&SuiAddress::from_str("0x1").unwrap()).unwrap()],
+                value_tuple: vec![
+                    bcs::to_bytes(&10u64).unwrap(),
+                    bcs::to_bytes(&20u64).unwrap(),
+                    bcs::to_bytes(&30u64).unwrap(),
+                ],
+            })
+        };
+        let sql = |policy: ConflictPolicy| {
+            let mut config = DubheConfig::from_json(get_test_json()).unwrap();
+            for table in &mut config.tables {
+                table.conflict_policy = policy;
+            }
+            assert_eq!(config.conflict_policy("counter3"), policy);
+            config
+                .convert_event_to_sql(event(), 7, "digest".to_string(), Dialect::Postgres)
+                .unwrap()
+        };
+
+        // Error: a plain INSERT, the database raises on a duplicate key
+        let insert = sql(ConflictPolicy::Error);
+        assert!(insert.starts_with("INSERT INTO store_counter3 ("));
+        assert!(insert.ends_with(",7,7,'digest');"));
+        assert!(!insert.contains("ON CONFLICT"));
+        let insert = insert.trim_end_matches(';');
+
+        let upsert = sql(ConflictPolicy::Update);
+        assert!(upsert.starts_with(&format!(
+            "{} ON CONFLICT (\"entity_id\") DO UPDATE SET ",
+            insert
+        )));
+        assert!(upsert.ends_with(
+            "\"hp\" = 10,\"attack\" = 20,\"defense\" = 30,is_deleted = FALSE,\
+             updated_at_timestamp_ms = 7,last_update_digest = 'digest';"
+        ));
+        // Ignore: the same upsert, applied only over a soft-deleted row
+        assert_eq!(
+            sql(ConflictPolicy::Ignore),
+            format!("{} WHERE store_counter3.is_deleted;", upsert.trim_end_matches(';'))
+        );
+
+        let policy: ConflictPolicy = serde_json::from_str("\"ignore\"").unwrap();
+        assert_eq!(policy, ConflictPolicy::Ignore);
+        let policy: ConflictPolicy = serde_json::from_str("\"error\"").unwrap();
+        assert_eq!(policy, ConflictPolicy::Error);
+        let config = DubheConfig::from_json(get_test_json()).unwrap();
+        assert_eq!(config.conflict_policy("counter3"), ConflictPolicy::Update);
+
+        // Rows of an append-only table never conflict
+        let append_only = DubheConfig::from_json(json!({
+          "components": [],
+          "resources": [
+            { "log": { "fields": [{ "text": "String" }], "keys": [], "offchain": true, "conflict_policy": "ignore" } }
+          ],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }));
+        assert!(append_only.unwrap_err().to_string().contains("append-only"));
+    }
+
+    #[tokio::test]
+    async fn test_ignore_policy_overwrites_soft_deleted_rows() {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false, "conflict_policy": "ignore" } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("ignore.db").display());
+        let database = crate::Database::new(&url).await.unwrap();
+        for sql in config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+
+        let key_tuple = vec![bcs::to_bytes(&1u64).unwrap()];
+        let set = |value: u32| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter".to_string(),
+                key_tuple: key_tuple.clone(),
+                value_tuple: vec![bcs::to_bytes(&value).unwrap()],
+            })
+        };
+        let delete = Event::StoreDeleteRecord(crate::events::StoreDeleteRecord {
+            dapp_key: "1::dapp_key::DappKey".to_string(),
+            table_id: "counter".to_string(),
+            key_tuple: key_tuple.clone(),
+        });
+        // The first write wins over a live row, a deleted row is written over and comes back
+        let steps = [(set(5), 5, 0), (set(6), 5, 0), (delete, 5, 1), (set(7), 7, 0)];
+        for (event, value, is_deleted) in steps {
+            let sql = config
+                .convert_event_to_sql(event, 0, "digest".to_string(), Dialect::Sqlite)
+                .unwrap();
+            database.execute(&sql).await.unwrap();
+            let rows = database
+                .query(&format!(
+                    "SELECT id FROM store_counter WHERE value = {} AND is_deleted = {}",
+                    value, is_deleted
+                ))
+                .await
+                .unwrap();
+            assert_eq!(rows.len(), 1, "{}", sql);
+        }
+    }
+
     #[test]
     fn test_convert_event_to_proto_struct() {
         let test_json = get_full_test_json();
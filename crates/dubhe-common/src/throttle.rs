@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Coalesces values per key for the subscriber pushes of throttled tables: the first value of
+/// a key is released after the interval, and every value arriving before then replaces it, so
+/// at most the latest value per key is released per interval.
+pub struct Debounce<K, V> {
+    /// Latest unreleased value by key
+    pending: Arc<Mutex<HashMap<K, V>>>,
+}
+
+impl<K, V> Clone for Debounce<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for Debounce<K, V> {
+    fn default() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<K, V> Debounce<K, V>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    V: Send + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hand `value` to `release` once `interval` has passed, or the latest value of `key`
+    /// pushed by then. Values pushed while one of `key` is pending only replace it.
+    pub fn push<F, Fut>(&self, key: K, value: V, interval: Duration, release: F)
+    where
+        F: FnOnce(V) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        if self.pending.lock().unwrap().insert(key.clone(), value).is_some() {
+            // A release of this key is already scheduled and will carry the new value
+            return;
+        }
+        let pending = self.pending.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(interval).await;
+            let value = pending.lock().unwrap().remove(&key);
+            if let Some(value) = value {
+                release(value).await;
+            }
+        });
+    }
+}
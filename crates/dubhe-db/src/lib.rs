@@ -14,6 +14,9 @@ use sui_json_rpc_types::SuiMoveStruct;
 use sui_json_rpc_types::SuiMovePackage;
 use sui_json_rpc_types::SuiParsedMoveObject;
 use sui_json_rpc_types::SuiMoveValue;
+use sui_json_rpc_types::SuiObjectResponse;
+use sui_json_rpc_types::DynamicFieldPage;
+use core::future::Future;
 use crate::interface::DatabaseAsyncRef;
 use core::error::Error;
 use core::fmt::Display;
@@ -21,7 +24,8 @@ use crate::interface::DBErrorMarker;
 use sui_sdk::SuiClientBuilder;
 use sui_types::base_types::ObjectID;
 use sui_types::TypeTag;
-use sui_types::dynamic_field::DynamicFieldName;
+use sui_types::error::SuiObjectResponseError;
+use sui_types::dynamic_field::{DynamicFieldInfo, DynamicFieldName};
 use serde_json::Value;
 use std::{primitive, str::FromStr};
 
@@ -46,40 +50,349 @@ impl From<SuiSdkError> for DBTransportError {
     }
 }
 
+/// Coarse classification of a [DBTransportError], used to decide whether a call is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DBErrorKind {
+    /// The requested object does not exist.
+    NotFound,
+    /// The RPC endpoint rejected the call because of rate limiting (HTTP 429).
+    RateLimited,
+    /// Network or connection level failure.
+    Transport,
+    /// The response could not be decoded or converted.
+    Data,
+    Other,
+}
+
+impl DBErrorKind {
+    /// Whether an error of this kind may go away if the call is repeated.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DBErrorKind::RateLimited | DBErrorKind::Transport)
+    }
+}
+
+fn is_rate_limit_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("429") || message.contains("too many requests") || message.contains("rate limit")
+}
+
+fn is_not_found_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("notexists") || message.contains("not exist") || message.contains("not found") || message.contains("deleted")
+}
+
+impl DBTransportError {
+    pub fn kind(&self) -> DBErrorKind {
+        match &self.0 {
+            SuiSdkError::RpcError(e) => {
+                let message = e.to_string();
+                if is_rate_limit_message(&message) {
+                    DBErrorKind::RateLimited
+                } else {
+                    DBErrorKind::Transport
+                }
+            }
+            SuiSdkError::JsonRpcError(e) => {
+                let message = e.to_string();
+                if is_rate_limit_message(&message) {
+                    DBErrorKind::RateLimited
+                } else if is_not_found_message(&message) {
+                    DBErrorKind::NotFound
+                } else {
+                    DBErrorKind::Other
+                }
+            }
+            SuiSdkError::IOError(_) => DBErrorKind::Transport,
+            SuiSdkError::UserInputError(e) => match e {
+                sui_types::error::UserInputError::ObjectNotFound { .. } => DBErrorKind::NotFound,
+                _ => DBErrorKind::Data,
+            },
+            SuiSdkError::DataError(message) => {
+                if is_not_found_message(message) {
+                    DBErrorKind::NotFound
+                } else {
+                    DBErrorKind::Data
+                }
+            }
+            SuiSdkError::BcsSerialisationError(_) | SuiSdkError::JsonSerializationError(_) => DBErrorKind::Data,
+            _ => DBErrorKind::Other,
+        }
+    }
+}
+
+/// Maximum number of attempts for an RPC call that keeps failing with a transient error.
+pub const MAX_RPC_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled after every failed attempt.
+pub const RPC_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Retry policy of the RPC calls made by [initialize_cache].
+#[derive(Debug, Clone)]
+pub struct CacheInitOptions {
+    /// Retries after the first attempt before a transient error is returned.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled after every failed attempt.
+    pub base_delay: std::time::Duration,
+    /// Add up to 50% random delay so concurrent callers do not retry in lockstep.
+    pub jitter: bool,
+}
+
+impl Default for CacheInitOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: RPC_RETRY_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+impl CacheInitOptions {
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_base_delay(mut self, base_delay: std::time::Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delay before retry number `retry` (0-based).
+    fn backoff_delay(&self, retry: u32) -> std::time::Duration {
+        let delay = self.base_delay.saturating_mul(2u32.saturating_pow(retry));
+        if !self.jitter {
+            return delay;
+        }
+        use std::hash::{BuildHasher, Hasher};
+        let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        delay + delay.mul_f64((random % 1000) as f64 / 2000.0)
+    }
+}
+
+/// Connection settings of the clients made by [build_sui_client], so a slow node fails
+/// requests instead of stalling every caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiClientOptions {
+    /// Longest time a single RPC request may take.
+    pub request_timeout: std::time::Duration,
+    /// Most RPC requests the client keeps in flight at once.
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for SuiClientOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: std::time::Duration::from_secs(60),
+            max_concurrent_requests: 256,
+        }
+    }
+}
+
+impl SuiClientOptions {
+    pub fn with_request_timeout(mut self, request_timeout: std::time::Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests;
+        self
+    }
+
+    /// A [SuiClientBuilder] with these settings applied.
+    pub fn builder(&self) -> SuiClientBuilder {
+        SuiClientBuilder::default()
+            .request_timeout(self.request_timeout)
+            .max_concurrent_requests(self.max_concurrent_requests)
+    }
+}
+
+/// Connect to the RPC node at `url` with `options`. Use this rather than
+/// `SuiClientBuilder::default()`, so every client follows the configured timeout and
+/// concurrency instead of the SDK's defaults.
+pub async fn build_sui_client(
+    url: &str,
+    options: &SuiClientOptions,
+) -> Result<SuiClient, SuiSdkError> {
+    options.builder().build(url).await
+}
+
+/// Run `call` until it succeeds, fails with an error that is not transient (see [DBErrorKind::is_transient]),
+/// or `options.max_retries` retries have failed. Rate-limited and transport errors back off exponentially.
+pub async fn retry_with_backoff<T, F, Fut>(options: &CacheInitOptions, mut call: F) -> Result<T, DBTransportError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, DBTransportError>>,
+{
+    let mut retry = 0;
+    loop {
+        match call().await {
+            Ok(value) => return Ok(value),
+            Err(e) if retry < options.max_retries && e.kind().is_transient() => {
+                let delay = options.backoff_delay(retry);
+                tracing::warn!("⚠️ RPC call failed ({:?}), retrying in {:?}: {}", e.kind(), delay, e);
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Run `call` until it succeeds, fails with a non-transient error, or [MAX_RPC_ATTEMPTS] is reached.
+pub async fn retry_transient<T, F, Fut>(call: F) -> Result<T, DBTransportError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, DBTransportError>>,
+{
+    let options = CacheInitOptions {
+        max_retries: MAX_RPC_ATTEMPTS - 1,
+        base_delay: RPC_RETRY_DELAY,
+        jitter: false,
+    };
+    retry_with_backoff(&options, call).await
+}
+
+/// The part of the Sui read API used by [initialize_cache], so the warm-up can run against a mock.
+pub trait ObjectReader {
+    fn get_object_with_options(
+        &self,
+        object_id: ObjectID,
+        options: SuiObjectDataOptions,
+    ) -> impl Future<Output = Result<SuiObjectResponse, DBTransportError>> + Send;
+
+    fn get_dynamic_fields(
+        &self,
+        parent_object_id: ObjectID,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> impl Future<Output = Result<DynamicFieldPage, DBTransportError>> + Send;
+
+    fn multi_get_object_with_options(
+        &self,
+        object_ids: Vec<ObjectID>,
+        options: SuiObjectDataOptions,
+    ) -> impl Future<Output = Result<Vec<SuiObjectResponse>, DBTransportError>> + Send;
+}
+
+impl ObjectReader for ReadApi {
+    async fn get_object_with_options(
+        &self,
+        object_id: ObjectID,
+        options: SuiObjectDataOptions,
+    ) -> Result<SuiObjectResponse, DBTransportError> {
+        ReadApi::get_object_with_options(self, object_id, options).await.map_err(DBTransportError::from)
+    }
+
+    async fn get_dynamic_fields(
+        &self,
+        parent_object_id: ObjectID,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> Result<DynamicFieldPage, DBTransportError> {
+        ReadApi::get_dynamic_fields(self, parent_object_id, cursor, limit).await.map_err(DBTransportError::from)
+    }
+
+    async fn multi_get_object_with_options(
+        &self,
+        object_ids: Vec<ObjectID>,
+        options: SuiObjectDataOptions,
+    ) -> Result<Vec<SuiObjectResponse>, DBTransportError> {
+        ReadApi::multi_get_object_with_options(self, object_ids, options).await.map_err(DBTransportError::from)
+    }
+}
+
+impl ObjectReader for SuiClient {
+    async fn get_object_with_options(
+        &self,
+        object_id: ObjectID,
+        options: SuiObjectDataOptions,
+    ) -> Result<SuiObjectResponse, DBTransportError> {
+        ObjectReader::get_object_with_options(self.read_api(), object_id, options).await
+    }
+
+    async fn get_dynamic_fields(
+        &self,
+        parent_object_id: ObjectID,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> Result<DynamicFieldPage, DBTransportError> {
+        ObjectReader::get_dynamic_fields(self.read_api(), parent_object_id, cursor, limit).await
+    }
+
+    async fn multi_get_object_with_options(
+        &self,
+        object_ids: Vec<ObjectID>,
+        options: SuiObjectDataOptions,
+    ) -> Result<Vec<SuiObjectResponse>, DBTransportError> {
+        ObjectReader::multi_get_object_with_options(self.read_api(), object_ids, options).await
+    }
+}
+
+fn data_error(message: impl Into<String>) -> DBTransportError {
+    DBTransportError(SuiSdkError::DataError(message.into()))
+}
+
 /// An alloy-powered REVM [Database][database_interface::Database].
 ///
 /// When accessing the database, it'll use the given provider to fetch the corresponding account's data.
 #[derive(Clone)]
-pub struct DubheDB {
+pub struct DubheDB<R = SuiClient> {
     /// The provider to fetch the data from.
-    provider: SuiClient,
+    provider: R,
+    /// Whether objects missing from the cache are fetched from the provider at all
+    resolve_missing_objects: bool,
 }
 
 
-impl DubheDB {
-    pub fn new(provider: SuiClient) -> Self {
-        Self { provider }
+impl<R: ObjectReader> DubheDB<R> {
+    pub fn new(provider: R) -> Self {
+        Self { provider, resolve_missing_objects: true }
+    }
+
+    /// Treat objects missing from the cache as absent instead of fetching them, saving an
+    /// RPC per miss
+    pub fn with_resolve_missing_objects(mut self, resolve_missing_objects: bool) -> Self {
+        self.resolve_missing_objects = resolve_missing_objects;
+        self
     }
 }
 
 
-impl DatabaseAsyncRef for DubheDB {
+impl<R: ObjectReader + Sync> DatabaseAsyncRef for DubheDB<R> {
     type Error = DBTransportError;
 
     async fn object_async_ref(
             &self,
             address: sui_types::base_types::ObjectID,
         ) -> Result<Option<sui_types::object::Object>, Self::Error> {
-            let sui_object_response = self.provider.read_api().get_object_with_options(address, sui_json_rpc_types::SuiObjectDataOptions {
-                show_type: true,
-                show_owner: true,
-                show_previous_transaction: true,
-                show_display: true,
-                show_content: true,
-                show_bcs: true,
-                show_storage_rebate: true,
+            if !self.resolve_missing_objects {
+                return Ok(None);
+            }
+            let sui_object_response = retry_transient(|| async {
+                self.provider.get_object_with_options(address, sui_json_rpc_types::SuiObjectDataOptions {
+                    show_type: true,
+                    show_owner: true,
+                    show_previous_transaction: true,
+                    show_display: true,
+                    show_content: true,
+                    show_bcs: true,
+                    show_storage_rebate: true,
+                }).await
             }).await?;
-            println!("sui_object_response: {:?}", sui_object_response);
+            tracing::debug!("sui_object_response: {:?}", sui_object_response);
+            // Missing and deleted objects are a miss, not a transport failure
+            if let Some(
+                SuiObjectResponseError::NotExists { .. } | SuiObjectResponseError::Deleted { .. },
+            ) = &sui_object_response.error
+            {
+                return Ok(None);
+            }
             let sui_object_data = sui_object_response.into_object().map_err(|e| DBTransportError(SuiSdkError::DataError(e.to_string())))?;
             let object: sui_types::object::Object = sui_object_data.try_into().map_err(|e| DBTransportError(SuiSdkError::DataError(format!("Failed to convert SuiObjectData to Object: {:?}", e))))?;
             Ok(Some(object))
@@ -87,10 +400,10 @@ impl DatabaseAsyncRef for DubheDB {
 }
 
 pub fn get_field_id(sui_object_data: &SuiObjectData, field_name: &str) -> Option<ObjectID> {
-    let sui_parsed_object = sui_object_data.content.clone().unwrap();
+    let sui_parsed_object = sui_object_data.content.clone()?;
     if let SuiParsedData::MoveObject(SuiParsedMoveObject { fields, .. }) = sui_parsed_object {
-        if let SuiMoveValue::Struct(fields) = fields.field_value(field_name).unwrap() {
-            if let SuiMoveValue::UID { id } =  fields.field_value("id").unwrap() {
+        if let SuiMoveValue::Struct(fields) = fields.field_value(field_name)? {
+            if let SuiMoveValue::UID { id } =  fields.field_value("id")? {
                 return Some(id);
             }
         }
@@ -101,18 +414,115 @@ pub fn get_field_id(sui_object_data: &SuiObjectData, field_name: &str) -> Option
 pub fn get_dapp_key_str(package_id: &str) -> String {
     // 0x86c8925b708ecd5570d70f3ccbc30035f9fa65480b546a563afdc046da98d103
     // ====> 86c8925b708ecd5570d70f3ccbc30035f9fa65480b546a563afdc046da98d103::dapp_key::DappKey
-    format!("{}::dapp_key::DappKey", package_id.strip_prefix("0x").unwrap())
+    format!("{}::dapp_key::DappKey", package_id.strip_prefix("0x").unwrap_or(package_id))
+}
+
+/// Package of the `<package>::dapp_key::DappKey` type a dapp store is keyed by. The key is
+/// either the type name string itself or a `TypeName { name }` struct.
+fn dapp_key_package(name: &DynamicFieldName) -> Option<ObjectID> {
+    let type_name = match &name.value {
+        Value::String(type_name) => type_name.as_str(),
+        Value::Object(fields) => fields.get("name")?.as_str()?,
+        _ => return None,
+    };
+    let (package, rest) = type_name.split_once("::")?;
+    if rest != "dapp_key::DappKey" {
+        return None;
+    }
+    ObjectID::from_hex_literal(&format!("0x{}", package.trim_start_matches("0x"))).ok()
 }
 
+/// Find the dapp store keyed by the `DappKey` of `package_id`, comparing package ids exactly.
+/// Names that do not parse as a `DappKey` fall back to a substring match.
+pub fn find_dapp_store<'a>(
+    infos: &'a [DynamicFieldInfo],
+    package_id: &str,
+) -> Option<&'a DynamicFieldInfo> {
+    let package = ObjectID::from_hex_literal(package_id).ok();
+    infos.iter().find(|info| match (dapp_key_package(&info.name), package) {
+        (Some(key_package), Some(package)) => key_package == package,
+        _ => {
+            let matched = info.name.value.to_string().contains(&get_dapp_key_str(package_id));
+            if matched {
+                tracing::warn!(
+                    "⚠️ Dapp store {} matched package {} by substring, its key did not parse as a DappKey",
+                    info.object_id, package_id
+                );
+            }
+            matched
+        }
+    })
+}
+
+fn to_object(data: SuiObjectData) -> Result<sui_types::object::Object, DBTransportError> {
+    data.try_into().map_err(|e| data_error(format!("Failed to convert SuiObjectData to Object: {:?}", e)))
+}
+
+fn parse_object_id(id: &str) -> Result<ObjectID, DBTransportError> {
+    ObjectID::from_hex_literal(id).map_err(|e| data_error(format!("Invalid object id {}: {}", id, e)))
+}
+
+/// Page size of the dynamic field listings and batched object reads made while warming the cache.
+pub const CACHE_PAGE_SIZE: usize = 50;
+
+/// Cache every dynamic field object of `parent_id`, one page at a time.
+///
+/// Each page of field ids is fetched and inserted into `cache_db` before the next page is
+/// requested, so memory stays bounded by `page_size` however many records a table holds.
+/// Returns the number of objects cached.
+pub async fn stream_dynamic_field_objects<ExtDB: DatabaseRef, R: ObjectReader>(
+    cache_db: &mut CacheDB<ExtDB>,
+    reader: &R,
+    parent_id: ObjectID,
+    page_size: usize,
+    retry: &CacheInitOptions,
+    options: &SuiObjectDataOptions,
+) -> Result<usize, DBTransportError> {
+    let mut cursor = None;
+    let mut cached = 0;
+    loop {
+        let page = retry_with_backoff(retry, || reader.get_dynamic_fields(parent_id, cursor, Some(page_size))).await?;
+        let ids: Vec<ObjectID> = page.data.iter().map(|info| info.object_id).collect();
+        if !ids.is_empty() {
+            let objects = retry_with_backoff(retry, || reader.multi_get_object_with_options(ids.clone(), options.clone())).await?;
+            for response in objects {
+                if let Ok(data) = response.into_object() {
+                    let _ = cache_db.insert_object(to_object(data)?);
+                    cached += 1;
+                }
+            }
+        }
+        match page.next_cursor {
+            Some(next) if page.has_next_page => cursor = Some(next),
+            _ => return Ok(cached),
+        }
+    }
+}
+
+/// Preload the Dubhe hub, both dapp stores, their tables, records and packages into `cache_db`.
+///
+/// Every RPC call is retried according to `options`, so a rate-limited endpoint slows the warm-up
+/// down instead of aborting it.
 pub async fn initialize_cache<ExtDB: DatabaseRef>(
     cache_db: &mut CacheDB<ExtDB>, 
     client: &SuiClient, 
     dubhe_hub_id: &str, 
     orogin_dubhe_package_id: &str, 
-    origin_package_id: &str
-) {
-    use std::collections::HashSet;
-    
+    origin_package_id: &str,
+    options: &CacheInitOptions,
+) -> Result<(), DBTransportError> {
+    initialize_cache_from(cache_db, client.read_api(), dubhe_hub_id, orogin_dubhe_package_id, origin_package_id, options).await
+}
+
+/// [initialize_cache] against any [ObjectReader].
+pub async fn initialize_cache_from<ExtDB: DatabaseRef, R: ObjectReader>(
+    cache_db: &mut CacheDB<ExtDB>, 
+    reader: &R, 
+    dubhe_hub_id: &str, 
+    orogin_dubhe_package_id: &str, 
+    origin_package_id: &str,
+    retry: &CacheInitOptions,
+) -> Result<(), DBTransportError> {
     let options = sui_json_rpc_types::SuiObjectDataOptions {
         show_type: true,
         show_owner: true,
@@ -123,66 +533,58 @@ pub async fn initialize_cache<ExtDB: DatabaseRef>(
         show_storage_rebate: true,
     };
 
-    println!("🚀 Step 1: Fetching Dubhe Hub object...");
+    tracing::info!("🚀 Step 1: Fetching Dubhe Hub object...");
     // Step 1: 获取 dubhe_hub 对象
-    let hub_id = ObjectID::from_hex_literal(dubhe_hub_id).unwrap();
-    let hub_response = client.read_api().get_object_with_options(hub_id, options.clone()).await.unwrap();
-    let hub_data = hub_response.into_object().unwrap();
-    let dapp_stores_field_id = get_field_id(&hub_data, "dapp_stores").unwrap();
-    let hub_object: sui_types::object::Object = hub_data.try_into().unwrap();
+    let hub_id = parse_object_id(dubhe_hub_id)?;
+    let hub_response = retry_with_backoff(retry, || reader.get_object_with_options(hub_id, options.clone())).await?;
+    let hub_data = hub_response.into_object().map_err(|e| data_error(e.to_string()))?;
+    let dapp_stores_field_id = get_field_id(&hub_data, "dapp_stores")
+        .ok_or_else(|| data_error("Dubhe Hub has no dapp_stores field"))?;
+    let hub_object = to_object(hub_data)?;
     let _ = cache_db.insert_object(hub_object);
-    println!("✅ Dubhe Hub cached, dapp_stores_field_id: {}", dapp_stores_field_id);
+    tracing::info!("✅ Dubhe Hub cached, dapp_stores_field_id: {}", dapp_stores_field_id);
 
-    println!("\n🚀 Step 2: Fetching dapp stores list...");
+    tracing::info!("🚀 Step 2: Fetching dapp stores list...");
     // Step 2: 获取 dapp_stores 的动态字段列表（只调用一次）
-    let dapp_stores_page = client.read_api()
-        .get_dynamic_fields(dapp_stores_field_id, None, Some(50))
-        .await.unwrap();
+    let dapp_stores_page = retry_with_backoff(retry, || reader.get_dynamic_fields(dapp_stores_field_id, None, Some(50))).await?;
     
     // 找到两个需要的 dapp_store
-    let origin_dapp_store_info = dapp_stores_page.data.iter()
-        .find(|info| info.name.value.to_string().contains(&get_dapp_key_str(origin_package_id)))
-        .unwrap();
-    let dubhe_dapp_store_info = dapp_stores_page.data.iter()
-        .find(|info| info.name.value.to_string().contains(&get_dapp_key_str(orogin_dubhe_package_id)))
-        .unwrap();
+    let origin_dapp_store_info = find_dapp_store(&dapp_stores_page.data, origin_package_id)
+        .ok_or_else(|| data_error(format!("No dapp store for package {}", origin_package_id)))?;
+    let dubhe_dapp_store_info = find_dapp_store(&dapp_stores_page.data, orogin_dubhe_package_id)
+        .ok_or_else(|| data_error(format!("No dapp store for package {}", orogin_dubhe_package_id)))?;
     
-    println!("✅ Found {} dapp stores", dapp_stores_page.data.len());
-    println!("  - Origin dapp store: {}", origin_dapp_store_info.object_id);
-    println!("  - Dubhe dapp store: {}", dubhe_dapp_store_info.object_id);
+    tracing::info!("✅ Found {} dapp stores", dapp_stores_page.data.len());
+    tracing::debug!("  - Origin dapp store: {}", origin_dapp_store_info.object_id);
+    tracing::debug!("  - Dubhe dapp store: {}", dubhe_dapp_store_info.object_id);
 
-    println!("\n🚀 Step 3: Batch fetching 2 dapp store objects...");
+    tracing::info!("🚀 Step 3: Batch fetching 2 dapp store objects...");
     // Step 3: 批量获取两个 dapp_store 对象
     let dapp_store_ids = vec![origin_dapp_store_info.object_id, dubhe_dapp_store_info.object_id];
-    let dapp_stores = client.read_api()
-        .multi_get_object_with_options(dapp_store_ids.clone(), options.clone())
-        .await.unwrap();
+    let dapp_stores = retry_with_backoff(retry, || reader.multi_get_object_with_options(dapp_store_ids.clone(), options.clone())).await?;
     
     let mut tables_field_ids = Vec::new();
     for store_response in dapp_stores {
-        let store_data = store_response.into_object().unwrap();
-        let tables_id = get_field_id(&store_data, "tables").unwrap();
+        let store_data = store_response.into_object().map_err(|e| data_error(e.to_string()))?;
+        let tables_id = get_field_id(&store_data, "tables")
+            .ok_or_else(|| data_error(format!("Dapp store {} has no tables field", store_data.object_id)))?;
         tables_field_ids.push(tables_id);
-        let store_object: sui_types::object::Object = store_data.try_into().unwrap();
+        let store_object = to_object(store_data)?;
         let _ = cache_db.insert_object(store_object);
     }
-    println!("✅ Cached 2 dapp store objects");
-    println!("  - Origin tables field: {}", tables_field_ids[0]);
-    println!("  - Dubhe tables field: {}", tables_field_ids[1]);
+    tracing::info!("✅ Cached 2 dapp store objects");
+    tracing::debug!("  - Origin tables field: {}", tables_field_ids[0]);
+    tracing::debug!("  - Dubhe tables field: {}", tables_field_ids[1]);
 
-    println!("\n🚀 Step 4: Fetching dynamic fields for all tables...");
+    tracing::info!("🚀 Step 4: Fetching dynamic fields for all tables...");
     // Step 4: 获取两个 tables 的动态字段列表
-    let origin_tables_page = client.read_api()
-        .get_dynamic_fields(tables_field_ids[0], None, Some(50))
-        .await.unwrap();
-    let dubhe_tables_page = client.read_api()
-        .get_dynamic_fields(tables_field_ids[1], None, Some(50))
-        .await.unwrap();
+    let origin_tables_page = retry_with_backoff(retry, || reader.get_dynamic_fields(tables_field_ids[0], None, Some(50))).await?;
+    let dubhe_tables_page = retry_with_backoff(retry, || reader.get_dynamic_fields(tables_field_ids[1], None, Some(50))).await?;
     
-    println!("✅ Origin package has {} tables", origin_tables_page.data.len());
-    println!("✅ Dubhe package has {} tables", dubhe_tables_page.data.len());
+    tracing::info!("✅ Origin package has {} tables", origin_tables_page.data.len());
+    tracing::info!("✅ Dubhe package has {} tables", dubhe_tables_page.data.len());
 
-    println!("\n🚀 Step 5: Batch fetching all table objects...");
+    tracing::info!("🚀 Step 5: Batch fetching all table objects...");
     // Step 5: 收集所有 table 对象的 ID
     let mut all_table_ids = Vec::new();
     all_table_ids.extend(origin_tables_page.data.iter().map(|info| info.object_id));
@@ -192,62 +594,54 @@ pub async fn initialize_cache<ExtDB: DatabaseRef>(
         .find(|info| info.name.value.to_string() == "\"dapp_fee_state\"") 
     {
         all_table_ids.push(fee_state_info.object_id);
-        println!("  - Including dapp_fee_state table");
+        tracing::debug!("  - Including dapp_fee_state table");
     }
     
     // 批量获取所有 table 对象
-    let table_objects = client.read_api()
-        .multi_get_object_with_options(all_table_ids.clone(), options.clone())
-        .await.unwrap();
+    let table_objects = retry_with_backoff(retry, || reader.multi_get_object_with_options(all_table_ids.clone(), options.clone())).await?;
     
     let mut table_value_ids = Vec::new();
     for table_response in table_objects {
-        let table_data = table_response.into_object().unwrap();
+        let table_data = table_response.into_object().map_err(|e| data_error(e.to_string()))?;
         if let Some(value_id) = get_field_id(&table_data, "value") {
             table_value_ids.push(value_id);
         }
-        let table_object: sui_types::object::Object = table_data.try_into().unwrap();
+        let table_object = to_object(table_data)?;
         let _ = cache_db.insert_object(table_object);
     }
-    println!("✅ Cached {} table objects", all_table_ids.len());
+    tracing::info!("✅ Cached {} table objects", all_table_ids.len());
 
-    println!("\n🚀 Step 6: Fetching all table records...");
-    // Step 6: 获取所有 table 的动态字段列表并收集记录 ID
-    let mut all_record_ids = HashSet::new();
+    tracing::info!("🚀 Step 6: Streaming all table records...");
+    // Step 6: 逐页获取每个 table 的记录并直接写入缓存，不预先收集全部记录 ID
+    let mut total_cached = 0;
     for table_id in &table_value_ids {
-        let records_page = client.read_api()
-            .get_dynamic_fields(*table_id, None, Some(50))
-            .await.unwrap();
-        
-        for record_info in records_page.data {
-            all_record_ids.insert(record_info.object_id);
-        }
+        total_cached += stream_dynamic_field_objects(cache_db, reader, *table_id, CACHE_PAGE_SIZE, retry, &options).await?;
     }
-    
-    println!("✅ Found {} total records across all tables", all_record_ids.len());
 
-    println!("\n🚀 Step 7: Batch fetching all record objects...");
-    // Step 7: 批量获取所有记录对象（分批，每次最多50个）
-    let record_ids: Vec<_> = all_record_ids.into_iter().collect();
-    let mut total_cached = 0;
-    
-    for chunk in record_ids.chunks(50) {
-        let records = client.read_api()
-            .multi_get_object_with_options(chunk.to_vec(), options.clone())
-            .await.unwrap();
-        
-        for record_response in records {
-            if let Ok(record_data) = record_response.into_object() {
-                let record_object: sui_types::object::Object = record_data.try_into().unwrap();
-                let _ = cache_db.insert_object(record_object);
-                total_cached += 1;
+    tracing::info!("✅ Cached {} record objects", total_cached);
+    tracing::info!("🚀 Step 7: Fetching package objects...");
+    // Step 7: 预加载 dapp 包和 dubhe 包，packages 会单独存放在 packages map 中
+    let package_ids = vec![
+        parse_object_id(origin_package_id)?,
+        parse_object_id(orogin_dubhe_package_id)?,
+    ];
+    let packages = retry_with_backoff(retry, || reader.multi_get_object_with_options(package_ids.clone(), options.clone())).await?;
+    let mut total_packages = 0;
+    for package_response in packages {
+        if let Ok(package_data) = package_response.into_object() {
+            let package_object = to_object(package_data)?;
+            if package_object.is_package() {
+                let _ = cache_db.insert_object(package_object);
+                total_packages += 1;
             }
         }
     }
-    
-    println!("✅ Cached {} record objects", total_cached);
-    println!("\n🎉 Cache initialization complete!");
-    println!("📊 Total objects in cache: {}", cache_db.cache.read().unwrap().objects.len());
+    tracing::info!("✅ Cached {} package objects", total_packages);
+
+    tracing::info!("🎉 Cache initialization complete!");
+    tracing::info!("📊 Total objects in cache: {}", cache_db.cache.read().unwrap().object_count());
+    tracing::info!("📦 Total packages in cache: {}", cache_db.cache.read().unwrap().packages.len());
+    Ok(())
 }
 
 #[cfg(test)]
@@ -261,20 +655,22 @@ mod tests {
     // origin dapp package: 0x4c3f65fa8562679d00076350b51c1c3f2d966d83a4a6609a13f4fb04561d1140
     #[tokio::test(flavor = "multi_thread")] 
     async fn can_get_object() {
-        let client = SuiClientBuilder::default().build_testnet().await.unwrap();
+        let options = SuiClientOptions::default();
+        let client = build_sui_client(sui_sdk::SUI_TESTNET_URL, &options).await.unwrap();
         let dubhedb = DubheDB::new(client);
         let wrapped_dubhedb = WrapDatabaseAsync::new(dubhedb).unwrap();
         let mut cache_db = CacheDB::new(wrapped_dubhedb);
 
-        let client = SuiClientBuilder::default().build_testnet().await.unwrap();
+        let client = build_sui_client(sui_sdk::SUI_TESTNET_URL, &options).await.unwrap();
 
         initialize_cache(
             &mut cache_db, 
             &client, 
             "0x86c8925b708ecd5570d70f3ccbc30035f9fa65480b546a563afdc046da98d103", 
             "0xa337791835d15223727ace33cce17ea0901c094c8cfbe34d089c1a18c2df7a15", 
-            "0x4c3f65fa8562679d00076350b51c1c3f2d966d83a4a6609a13f4fb04561d1140"
-        ).await;
+            "0x4c3f65fa8562679d00076350b51c1c3f2d966d83a4a6609a13f4fb04561d1140",
+            &CacheInitOptions::default(),
+        ).await.unwrap();
 
         // cache hash map length
         println!("cache hash map length: {:?}", cache_db.cache.read().unwrap().sui_object_data.len());
@@ -287,4 +683,279 @@ mod tests {
       // //   assert!(object_data.is_some());
       //   println!("object_data: {:?}", object_data);
     }
+
+    #[tokio::test]
+    async fn test_sui_client_request_timeout_is_applied() {
+        // A node that accepts connections and never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let accept = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                connections.push(socket);
+            }
+        });
+
+        let options = SuiClientOptions::default()
+            .with_request_timeout(std::time::Duration::from_millis(200))
+            .with_max_concurrent_requests(4);
+        assert_eq!(options.request_timeout, std::time::Duration::from_millis(200));
+        assert_eq!(options.max_concurrent_requests, 4);
+        let started = std::time::Instant::now();
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            build_sui_client(&url, &options),
+        )
+        .await
+        .expect("the request timeout was not applied");
+        assert!(result.is_err());
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        accept.abort();
+    }
+
+    #[test]
+    fn test_transport_error_kind() {
+        let not_found = DBTransportError(SuiSdkError::UserInputError(
+            sui_types::error::UserInputError::ObjectNotFound { object_id: ObjectID::ZERO, version: None },
+        ));
+        assert_eq!(not_found.kind(), DBErrorKind::NotFound);
+        let not_exists = DBTransportError(SuiSdkError::DataError("NotExists { object_id: 0x1 }".to_string()));
+        assert_eq!(not_exists.kind(), DBErrorKind::NotFound);
+
+        let io = DBTransportError(SuiSdkError::IOError(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset")));
+        assert_eq!(io.kind(), DBErrorKind::Transport);
+        assert!(io.kind().is_transient());
+
+        let data = DBTransportError(SuiSdkError::DataError("Failed to convert SuiObjectData to Object".to_string()));
+        assert_eq!(data.kind(), DBErrorKind::Data);
+        let json = DBTransportError(SuiSdkError::JsonSerializationError(serde_json::from_str::<Value>("{").unwrap_err()));
+        assert_eq!(json.kind(), DBErrorKind::Data);
+        assert!(!json.kind().is_transient());
+
+        let other = DBTransportError(SuiSdkError::Subscription("closed".to_string()));
+        assert_eq!(other.kind(), DBErrorKind::Other);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_only_retries_transient_kinds() {
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let attempts_ref = &attempts;
+        let result = retry_transient(move || async move {
+            if attempts_ref.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                Err(DBTransportError(SuiSdkError::IOError(std::io::Error::new(std::io::ErrorKind::TimedOut, "timeout"))))
+            } else {
+                Ok(7)
+            }
+        }).await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        attempts.store(0, std::sync::atomic::Ordering::SeqCst);
+        let result: Result<(), _> = retry_transient(move || async move {
+            attempts_ref.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(DBTransportError(SuiSdkError::DataError("bad bcs".to_string())))
+        }).await;
+        assert_eq!(result.unwrap_err().kind(), DBErrorKind::Data);
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    /// Read API that answers with HTTP 429 until `rate_limited_calls` requests have been made.
+    struct RateLimitedReader {
+        rate_limited_calls: u32,
+        calls: std::sync::atomic::AtomicU32,
+    }
+
+    impl RateLimitedReader {
+        fn next_call(&self) -> Result<(), DBTransportError> {
+            if self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < self.rate_limited_calls {
+                Err(DBTransportError(SuiSdkError::JsonRpcError(sui_sdk::error::JsonRpcError {
+                    code: 429,
+                    message: "Too Many Requests".to_string(),
+                    data: None,
+                })))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl ObjectReader for RateLimitedReader {
+        async fn get_object_with_options(
+            &self,
+            object_id: ObjectID,
+            _options: SuiObjectDataOptions,
+        ) -> Result<SuiObjectResponse, DBTransportError> {
+            self.next_call()?;
+            Ok(SuiObjectResponse::new_with_error(
+                sui_types::error::SuiObjectResponseError::NotExists { object_id },
+            ))
+        }
+
+        async fn get_dynamic_fields(
+            &self,
+            _parent_object_id: ObjectID,
+            _cursor: Option<ObjectID>,
+            _limit: Option<usize>,
+        ) -> Result<DynamicFieldPage, DBTransportError> {
+            self.next_call()?;
+            Ok(DynamicFieldPage { data: vec![], next_cursor: None, has_next_page: false })
+        }
+
+        async fn multi_get_object_with_options(
+            &self,
+            _object_ids: Vec<ObjectID>,
+            _options: SuiObjectDataOptions,
+        ) -> Result<Vec<SuiObjectResponse>, DBTransportError> {
+            self.next_call()?;
+            Ok(vec![])
+        }
+    }
+
+    /// Read API serving `records` dynamic fields under one parent, `page_size` at a time.
+    struct PagedReader {
+        records: Vec<ObjectID>,
+        /// Every call in order: `page` for a listing, the batch size for an object read
+        calls: std::sync::Mutex<Vec<String>>,
+        largest_batch: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ObjectReader for PagedReader {
+        async fn get_object_with_options(
+            &self,
+            object_id: ObjectID,
+            _options: SuiObjectDataOptions,
+        ) -> Result<SuiObjectResponse, DBTransportError> {
+            Ok(SuiObjectResponse::new_with_error(
+                sui_types::error::SuiObjectResponseError::NotExists { object_id },
+            ))
+        }
+
+        async fn get_dynamic_fields(
+            &self,
+            _parent_object_id: ObjectID,
+            cursor: Option<ObjectID>,
+            limit: Option<usize>,
+        ) -> Result<DynamicFieldPage, DBTransportError> {
+            use sui_types::dynamic_field::{BcsName, DynamicFieldType};
+
+            self.calls.lock().unwrap().push("page".to_string());
+            let start = cursor.map_or(0, |cursor| self.records.iter().position(|id| *id == cursor).unwrap() + 1);
+            let end = (start + limit.unwrap()).min(self.records.len());
+            let data = self.records[start..end].iter().enumerate().map(|(index, object_id)| DynamicFieldInfo {
+                name: DynamicFieldName { type_: TypeTag::U64, value: Value::from(start + index) },
+                bcs_name: BcsName::new(bcs::to_bytes(&((start + index) as u64)).unwrap()),
+                type_: DynamicFieldType::DynamicField,
+                object_type: "u64".to_string(),
+                object_id: *object_id,
+                version: sui_types::base_types::SequenceNumber::new(),
+                digest: sui_types::digests::ObjectDigest::MIN,
+            }).collect::<Vec<_>>();
+            Ok(DynamicFieldPage {
+                next_cursor: data.last().map(|info| info.object_id),
+                has_next_page: end < self.records.len(),
+                data,
+            })
+        }
+
+        async fn multi_get_object_with_options(
+            &self,
+            object_ids: Vec<ObjectID>,
+            _options: SuiObjectDataOptions,
+        ) -> Result<Vec<SuiObjectResponse>, DBTransportError> {
+            self.calls.lock().unwrap().push(object_ids.len().to_string());
+            self.largest_batch.fetch_max(object_ids.len(), std::sync::atomic::Ordering::SeqCst);
+            Ok(object_ids.into_iter().map(|object_id| SuiObjectResponse::new_with_error(
+                sui_types::error::SuiObjectResponseError::NotExists { object_id },
+            )).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_are_streamed_page_by_page() {
+        let reader = PagedReader {
+            records: (0..7).map(|_| ObjectID::random()).collect(),
+            calls: std::sync::Mutex::new(vec![]),
+            largest_batch: std::sync::atomic::AtomicUsize::new(0),
+        };
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        stream_dynamic_field_objects(
+            &mut cache_db,
+            &reader,
+            ObjectID::ZERO,
+            3,
+            &CacheInitOptions::default(),
+            &SuiObjectDataOptions::new(),
+        ).await.unwrap();
+
+        // Each page is read before the next one is listed, never more than a page at once
+        assert_eq!(*reader.calls.lock().unwrap(), vec!["page", "3", "page", "3", "page", "1"]);
+        assert_eq!(reader.largest_batch.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_dapp_store_matches_exact_package_id() {
+        use sui_types::dynamic_field::{BcsName, DynamicFieldType};
+
+        let store = |key: &str| DynamicFieldInfo {
+            name: DynamicFieldName {
+                type_: TypeTag::from_str("0x1::type_name::TypeName").unwrap(),
+                value: serde_json::json!({ "name": key }),
+            },
+            bcs_name: BcsName::new(bcs::to_bytes(key).unwrap()),
+            type_: DynamicFieldType::DynamicField,
+            object_type: "0x2::dapp_store::DappStore".to_string(),
+            object_id: ObjectID::random(),
+            version: sui_types::base_types::SequenceNumber::new(),
+            digest: sui_types::digests::ObjectDigest::MIN,
+        };
+        // The dubhe package id is a suffix of the other one, a substring match picks the first
+        let stores = vec![
+            store("00000000000000000000000000000000000000000000000000000000000abc12::dapp_key::DappKey"),
+            store("0000000000000000000000000000000000000000000000000000000000000012::dapp_key::DappKey"),
+        ];
+
+        assert_eq!(find_dapp_store(&stores, "0x12").unwrap().object_id, stores[1].object_id);
+        assert_eq!(find_dapp_store(&stores, "0xabc12").unwrap().object_id, stores[0].object_id);
+        assert!(find_dapp_store(&stores, "0xbc12").is_none());
+
+        // Names that are not a DappKey type name still match by substring
+        let legacy = vec![DynamicFieldInfo {
+            name: DynamicFieldName { type_: TypeTag::U64, value: serde_json::json!(["abc12::dapp_key::DappKey"]) },
+            ..store("")
+        }];
+        assert_eq!(find_dapp_store(&legacy, "0xabc12").unwrap().object_id, legacy[0].object_id);
+    }
+
+    #[tokio::test]
+    async fn test_read_api_retries_rate_limited_calls() {
+        let options = CacheInitOptions::default().with_base_delay(std::time::Duration::from_millis(1));
+        let reader = RateLimitedReader { rate_limited_calls: 2, calls: std::sync::atomic::AtomicU32::new(0) };
+        assert_eq!(
+            DBTransportError(SuiSdkError::JsonRpcError(sui_sdk::error::JsonRpcError {
+                code: 429,
+                message: "Too Many Requests".to_string(),
+                data: None,
+            })).kind(),
+            DBErrorKind::RateLimited
+        );
+
+        // The first two attempts are rate limited, the third one goes through
+        let response = retry_with_backoff(&options, || reader.get_object_with_options(ObjectID::ZERO, SuiObjectDataOptions::new())).await;
+        assert!(response.is_ok());
+        assert_eq!(reader.calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // Once retries run out, initialize_cache reports the rate limit instead of panicking
+        let reader = RateLimitedReader { rate_limited_calls: u32::MAX, calls: std::sync::atomic::AtomicU32::new(0) };
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        let err = initialize_cache_from(
+            &mut cache_db,
+            &reader,
+            "0x1",
+            "0x2",
+            "0x3",
+            &options.clone().with_max_retries(1),
+        ).await.unwrap_err();
+        assert_eq!(err.kind(), DBErrorKind::RateLimited);
+        assert_eq!(reader.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
 }
@@ -36,6 +36,8 @@ pub struct GraphQLConfig {
     pub enable_native_websocket: bool,
     /// Real-time port
     pub realtime_port: Option<u16>,
+    /// Serve HTTP on this Unix domain socket instead of the TCP port
+    pub unix_socket: Option<String>,
 }
 
 impl Default for GraphQLConfig {
@@ -60,6 +62,7 @@ impl Default for GraphQLConfig {
             enable_pg_subscriptions: get_env_bool("ENABLE_PG_SUBSCRIPTIONS", true),
             enable_native_websocket: get_env_bool("ENABLE_NATIVE_WEBSOCKET", true),
             realtime_port: get_env_u16_opt("REALTIME_PORT"),
+            unix_socket: env::var("GRAPHQL_UNIX_SOCKET").ok(),
         }
     }
 }
@@ -75,9 +78,25 @@ impl GraphQLConfig {
         format!("http://localhost:{}{}", self.port, self.endpoint)
     }
 
+    /// Port of the dedicated subscription WebSocket listener, if one should run apart from
+    /// the HTTP port
+    pub fn dedicated_websocket_port(&self) -> Option<u16> {
+        match self.realtime_port {
+            Some(port) if self.enable_native_websocket && port != self.port => Some(port),
+            _ => None,
+        }
+    }
+
+    /// Whether table changes are delivered from Postgres NOTIFY, the only source live
+    /// queries update from
+    pub fn pg_subscriptions_enabled(&self) -> bool {
+        self.enable_pg_subscriptions && self.database_url.starts_with("postgres")
+    }
+
     /// Get WebSocket endpoint URL
     pub fn websocket_endpoint(&self) -> String {
-        format!("ws://localhost:{}{}", self.port, self.endpoint)
+        let port = self.dedicated_websocket_port().unwrap_or(self.port);
+        format!("ws://localhost:{}{}", port, self.endpoint)
     }
 
     /// Get health check endpoint
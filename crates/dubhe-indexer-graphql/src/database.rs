@@ -1,21 +1,402 @@
 use crate::DynamicTable;
 use anyhow::Result;
-use dubhe_common::Database;
+use dubhe_common::{
+    quote_identifier, Aggregate, BoundQuery, Database, QueryBuilder, QueryOperator, QueryValue,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How long a table list stays fresh before the catalog is scanned again
+pub const DEFAULT_TABLE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+static SHARED_TABLE_CACHES: OnceLock<Mutex<HashMap<String, Arc<DynamicTableCache>>>> =
+    OnceLock::new();
+
+/// Process-wide table cache of the database at `database_url`, shared by every
+/// [`DatabasePool`] connected to it
+pub fn shared_table_cache(database_url: &str) -> Arc<DynamicTableCache> {
+    SHARED_TABLE_CACHES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+        .entry(database_url.to_string())
+        .or_insert_with(|| Arc::new(DynamicTableCache::new(DEFAULT_TABLE_CACHE_TTL)))
+        .clone()
+}
+
+/// Drop the cached table list of the database at `database_url`, call this after
+/// `create_tables` or a migration
+pub async fn invalidate_table_cache(database_url: &str) {
+    shared_table_cache(database_url).invalidate().await;
+}
+
+/// Cached result of the table introspection query
+pub struct DynamicTableCache {
+    ttl: Duration,
+    entry: RwLock<Option<(Instant, Vec<DynamicTable>)>>,
+}
+
+impl DynamicTableCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: RwLock::new(None),
+        }
+    }
+
+    /// Return the cached tables, or run `load` if the cache is empty or expired
+    pub async fn get_or_load<F, Fut>(&self, load: F) -> Result<Vec<DynamicTable>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<DynamicTable>>>,
+    {
+        if let Some((loaded_at, tables)) = self.entry.read().await.as_ref() {
+            if loaded_at.elapsed() < self.ttl {
+                return Ok(tables.clone());
+            }
+        }
+
+        let mut entry = self.entry.write().await;
+        // Another caller may have refreshed the cache while we waited for the lock
+        if let Some((loaded_at, tables)) = entry.as_ref() {
+            if loaded_at.elapsed() < self.ttl {
+                return Ok(tables.clone());
+            }
+        }
+        let tables = load().await?;
+        *entry = Some((Instant::now(), tables.clone()));
+        Ok(tables)
+    }
+
+    pub async fn invalidate(&self) {
+        *self.entry.write().await = None;
+    }
+}
+
+/// Comparison operator of a row filter
+#[derive(async_graphql::Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FilterOperator {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Like,
+}
+
+impl From<FilterOperator> for QueryOperator {
+    fn from(operator: FilterOperator) -> Self {
+        match operator {
+            FilterOperator::Eq => QueryOperator::Eq,
+            FilterOperator::Ne => QueryOperator::Ne,
+            FilterOperator::Gt => QueryOperator::Gt,
+            FilterOperator::Gte => QueryOperator::Gte,
+            FilterOperator::Lt => QueryOperator::Lt,
+            FilterOperator::Lte => QueryOperator::Lte,
+            FilterOperator::Like => QueryOperator::Like,
+        }
+    }
+}
+
+/// Aggregate computed per group by `groupBy`
+#[derive(async_graphql::Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl From<AggregateFunction> for Aggregate {
+    fn from(aggregate: AggregateFunction) -> Self {
+        match aggregate {
+            AggregateFunction::Count => Aggregate::Count,
+            AggregateFunction::Sum => Aggregate::Sum,
+            AggregateFunction::Avg => Aggregate::Avg,
+            AggregateFunction::Min => Aggregate::Min,
+            AggregateFunction::Max => Aggregate::Max,
+        }
+    }
+}
+
+/// A single `column <operator> value` condition on a table
+#[derive(async_graphql::InputObject, Clone, Debug)]
+pub struct RowFilter {
+    pub field: String,
+    pub operator: FilterOperator,
+    pub value: String,
+}
+
+fn validate_identifier(identifier: &str) -> Result<()> {
+    if identifier.is_empty()
+        || !identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(anyhow::anyhow!("Invalid identifier: {}", identifier));
+    }
+    Ok(())
+}
+
+/// Translate row filters into a ` WHERE ...` clause (empty when there is nothing to filter).
+/// Soft-deleted rows are excluded unless `include_deleted` is set.
+pub fn build_where_clause(filters: &[RowFilter], include_deleted: bool) -> Result<String> {
+    Ok(filtered_query("", filters, include_deleted)?.to_where_clause())
+}
+
+/// Like [`build_where_clause`], but with the filter values bound as parameters rendered by
+/// `placeholder`
+pub fn build_bound_where_clause(
+    filters: &[RowFilter],
+    include_deleted: bool,
+    placeholder: impl Fn(usize, &str) -> String,
+) -> Result<(String, Vec<QueryValue>)> {
+    Ok(filtered_query("", filters, include_deleted)?.to_bound_where_clause(placeholder))
+}
+
+fn filtered_query(
+    table: &str,
+    filters: &[RowFilter],
+    include_deleted: bool,
+) -> Result<QueryBuilder> {
+    let mut query = QueryBuilder::new(table).include_deleted(include_deleted);
+    for filter in filters {
+        validate_identifier(&filter.field)?;
+        query = query.filter(
+            &filter.field,
+            filter.operator.into(),
+            QueryValue::String(filter.value.clone()),
+        );
+    }
+    Ok(query)
+}
+
+/// How bound parameters are written. SQLite converts the `?` text parameters by column
+/// affinity; PostgreSQL needs `$n` cast to the column type explicitly.
+enum Placeholders {
+    Positional,
+    Numbered(HashMap<String, String>),
+}
+
+impl Placeholders {
+    fn render(&self, index: usize, field: &str) -> String {
+        // Joined queries qualify columns with their table alias
+        let column = field.rsplit_once('.').map_or(field, |(_, column)| column);
+        let column = column.trim_matches('"');
+        match self {
+            Placeholders::Positional => "?".to_string(),
+            Placeholders::Numbered(types) => match types.get(column) {
+                Some(type_name) => format!("${}::{}", index, type_name),
+                None => format!("${}", index),
+            },
+        }
+    }
+}
+
+/// One table read by [`DatabasePool::query_batch`]
+#[derive(Debug, Clone)]
+pub struct TableQuery {
+    pub table_name: String,
+    /// Columns to select, empty for every column
+    pub fields: Vec<String>,
+    /// Columns of the table that may be selected
+    pub columns: Vec<String>,
+    pub filters: Vec<RowFilter>,
+    pub include_deleted: bool,
+    pub limit: u32,
+}
+
+/// A table left-joined to the root table of [`DatabasePool::query_joined_rows`]
+#[derive(Debug, Clone)]
+pub struct Join {
+    /// Key of the nested object holding the joined row
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    /// `(root column, joined column)` pairs that must be equal
+    pub on: Vec<(String, String)>,
+}
+
+/// SELECT and FROM clauses joining `joins` to `table_name`. Root columns keep their name,
+/// joined columns are returned as `<join name>.<column>`; the root table is aliased `t0`.
+pub fn build_join_sql(
+    table_name: &str,
+    columns: &[String],
+    joins: &[Join],
+    include_deleted: bool,
+) -> Result<String> {
+    validate_identifier(table_name)?;
+    let mut select = Vec::new();
+    for column in columns {
+        validate_identifier(column)?;
+        let column = quote_identifier(column);
+        select.push(format!("t0.{} AS {}", column, column));
+    }
+    let mut from = format!("{} t0", table_name);
+    for (i, join) in joins.iter().enumerate() {
+        let alias = format!("t{}", i + 1);
+        validate_identifier(&join.name)?;
+        validate_identifier(&join.table)?;
+        for column in &join.columns {
+            validate_identifier(column)?;
+            select.push(format!(
+                "{}.{} AS \"{}.{}\"",
+                alias,
+                quote_identifier(column),
+                join.name,
+                column
+            ));
+        }
+        let mut conditions = Vec::new();
+        for (root_column, column) in &join.on {
+            validate_identifier(root_column)?;
+            validate_identifier(column)?;
+            conditions.push(format!(
+                "t0.{} = {}.{}",
+                quote_identifier(root_column),
+                alias,
+                quote_identifier(column)
+            ));
+        }
+        if !include_deleted {
+            conditions.push(format!("{}.{}", alias, dubhe_common::SOFT_DELETE_CONDITION));
+        }
+        from.push_str(&format!(
+            " LEFT JOIN {} {} ON {}",
+            join.table,
+            alias,
+            conditions.join(" AND ")
+        ));
+    }
+    Ok(format!("SELECT {} FROM {}", select.join(", "), from))
+}
+
+/// Move the `<join name>.<column>` values of a joined row into one nested object per join,
+/// which is null when the join found no row
+pub fn nest_joined_row(row: serde_json::Value, joins: &[Join]) -> serde_json::Value {
+    let serde_json::Value::Object(mut row) = row else {
+        return row;
+    };
+    for join in joins {
+        let mut nested = serde_json::Map::new();
+        for column in &join.columns {
+            let value = row
+                .remove(&format!("{}.{}", join.name, column))
+                .unwrap_or(serde_json::Value::Null);
+            nested.insert(column.clone(), value);
+        }
+        let found = nested.values().any(|value| !value.is_null());
+        row.insert(
+            join.name.clone(),
+            if found {
+                serde_json::Value::Object(nested)
+            } else {
+                serde_json::Value::Null
+            },
+        );
+    }
+    serde_json::Value::Object(row)
+}
 
 /// Database connection pool (using dubhe-common's Database)
 pub struct DatabasePool {
     database: Database,
+    table_cache: Arc<DynamicTableCache>,
+    /// Column types of the tables filtered so far, used to cast bound parameters on PostgreSQL
+    column_types: RwLock<HashMap<String, HashMap<String, String>>>,
 }
 
 impl DatabasePool {
     /// Create a new database connection pool
     pub async fn new(database_url: &str) -> Result<Self> {
         let database = Database::new(database_url).await?;
-        Ok(Self { database })
+        Ok(Self {
+            database,
+            table_cache: shared_table_cache(database_url),
+            column_types: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Run a query with bound parameters as a persistent prepared statement, so sqlx's
+    /// per-connection statement cache reuses it for every query of the same shape
+    pub async fn prepared_query(
+        &self,
+        sql: &str,
+        params: &[QueryValue],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.database.query_with_params(sql, params, true).await
+    }
+
+    /// PostgreSQL type name of every column of `table_name`, loaded once per table
+    async fn table_column_types(&self, table_name: &str) -> Result<HashMap<String, String>> {
+        if let Some(types) = self.column_types.read().await.get(table_name) {
+            return Ok(types.clone());
+        }
+        let rows = self
+            .database
+            .query_with_params(
+                "SELECT column_name, udt_name FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1",
+                &[QueryValue::String(table_name.to_string())],
+                true,
+            )
+            .await?;
+        let types: HashMap<String, String> = rows
+            .iter()
+            .filter_map(|row| {
+                Some((
+                    row.get("column_name")?.as_str()?.to_string(),
+                    row.get("udt_name")?.as_str()?.to_string(),
+                ))
+            })
+            .collect();
+        // A table that does not exist yet is looked up again next time
+        if !types.is_empty() {
+            self.column_types
+                .write()
+                .await
+                .insert(table_name.to_string(), types.clone());
+        }
+        Ok(types)
     }
 
-    /// Get all table information
+    /// Placeholder style for parameters compared to the columns of `table_name`
+    async fn placeholders(
+        &self,
+        table_name: &str,
+        filters: &[RowFilter],
+    ) -> Result<Placeholders> {
+        match self.database.db_type() {
+            "postgres" if filters.is_empty() => Ok(Placeholders::Numbered(HashMap::new())),
+            "postgres" => Ok(Placeholders::Numbered(self.table_column_types(table_name).await?)),
+            _ => Ok(Placeholders::Positional),
+        }
+    }
+
+    /// WHERE clause of `filters` with bound values
+    async fn bound_where_clause(
+        &self,
+        table_name: &str,
+        filters: &[RowFilter],
+        include_deleted: bool,
+    ) -> Result<(String, Vec<QueryValue>)> {
+        let placeholders = self.placeholders(table_name, filters).await?;
+        build_bound_where_clause(filters, include_deleted, |index, field| {
+            placeholders.render(index, field)
+        })
+    }
+
+    /// Get all table information, served from the table cache while it is fresh
     pub async fn get_tables(&self) -> Result<Vec<DynamicTable>> {
+        self.table_cache.get_or_load(|| self.load_tables()).await
+    }
+
+    /// Query the database catalog for all tables
+    async fn load_tables(&self) -> Result<Vec<DynamicTable>> {
         // Execute different queries based on database type
         match self.database.db_type() {
             "sqlite" => {
@@ -131,6 +512,161 @@ impl DatabasePool {
         }
     }
 
+    /// Rows of a table matching `filters` with only the `fields` columns selected, each of
+    /// which must be one of `columns`. Empty `fields` selects every column.
+    pub async fn query_rows(
+        &self,
+        table_name: &str,
+        fields: &[String],
+        columns: &[String],
+        filters: &[RowFilter],
+        include_deleted: bool,
+        limit: u32,
+    ) -> Result<Vec<serde_json::Value>> {
+        let query = self
+            .bound_select(table_name, fields, columns, filters, include_deleted, limit)
+            .await?;
+        self.database
+            .query_with_params(&query.sql, &query.params, query.persistent)
+            .await
+    }
+
+    /// Run every query of the batch in one read transaction, so the rows of all tables come
+    /// from the same snapshot. Rows are returned in the order of `queries`.
+    pub async fn query_batch(
+        &self,
+        queries: &[TableQuery],
+    ) -> Result<Vec<Vec<serde_json::Value>>> {
+        let mut bound = Vec::with_capacity(queries.len());
+        for query in queries {
+            bound.push(
+                self.bound_select(
+                    &query.table_name,
+                    &query.fields,
+                    &query.columns,
+                    &query.filters,
+                    query.include_deleted,
+                    query.limit,
+                )
+                .await?,
+            );
+        }
+        self.database.query_batch(&bound).await
+    }
+
+    async fn bound_select(
+        &self,
+        table_name: &str,
+        fields: &[String],
+        columns: &[String],
+        filters: &[RowFilter],
+        include_deleted: bool,
+        limit: u32,
+    ) -> Result<BoundQuery> {
+        validate_identifier(table_name)?;
+        let query = filtered_query(table_name, filters, include_deleted)?
+            .project(fields, columns)?
+            .limit(limit);
+        let placeholders = self.placeholders(table_name, filters).await?;
+        let (sql, params) = query.to_bound_sql(|index, field| placeholders.render(index, field));
+        Ok(BoundQuery {
+            sql,
+            params,
+            persistent: true,
+        })
+    }
+
+    /// Rows of `table_name` matching `filters`, each with the rows of `joins` nested under the
+    /// join name
+    pub async fn query_joined_rows(
+        &self,
+        table_name: &str,
+        columns: &[String],
+        joins: &[Join],
+        filters: &[RowFilter],
+        include_deleted: bool,
+        limit: u32,
+    ) -> Result<Vec<serde_json::Value>> {
+        let mut sql = build_join_sql(table_name, columns, joins, include_deleted)?;
+        let mut query = filtered_query("", filters, true)?;
+        for filter in query.filters.iter_mut() {
+            filter.field = format!("t0.{}", quote_identifier(&filter.field));
+        }
+        let placeholders = self.placeholders(table_name, filters).await?;
+        let (where_clause, params) =
+            query.to_bound_where_clause(|index, field| placeholders.render(index, field));
+        sql.push_str(&where_clause);
+        if !include_deleted {
+            sql.push_str(if where_clause.is_empty() { " WHERE " } else { " AND " });
+            sql.push_str(&format!("t0.{}", dubhe_common::SOFT_DELETE_CONDITION));
+        }
+        sql.push_str(&format!(" LIMIT {}", limit));
+        let rows = self.prepared_query(&sql, &params).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| nest_joined_row(row, joins))
+            .collect())
+    }
+
+    /// One row per distinct value of `column` with `aggregate` over `aggregate_column`, see
+    /// [`QueryBuilder::aggregate_by`]. The columns must have been validated by the caller.
+    pub async fn aggregate_rows(
+        &self,
+        table_name: &str,
+        column: &str,
+        aggregate: Aggregate,
+        aggregate_column: Option<&str>,
+        filters: &[RowFilter],
+        include_deleted: bool,
+    ) -> Result<Vec<serde_json::Value>> {
+        validate_identifier(table_name)?;
+        validate_identifier(column)?;
+        if let Some(aggregate_column) = aggregate_column {
+            validate_identifier(aggregate_column)?;
+        }
+        let query = filtered_query(table_name, filters, include_deleted)?.aggregate_by(
+            column,
+            aggregate,
+            aggregate_column,
+        )?;
+        let placeholders = self.placeholders(table_name, filters).await?;
+        let (sql, params) = query.to_bound_sql(|index, field| placeholders.render(index, field));
+        self.prepared_query(&sql, &params).await
+    }
+
+    /// Count the rows of a table matching `filters`, soft-deleted rows are skipped by default
+    pub async fn count_rows(
+        &self,
+        table_name: &str,
+        filters: &[RowFilter],
+        include_deleted: bool,
+    ) -> Result<u64> {
+        validate_identifier(table_name)?;
+        let (where_clause, params) =
+            self.bound_where_clause(table_name, filters, include_deleted).await?;
+        let sql = format!("SELECT COUNT(*) AS count FROM {}{}", table_name, where_clause);
+        let rows = self.prepared_query(&sql, &params).await?;
+        let count = rows.first().and_then(|row| row.get("count"));
+        Ok(count
+            .and_then(|count| count.as_u64().or_else(|| count.as_i64().map(|count| count as u64)))
+            .unwrap_or(0))
+    }
+
+    /// Names of all `store_*` tables created by the indexer
+    pub async fn store_table_names(&self) -> Result<Vec<String>> {
+        let sql = match self.database.db_type() {
+            "sqlite" => "SELECT name AS table_name FROM sqlite_master WHERE type = 'table' AND name LIKE 'store_%' ORDER BY name",
+            _ => "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE 'store_%' ORDER BY table_name",
+        };
+        Ok(self
+            .database
+            .query(sql)
+            .await?
+            .iter()
+            .filter_map(|row| row.get("table_name").and_then(|name| name.as_str()).map(|name| name.to_string()))
+            .collect())
+    }
+
     /// Get table row count
     pub async fn get_table_count(&self, table_name: &str) -> Result<i64> {
         match self.database.db_type() {
@@ -151,3 +687,181 @@ impl DatabasePool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn sample_tables() -> Vec<DynamicTable> {
+        vec![DynamicTable {
+            name: "store_counter".to_string(),
+            schema: "public".to_string(),
+            columns: vec![],
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_table_cache_hits_within_ttl() {
+        let cache = DynamicTableCache::new(Duration::from_secs(60));
+        let counter = AtomicUsize::new(0);
+        let scans = &counter;
+        let load = move || async move {
+            scans.fetch_add(1, Ordering::SeqCst);
+            Ok(sample_tables())
+        };
+
+        let first = cache.get_or_load(load).await.unwrap();
+        let second = cache.get_or_load(load).await.unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+        assert_eq!(first[0].name, second[0].name);
+
+        cache.invalidate().await;
+        cache.get_or_load(load).await.unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_build_where_clause() {
+        assert_eq!(build_where_clause(&[], true).unwrap(), "");
+        assert_eq!(build_where_clause(&[], false).unwrap(), " WHERE is_deleted = FALSE");
+
+        let filters = vec![RowFilter {
+            field: "name".to_string(),
+            operator: FilterOperator::Eq,
+            value: "o'brien".to_string(),
+        }];
+        assert_eq!(
+            build_where_clause(&filters, false).unwrap(),
+            r#" WHERE "name" = 'o''brien' AND is_deleted = FALSE"#
+        );
+
+        let injected = vec![RowFilter {
+            field: "1=1; DROP TABLE store_counter1".to_string(),
+            operator: FilterOperator::Eq,
+            value: "x".to_string(),
+        }];
+        assert!(build_where_clause(&injected, true).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running PostgreSQL instance"]
+    async fn test_count_rows_skips_soft_deleted() {
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or("postgres://postgres@localhost:5432/postgres".to_string());
+        let pool = DatabasePool::new(&url).await.unwrap();
+        let database = &pool.database;
+        database.execute("DROP TABLE IF EXISTS store_counter1").await.unwrap();
+        database
+            .execute("CREATE TABLE store_counter1 (entity_id TEXT PRIMARY KEY, value BIGINT, is_deleted BOOLEAN DEFAULT FALSE)")
+            .await
+            .unwrap();
+        for (id, value) in [("0x1", 1), ("0x2", 2), ("0x3", 3)] {
+            database
+                .execute(&format!("INSERT INTO store_counter1 (entity_id, value) VALUES ('{}', {})", id, value))
+                .await
+                .unwrap();
+        }
+        database
+            .execute("UPDATE store_counter1 SET is_deleted = true WHERE entity_id = '0x2'")
+            .await
+            .unwrap();
+
+        assert_eq!(pool.count_rows("store_counter1", &[], false).await.unwrap(), 2);
+        assert_eq!(pool.count_rows("store_counter1", &[], true).await.unwrap(), 3);
+        let filters = vec![RowFilter {
+            field: "value".to_string(),
+            operator: FilterOperator::Gte,
+            value: "2".to_string(),
+        }];
+        assert_eq!(pool.count_rows("store_counter1", &filters, false).await.unwrap(), 1);
+        assert!(pool
+            .store_table_names()
+            .await
+            .unwrap()
+            .contains(&"store_counter1".to_string()));
+
+        database.execute("DROP TABLE store_counter1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filter_values_are_bound() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("graphql.db").display());
+        let pool = DatabasePool::new(&url).await.unwrap();
+        pool.database
+            .execute("CREATE TABLE store_counter1 (entity_id TEXT PRIMARY KEY, value INTEGER, is_deleted BOOLEAN DEFAULT FALSE)")
+            .await
+            .unwrap();
+        for (id, value) in [("0x1", 1), ("0x2", 2), ("0x3", 3)] {
+            pool.database
+                .execute(&format!("INSERT INTO store_counter1 (entity_id, value) VALUES ('{}', {})", id, value))
+                .await
+                .unwrap();
+        }
+
+        let filter = |value: &str| {
+            vec![RowFilter {
+                field: "value".to_string(),
+                operator: FilterOperator::Gte,
+                value: value.to_string(),
+            }]
+        };
+        assert_eq!(pool.count_rows("store_counter1", &filter("2"), false).await.unwrap(), 2);
+        assert_eq!(pool.count_rows("store_counter1", &filter("3"), false).await.unwrap(), 1);
+        assert_eq!(pool.count_rows("store_counter1", &filter("o'brien"), false).await.unwrap(), 0);
+
+        // Only the requested columns are selected, and the filter value is a parameter
+        let columns = vec!["entity_id".to_string(), "value".to_string()];
+        let query = pool
+            .bound_select("store_counter1", &columns[1..], &columns, &filter("2"), false, 100)
+            .await
+            .unwrap();
+        assert!(
+            query.sql.starts_with(r#"SELECT "value" FROM store_counter1 WHERE "#),
+            "{}",
+            query.sql
+        );
+        assert!(!query.sql.contains('2'));
+        assert_eq!(query.params.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shared_table_cache_is_kept_per_database_url() {
+        let first_url = "sqlite:table-cache-first.db";
+        let second_url = "sqlite:table-cache-second.db";
+        assert!(Arc::ptr_eq(&shared_table_cache(first_url), &shared_table_cache(first_url)));
+        assert!(!Arc::ptr_eq(&shared_table_cache(first_url), &shared_table_cache(second_url)));
+
+        let load = || async { Ok(sample_tables()) };
+        shared_table_cache(first_url).get_or_load(load).await.unwrap();
+        shared_table_cache(second_url).get_or_load(load).await.unwrap();
+        invalidate_table_cache(first_url).await;
+
+        // Only the invalidated database is scanned again
+        let counter = AtomicUsize::new(0);
+        let scans = &counter;
+        let counting_load = move || async move {
+            scans.fetch_add(1, Ordering::SeqCst);
+            Ok(sample_tables())
+        };
+        shared_table_cache(first_url).get_or_load(counting_load).await.unwrap();
+        shared_table_cache(second_url).get_or_load(counting_load).await.unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_table_cache_reloads_after_ttl() {
+        let cache = DynamicTableCache::new(Duration::ZERO);
+        let counter = AtomicUsize::new(0);
+        let scans = &counter;
+        let load = move || async move {
+            scans.fetch_add(1, Ordering::SeqCst);
+            Ok(sample_tables())
+        };
+
+        cache.get_or_load(load).await.unwrap();
+        cache.get_or_load(load).await.unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 2);
+    }
+}
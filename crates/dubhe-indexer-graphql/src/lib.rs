@@ -1,6 +1,8 @@
 pub mod config;
 pub mod database;
 pub mod health;
+pub mod live_query;
+pub mod pg_listener;
 pub mod playground;
 pub mod schema;
 pub mod server;
@@ -42,6 +44,7 @@ pub struct GraphQLServerManager {
     server: Option<GraphQLServer>,
     subscribers: GrpcSubscribers,
     graphql_subscribers: Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>,
+    dubhe_config: Option<Arc<dubhe_common::DubheConfig>>,
 }
 
 impl GraphQLServerManager {
@@ -54,9 +57,16 @@ impl GraphQLServerManager {
             server: None,
             subscribers: Arc::new(RwLock::new(HashMap::new())),
             graphql_subscribers,
+            dubhe_config: None,
         }
     }
 
+    /// Expose table schemas from the dubhe config through `tableSchema`
+    pub fn with_dubhe_config(mut self, dubhe_config: Arc<dubhe_common::DubheConfig>) -> Self {
+        self.dubhe_config = Some(dubhe_config);
+        self
+    }
+
     /// Starts the GraphQL server
     pub async fn start(&mut self) -> Result<()> {
         log::info!("🚀 Starting GraphQL server...");
@@ -65,6 +75,7 @@ impl GraphQLServerManager {
             self.config.clone(),
             self.subscribers.clone(),
             self.graphql_subscribers.clone(),
+            self.dubhe_config.clone(),
         )
         .await?;
 
@@ -0,0 +1,309 @@
+use crate::pg_listener::GraphQLSubscribers;
+use crate::schema::QueryRoot;
+use crate::subscriptions::{SubscriptionRoot, TableChange};
+use async_graphql::parser::types::{
+    DocumentOperations, ExecutableDocument, OperationDefinition, Selection, SelectionSet,
+};
+use async_graphql::parser::{parse_query, Pos, Positioned};
+use async_graphql::{EmptyMutation, Request, Response, Schema, Value};
+use dubhe_common::DubheConfig;
+use futures_util::Stream;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+
+/// Subscriber key receiving changes of every table, used when a live query names no table
+pub const ALL_TABLES: &str = "*";
+
+/// Operation directive that turns a query into a live query
+const LIVE_DIRECTIVE: &str = "live";
+
+/// Arguments naming the table a field reads
+const TABLE_ARGUMENTS: [&str; 2] = ["table", "tableName"];
+
+/// A query whose operation carries the `@live` directive
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveQuery {
+    /// The query without its `@live` directive, which the schema does not know
+    pub query: String,
+    /// Tables the query reads, [`ALL_TABLES`] when they cannot be told from the query
+    pub tables: Vec<String>,
+}
+
+impl LiveQuery {
+    /// Parse `query` and return it as a live query when one of its operations has the `@live`
+    /// directive, `None` for a regular query. A query that does not parse is not a live query
+    /// either, executing it reports the syntax error. `table` arguments are resolved to table
+    /// ids through `dubhe_config` when there is one.
+    pub fn parse(query: &str, dubhe_config: Option<&DubheConfig>) -> Option<LiveQuery> {
+        let document = parse_query(query).ok()?;
+        let operations: Vec<&Positioned<OperationDefinition>> = match &document.operations {
+            DocumentOperations::Single(operation) => vec![operation],
+            DocumentOperations::Multiple(operations) => operations.values().collect(),
+        };
+        let directive = operations
+            .iter()
+            .flat_map(|operation| &operation.node.directives)
+            .find(|directive| directive.node.name.node.as_str() == LIVE_DIRECTIVE)?;
+        let (start, end) = directive_span(query, directive.pos)?;
+
+        let mut tables = Vec::new();
+        let mut visited = HashSet::new();
+        for operation in &operations {
+            collect_selection_tables(
+                &document,
+                &operation.node.selection_set.node,
+                &mut visited,
+                &mut tables,
+            );
+        }
+        let mut table_ids: Vec<String> = Vec::new();
+        for table in tables {
+            let table_id = resolve_table(&table, dubhe_config);
+            if !table_ids.contains(&table_id) {
+                table_ids.push(table_id);
+            }
+        }
+        if table_ids.is_empty() || table_ids.iter().any(|table| table == ALL_TABLES) {
+            table_ids = vec![ALL_TABLES.to_string()];
+        }
+
+        Some(LiveQuery {
+            query: format!("{}{}", &query[..start], &query[end..]),
+            tables: table_ids,
+        })
+    }
+}
+
+/// Byte range of the `@live` directive starting at `pos`. `None` when the directive has
+/// arguments, which it does not take.
+fn directive_span(query: &str, pos: Pos) -> Option<(usize, usize)> {
+    let line_start: usize = query
+        .split_inclusive('\n')
+        .take(pos.line.checked_sub(1)?)
+        .map(str::len)
+        .sum();
+    // Positions count characters from 1
+    let (column, _) = query[line_start..].char_indices().nth(pos.column.checked_sub(1)?)?;
+    let start = line_start + column;
+    let rest = query[start..].strip_prefix('@')?.trim_start();
+    let rest = rest.strip_prefix(LIVE_DIRECTIVE)?;
+    if rest.trim_start().starts_with('(') {
+        return None;
+    }
+    Some((start, query.len() - rest.len()))
+}
+
+/// Add the tables named by the fields of `selection_set` and the fragments it spreads
+fn collect_selection_tables<'a>(
+    document: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    visited: &mut HashSet<&'a str>,
+    tables: &mut Vec<String>,
+) {
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                for (name, value) in &field.node.arguments {
+                    // A variable could name any table
+                    let Ok(value) = value.node.clone().into_const_with(|_| {
+                        Ok::<_, Infallible>(Value::String(ALL_TABLES.to_string()))
+                    });
+                    collect_value_tables(name.node.as_str(), &value, tables);
+                }
+                collect_selection_tables(
+                    document,
+                    &field.node.selection_set.node,
+                    visited,
+                    tables,
+                );
+            }
+            Selection::InlineFragment(fragment) => collect_selection_tables(
+                document,
+                &fragment.node.selection_set.node,
+                visited,
+                tables,
+            ),
+            Selection::FragmentSpread(spread) => {
+                let name = spread.node.fragment_name.node.as_str();
+                if let Some(fragment) = document.fragments.get(&spread.node.fragment_name.node) {
+                    if visited.insert(name) {
+                        collect_selection_tables(
+                            document,
+                            &fragment.node.selection_set.node,
+                            visited,
+                            tables,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Add the tables named by argument `name` with `value`, including those of input objects
+/// such as the queries of `batchQuery`
+fn collect_value_tables(name: &str, value: &Value, tables: &mut Vec<String>) {
+    match value {
+        Value::String(table) if TABLE_ARGUMENTS.contains(&name) => {
+            if !tables.contains(table) {
+                tables.push(table.clone());
+            }
+        }
+        Value::List(values) => {
+            for value in values {
+                collect_value_tables(name, value, tables);
+            }
+        }
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                collect_value_tables(field.as_str(), value, tables);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Subscriber key of a table argument: the configured table id it names, else the name
+/// without the `store_` prefix the Postgres change notifications drop as well
+fn resolve_table(table: &str, dubhe_config: Option<&DubheConfig>) -> String {
+    match dubhe_config {
+        Some(dubhe_config) => dubhe_config.table_id_of(table).unwrap_or(table).to_string(),
+        None => table.strip_prefix("store_").unwrap_or(table).to_string(),
+    }
+}
+
+/// Execute a live query once, then again every time one of the tables it reads changes
+pub fn live_query_stream(
+    schema: Schema<QueryRoot, EmptyMutation, SubscriptionRoot>,
+    live_query: LiveQuery,
+    subscribers: GraphQLSubscribers,
+) -> impl Stream<Item = Response> + Send {
+    async_stream::stream! {
+        let LiveQuery { query, tables } = live_query;
+        let (tx, mut rx) = mpsc::unbounded_channel::<TableChange>();
+        {
+            let mut subscribers = subscribers.write().await;
+            for table in &tables {
+                subscribers.entry(table.clone()).or_insert_with(Vec::new).push(tx.clone());
+            }
+        }
+        drop(tx);
+        log::info!("🔴 Live query registered for tables: {:?}", tables);
+
+        yield schema.execute(Request::new(query.clone())).await;
+        while let Some(change) = rx.recv().await {
+            log::debug!("🔁 Re-running live query after change in {}", change.table_name);
+            yield schema.execute(Request::new(query.clone())).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pg_listener::dispatch_table_change;
+    use futures_util::StreamExt;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn table_change(table_name: &str) -> TableChange {
+        TableChange {
+            id: "1".to_string(),
+            table_name: table_name.to_string(),
+            operation: "UPDATE".to_string(),
+            timestamp: "0".to_string(),
+            data: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_live_directive_and_tables() {
+        assert_eq!(LiveQuery::parse("query { serverInfo { name } }", None), None);
+        // Only an operation directive makes a live query, not the text `@live` elsewhere
+        let not_live = r#"query { tableCount(table: "@live", filter: []) }"#;
+        assert_eq!(LiveQuery::parse(not_live, None), None);
+        assert_eq!(LiveQuery::parse("query @live {", None), None);
+
+        let live = LiveQuery::parse(
+            "query Counts @live {\n  tableCount(table: \"store_counter1\")\n}",
+            None,
+        )
+        .unwrap();
+        assert_eq!(live.query, "query Counts  {\n  tableCount(table: \"store_counter1\")\n}");
+        assert_eq!(live.tables, vec!["counter1".to_string()]);
+
+        let batch = LiveQuery::parse(
+            r#"query @live {
+                batchQuery(queries: [{ table: "counter1" }, { table: "store_counter2" }])
+                ...Rows
+            }
+            fragment Rows on QueryRoot { tableRows(table: "counter1") { value(column: "x") } }"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(batch.tables, vec!["counter1".to_string(), "counter2".to_string()]);
+
+        // A table given by a variable or no table at all could be any table
+        let variable = r#"query Count($table: String!) @live { tableCount(table: $table) }"#;
+        let variable = LiveQuery::parse(variable, None).unwrap();
+        assert_eq!(variable.tables, vec![ALL_TABLES.to_string()]);
+        let no_table = LiveQuery::parse("query @live { serverInfo { name } }", None).unwrap();
+        assert_eq!(no_table.tables, vec![ALL_TABLES.to_string()]);
+    }
+
+    #[test]
+    fn test_live_query_tables_resolve_through_the_config() {
+        let config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "counter1": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let store_table = config.store_table_name(Some(&config.original_package_id), "counter1");
+        let query = format!(r#"query @live {{ tableCount(table: "{}") }}"#, store_table);
+        let live = LiveQuery::parse(&query, Some(&config)).unwrap();
+        assert_eq!(live.tables, vec!["counter1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_live_query_updates_after_table_change() {
+        let subscribers: GraphQLSubscribers = Arc::new(RwLock::new(HashMap::new()));
+        let schema = Schema::build(
+            QueryRoot::new(None),
+            EmptyMutation,
+            SubscriptionRoot::new(Arc::new(RwLock::new(HashMap::new())), subscribers.clone()),
+        )
+        .finish();
+        let query = LiveQuery::parse(
+            r#"query @live { tableData(tableName: "counter1") { tableName } }"#,
+            None,
+        )
+        .unwrap();
+        let stream = live_query_stream(schema, query, subscribers.clone());
+        futures_util::pin_mut!(stream);
+
+        let first = stream.next().await.unwrap();
+        assert!(first.errors.is_empty());
+
+        // A change on an unrelated table does not trigger a re-run
+        dispatch_table_change(&subscribers, table_change("counter2")).await;
+        dispatch_table_change(&subscribers, table_change("counter1")).await;
+        let second = tokio::time::timeout(std::time::Duration::from_secs(1), stream.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            second.data.into_json().unwrap()["tableData"]["tableName"],
+            "counter1"
+        );
+        assert!(subscribers.read().await.get("counter2").is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::subscriptions::TableChange;
+use anyhow::Result;
+use dubhe_common::{Debounce, DubheConfig, TABLE_CHANGES_CHANNEL};
+use sqlx::postgres::PgListener;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub type GraphQLSubscribers = Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>;
+
+/// Parse a `dubhe_table_changes` NOTIFY payload into a [`TableChange`]
+pub fn parse_table_change(payload: &str) -> Result<TableChange> {
+    let payload: serde_json::Value = serde_json::from_str(payload)?;
+    let table_name = payload["table"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Notification payload has no table"))?
+        .to_string();
+    let operation = payload["op"].as_str().unwrap_or("UPDATE").to_string();
+    Ok(TableChange {
+        id: Uuid::new_v4().to_string(),
+        table_name,
+        operation,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        data: payload.get("data").cloned().unwrap_or(serde_json::Value::Null),
+    })
+}
+
+/// Push a change to every GraphQL subscriber of its table and to catch-all live queries,
+/// dropping closed subscriptions
+pub async fn dispatch_table_change(subscribers: &GraphQLSubscribers, change: TableChange) {
+    let mut subscribers = subscribers.write().await;
+    for key in [change.table_name.as_str(), crate::live_query::ALL_TABLES] {
+        if let Some(senders) = subscribers.get_mut(key) {
+            senders.retain(|sender| sender.send(change.clone()).is_ok());
+        }
+    }
+}
+
+/// Coalesces the changes of tables with a `push_min_interval_ms` per row key, the same way
+/// the indexer throttles its gRPC pushes
+#[derive(Clone, Default)]
+pub struct ChangeThrottle {
+    dubhe_config: Option<Arc<DubheConfig>>,
+    /// Latest undispatched change by table and key column values
+    pending: Debounce<(String, Vec<String>), TableChange>,
+}
+
+impl ChangeThrottle {
+    pub fn new(dubhe_config: Option<Arc<DubheConfig>>) -> Self {
+        Self {
+            dubhe_config,
+            pending: Debounce::new(),
+        }
+    }
+
+    /// Dispatch `change` right away, or at most once per interval and row key for a table
+    /// with a `push_min_interval_ms`
+    pub async fn dispatch(&self, subscribers: &GraphQLSubscribers, change: TableChange) {
+        let config = self.dubhe_config.as_deref();
+        let Some(interval) = config.and_then(|config| config.push_min_interval(&change.table_name))
+        else {
+            return dispatch_table_change(subscribers, change).await;
+        };
+        let key: Vec<String> = config
+            .into_iter()
+            .flat_map(|config| config.field_names_by_table_and_primary_key(&change.table_name))
+            .map(|column| change.data[column.trim_matches('"')].to_string())
+            .collect();
+        let subscribers = subscribers.clone();
+        self.pending.push(
+            (change.table_name.clone(), key),
+            change,
+            interval,
+            move |change| async move { dispatch_table_change(&subscribers, change).await },
+        );
+    }
+}
+
+/// Drop senders whose subscription has ended, and tables left without subscribers
+pub async fn prune_closed_subscribers(subscribers: &GraphQLSubscribers) {
+    let mut subscribers = subscribers.write().await;
+    subscribers.retain(|_, senders| {
+        senders.retain(|sender| !sender.is_closed());
+        !senders.is_empty()
+    });
+}
+
+/// LISTEN on the table change channel and forward notifications to GraphQL subscribers,
+/// coalesced by `throttle`.
+///
+/// Runs until the connection fails; `PgListener` reconnects on its own after transient drops.
+pub async fn listen_table_changes(
+    database_url: &str,
+    subscribers: GraphQLSubscribers,
+    throttle: ChangeThrottle,
+) -> Result<()> {
+    let mut listener = PgListener::connect(database_url).await?;
+    listener.listen(TABLE_CHANGES_CHANNEL).await?;
+    log::info!("👂 Listening for table changes on channel {}", TABLE_CHANGES_CHANNEL);
+
+    loop {
+        let notification = listener.recv().await?;
+        match parse_table_change(notification.payload()) {
+            Ok(change) => throttle.dispatch(&subscribers, change).await,
+            Err(e) => log::warn!("⚠️ Ignoring malformed table change notification: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table_change() {
+        let change = parse_table_change(
+            r#"{"table":"counter1","op":"INSERT","data":{"entity_id":"0x1","value":7}}"#,
+        )
+        .unwrap();
+        assert_eq!(change.table_name, "counter1");
+        assert_eq!(change.operation, "INSERT");
+        assert_eq!(change.data["value"], 7);
+
+        // Oversized rows arrive without data
+        let change = parse_table_change(r#"{"table":"counter1","op":"UPDATE"}"#).unwrap();
+        assert!(change.data.is_null());
+
+        assert!(parse_table_change(r#"{"op":"UPDATE"}"#).is_err());
+        assert!(parse_table_change("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_throttled_changes_are_coalesced_per_key() {
+        let config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "price_feed": {
+                "fields": [{ "pair": "String" }, { "price": "u64" }],
+                "keys": ["pair"],
+                "offchain": false,
+                "push_min_interval_ms": 200
+            } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let throttle = ChangeThrottle::new(Some(Arc::new(config)));
+        let subscribers: GraphQLSubscribers = Arc::new(RwLock::new(HashMap::new()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        subscribers.write().await.insert("price_feed".to_string(), vec![tx]);
+
+        for (pair, price) in [("sui", 1), ("btc", 2), ("sui", 3), ("sui", 4)] {
+            let payload = serde_json::json!({
+                "table": "price_feed",
+                "op": "UPDATE",
+                "data": { "pair": pair, "price": price }
+            });
+            let change = parse_table_change(&payload.to_string()).unwrap();
+            throttle.dispatch(&subscribers, change).await;
+        }
+        assert!(rx.try_recv().is_err());
+
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        let mut prices: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|change| change.data["price"].as_u64().unwrap())
+            .collect();
+        prices.sort();
+        assert_eq!(prices, vec![2, 4]);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running PostgreSQL instance"]
+    async fn test_notify_reaches_subscriber() {
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or("postgres://postgres@localhost:5432/postgres".to_string());
+        let subscribers: GraphQLSubscribers = Arc::new(RwLock::new(HashMap::new()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        subscribers
+            .write()
+            .await
+            .insert("counter1".to_string(), vec![tx]);
+
+        let listener_url = url.clone();
+        let listener_subscribers = subscribers.clone();
+        let listener = tokio::spawn(async move {
+            listen_table_changes(&listener_url, listener_subscribers, ChangeThrottle::default())
+                .await
+        });
+        // Give the listener time to issue LISTEN before notifying
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(TABLE_CHANGES_CHANNEL)
+            .bind(r#"{"table":"counter1","op":"INSERT","data":{"value":1}}"#)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let change = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.table_name, "counter1");
+        assert_eq!(change.data["value"], 1);
+        listener.abort();
+    }
+}
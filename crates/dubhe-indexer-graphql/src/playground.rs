@@ -62,6 +62,7 @@ mod tests {
             enable_pg_subscriptions: true,
             enable_native_websocket: true,
             realtime_port: None,
+            unix_socket: None,
         };
 
         let service = PlaygroundService::new(config);
@@ -1,19 +1,60 @@
-use crate::database::DatabasePool;
-use async_graphql::{Context, Object, SimpleObject};
+use crate::database::{AggregateFunction, DatabasePool, Join, RowFilter, TableQuery};
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use dubhe_common::{DubheConfig, GROUP_KEY_COLUMN, GROUP_VALUE_COLUMN};
 use std::sync::Arc;
 
 /// Query root type
 #[derive(Default)]
 pub struct QueryRoot {
     db_pool: Option<Arc<DatabasePool>>,
+    dubhe_config: Option<Arc<DubheConfig>>,
 }
 
 impl QueryRoot {
     pub fn new(db_pool: Option<Arc<DatabasePool>>) -> Self {
-        Self { db_pool }
+        Self {
+            db_pool,
+            dubhe_config: None,
+        }
+    }
+
+    /// Serve table schemas from the parsed dubhe config
+    pub fn with_dubhe_config(mut self, dubhe_config: Arc<DubheConfig>) -> Self {
+        self.dubhe_config = Some(dubhe_config);
+        self
+    }
+
+    fn db_pool(&self) -> async_graphql::Result<&DatabasePool> {
+        self.db_pool
+            .as_deref()
+            .ok_or_else(|| async_graphql::Error::new("Database not connected"))
+    }
+
+    /// The config and id of a configured table, which may be given with its `store_` prefix
+    fn configured_table<'a>(
+        &'a self,
+        table: &'a str,
+    ) -> async_graphql::Result<(&'a DubheConfig, &'a str)> {
+        let dubhe_config = self
+            .dubhe_config
+            .as_ref()
+            .ok_or_else(|| async_graphql::Error::new("Table schema is not available"))?;
+        let table_id = dubhe_config
+            .table_id_of(table)
+            .ok_or_else(|| async_graphql::Error::new(format!("Unknown table: {}", table)))?;
+        Ok((dubhe_config, table_id))
     }
 }
 
+/// Rows returned per table unless the query sets a limit
+const DEFAULT_ROW_LIMIT: i32 = 100;
+/// Upper bound of a query's `limit`
+const MAX_ROW_LIMIT: i32 = 1000;
+
+fn row_limit(limit: Option<i32>) -> u32 {
+    limit.unwrap_or(DEFAULT_ROW_LIMIT).clamp(1, MAX_ROW_LIMIT) as u32
+}
+
 #[Object]
 impl QueryRoot {
     /// Get server information
@@ -154,6 +195,253 @@ impl QueryRoot {
         }
     }
 
+    /// Rows of a configured table. Only the columns read through `value(column:)` in the
+    /// selection set are fetched; selecting `data` fetches every column. Fields with
+    /// `decimals` also get a `<column>_decimal` string with the scaled amount.
+    async fn table_rows(
+        &self,
+        ctx: &Context<'_>,
+        table: String,
+        filter: Option<Vec<RowFilter>>,
+        include_deleted: Option<bool>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<TableRow>> {
+        let db_pool = self.db_pool()?;
+        let (dubhe_config, table_id) = self.configured_table(&table)?;
+
+        // A scaled column is computed from the raw integer column, which is what gets fetched
+        let mut fields = Vec::new();
+        for column in selected_columns(ctx)? {
+            let column = dubhe_config
+                .decimal_source_column(table_id, &column)
+                .unwrap_or(column);
+            if !fields.contains(&column) {
+                fields.push(column);
+            }
+        }
+        let rows = db_pool
+            .query_rows(
+                &dubhe_config.store_table_name(Some(&dubhe_config.original_package_id), table_id),
+                &fields,
+                &dubhe_config.selectable_columns(table_id),
+                &filter.unwrap_or_default(),
+                include_deleted.unwrap_or(false),
+                row_limit(limit),
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|mut data| {
+                dubhe_config.with_scaled_decimals(table_id, &mut data);
+                TableRow { data }
+            })
+            .collect())
+    }
+
+    /// Rows of a configured table with the rows of every related table nested under the
+    /// related table's name, joined on the fields declared in the config's `relations`
+    async fn joined_rows(
+        &self,
+        table: String,
+        filter: Option<Vec<RowFilter>>,
+        include_deleted: Option<bool>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<serde_json::Value>> {
+        let db_pool = self.db_pool()?;
+        let (dubhe_config, table_id) = self.configured_table(&table)?;
+
+        let package_id = Some(dubhe_config.original_package_id.as_str());
+        let joins: Vec<Join> = dubhe_config
+            .relations_of(table_id)
+            .map(|relation| Join {
+                name: relation.related_table.clone(),
+                table: dubhe_config.store_table_name(package_id, &relation.related_table),
+                columns: dubhe_config.selectable_columns(&relation.related_table),
+                on: relation
+                    .on
+                    .iter()
+                    .map(|field| {
+                        (
+                            dubhe_config.column_name(table_id, field),
+                            dubhe_config.column_name(&relation.related_table, field),
+                        )
+                    })
+                    .collect(),
+            })
+            .collect();
+        let mut rows = db_pool
+            .query_joined_rows(
+                &dubhe_config.store_table_name(package_id, table_id),
+                &dubhe_config.selectable_columns(table_id),
+                &joins,
+                &filter.unwrap_or_default(),
+                include_deleted.unwrap_or(false),
+                row_limit(limit),
+            )
+            .await?;
+        for row in &mut rows {
+            dubhe_config.with_scaled_decimals(table_id, row);
+            for join in &joins {
+                if let Some(related) = row.get_mut(&join.name) {
+                    dubhe_config.with_scaled_decimals(&join.name, related);
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Rows of several tables read from one consistent snapshot, keyed by table name
+    async fn batch_query(
+        &self,
+        queries: Vec<BatchQueryInput>,
+    ) -> async_graphql::Result<serde_json::Value> {
+        let db_pool = self.db_pool()?;
+        let mut names = Vec::with_capacity(queries.len());
+        let mut table_queries = Vec::with_capacity(queries.len());
+        for query in &queries {
+            let (dubhe_config, table_id) = self.configured_table(&query.table)?;
+            if names.contains(&table_id) {
+                return Err(async_graphql::Error::new(format!(
+                    "Table {} is queried more than once",
+                    table_id
+                )));
+            }
+            names.push(table_id);
+            table_queries.push(TableQuery {
+                table_name: dubhe_config
+                    .store_table_name(Some(&dubhe_config.original_package_id), table_id),
+                fields: Vec::new(),
+                columns: dubhe_config.selectable_columns(table_id),
+                filters: query.filter.clone().unwrap_or_default(),
+                include_deleted: query.include_deleted.unwrap_or(false),
+                limit: row_limit(query.limit),
+            });
+        }
+
+        let results = db_pool.query_batch(&table_queries).await?;
+        Ok(serde_json::Value::Object(
+            names
+                .into_iter()
+                .zip(results)
+                .map(|(table_id, mut rows)| {
+                    if let Some(dubhe_config) = &self.dubhe_config {
+                        for row in &mut rows {
+                            dubhe_config.with_scaled_decimals(table_id, row);
+                        }
+                    }
+                    (table_id.to_string(), serde_json::Value::Array(rows))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Rows of a table grouped by `column`, with `aggregate` computed over `aggregateColumn`
+    /// for each group. Every aggregate but `COUNT` needs a numeric `aggregateColumn`.
+    async fn group_by(
+        &self,
+        table: String,
+        column: String,
+        aggregate: Option<AggregateFunction>,
+        aggregate_column: Option<String>,
+        filter: Option<Vec<RowFilter>>,
+        include_deleted: Option<bool>,
+    ) -> async_graphql::Result<Vec<GroupBucket>> {
+        let db_pool = self.db_pool()?;
+        let (dubhe_config, table_id) = self.configured_table(&table)?;
+        let aggregate = aggregate.unwrap_or(AggregateFunction::Count).into();
+        let (column, aggregate_column) = dubhe_config.group_by_columns(
+            table_id,
+            &column,
+            aggregate,
+            aggregate_column.as_deref(),
+        )?;
+
+        let rows = db_pool
+            .aggregate_rows(
+                &dubhe_config.store_table_name(Some(&dubhe_config.original_package_id), table_id),
+                &column,
+                aggregate,
+                aggregate_column.as_deref(),
+                &filter.unwrap_or_default(),
+                include_deleted.unwrap_or(false),
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|mut row| GroupBucket {
+                key: row[GROUP_KEY_COLUMN].take(),
+                value: row[GROUP_VALUE_COLUMN].take(),
+            })
+            .collect())
+    }
+
+    /// Count the rows of a table, soft-deleted rows are excluded unless `includeDeleted` is set
+    async fn table_count(
+        &self,
+        _ctx: &Context<'_>,
+        table: String,
+        filter: Option<Vec<RowFilter>>,
+        include_deleted: Option<bool>,
+    ) -> async_graphql::Result<i64> {
+        let db_pool = self
+            .db_pool
+            .as_ref()
+            .ok_or_else(|| async_graphql::Error::new("Database not connected"))?;
+        let count = db_pool
+            .count_rows(&table, &filter.unwrap_or_default(), include_deleted.unwrap_or(false))
+            .await?;
+        Ok(count as i64)
+    }
+
+    /// Row counts for every store table
+    async fn row_counts_by_table(
+        &self,
+        _ctx: &Context<'_>,
+        include_deleted: Option<bool>,
+    ) -> async_graphql::Result<Vec<TableCount>> {
+        let db_pool = self
+            .db_pool
+            .as_ref()
+            .ok_or_else(|| async_graphql::Error::new("Database not connected"))?;
+        let include_deleted = include_deleted.unwrap_or(false);
+        let mut counts = Vec::new();
+        for table in db_pool.store_table_names().await? {
+            let count = db_pool.count_rows(&table, &[], include_deleted).await?;
+            counts.push(TableCount {
+                table,
+                count: count as i64,
+            });
+        }
+        Ok(counts)
+    }
+
+    /// Columns of a configured table with their Move and SQL types
+    async fn table_schema(&self, table: String) -> async_graphql::Result<Vec<ColumnSchema>> {
+        let (dubhe_config, table) = self.configured_table(&table)?;
+        Ok(dubhe_config
+            .fields
+            .iter()
+            .filter(|field| field.table == table)
+            .map(|field| {
+                let is_enum = dubhe_config.is_enum(&field.move_type);
+                let mut enum_values: Vec<_> = dubhe_config
+                    .enums
+                    .iter()
+                    .filter(|e| is_enum && e.name == field.move_type)
+                    .collect();
+                enum_values.sort_by_key(|e| e.index);
+                ColumnSchema {
+                    name: field.name.clone(),
+                    move_type: field.move_type.clone(),
+                    db_type: field.db_type.clone(),
+                    is_key: field.primary_key,
+                    is_enum,
+                    enum_values: enum_values.into_iter().map(|e| e.value.clone()).collect(),
+                }
+            })
+            .collect())
+    }
+
     /// Get subscription status
     async fn subscription_status(&self) -> SubscriptionStatus {
         SubscriptionStatus {
@@ -189,6 +477,18 @@ pub struct ColumnInfo {
     pub is_nullable: bool,
 }
 
+/// Column of a configured table
+#[derive(SimpleObject)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub move_type: String,
+    pub db_type: String,
+    pub is_key: bool,
+    pub is_enum: bool,
+    /// Variant names in index order, empty unless `is_enum`
+    pub enum_values: Vec<String>,
+}
+
 /// Table data
 #[derive(SimpleObject)]
 pub struct TableData {
@@ -197,6 +497,76 @@ pub struct TableData {
     pub data: Vec<serde_json::Value>,
 }
 
+/// One table read by `batchQuery`
+#[derive(InputObject)]
+pub struct BatchQueryInput {
+    pub table: String,
+    #[graphql(name = "where")]
+    pub filter: Option<Vec<RowFilter>>,
+    pub include_deleted: Option<bool>,
+    pub limit: Option<i32>,
+}
+
+/// A row of a configured table, holding only the columns that were selected
+pub struct TableRow {
+    data: serde_json::Value,
+}
+
+#[Object]
+impl TableRow {
+    /// Value of a single column
+    async fn value(&self, column: String) -> Option<serde_json::Value> {
+        self.data.get(&column).cloned()
+    }
+
+    /// Every column of the row
+    async fn data(&self) -> &serde_json::Value {
+        &self.data
+    }
+}
+
+/// Columns read by the `value(column:)` fields of the current selection, empty when `data`
+/// needs the whole row
+fn selected_columns(ctx: &Context<'_>) -> async_graphql::Result<Vec<String>> {
+    let mut columns = Vec::new();
+    for field in ctx.field().selection_set() {
+        match field.name() {
+            "data" => return Ok(Vec::new()),
+            "value" => {
+                let arguments = field
+                    .arguments()
+                    .map_err(|e| async_graphql::Error::new(e.message))?;
+                for (name, value) in arguments {
+                    match value {
+                        async_graphql::Value::String(column)
+                            if name.as_str() == "column" && !columns.contains(&column) =>
+                        {
+                            columns.push(column)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(columns)
+}
+
+/// Aggregate of the rows sharing one value of the grouped column
+#[derive(SimpleObject)]
+pub struct GroupBucket {
+    pub key: serde_json::Value,
+    pub value: serde_json::Value,
+}
+
+/// Row count of a single table
+#[derive(SimpleObject)]
+pub struct TableCount {
+    pub table: String,
+    pub count: i64,
+}
+
 /// Subscription status
 #[derive(SimpleObject)]
 pub struct SubscriptionStatus {
@@ -205,3 +575,390 @@ pub struct SubscriptionStatus {
     pub graphql_endpoint: String,
     pub subscription_endpoint: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscriptions::SubscriptionRoot;
+    use async_graphql::{EmptyMutation, Schema};
+    use std::collections::HashMap;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_table_schema_returns_move_types() {
+        let dubhe_config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "facing": "Direction" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [{ "Direction": ["North", "East", "South", "West"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let schema = Schema::build(
+            QueryRoot::new(None).with_dubhe_config(Arc::new(dubhe_config)),
+            EmptyMutation,
+            SubscriptionRoot::new(
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::new(RwLock::new(HashMap::new())),
+            ),
+        )
+        .finish();
+
+        let response = schema
+            .execute(
+                r#"{ tableSchema(table: "store_position") { name moveType dbType isKey isEnum enumValues } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let columns = response.data.into_json().unwrap()["tableSchema"].clone();
+        assert_eq!(columns[0]["name"], "player");
+        assert_eq!(columns[0]["moveType"], "address");
+        assert_eq!(columns[0]["isKey"], true);
+        assert_eq!(columns[1]["moveType"], "u64");
+        assert_eq!(columns[2]["isEnum"], true);
+        assert_eq!(
+            columns[2]["enumValues"],
+            serde_json::json!(["North", "East", "South", "West"])
+        );
+
+        let response = schema.execute(r#"{ tableSchema(table: "missing") { name } }"#).await;
+        assert_eq!(response.errors[0].message, "Unknown table: missing");
+    }
+
+    #[tokio::test]
+    async fn test_joined_rows_nest_related_tables() {
+        let dubhe_config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "position": { "fields": [{ "entity_id": "address" }, { "x": "u64" }], "keys": ["entity_id"], "offchain": false } },
+            { "health": { "fields": [{ "entity_id": "address" }, { "value": "u32" }], "keys": ["entity_id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "relations": { "position": { "health": ["entity_id"] } }
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("graphql.db").display());
+        let database = dubhe_common::Database::new(&url).await.unwrap();
+        for sql in dubhe_config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        for sql in [
+            "INSERT INTO store_position (entity_id, x) VALUES ('0xa', 1)",
+            "INSERT INTO store_position (entity_id, x) VALUES ('0xb', 2)",
+            "INSERT INTO store_health (entity_id, value) VALUES ('0xa', 90)",
+        ] {
+            database.execute(sql).await.unwrap();
+        }
+
+        let db_pool = Arc::new(DatabasePool::new(&url).await.unwrap());
+        let schema = Schema::build(
+            QueryRoot::new(Some(db_pool)).with_dubhe_config(Arc::new(dubhe_config)),
+            EmptyMutation,
+            SubscriptionRoot::new(
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::new(RwLock::new(HashMap::new())),
+            ),
+        )
+        .finish();
+
+        let response = schema
+            .execute(
+                r#"{ joinedRows(table: "position", filter: [{ field: "x", operator: GTE, value: "1" }]) }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let rows = response.data.into_json().unwrap()["joinedRows"].clone();
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+        let row = |entity_id: &str| {
+            rows.as_array()
+                .unwrap()
+                .iter()
+                .find(|row| row["entity_id"] == entity_id)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(row("0xa")["x"], 1);
+        assert_eq!(row("0xa")["health"]["entity_id"], "0xa");
+        assert_eq!(row("0xa")["health"]["value"], 90);
+        assert!(row("0xa").get("health.value").is_none());
+        assert_eq!(row("0xb")["x"], 2);
+        assert!(row("0xb")["health"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_table_rows_scale_amounts_with_decimals() {
+        let dubhe_config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "balance": { "fields": [{ "owner": "address" }, { "amount": "u64" }], "keys": ["owner"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "decimals": { "balance": { "amount": 9 } }
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("graphql.db").display());
+        let database = dubhe_common::Database::new(&url).await.unwrap();
+        for sql in dubhe_config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        for sql in [
+            "INSERT INTO store_balance (owner, amount) VALUES ('0xa', 1500000000)",
+            "INSERT INTO store_balance (owner, amount) VALUES ('0xb', 42)",
+        ] {
+            database.execute(sql).await.unwrap();
+        }
+
+        let db_pool = Arc::new(DatabasePool::new(&url).await.unwrap());
+        let schema = Schema::build(
+            QueryRoot::new(Some(db_pool)).with_dubhe_config(Arc::new(dubhe_config)),
+            EmptyMutation,
+            SubscriptionRoot::new(
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::new(RwLock::new(HashMap::new())),
+            ),
+        )
+        .finish();
+
+        let response = schema
+            .execute(
+                r#"{ tableRows(table: "balance", filter: [{ field: "owner", operator: EQ, value: "0xa" }]) { raw: value(column: "amount") scaled: value(column: "amount_decimal") } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let rows = response.data.into_json().unwrap()["tableRows"].clone();
+        assert_eq!(rows, serde_json::json!([{ "raw": 1500000000, "scaled": "1.5" }]));
+
+        let response = schema
+            .execute(
+                r#"{ tableRows(table: "balance", filter: [{ field: "owner", operator: EQ, value: "0xb" }]) { data } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap()["tableRows"][0]["data"].clone();
+        assert_eq!(data["amount"], 42);
+        assert_eq!(data["amount_decimal"], "0.000000042");
+    }
+
+    #[tokio::test]
+    async fn test_batch_query_reads_every_table() {
+        let dubhe_config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "position": { "fields": [{ "entity_id": "address" }, { "x": "u64" }], "keys": ["entity_id"], "offchain": false } },
+            { "health": { "fields": [{ "entity_id": "address" }, { "value": "u32" }], "keys": ["entity_id"], "offchain": false } }
+          ],
+          "resources": [
+            { "round": { "fields": [{ "number": "u64" }], "keys": [], "offchain": false } }
+          ],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("graphql.db").display());
+        let database = dubhe_common::Database::new(&url).await.unwrap();
+        for sql in dubhe_config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        for sql in [
+            "INSERT INTO store_position (entity_id, x) VALUES ('0xa', 1)",
+            "INSERT INTO store_position (entity_id, x) VALUES ('0xb', 2)",
+            "INSERT INTO store_position (entity_id, x) VALUES ('0xc', 3)",
+            "INSERT INTO store_health (entity_id, value) VALUES ('0xa', 90)",
+            "INSERT INTO store_round (number) VALUES (7)",
+        ] {
+            database.execute(sql).await.unwrap();
+        }
+
+        let db_pool = Arc::new(DatabasePool::new(&url).await.unwrap());
+        let schema = Schema::build(
+            QueryRoot::new(Some(db_pool)).with_dubhe_config(Arc::new(dubhe_config)),
+            EmptyMutation,
+            SubscriptionRoot::new(
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::new(RwLock::new(HashMap::new())),
+            ),
+        )
+        .finish();
+
+        let response = schema
+            .execute(
+                r#"{
+                  batchQuery(queries: [
+                    { table: "position", where: [{ field: "x", operator: GTE, value: "2" }], limit: 10 },
+                    { table: "store_health" },
+                    { table: "round" }
+                  ])
+                  positions: tableCount(table: "store_position", filter: [{ field: "x", operator: GTE, value: "2" }])
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let batch = &data["batchQuery"];
+        assert_eq!(batch.as_object().unwrap().len(), 3);
+        let positions = batch["position"].as_array().unwrap();
+        assert_eq!(positions.len() as i64, data["positions"].as_i64().unwrap());
+        let mut xs: Vec<_> = positions.iter().map(|row| row["x"].as_i64().unwrap()).collect();
+        xs.sort();
+        assert_eq!(xs, vec![2, 3]);
+        assert_eq!(batch["health"][0]["value"], 90);
+        assert_eq!(batch["round"][0]["number"], 7);
+
+        let response = schema
+            .execute(r#"{ batchQuery(queries: [{ table: "round" }, { table: "store_round" }]) }"#)
+            .await;
+        assert_eq!(response.errors[0].message, "Table round is queried more than once");
+    }
+
+    #[tokio::test]
+    async fn test_group_by_enum_column_counts_buckets() {
+        let dubhe_config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "facing": "Direction" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [{ "Direction": ["North", "East", "South", "West"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("graphql.db").display());
+        let database = dubhe_common::Database::new(&url).await.unwrap();
+        for sql in dubhe_config.create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        for (player, x, facing) in [
+            ("0xa", 1, "North"),
+            ("0xb", 2, "North"),
+            ("0xc", 3, "East"),
+            ("0xd", 4, "North"),
+        ] {
+            database
+                .execute(&format!(
+                    "INSERT INTO store_position (player, x, facing) VALUES ('{}', {}, '{}')",
+                    player, x, facing
+                ))
+                .await
+                .unwrap();
+        }
+        database
+            .execute("UPDATE store_position SET is_deleted = TRUE WHERE player = '0xd'")
+            .await
+            .unwrap();
+
+        let db_pool = Arc::new(DatabasePool::new(&url).await.unwrap());
+        let schema = Schema::build(
+            QueryRoot::new(Some(db_pool)).with_dubhe_config(Arc::new(dubhe_config)),
+            EmptyMutation,
+            SubscriptionRoot::new(
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::new(RwLock::new(HashMap::new())),
+            ),
+        )
+        .finish();
+
+        let response = schema
+            .execute(
+                r#"{
+                  counts: groupBy(table: "position", column: "facing") { key value }
+                  sums: groupBy(table: "position", column: "facing", aggregate: SUM, aggregateColumn: "x") { key value }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(
+            data["counts"],
+            serde_json::json!([{ "key": "East", "value": 1 }, { "key": "North", "value": 2 }])
+        );
+        assert_eq!(
+            data["sums"],
+            serde_json::json!([{ "key": "East", "value": 3 }, { "key": "North", "value": 3 }])
+        );
+
+        let response = schema
+            .execute(
+                r#"{ groupBy(table: "position", column: "facing", aggregate: SUM, aggregateColumn: "player") { key } }"#,
+            )
+            .await;
+        assert!(response.errors[0].message.contains("it is not numeric"));
+        let response = schema
+            .execute(r#"{ groupBy(table: "position", column: "speed") { key } }"#)
+            .await;
+        assert_eq!(response.errors[0].message, "Unknown column 'speed' in table position");
+    }
+
+    #[tokio::test]
+    async fn test_table_rows_select_only_requested_columns() {
+        let dubhe_config = DubheConfig::from_json(serde_json::json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "y": "u64" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("graphql.db").display());
+        let database = dubhe_common::Database::new(&url).await.unwrap();
+        database
+            .execute(&dubhe_config.create_table_sql("position").unwrap())
+            .await
+            .unwrap();
+        database
+            .execute("INSERT INTO store_position (player, x, y) VALUES ('0xa', 1, 2)")
+            .await
+            .unwrap();
+
+        let db_pool = Arc::new(DatabasePool::new(&url).await.unwrap());
+        let schema = Schema::build(
+            QueryRoot::new(Some(db_pool)).with_dubhe_config(Arc::new(dubhe_config)),
+            EmptyMutation,
+            SubscriptionRoot::new(
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::new(RwLock::new(HashMap::new())),
+            ),
+        )
+        .finish();
+
+        let response = schema
+            .execute(
+                r#"{ tableRows(table: "store_position") { player: value(column: "player") x: value(column: "x") } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap()["tableRows"],
+            serde_json::json!([{ "player": "0xa", "x": 1 }])
+        );
+
+        let response = schema
+            .execute(r#"{ tableRows(table: "store_position") { value(column: "secret") } }"#)
+            .await;
+        assert!(response.errors[0].message.contains("Unknown column 'secret'"));
+    }
+}
@@ -1,25 +1,99 @@
 use crate::config::GraphQLConfig;
 use crate::database::DatabasePool;
 use crate::health::HealthService;
+use crate::live_query::{live_query_stream, LiveQuery};
+use crate::pg_listener::{
+    listen_table_changes, prune_closed_subscribers, ChangeThrottle, GraphQLSubscribers,
+};
 use crate::playground::PlaygroundService;
 use crate::schema::QueryRoot;
 use crate::subscriptions::SubscriptionRoot;
 use crate::GrpcSubscribers;
 use crate::TableChange;
 use anyhow::Result;
-use async_graphql::Request;
+use async_graphql::{Request, ServerError};
 use async_graphql::{http::GraphiQLSource, Schema};
 use async_graphql_warp::{GraphQLBadRequest, GraphQLResponse};
-use futures_util::{SinkExt, StreamExt};
+use dubhe_common::DubheConfig;
+use futures_util::{SinkExt, Stream, StreamExt};
 use serde_json::json;
 use std::collections::HashMap;
 use std::convert::Infallible;
+use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use warp::ws::{Message, Ws};
 use warp::{Filter, Rejection, Reply};
 
+/// Error answering a live query when live queries are turned off
+pub const LIVE_QUERIES_DISABLED: &str = "Live queries are disabled on this server";
+/// Error answering a live query when no table changes reach the server
+pub const LIVE_QUERIES_NEED_PG_SUBSCRIPTIONS: &str = "Live queries need PostgreSQL change \
+    notifications, which this server does not receive: set ENABLE_PG_SUBSCRIPTIONS and use a \
+    PostgreSQL DATABASE_URL. Without them a live query would never update.";
+
+/// How the WebSocket route answers `@live` queries
+#[derive(Clone)]
+pub enum LiveQueries {
+    /// Re-run live queries on the table changes delivered to `subscribers`
+    Enabled {
+        subscribers: GraphQLSubscribers,
+        dubhe_config: Option<Arc<DubheConfig>>,
+    },
+    /// Answer live queries with this error
+    Unavailable(&'static str),
+}
+
+impl LiveQueries {
+    /// Live queries as `config` allows them
+    fn new(
+        config: &GraphQLConfig,
+        subscribers: GraphQLSubscribers,
+        dubhe_config: Option<Arc<DubheConfig>>,
+    ) -> Self {
+        if !config.enable_live_queries {
+            LiveQueries::Unavailable(LIVE_QUERIES_DISABLED)
+        } else if !config.pg_subscriptions_enabled() {
+            LiveQueries::Unavailable(LIVE_QUERIES_NEED_PG_SUBSCRIPTIONS)
+        } else {
+            LiveQueries::Enabled {
+                subscribers,
+                dubhe_config,
+            }
+        }
+    }
+
+    /// Stream answering `query`: a live query while they are enabled, the error saying why
+    /// they are not otherwise, and `None` for a regular query
+    fn stream(
+        &self,
+        schema: &Schema<QueryRoot, async_graphql::EmptyMutation, SubscriptionRoot>,
+        query: &str,
+    ) -> Option<Pin<Box<dyn Stream<Item = async_graphql::Response> + Send>>> {
+        match self {
+            LiveQueries::Enabled {
+                subscribers,
+                dubhe_config,
+            } => {
+                let live_query = LiveQuery::parse(query, dubhe_config.as_deref())?;
+                Some(Box::pin(live_query_stream(
+                    schema.clone(),
+                    live_query,
+                    subscribers.clone(),
+                )))
+            }
+            LiveQueries::Unavailable(reason) => {
+                LiveQuery::parse(query, None)?;
+                let error =
+                    async_graphql::Response::from_errors(vec![ServerError::new(*reason, None)]);
+                Some(Box::pin(futures_util::stream::once(async move { error })))
+            }
+        }
+    }
+}
+
 /// GraphQL server
 pub struct GraphQLServer {
     config: GraphQLConfig,
@@ -30,6 +104,9 @@ pub struct GraphQLServer {
     playground_service: PlaygroundService,
     graphql_subscribers:
         Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<crate::subscriptions::TableChange>>>>>,
+    /// Coalesces the Postgres changes of tables with a `push_min_interval_ms`
+    change_throttle: ChangeThrottle,
+    dubhe_config: Option<Arc<DubheConfig>>,
 }
 
 impl GraphQLServer {
@@ -38,6 +115,7 @@ impl GraphQLServer {
         config: GraphQLConfig,
         subscribers: GrpcSubscribers,
         graphql_subscribers: Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>,
+        dubhe_config: Option<Arc<DubheConfig>>,
     ) -> Result<Self> {
         // Try to create database connection pool
         let db_pool = DatabasePool::new(&config.database_url)
@@ -45,7 +123,11 @@ impl GraphQLServer {
             .ok()
             .map(Arc::new);
 
-        let query_root = QueryRoot::new(db_pool.clone());
+        let mut query_root = QueryRoot::new(db_pool.clone());
+        if let Some(dubhe_config) = dubhe_config.clone() {
+            query_root = query_root.with_dubhe_config(dubhe_config);
+        }
+        let change_throttle = ChangeThrottle::new(dubhe_config.clone());
         let schema = Schema::build(
             query_root,
             async_graphql::EmptyMutation,
@@ -64,6 +146,8 @@ impl GraphQLServer {
             health_service,
             playground_service,
             graphql_subscribers,
+            change_throttle,
+            dubhe_config,
         })
     }
 
@@ -71,16 +155,36 @@ impl GraphQLServer {
     async fn handle_websocket(
         ws: warp::ws::Ws,
         schema: Schema<QueryRoot, async_graphql::EmptyMutation, SubscriptionRoot>,
+        graphql_subscribers: GraphQLSubscribers,
+        live_queries: LiveQueries,
     ) -> Result<impl Reply, Rejection> {
-        Ok(ws.on_upgrade(|socket| Self::handle_socket(socket, schema)))
+        Ok(ws.on_upgrade(|socket| {
+            Self::handle_socket(socket, schema, graphql_subscribers, live_queries)
+        }))
     }
 
-    /// Handle WebSocket messages
+    /// Handle WebSocket messages, `@live` queries are answered as `live_queries` says.
+    /// Senders left in `graphql_subscribers` by a subscription are pruned when it is stopped or
+    /// the client disconnects.
     async fn handle_socket(
         socket: warp::ws::WebSocket,
         schema: Schema<QueryRoot, async_graphql::EmptyMutation, SubscriptionRoot>,
+        graphql_subscribers: GraphQLSubscribers,
+        live_queries: LiveQueries,
     ) {
         let (mut sender, mut receiver) = socket.split();
+        // Subscriptions run as their own tasks and send through `outgoing`, so client messages
+        // are read while they stream
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Message>();
+        let writer = tokio::spawn(async move {
+            while let Some(message) = outgoing_rx.recv().await {
+                if let Err(e) = sender.send(message).await {
+                    log::error!("❌ Failed to send WebSocket message: {}", e);
+                    break;
+                }
+            }
+        });
+        let mut subscriptions: HashMap<String, JoinHandle<()>> = HashMap::new();
 
         while let Some(result) = receiver.next().await {
             let msg = match result {
@@ -118,6 +222,7 @@ impl GraphQLServer {
 
                         if let Some(msg_type) = json.get("type").and_then(|v| v.as_str()) {
                             log::info!("🎯 Message type: {}", msg_type);
+                            let id = json.get("id").cloned().unwrap_or(json!("1"));
                             match msg_type {
                                 "connection_init" => {
                                     log::info!("🔄 Handling connection initialization");
@@ -130,10 +235,9 @@ impl GraphQLServer {
                                         "📤 Sending connection acknowledgment: {}",
                                         response_json.to_string()
                                     );
-                                    if let Err(e) =
-                                        sender.send(Message::text(response_json.to_string())).await
-                                    {
-                                        log::error!("❌ Failed to send connection_ack: {}", e);
+                                    let message = Message::text(response_json.to_string());
+                                    if outgoing.send(message).is_err() {
+                                        log::error!("❌ Failed to send connection_ack");
                                         break;
                                     }
                                     log::info!("✅ Connection acknowledgment sent successfully");
@@ -150,57 +254,26 @@ impl GraphQLServer {
                                             payload.get("query").and_then(|v| v.as_str())
                                         {
                                             log::info!("🔍 Subscription query: {}", query);
-                                            let request = Request::new(query.to_string());
-                                            let mut response_stream =
-                                                schema.execute_stream(request);
-
-                                            log::info!("📡 Starting subscription stream execution");
-                                            // Handle subscription stream
-                                            while let Some(response) = response_stream.next().await
-                                            {
-                                                let response_json = json!({
-                                                    "type": "data",
-                                                    "id": json.get("id").unwrap_or(&json!("1")),
-                                                    "payload": {
-                                                        "data": response.data,
-                                                        "errors": response.errors
-                                                    }
+                                            let response_stream = live_queries
+                                                .stream(&schema, query)
+                                                .unwrap_or_else(|| {
+                                                    Box::pin(schema.execute_stream(Request::new(
+                                                        query.to_string(),
+                                                    )))
                                                 });
 
-                                                log::info!(
-                                                    "📤 Sending data response: {}",
-                                                    response_json.to_string()
-                                                );
-                                                if let Err(e) = sender
-                                                    .send(Message::text(response_json.to_string()))
-                                                    .await
-                                                {
-                                                    log::error!(
-                                                        "❌ Failed to send response: {}",
-                                                        e
-                                                    );
-                                                    break;
-                                                }
-                                            }
-
-                                            // Send completion message
-                                            let complete_json = json!({
-                                                "type": "complete",
-                                                "id": json.get("id").unwrap_or(&json!("1"))
-                                            });
-
-                                            log::info!(
-                                                "📤 Sending completion message: {}",
-                                                complete_json.to_string()
-                                            );
-                                            if let Err(e) = sender
-                                                .send(Message::text(complete_json.to_string()))
-                                                .await
+                                            log::info!("📡 Starting subscription stream execution");
+                                            let task = tokio::spawn(forward_subscription(
+                                                id.clone(),
+                                                response_stream,
+                                                outgoing.clone(),
+                                            ));
+                                            // A start reusing the id of a running subscription
+                                            // replaces it
+                                            if let Some(previous) =
+                                                subscriptions.insert(id.to_string(), task)
                                             {
-                                                log::error!(
-                                                    "❌ Failed to send completion message: {}",
-                                                    e
-                                                );
+                                                previous.abort();
                                             }
                                         } else {
                                             log::warn!("⚠️ No query found in subscription payload");
@@ -211,20 +284,24 @@ impl GraphQLServer {
                                 }
                                 "stop" => {
                                     log::info!("🛑 Handling subscription stop");
+                                    if let Some(task) = subscriptions.remove(&id.to_string()) {
+                                        task.abort();
+                                        let _ = task.await;
+                                        prune_closed_subscribers(&graphql_subscribers).await;
+                                    }
                                     // Handle subscription stop
                                     let response_json = json!({
                                         "type": "complete",
-                                        "id": json.get("id").unwrap_or(&json!("1"))
+                                        "id": id
                                     });
 
                                     log::info!(
                                         "📤 Sending stop response: {}",
                                         response_json.to_string()
                                     );
-                                    if let Err(e) =
-                                        sender.send(Message::text(response_json.to_string())).await
-                                    {
-                                        log::error!("❌ Failed to send stop response: {}", e);
+                                    let message = Message::text(response_json.to_string());
+                                    if outgoing.send(message).is_err() {
+                                        log::error!("❌ Failed to send stop response");
                                     }
                                 }
                                 "ping" => {
@@ -235,10 +312,9 @@ impl GraphQLServer {
                                     });
 
                                     log::info!("📤 Sending pong: {}", response_json.to_string());
-                                    if let Err(e) =
-                                        sender.send(Message::text(response_json.to_string())).await
-                                    {
-                                        log::error!("❌ Failed to send pong: {}", e);
+                                    let message = Message::text(response_json.to_string());
+                                    if outgoing.send(message).is_err() {
+                                        log::error!("❌ Failed to send pong");
                                     }
                                 }
                                 _ => {
@@ -257,6 +333,20 @@ impl GraphQLServer {
             }
         }
 
+        // Cancel what the client left running, their senders are closed once the tasks are gone
+        if !subscriptions.is_empty() {
+            log::info!(
+                "🔌 Client disconnected, {} subscription(s) cancelled",
+                subscriptions.len()
+            );
+        }
+        for task in subscriptions.into_values() {
+            task.abort();
+            let _ = task.await;
+        }
+        prune_closed_subscribers(&graphql_subscribers).await;
+        writer.abort();
+
         log::info!("🔚 WebSocket connection closed");
     }
 
@@ -267,6 +357,11 @@ impl GraphQLServer {
         let health_service = self.health_service.clone();
         let playground_service = self.playground_service.clone();
         let graphql_subscribers = self.graphql_subscribers.clone();
+        let live_queries = LiveQueries::new(
+            &config,
+            graphql_subscribers.clone(),
+            self.dubhe_config.clone(),
+        );
 
         // GraphQL route - supports POST and GET
         let graphql_post_route = warp::path("graphql")
@@ -293,11 +388,21 @@ impl GraphQLServer {
                 },
             );
 
-        // WebSocket route - uses the same path as HTTP route
-        let websocket_route = warp::path("graphql")
-            .and(warp::ws())
-            .and(with_schema(schema.clone()))
-            .and_then(|ws: Ws, schema| async move { Self::handle_websocket(ws, schema).await });
+        // WebSocket route - uses the same path as HTTP route, on the main port unless a
+        // dedicated realtime port is configured
+        let websocket_route =
+            websocket_filter(schema.clone(), graphql_subscribers.clone(), live_queries);
+        let dedicated_websocket_port = config.dedicated_websocket_port();
+        let main_websocket_route = warp::any()
+            .and_then(move || async move {
+                if dedicated_websocket_port.is_none() {
+                    Ok(())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            })
+            .untuple_one()
+            .and(websocket_route.clone());
 
         // GraphiQL route
         let graphiql_route = warp::path("playground")
@@ -318,7 +423,7 @@ impl GraphQLServer {
             .and_then(handle_welcome_page); // Use new async handler
 
         // Combine all routes - ensure WebSocket route comes before HTTP route
-        let routes = websocket_route
+        let routes = main_websocket_route
             .or(graphql_post_route)
             .or(graphql_get_route)
             .or(graphiql_route)
@@ -332,18 +437,47 @@ impl GraphQLServer {
                     .allow_credentials(true),
             );
 
+        if let Some(realtime_port) = dedicated_websocket_port {
+            log::info!(
+                "🔌 Realtime WebSocket endpoint: ws://localhost:{}/graphql",
+                realtime_port
+            );
+            tokio::spawn(warp::serve(websocket_route).run(([0, 0, 0, 0], realtime_port)));
+        }
+
+        // Deliver subscriptions from Postgres NOTIFY so the server can run apart from the indexer
+        if config.pg_subscriptions_enabled() {
+            let database_url = config.database_url.clone();
+            let subscribers = graphql_subscribers.clone();
+            let throttle = self.change_throttle.clone();
+            tokio::spawn(async move {
+                if let Err(e) = listen_table_changes(&database_url, subscribers, throttle).await {
+                    log::error!("❌ Postgres subscription listener stopped: {}", e);
+                }
+            });
+        }
+
         log::info!("🚀 GraphQL server starting on port {}", config.port);
         log::info!(
             "📊 GraphQL endpoint: http://localhost:{}/graphql",
             config.port
         );
-        log::info!(
-            "🔌 WebSocket endpoint: ws://localhost:{}/graphql",
-            config.port
-        );
+        log::info!("🔌 WebSocket endpoint: {}", config.websocket_endpoint());
         log::info!("🎮 Playground: http://localhost:{}/playground", config.port);
         log::info!("💚 Health check: http://localhost:{}/health", config.port);
 
+        #[cfg(unix)]
+        if let Some(socket_path) = &config.unix_socket {
+            // A socket file left by a previous run would make bind fail
+            let _ = std::fs::remove_file(socket_path);
+            let listener = tokio::net::UnixListener::bind(socket_path)?;
+            log::info!("🧦 GraphQL server listening on unix socket {}", socket_path);
+            warp::serve(routes)
+                .run_incoming(tokio_stream::wrappers::UnixListenerStream::new(listener))
+                .await;
+            return Ok(());
+        }
+
         warp::serve(routes).run(([0, 0, 0, 0], config.port)).await;
 
         Ok(())
@@ -356,6 +490,38 @@ impl GraphQLServer {
     }
 }
 
+/// Send the responses of one subscription to the client as `data` messages, then `complete`
+/// once the stream ends
+async fn forward_subscription(
+    id: serde_json::Value,
+    mut response_stream: Pin<Box<dyn Stream<Item = async_graphql::Response> + Send>>,
+    outgoing: mpsc::UnboundedSender<Message>,
+) {
+    while let Some(response) = response_stream.next().await {
+        let response_json = json!({
+            "type": "data",
+            "id": id,
+            "payload": {
+                "data": response.data,
+                "errors": response.errors
+            }
+        });
+
+        log::info!("📤 Sending data response: {}", response_json.to_string());
+        if outgoing.send(Message::text(response_json.to_string())).is_err() {
+            // The connection is gone
+            return;
+        }
+    }
+
+    let complete_json = json!({
+        "type": "complete",
+        "id": id
+    });
+    log::info!("📤 Sending completion message: {}", complete_json.to_string());
+    let _ = outgoing.send(Message::text(complete_json.to_string()));
+}
+
 // Helper functions
 fn with_service<T: Clone + Send>(
     service: T,
@@ -363,6 +529,22 @@ fn with_service<T: Clone + Send>(
     warp::any().map(move || service.clone())
 }
 
+/// `/graphql` WebSocket upgrade route for subscriptions and live queries
+fn websocket_filter(
+    schema: Schema<QueryRoot, async_graphql::EmptyMutation, SubscriptionRoot>,
+    graphql_subscribers: GraphQLSubscribers,
+    live_queries: LiveQueries,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path("graphql")
+        .and(warp::ws())
+        .and(with_schema(schema))
+        .and(with_service(graphql_subscribers))
+        .and(with_service(live_queries))
+        .and_then(|ws: Ws, schema, graphql_subscribers, live_queries| async move {
+            GraphQLServer::handle_websocket(ws, schema, graphql_subscribers, live_queries).await
+        })
+}
+
 fn with_schema(
     schema: Schema<QueryRoot, async_graphql::EmptyMutation, SubscriptionRoot>,
 ) -> impl Filter<
@@ -618,3 +800,186 @@ async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
             .unwrap_or(warp::http::StatusCode::INTERNAL_SERVER_ERROR),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn test_schema(
+        graphql_subscribers: GraphQLSubscribers,
+    ) -> Schema<QueryRoot, async_graphql::EmptyMutation, SubscriptionRoot> {
+        Schema::build(
+            QueryRoot::new(None),
+            async_graphql::EmptyMutation,
+            SubscriptionRoot::new(Arc::new(RwLock::new(HashMap::new())), graphql_subscribers),
+        )
+        .finish()
+    }
+
+    async fn recv_json(client: &mut warp::test::WsClient) -> serde_json::Value {
+        let message = client.recv().await.unwrap();
+        serde_json::from_str(message.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_dedicated_websocket_port() {
+        let config = GraphQLConfig {
+            port: 4000,
+            realtime_port: Some(4001),
+            enable_native_websocket: true,
+            ..GraphQLConfig::default()
+        };
+        assert_eq!(config.dedicated_websocket_port(), Some(4001));
+        assert_eq!(config.websocket_endpoint(), "ws://localhost:4001/graphql");
+
+        let disabled = GraphQLConfig {
+            enable_native_websocket: false,
+            ..config.clone()
+        };
+        assert_eq!(disabled.dedicated_websocket_port(), None);
+        let same_port = GraphQLConfig {
+            realtime_port: Some(4000),
+            ..config
+        };
+        assert_eq!(same_port.dedicated_websocket_port(), None);
+    }
+
+    #[tokio::test]
+    async fn test_realtime_port_serves_websocket() {
+        let schema = test_schema(Arc::new(RwLock::new(HashMap::new())));
+        let (addr, server) = warp::serve(websocket_filter(
+            schema,
+            Arc::new(RwLock::new(HashMap::new())),
+            LiveQueries::Unavailable(LIVE_QUERIES_DISABLED),
+        ))
+        .bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /graphql HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut buf = [0u8; 256];
+        let n = stream.read(&mut buf).await.unwrap();
+        let response = String::from_utf8_lossy(&buf[..n]);
+        assert!(response.starts_with("HTTP/1.1 101"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_cancels_subscription() {
+        let subscribers: GraphQLSubscribers = Arc::new(RwLock::new(HashMap::new()));
+        let filter = websocket_filter(
+            test_schema(subscribers.clone()),
+            subscribers.clone(),
+            LiveQueries::Unavailable(LIVE_QUERIES_DISABLED),
+        );
+        let mut client = warp::test::ws().path("/graphql").handshake(filter).await.unwrap();
+        client
+            .send_text(
+                json!({
+                    "id": "1",
+                    "type": "start",
+                    "payload": {
+                        "query": "subscription { tableChanges(tableName: \"counter1\") { tableName } }"
+                    }
+                })
+                .to_string(),
+            )
+            .await;
+
+        let registered = async {
+            while !subscribers.read().await.contains_key("counter1") {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(1), registered)
+            .await
+            .unwrap();
+
+        drop(client);
+        let cleaned_up = async {
+            while subscribers.read().await.contains_key("counter1") {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(1), cleaned_up)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_messages_are_handled_while_subscription_streams() {
+        let subscribers: GraphQLSubscribers = Arc::new(RwLock::new(HashMap::new()));
+        let filter = websocket_filter(
+            test_schema(subscribers.clone()),
+            subscribers.clone(),
+            LiveQueries::Unavailable(LIVE_QUERIES_DISABLED),
+        );
+        let mut client = warp::test::ws().path("/graphql").handshake(filter).await.unwrap();
+        client
+            .send_text(
+                json!({
+                    "id": "1",
+                    "type": "start",
+                    "payload": {
+                        "query": "subscription { tableChanges(tableName: \"counter1\") { tableName } }"
+                    }
+                })
+                .to_string(),
+            )
+            .await;
+        let registered = async {
+            while !subscribers.read().await.contains_key("counter1") {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(1), registered)
+            .await
+            .unwrap();
+
+        client.send_text(json!({ "type": "ping" }).to_string()).await;
+        assert_eq!(recv_json(&mut client).await["type"], "pong");
+
+        client.send_text(json!({ "id": "1", "type": "stop" }).to_string()).await;
+        let complete = recv_json(&mut client).await;
+        assert_eq!(complete["type"], "complete");
+        assert_eq!(complete["id"], "1");
+        assert!(subscribers.read().await.get("counter1").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_live_query_without_pg_subscriptions_is_an_error() {
+        let subscribers: GraphQLSubscribers = Arc::new(RwLock::new(HashMap::new()));
+        let config = GraphQLConfig {
+            enable_live_queries: true,
+            enable_pg_subscriptions: false,
+            ..GraphQLConfig::default()
+        };
+        let live_queries = LiveQueries::new(&config, subscribers.clone(), None);
+        let filter =
+            websocket_filter(test_schema(subscribers.clone()), subscribers.clone(), live_queries);
+        let mut client = warp::test::ws().path("/graphql").handshake(filter).await.unwrap();
+        client
+            .send_text(
+                json!({
+                    "id": "1",
+                    "type": "start",
+                    "payload": { "query": "query @live { serverInfo { name } }" }
+                })
+                .to_string(),
+            )
+            .await;
+
+        let message = recv_json(&mut client).await;
+        assert_eq!(message["type"], "data");
+        assert_eq!(
+            message["payload"]["errors"][0]["message"],
+            LIVE_QUERIES_NEED_PG_SUBSCRIPTIONS
+        );
+        assert!(subscribers.read().await.is_empty());
+    }
+}
@@ -37,6 +37,7 @@ async fn main() -> Result<()> {
     // Create a subscribe request with empty table_ids to subscribe to all tables
     let request = SubscribeRequest {
         table_ids: vec![], // Empty vector means subscribe to all tables
+        resume_from: None,
     };
     
     println!("🔔 Subscribing to all tables...");
@@ -50,6 +51,8 @@ async fn main() -> Result<()> {
         tokio::select! {
             Some(result) = stream.next() => {
                 match result {
+                    // Keepalive on an idle stream, not a change
+                    Ok(change) if change.heartbeat => {}
                     Ok(change) => {
                         println!("📊 [Table Update]");
                         println!("   Table: {}", change.table_id);
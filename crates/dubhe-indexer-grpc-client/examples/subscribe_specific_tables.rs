@@ -43,6 +43,7 @@ async fn main() -> Result<()> {
     // Create a subscribe request with specific table IDs
     let request = SubscribeRequest {
         table_ids: tables.clone(),
+        resume_from: None,
     };
     
     println!("🔔 Subscribing to tables: {:?}", tables);
@@ -56,6 +57,8 @@ async fn main() -> Result<()> {
         tokio::select! {
             Some(result) = stream.next() => {
                 match result {
+                    // Keepalive on an idle stream, not a change
+                    Ok(change) if change.heartbeat => {}
                     Ok(change) => {
                         println!("📊 [Table Update]");
                         println!("   Table: {}", change.table_id);
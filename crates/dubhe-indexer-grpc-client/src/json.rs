@@ -0,0 +1,74 @@
+use anyhow::Result;
+use prost_types::{value::Kind, ListValue, Struct, Value as ProtoValue};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value};
+
+use crate::TableChange;
+
+/// Convert a table change to `{ "table_id": ..., "data": {...} }`, with the proto payload
+/// turned into plain JSON. A change without data has `"data": null`, and changes made by a
+/// `/submit` request also carry its `"trace_id"`.
+pub fn table_change_to_json(change: &TableChange) -> Value {
+    let mut json = Map::new();
+    json.insert("table_id".to_string(), Value::String(change.table_id.clone()));
+    json.insert(
+        "data".to_string(),
+        change.data.as_ref().map_or(Value::Null, proto_struct_to_json),
+    );
+    if !change.trace_id.is_empty() {
+        json.insert("trace_id".to_string(), Value::String(change.trace_id.clone()));
+    }
+    Value::Object(json)
+}
+
+/// Decoding of a table change's payload into a consumer's own types
+pub trait TableChangeExt {
+    /// Deserialize the change's data into `T`, going through the same JSON as
+    /// [`table_change_to_json`]. Fails if a field of `T` is missing or has another type.
+    fn decode_into<T: DeserializeOwned>(&self) -> Result<T>;
+}
+
+impl TableChangeExt for TableChange {
+    fn decode_into<T: DeserializeOwned>(&self) -> Result<T> {
+        let data = self.data.as_ref().map_or(Value::Null, proto_struct_to_json);
+        serde_json::from_value(data).map_err(|e| {
+            anyhow::anyhow!("Failed to decode change of table {}: {}", self.table_id, e)
+        })
+    }
+}
+
+/// Convert a proto `Struct` to a JSON object
+pub fn proto_struct_to_json(proto_struct: &Struct) -> Value {
+    Value::Object(
+        proto_struct
+            .fields
+            .iter()
+            .map(|(key, value)| (key.clone(), proto_value_to_json(value)))
+            .collect(),
+    )
+}
+
+/// Convert a proto `Value` to JSON. Whole numbers become JSON integers rather than floats,
+/// and NaN or infinite numbers, which JSON cannot hold, become `null`.
+pub fn proto_value_to_json(proto_value: &ProtoValue) -> Value {
+    match &proto_value.kind {
+        None | Some(Kind::NullValue(_)) => Value::Null,
+        Some(Kind::BoolValue(b)) => Value::Bool(*b),
+        Some(Kind::NumberValue(n)) => number_to_json(*n),
+        Some(Kind::StringValue(s)) => Value::String(s.clone()),
+        Some(Kind::ListValue(ListValue { values })) => {
+            Value::Array(values.iter().map(proto_value_to_json).collect())
+        }
+        Some(Kind::StructValue(proto_struct)) => proto_struct_to_json(proto_struct),
+    }
+}
+
+fn number_to_json(n: f64) -> Value {
+    // Integers up to 2^53 are exact in an f64, larger ones already lost precision
+    const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+    if n.fract() == 0.0 && n.abs() <= MAX_EXACT_INTEGER {
+        Value::Number(Number::from(n as i64))
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
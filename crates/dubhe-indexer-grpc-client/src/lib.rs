@@ -1,4 +1,5 @@
 pub mod client;
+pub mod json;
 pub mod types;
 
 #[cfg(test)]
@@ -16,6 +17,7 @@ pub use dubhe_indexer_grpc::types::{
 };
 
 pub use client::DubheIndexerGrpcClient;
+pub use json::{table_change_to_json, TableChangeExt};
 
 /// Client trait for table subscription
 #[async_trait]
@@ -1,9 +1,12 @@
-use crate::DubheIndexerGrpcClient;
+use crate::{table_change_to_json, DubheIndexerGrpcClient, TableChange, TableChangeExt};
 use anyhow::Result;
 use dubhe_indexer_grpc::types::dubhe_grpc_client::DubheGrpcClient;
 use dubhe_indexer_grpc::types::QueryRequest;
 use dubhe_indexer_grpc::PaginationRequest;
 use futures_util::StreamExt;
+use prost_types::{value::Kind, ListValue, Struct, Value as ProtoValue};
+use serde_json::json;
+use std::collections::BTreeMap;
 
 // #[tokio::test]
 
@@ -78,3 +81,127 @@ async fn test_grpc_client_query() -> Result<()> {
     println!("📋 Raw gRPC response: {:#?}", response);
     Ok(())
 }
+
+fn proto(kind: Kind) -> ProtoValue {
+    ProtoValue { kind: Some(kind) }
+}
+
+fn list(values: Vec<ProtoValue>) -> ProtoValue {
+    proto(Kind::ListValue(ListValue { values }))
+}
+
+fn change(fields: Vec<(&str, ProtoValue)>) -> TableChange {
+    TableChange {
+        table_id: "player".to_string(),
+        data: Some(Struct {
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect::<BTreeMap<_, _>>(),
+        }),
+        trace_id: String::new(),
+        cursor: 0,
+        end_of_stream: false,
+        heartbeat: false,
+    }
+}
+
+#[test]
+fn test_table_change_to_json_scalars() {
+    let json = table_change_to_json(&change(vec![
+        ("hp", proto(Kind::NumberValue(100.0))),
+        ("speed", proto(Kind::NumberValue(1.5))),
+        ("name", proto(Kind::StringValue("alice".to_string()))),
+        ("alive", proto(Kind::BoolValue(true))),
+        ("guild", proto(Kind::NullValue(0))),
+        ("unset", ProtoValue { kind: None }),
+        ("ratio", proto(Kind::NumberValue(f64::NAN))),
+    ]));
+    assert_eq!(
+        json,
+        json!({
+            "table_id": "player",
+            "data": {
+                "hp": 100,
+                "speed": 1.5,
+                "name": "alice",
+                "alive": true,
+                "guild": null,
+                "unset": null,
+                "ratio": null
+            }
+        })
+    );
+
+    let empty = TableChange {
+        table_id: "player".to_string(),
+        data: None,
+        trace_id: String::new(),
+        cursor: 0,
+        end_of_stream: false,
+        heartbeat: false,
+    };
+    assert_eq!(table_change_to_json(&empty), json!({ "table_id": "player", "data": null }));
+}
+
+#[test]
+fn test_table_change_to_json_list() {
+    let json = table_change_to_json(&change(vec![(
+        "inventory",
+        list(vec![
+            proto(Kind::NumberValue(1.0)),
+            proto(Kind::StringValue("sword".to_string())),
+            proto(Kind::BoolValue(false)),
+        ]),
+    )]));
+    assert_eq!(json["data"]["inventory"], json!([1, "sword", false]));
+}
+
+#[test]
+fn test_table_change_to_json_nested_lists() {
+    let position = Struct {
+        fields: BTreeMap::from([("x".to_string(), proto(Kind::NumberValue(-3.0)))]),
+    };
+    let json = table_change_to_json(&change(vec![(
+        "grid",
+        list(vec![
+            list(vec![proto(Kind::NumberValue(1.0)), proto(Kind::NumberValue(2.0))]),
+            list(vec![]),
+            list(vec![list(vec![proto(Kind::StructValue(position))])]),
+        ]),
+    )]));
+    assert_eq!(json["data"]["grid"], json!([[1, 2], [], [[{ "x": -3 }]]]));
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct Component6 {
+    entity_id: String,
+    attack: u32,
+    hp: u32,
+}
+
+#[test]
+fn test_table_change_decode_into_struct() {
+    let entity_id = "0xd8f042479dcb0028d868051bd53f0d3a41c600db7b14241674db1c2e60124975";
+    let mut table_change = change(vec![
+        ("entity_id", proto(Kind::StringValue(entity_id.to_string()))),
+        ("attack", proto(Kind::NumberValue(12.0))),
+        ("hp", proto(Kind::NumberValue(100.0))),
+        ("is_deleted", proto(Kind::BoolValue(false))),
+    ]);
+    table_change.table_id = "component6".to_string();
+    assert_eq!(
+        table_change.decode_into::<Component6>().unwrap(),
+        Component6 { entity_id: entity_id.to_string(), attack: 12, hp: 100 }
+    );
+
+    table_change
+        .data
+        .as_mut()
+        .unwrap()
+        .fields
+        .insert("hp".to_string(), proto(Kind::StringValue("full".to_string())));
+    let err = table_change.decode_into::<Component6>().unwrap_err().to_string();
+    assert!(err.contains("component6"), "{}", err);
+    assert!(err.contains("invalid type: string \"full\", expected u32"), "{}", err);
+}
@@ -0,0 +1,90 @@
+use crate::types::TableChange;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::mpsc;
+
+/// How many of the latest changes are kept for subscribers resuming from a cursor
+pub const DEFAULT_ARCHIVE_CAPACITY: usize = 10_000;
+
+static CHANGE_ARCHIVE: OnceLock<Arc<ChangeArchive>> = OnceLock::new();
+
+/// Process-wide archive every table change is published through
+pub fn change_archive() -> Arc<ChangeArchive> {
+    CHANGE_ARCHIVE
+        .get_or_init(|| Arc::new(ChangeArchive::new(DEFAULT_ARCHIVE_CAPACITY)))
+        .clone()
+}
+
+/// The latest table changes, numbered by a cursor that grows by one per change, so a
+/// subscriber that reconnects can replay what it missed in between
+pub struct ChangeArchive {
+    capacity: usize,
+    state: Mutex<ArchiveState>,
+}
+
+#[derive(Default)]
+struct ArchiveState {
+    /// Cursor of the latest change, 0 before the first one
+    last_cursor: u64,
+    changes: VecDeque<TableChange>,
+}
+
+impl ChangeArchive {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(ArchiveState::default()),
+        }
+    }
+
+    /// Number `change`, keep it and send it to the subscribers of its table. Numbering and
+    /// sending happen under one lock, so every subscriber receives changes in cursor order.
+    pub fn publish(
+        &self,
+        subscribers: &HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>,
+        mut change: TableChange,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.last_cursor += 1;
+        change.cursor = state.last_cursor;
+        if let Some(senders) = subscribers.get(&change.table_id) {
+            for sender in senders {
+                let _ = sender.send(change.clone());
+            }
+        }
+        if self.capacity > 0 {
+            if state.changes.len() == self.capacity {
+                state.changes.pop_front();
+            }
+            state.changes.push_back(change);
+        }
+    }
+
+    /// Archived changes of `table_ids` after `cursor`, in cursor order. Fails if some change
+    /// after `cursor` was already dropped, or if `cursor` was never handed out, e.g. by a
+    /// previous run of the server.
+    pub fn since(&self, cursor: u64, table_ids: &[String]) -> anyhow::Result<Vec<TableChange>> {
+        let state = self.state.lock().unwrap();
+        if cursor > state.last_cursor {
+            return Err(anyhow::anyhow!(
+                "Cursor {} is ahead of the latest change {}",
+                cursor,
+                state.last_cursor
+            ));
+        }
+        let dropped = state.last_cursor - state.changes.len() as u64;
+        if cursor < dropped {
+            return Err(anyhow::anyhow!(
+                "Changes after cursor {} are no longer archived, the oldest one is {}",
+                cursor,
+                dropped + 1
+            ));
+        }
+        Ok(state
+            .changes
+            .iter()
+            .filter(|change| change.cursor > cursor && table_ids.contains(&change.table_id))
+            .cloned()
+            .collect())
+    }
+}
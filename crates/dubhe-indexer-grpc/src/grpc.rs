@@ -1,22 +1,71 @@
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 use tonic::{transport::Server, Request, Response, Status};
 
+use crate::archive::{change_archive, ChangeArchive};
 use crate::types::dubhe_grpc_server::{DubheGrpc, DubheGrpcServer};
 use crate::types::{
-    filter_value, value_range, FilterCondition, FilterOperator, FilterValue, PaginationResponse,
-    QueryRequest, QueryResponse, SortDirection, SubscribeRequest, TableChange,
+    filter_value, value_range, AggregateFunction, BatchQueryRequest, BatchQueryResponse,
+    ColumnDescription, DescribeTablesRequest, DescribeTablesResponse, FilterCondition,
+    FilterOperator, FilterValue, GroupBucket, GroupByRequest, GroupByResponse, PaginationResponse,
+    QueryRequest, QueryResponse, SortDirection, SubscribeRequest, TableChange, TableDescription,
+};
+use dubhe_common::{
+    quote_identifier, Aggregate, BoundQuery, Database, DubheConfig, QueryBuilder,
+    GROUP_KEY_COLUMN, GROUP_VALUE_COLUMN,
 };
-use dubhe_common::{Database, DubheConfig};
 
 pub type GrpcSubscribers = Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>;
 
+/// How long a shutting down server waits for subscriptions to flush their queued changes
+pub const SUBSCRIPTION_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a subscription stays idle before a heartbeat is sent, unless configured. Well
+/// under the 60 second idle timeout common to load balancers and reverse proxies.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Graceful end of the open subscriptions. Once drained, every stream stops taking new
+/// changes, sends the ones already queued for it, then an end-of-stream marker, and closes.
+#[derive(Clone)]
+pub struct SubscriptionDrain {
+    /// Each open stream holds a receiver until it has flushed
+    draining: Arc<watch::Sender<bool>>,
+}
+
+impl Default for SubscriptionDrain {
+    fn default() -> Self {
+        let (draining, _) = watch::channel(false);
+        Self {
+            draining: Arc::new(draining),
+        }
+    }
+}
+
+impl SubscriptionDrain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start draining and wait up to `timeout` for every stream to flush. Returns false if
+    /// some streams were still flushing when the timeout expired.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.draining.send_replace(true);
+        tokio::time::timeout(timeout, self.draining.closed())
+            .await
+            .is_ok()
+    }
+}
+
 pub struct DubheGrpcService {
     subscribers: GrpcSubscribers,
     database: Arc<Database>,
     dubhe_config: Arc<DubheConfig>,
+    archive: Arc<ChangeArchive>,
+    drain: SubscriptionDrain,
+    heartbeat_interval: Duration,
 }
 
 impl DubheGrpcService {
@@ -25,9 +74,36 @@ impl DubheGrpcService {
             subscribers,
             database,
             dubhe_config,
+            archive: change_archive(),
+            drain: SubscriptionDrain::new(),
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
         }
     }
 
+    /// End open subscriptions gracefully when `drain` is drained
+    pub fn with_subscription_drain(mut self, drain: SubscriptionDrain) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Replay resumed subscriptions from `archive` instead of the process-wide one
+    pub fn with_change_archive(mut self, archive: Arc<ChangeArchive>) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    /// Send a heartbeat on subscriptions that stay idle for `interval`, zero disables them
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Storage table of `table_id`, the one the indexer writes its rows to
+    fn store_table(&self, table_id: &str) -> String {
+        self.dubhe_config
+            .store_table_name(Some(&self.dubhe_config.original_package_id), table_id)
+    }
+
     /// Build SQL query from QueryRequest
     async fn build_sql_query(
         &self,
@@ -35,29 +111,28 @@ impl DubheGrpcService {
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let mut sql = String::new();
 
-        // SELECT clause
-        if req.select_fields.is_empty() {
+        // SELECT clause, only columns of the table may be selected
+        let fields: Vec<String> = req
+            .select_fields
+            .iter()
+            .map(|field| self.dubhe_config.column_name(&req.table_name, field))
+            .collect();
+        let projection = QueryBuilder::new(&self.store_table(&req.table_name))
+            .project(&fields, &self.dubhe_config.selectable_columns(&req.table_name))?;
+        if projection.select_fields.is_empty() {
             sql.push_str("SELECT *");
         } else {
+            let columns: Vec<String> =
+                projection.select_fields.iter().map(|c| quote_identifier(c)).collect();
             sql.push_str("SELECT ");
-            sql.push_str(&req.select_fields.join(", "));
+            sql.push_str(&columns.join(", "));
         }
 
         // FROM clause
-        sql.push_str(&format!(" FROM store_{}", req.table_name));
+        sql.push_str(&format!(" FROM {}", self.store_table(&req.table_name)));
 
         // WHERE clause
-        if !req.filters.is_empty() {
-            sql.push_str(" WHERE ");
-            let mut conditions = Vec::new();
-
-            for filter in &req.filters {
-                let condition = self.build_filter_condition(filter)?;
-                conditions.push(condition);
-            }
-
-            sql.push_str(&conditions.join(" AND "));
-        }
+        sql.push_str(&self.build_where_clause(req)?);
 
         // ORDER BY clause
         if !req.sorts.is_empty() {
@@ -74,7 +149,8 @@ impl DubheGrpcService {
                         SortDirection::Ascending => "ASC",
                         SortDirection::Descending => "DESC",
                     };
-                    format!("{} {}", sort.field_name, direction)
+                    let column = self.dubhe_config.column_name(&req.table_name, &sort.field_name);
+                    format!("{} {}", quote_identifier(&column), direction)
                 })
                 .collect();
 
@@ -102,12 +178,65 @@ impl DubheGrpcService {
         Ok(sql)
     }
 
+    /// Build the grouped query of a GroupByRequest after validating its columns
+    fn build_group_by_query(
+        &self,
+        req: &GroupByRequest,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let aggregate = match req.aggregate() {
+            AggregateFunction::Count => Aggregate::Count,
+            AggregateFunction::Sum => Aggregate::Sum,
+            AggregateFunction::Avg => Aggregate::Avg,
+            AggregateFunction::Min => Aggregate::Min,
+            AggregateFunction::Max => Aggregate::Max,
+        };
+        let (column, aggregate_column) = self.dubhe_config.group_by_columns(
+            &req.table_name,
+            &req.column,
+            aggregate,
+            req.aggregate_column.as_deref(),
+        )?;
+
+        let where_clause = self.build_where_clause(&QueryRequest {
+            table_name: req.table_name.clone(),
+            filters: req.filters.clone(),
+            include_deleted: req.include_deleted,
+            ..Default::default()
+        })?;
+        Ok(QueryBuilder::new(&self.store_table(&req.table_name))
+            .aggregate_by(&column, aggregate, aggregate_column.as_deref())?
+            .to_sql_with_where_clause(&where_clause))
+    }
+
+    /// Build the WHERE clause, soft-deleted rows are hidden unless `include_deleted` is set
+    fn build_where_clause(
+        &self,
+        req: &QueryRequest,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conditions = Vec::new();
+        for filter in &req.filters {
+            conditions.push(self.build_filter_condition(&req.table_name, filter)?);
+        }
+        if !req.include_deleted {
+            conditions.push(dubhe_common::SOFT_DELETE_CONDITION.to_string());
+        }
+
+        if conditions.is_empty() {
+            Ok(String::new())
+        } else {
+            Ok(format!(" WHERE {}", conditions.join(" AND ")))
+        }
+    }
+
     /// Build individual filter condition
     fn build_filter_condition(
         &self,
+        table_name: &str,
         filter: &FilterCondition,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let field = &filter.field_name;
+        // Clients filter by Move field name, the column may be aliased
+        let column = self.dubhe_config.column_name(table_name, &filter.field_name);
+        let field = &quote_identifier(&column);
         let operator = filter.operator();
 
         let condition = match operator {
@@ -336,20 +465,11 @@ impl DubheGrpcService {
         database: &Database,
         req: &QueryRequest,
     ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
-        let mut count_sql = format!("SELECT COUNT(*) as count FROM store_{}", req.table_name);
-
-        // Add WHERE clause if filters exist
-        if !req.filters.is_empty() {
-            count_sql.push_str(" WHERE ");
-            let mut conditions = Vec::new();
-
-            for filter in &req.filters {
-                let condition = self.build_filter_condition(filter)?;
-                conditions.push(condition);
-            }
-
-            count_sql.push_str(&conditions.join(" AND "));
-        }
+        let mut count_sql = format!(
+            "SELECT COUNT(*) as count FROM {}",
+            self.store_table(&req.table_name)
+        );
+        count_sql.push_str(&self.build_where_clause(req)?);
 
         match database.query(&count_sql).await {
             Ok(results) => {
@@ -369,13 +489,46 @@ impl DubheGrpcService {
         }
     }
 
-    pub async fn broadcast_update(&self, table_id: &str, update: TableChange) {
+    /// Schema of the configured tables, limited to `table_names` when non-empty
+    fn describe_tables_from_config(&self, table_names: &[String]) -> Vec<TableDescription> {
+        self.dubhe_config
+            .tables
+            .iter()
+            .filter(|table| !self.dubhe_config.is_excluded_table(&table.name))
+            .filter(|table| table_names.is_empty() || table_names.contains(&table.name))
+            .map(|table| {
+                let fields = self
+                    .dubhe_config
+                    .fields
+                    .iter()
+                    .filter(|field| field.table == table.name);
+                TableDescription {
+                    name: table.name.clone(),
+                    columns: fields
+                        .clone()
+                        .map(|field| ColumnDescription {
+                            name: field.name.clone(),
+                            db_type: field.db_type.clone(),
+                            move_type: field.move_type.clone(),
+                            is_primary_key: field.primary_key,
+                        })
+                        .collect(),
+                    primary_keys: fields
+                        .filter(|field| field.primary_key)
+                        .map(|field| field.name.clone())
+                        .collect(),
+                    offchain: table.offchain,
+                }
+            })
+            .collect()
+    }
+
+    /// Publish `update` to the subscribers of `table_id` through the change archive, so it
+    /// gets a cursor and is replayed to subscribers resuming from an earlier one
+    pub async fn broadcast_update(&self, table_id: &str, mut update: TableChange) {
+        update.table_id = table_id.to_string();
         let subscribers = self.subscribers.read().await;
-        if let Some(senders) = subscribers.get(table_id) {
-            for sender in senders {
-                let _ = sender.send(update.clone());
-            }
-        }
+        self.archive.publish(&subscribers, update);
     }
 }
 
@@ -504,6 +657,28 @@ impl DubheGrpc for DubheGrpcService {
             println!("✅ Added subscriber for table: {} (total: {})", table_id, senders.len());
         }
 
+        // Read the archive only once the subscriber is registered, so no change falls in
+        // between; changes published in the meantime arrive twice and are skipped below
+        let replay = match req.resume_from {
+            Some(cursor) => match self.archive.since(cursor, &table_ids) {
+                Ok(replay) => {
+                    println!("⏪ Replaying {} change(s) after cursor {}", replay.len(), cursor);
+                    replay
+                }
+                Err(e) => {
+                    drop(rx);
+                    let mut subscribers = self.subscribers.write().await;
+                    for table_id in &table_ids {
+                        if let Some(senders) = subscribers.get_mut(table_id) {
+                            senders.retain(|sender| !sender.is_closed());
+                        }
+                    }
+                    return Err(Status::out_of_range(e.to_string()));
+                }
+            },
+            None => Vec::new(),
+        };
+
         // Convert UnboundedReceiver<TableChange> to UnboundedReceiver<Result<TableChange, Status>>
         let (result_tx, result_rx) = mpsc::unbounded_channel::<Result<TableChange, Status>>();
 
@@ -511,13 +686,60 @@ impl DubheGrpc for DubheGrpcService {
         let subscribers_clone = self.subscribers.clone();
         let table_ids_clone = table_ids.clone();
         let mut rx_clone = rx;
+        let mut drain_rx = self.drain.draining.subscribe();
+        let heartbeat_interval = self.heartbeat_interval;
         tokio::spawn(async move {
-            while let Some(item) = rx_clone.recv().await {
+            let mut last_cursor = req.resume_from.unwrap_or(0);
+            for change in replay {
+                last_cursor = change.cursor;
+                let _ = result_tx.send(Ok(change));
+            }
+            let mut draining = *drain_rx.borrow();
+            if draining {
+                rx_clone.close();
+            }
+            loop {
+                let item = tokio::select! {
+                    item = rx_clone.recv() => item,
+                    Ok(()) = drain_rx.changed(), if !draining => {
+                        // Refuse new changes, the queued ones are still received
+                        draining = true;
+                        rx_clone.close();
+                        continue;
+                    }
+                    _ = idle(heartbeat_interval) => {
+                        let heartbeat = TableChange {
+                            heartbeat: true,
+                            ..Default::default()
+                        };
+                        if result_tx.send(Ok(heartbeat)).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                // Already replayed; changes sent outside the archive have no cursor
+                if item.cursor != 0 && item.cursor <= last_cursor {
+                    continue;
+                }
                 if result_tx.send(Ok(item)).is_err() {
                     // Client disconnected, break the loop
                     break;
                 }
             }
+            if draining {
+                println!("🏁 Subscription flushed, ending stream for tables: {:?}", table_ids_clone);
+                let _ = result_tx.send(Ok(TableChange {
+                    end_of_stream: true,
+                    ..Default::default()
+                }));
+            }
+            // Close the stream before reporting this subscription as flushed
+            drop(result_tx);
+            drop(drain_rx);
             
             // Client disconnected - clean up subscribers
             println!("🧹 Client disconnected, cleaning up subscriptions for tables: {:?}", table_ids_clone);
@@ -540,6 +762,91 @@ impl DubheGrpc for DubheGrpcService {
         let output_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(result_rx);
         Ok(Response::new(output_stream))
     }
+
+    async fn describe_tables(
+        &self,
+        request: Request<DescribeTablesRequest>,
+    ) -> Result<Response<DescribeTablesResponse>, Status> {
+        let req = request.into_inner();
+        let tables = self.describe_tables_from_config(&req.table_names);
+        log::info!("📋 gRPC describe_tables: returned {} tables", tables.len());
+        Ok(Response::new(DescribeTablesResponse { tables }))
+    }
+
+    async fn batch_query(
+        &self,
+        request: Request<BatchQueryRequest>,
+    ) -> Result<Response<BatchQueryResponse>, Status> {
+        let req = request.into_inner();
+        let mut queries = Vec::with_capacity(req.queries.len());
+        for (i, query) in req.queries.iter().enumerate() {
+            if req.queries[..i].iter().any(|q| q.table_name == query.table_name) {
+                return Err(Status::invalid_argument(format!(
+                    "Table {} is queried more than once",
+                    query.table_name
+                )));
+            }
+            let sql = self.build_sql_query(query).await.map_err(|e| {
+                Status::invalid_argument(format!("Failed to build SQL query: {}", e))
+            })?;
+            queries.push(BoundQuery {
+                sql,
+                params: Vec::new(),
+                persistent: false,
+            });
+        }
+
+        let results = self.database.query_batch(&queries).await.map_err(|e| {
+            log::error!("❌ gRPC batch_query failed: {}", e);
+            Status::internal(format!("Failed to execute batch query: {}", e))
+        })?;
+        let mut responses = HashMap::new();
+        for (query, results) in req.queries.into_iter().zip(results) {
+            let rows = results
+                .iter()
+                .map(dubhe_common::json_to_proto_struct)
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map_err(|e| Status::internal(e.to_string()))?;
+            responses.insert(query.table_name, QueryResponse { rows, pagination: None });
+        }
+        log::info!("✅ gRPC batch_query successful: queried {} tables", responses.len());
+        Ok(Response::new(BatchQueryResponse { results: responses }))
+    }
+
+    async fn group_by(
+        &self,
+        request: Request<GroupByRequest>,
+    ) -> Result<Response<GroupByResponse>, Status> {
+        let req = request.into_inner();
+        let sql = self
+            .build_group_by_query(&req)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let rows = self.database.query(&sql).await.map_err(|e| {
+            log::error!("❌ gRPC group_by failed: {}", e);
+            Status::internal(format!("Failed to execute query: {}", e))
+        })?;
+        let buckets = rows
+            .iter()
+            .map(|row| {
+                Ok(GroupBucket {
+                    key: Some(dubhe_common::json_value_to_proto_value(&row[GROUP_KEY_COLUMN])?),
+                    value: Some(dubhe_common::json_value_to_proto_value(
+                        &row[GROUP_VALUE_COLUMN],
+                    )?),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(GroupByResponse { buckets }))
+    }
+}
+
+/// Resolves once a stream has been idle for `interval`, never if it is zero
+async fn idle(interval: Duration) {
+    if interval.is_zero() {
+        std::future::pending::<()>().await;
+    }
+    tokio::time::sleep(interval).await;
 }
 
 pub async fn start_grpc_server(
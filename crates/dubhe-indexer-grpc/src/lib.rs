@@ -1,6 +1,8 @@
+pub mod archive;
 pub mod grpc;
 pub mod types;
 
+pub use archive::*;
 pub use grpc::*;
 pub use types::*;
 
@@ -1,7 +1,15 @@
-use crate::grpc::start_grpc_server;
-use dubhe_common::Database;
+use crate::archive::ChangeArchive;
+use crate::grpc::{start_grpc_server, DubheGrpcService, SubscriptionDrain};
+use crate::types::dubhe_grpc_server::DubheGrpc;
+use crate::types::{
+    AggregateFunction, DescribeTablesRequest, GroupByRequest, SubscribeRequest, TableChange,
+};
+use dubhe_common::{Database, DubheConfig};
+use futures::StreamExt;
+use prost_types::value::Kind;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 #[tokio::test]
@@ -17,3 +25,323 @@ async fn test_grpc_server() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn test_describe_tables() {
+    let dubhe_config = DubheConfig::from_json(serde_json::json!({
+      "components": [
+        { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "y": "u64" }], "keys": ["player"], "offchain": false } },
+        { "message": { "fields": [{ "text": "String" }], "keys": [], "offchain": true } }
+      ],
+      "resources": [],
+      "enums": [],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap();
+    let service = DubheGrpcService::new(
+        Arc::new(RwLock::new(HashMap::new())),
+        Arc::new(Database::new("sqlite::memory:").await.unwrap()),
+        Arc::new(dubhe_config),
+    );
+
+    let tables = service
+        .describe_tables(tonic::Request::new(DescribeTablesRequest { table_names: vec![] }))
+        .await
+        .unwrap()
+        .into_inner()
+        .tables;
+    assert_eq!(tables.len(), 2);
+
+    let position = tables.iter().find(|table| table.name == "position").unwrap();
+    assert_eq!(position.primary_keys, vec!["player".to_string()]);
+    assert!(!position.offchain);
+    let columns: Vec<_> = position.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(columns, vec!["player", "x", "y"]);
+    assert_eq!(position.columns[0].move_type, "address");
+    assert!(position.columns[0].is_primary_key);
+
+    let message = tables.iter().find(|table| table.name == "message").unwrap();
+    assert!(message.offchain);
+    assert!(message.primary_keys.is_empty());
+
+    let only_message = service
+        .describe_tables(tonic::Request::new(DescribeTablesRequest {
+            table_names: vec!["message".to_string()],
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+        .tables;
+    assert_eq!(only_message.len(), 1);
+}
+
+#[tokio::test]
+async fn test_group_by() {
+    let dubhe_config = DubheConfig::from_json(serde_json::json!({
+      "components": [
+        { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "facing": "Direction" }], "keys": ["player"], "offchain": false } }
+      ],
+      "resources": [],
+      "enums": [{ "Direction": ["North", "East", "South", "West"] }],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("grpc.db").display());
+    let database = Database::new(&url).await.unwrap();
+    for sql in dubhe_config.create_tables_sql() {
+        database.execute(&sql).await.unwrap();
+    }
+    for (player, x, facing) in [("0xa", 1, "North"), ("0xb", 2, "North"), ("0xc", 3, "East")] {
+        database
+            .execute(&format!(
+                "INSERT INTO store_position (player, x, facing) VALUES ('{}', {}, '{}')",
+                player, x, facing
+            ))
+            .await
+            .unwrap();
+    }
+    let service = DubheGrpcService::new(
+        Arc::new(RwLock::new(HashMap::new())),
+        Arc::new(database),
+        Arc::new(dubhe_config),
+    );
+    let group_by = |aggregate: AggregateFunction, aggregate_column: Option<&str>, column: &str| {
+        let request = GroupByRequest {
+            table_name: "position".to_string(),
+            column: column.to_string(),
+            aggregate: aggregate as i32,
+            aggregate_column: aggregate_column.map(str::to_string),
+            filters: vec![],
+            include_deleted: false,
+        };
+        service.group_by(tonic::Request::new(request))
+    };
+    let buckets = |response: tonic::Response<crate::types::GroupByResponse>| {
+        response
+            .into_inner()
+            .buckets
+            .into_iter()
+            .map(|bucket| match (bucket.key.unwrap().kind, bucket.value.unwrap().kind) {
+                (Some(Kind::StringValue(key)), Some(Kind::NumberValue(value))) => (key, value),
+                other => panic!("Unexpected bucket {:?}", other),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let counts = buckets(group_by(AggregateFunction::Count, None, "facing").await.unwrap());
+    assert_eq!(counts, vec![("East".to_string(), 1.0), ("North".to_string(), 2.0)]);
+    let sums = buckets(group_by(AggregateFunction::Sum, Some("x"), "facing").await.unwrap());
+    assert_eq!(sums, vec![("East".to_string(), 3.0), ("North".to_string(), 3.0)]);
+
+    // Rejected the same way GraphQL rejects them
+    let status = group_by(AggregateFunction::Sum, Some("player"), "facing").await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(status.message().contains("it is not numeric"));
+    let status = group_by(AggregateFunction::Count, None, "speed").await.unwrap_err();
+    assert_eq!(status.message(), "Unknown column 'speed' in table position");
+}
+
+#[tokio::test]
+async fn test_resume_replays_missed_changes_once() {
+    let dubhe_config = DubheConfig::from_json(serde_json::json!({
+      "components": [
+        { "counter": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+      ],
+      "resources": [],
+      "enums": [],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap();
+    let subscribers = Arc::new(RwLock::new(HashMap::new()));
+    let archive = Arc::new(ChangeArchive::new(100));
+    let service = DubheGrpcService::new(
+        subscribers.clone(),
+        Arc::new(Database::new("sqlite::memory:").await.unwrap()),
+        Arc::new(dubhe_config),
+    )
+    .with_change_archive(archive.clone());
+    let change = |value: f64| TableChange {
+        table_id: "counter".to_string(),
+        data: Some(prost_types::Struct {
+            fields: std::collections::BTreeMap::from([(
+                "value".to_string(),
+                prost_types::Value {
+                    kind: Some(prost_types::value::Kind::NumberValue(value)),
+                },
+            )]),
+        }),
+        trace_id: String::new(),
+        cursor: 0,
+        end_of_stream: false,
+        heartbeat: false,
+    };
+    let subscribe = |resume_from| {
+        service.subscribe_table(tonic::Request::new(SubscribeRequest {
+            table_ids: vec!["counter".to_string()],
+            resume_from,
+        }))
+    };
+
+    let mut stream = subscribe(None).await.unwrap().into_inner();
+    archive.publish(&*subscribers.read().await, change(1.0));
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first.cursor, 1);
+    drop(stream);
+
+    // Published while the client is disconnected
+    archive.publish(&*subscribers.read().await, change(2.0));
+    // broadcast_update goes through the archive as well
+    service.broadcast_update("counter", change(3.0)).await;
+
+    let mut stream = subscribe(Some(first.cursor)).await.unwrap().into_inner();
+    archive.publish(&*subscribers.read().await, change(4.0));
+    let mut cursors = Vec::new();
+    while let Ok(Some(item)) = tokio::time::timeout(Duration::from_millis(200), stream.next()).await
+    {
+        let item = item.unwrap();
+        assert_eq!(TableChange { cursor: 0, ..item.clone() }, change(item.cursor as f64));
+        cursors.push(item.cursor);
+    }
+    assert_eq!(cursors, vec![2, 3, 4]);
+
+    let status = subscribe(Some(10)).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::OutOfRange);
+}
+
+#[tokio::test]
+async fn test_drain_flushes_queued_changes() {
+    let dubhe_config = DubheConfig::from_json(serde_json::json!({
+      "components": [
+        { "counter": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+      ],
+      "resources": [],
+      "enums": [],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap();
+    let subscribers = Arc::new(RwLock::new(HashMap::new()));
+    let archive = Arc::new(ChangeArchive::new(100));
+    let drain = SubscriptionDrain::new();
+    let service = DubheGrpcService::new(
+        subscribers.clone(),
+        Arc::new(Database::new("sqlite::memory:").await.unwrap()),
+        Arc::new(dubhe_config),
+    )
+    .with_change_archive(archive.clone())
+    .with_subscription_drain(drain.clone());
+
+    let mut stream = service
+        .subscribe_table(tonic::Request::new(SubscribeRequest {
+            table_ids: vec!["counter".to_string()],
+            resume_from: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    for _ in 0..3 {
+        let change = TableChange {
+            table_id: "counter".to_string(),
+            ..Default::default()
+        };
+        archive.publish(&*subscribers.read().await, change);
+    }
+
+    assert!(drain.drain(Duration::from_secs(5)).await);
+    // Changes published after the drain started are no longer taken
+    let late = TableChange {
+        table_id: "counter".to_string(),
+        ..Default::default()
+    };
+    archive.publish(&*subscribers.read().await, late);
+
+    let mut received = Vec::new();
+    while let Some(item) = stream.next().await {
+        received.push(item.unwrap());
+    }
+    let cursors: Vec<_> = received.iter().map(|change| change.cursor).collect();
+    assert_eq!(cursors, vec![1, 2, 3, 0]);
+    assert!(received[3].end_of_stream);
+    assert!(received[..3].iter().all(|change| !change.end_of_stream));
+}
+
+#[tokio::test]
+async fn test_idle_subscription_gets_heartbeats() {
+    let dubhe_config = DubheConfig::from_json(serde_json::json!({
+      "components": [
+        { "counter": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+      ],
+      "resources": [],
+      "enums": [],
+      "original_package_id": "0x1",
+      "dubhe_object_id": "0x2",
+      "original_dubhe_package_id": "0x3",
+      "start_checkpoint": "1"
+    }))
+    .unwrap();
+    let dubhe_config = Arc::new(dubhe_config);
+    let subscribers = Arc::new(RwLock::new(HashMap::new()));
+    let archive = Arc::new(ChangeArchive::new(100));
+    let database = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+    let service = |heartbeat_interval| {
+        DubheGrpcService::new(subscribers.clone(), database.clone(), dubhe_config.clone())
+            .with_change_archive(archive.clone())
+            .with_heartbeat_interval(heartbeat_interval)
+    };
+    let request = || {
+        tonic::Request::new(SubscribeRequest {
+            table_ids: vec!["counter".to_string()],
+            resume_from: None,
+        })
+    };
+    let mut stream = service(Duration::from_secs(15))
+        .subscribe_table(request())
+        .await
+        .unwrap()
+        .into_inner();
+    let mut silent = service(Duration::ZERO)
+        .subscribe_table(request())
+        .await
+        .unwrap()
+        .into_inner();
+
+    // Idle well past the 60 second timeout of common proxies
+    tokio::time::pause();
+    let start = tokio::time::Instant::now();
+    let mut heartbeats = 0;
+    while start.elapsed() < Duration::from_secs(90) {
+        let item = stream.next().await.expect("idle subscription closed").unwrap();
+        assert!(item.heartbeat && !item.end_of_stream);
+        assert!(item.table_id.is_empty() && item.data.is_none() && item.cursor == 0);
+        heartbeats += 1;
+    }
+    assert!(heartbeats >= 6, "{} heartbeats", heartbeats);
+    assert!(tokio::time::timeout(Duration::from_secs(90), silent.next()).await.is_err());
+
+    let change = TableChange {
+        table_id: "counter".to_string(),
+        ..Default::default()
+    };
+    archive.publish(&*subscribers.read().await, change);
+    // Heartbeats sent in the meantime come first, clients skip them
+    let item = loop {
+        let item = stream.next().await.unwrap().unwrap();
+        if !item.heartbeat {
+            break item;
+        }
+    };
+    assert_eq!(item.cursor, 1);
+    assert_eq!(item.table_id, "counter");
+}
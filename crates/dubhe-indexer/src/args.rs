@@ -4,12 +4,15 @@
 use anyhow::Result;
 use clap::Args;
 use clap::Parser;
+use clap::Subcommand;
+use crate::schema::PrintSchemaArgs;
+use dubhe_common::{DeadLetterPolicy, DubheConfig};
 use serde_json::Value;
-use std::fs;
 use std::path::PathBuf;
 use sui_indexer_alt_framework::IndexerArgs;
+use dubhe_db::{build_sui_client, SuiClientOptions};
+use std::time::Duration;
 use sui_sdk::SuiClient;
-use sui_sdk::SuiClientBuilder;
 use url::Url;
 
 use sui_indexer_alt_framework::postgres::DbArgs;
@@ -17,12 +20,14 @@ use sui_indexer_alt_framework::postgres::DbArgs;
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct DubheIndexerArgs {
-    /// Configuration file path
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Configuration file path; log level and timeouts are reloaded on SIGHUP
     #[arg(long, default_value = "config.example.toml")]
     pub config: String,
     #[command(flatten)]
     pub indexer_args: IndexerArgs,
-    /// Path to the configuration file
+    /// Path to the configuration file (.json, .toml, .yaml or .yml)
     #[arg(short, long, default_value = "dubhe.config.json")]
     pub config_json: String,
     /// Force restart: clear indexer database (only for local nodes)
@@ -31,6 +36,12 @@ pub struct DubheIndexerArgs {
     /// sui rpc url
     #[arg(long, default_value = "http://localhost:9000")]
     pub rpc_url: String,
+    /// Longest time a single Sui RPC request may take, in milliseconds
+    #[arg(long, default_value = "60000")]
+    pub rpc_timeout_ms: u64,
+    /// Most Sui RPC requests a client keeps in flight at once
+    #[arg(long, default_value = "256")]
+    pub rpc_max_concurrent_requests: usize,
     /// checkpoint url
     #[arg(long, default_value = ".chk")]
     pub checkpoint_url: String,
@@ -40,19 +51,61 @@ pub struct DubheIndexerArgs {
     /// server port
     #[arg(long, default_value = "8080")]
     pub port: u16,
+    /// Connect the proxy to the gRPC/GraphQL backends over Unix sockets in this directory
+    /// instead of localhost TCP (Unix only)
+    #[arg(long)]
+    pub backend_socket_dir: Option<PathBuf>,
+    /// What to do with events that fail indexing: "skip" records them in the
+    /// dubhe_dead_letters table and keeps going, "halt" stops the indexer
+    #[arg(long, default_value = "skip")]
+    pub dead_letter_policy: DeadLetterPolicy,
+    /// Statement batches buffered between checkpoint processing and a dedicated database
+    /// writer; 0 writes them inside the pipeline's commit. The pipeline waits for the
+    /// writer to commit a batch before it records the checkpoint watermark.
+    #[arg(long, default_value = "0")]
+    pub sql_buffer_size: usize,
+    /// Checkpoints whose statements the SQL writer commits together in one database
+    /// transaction; 1 commits every checkpoint on its own. Enables the SQL writer when
+    /// above 1.
+    #[arg(long, default_value = "1")]
+    pub commit_batch_size: usize,
+    /// How long the pipeline collects processed checkpoints before committing them, in
+    /// milliseconds; defaults to the indexer framework's interval
+    #[arg(long)]
+    pub commit_flush_ms: Option<u64>,
+    /// Longest time a `GET /poll/{table}` request waits for a change, in milliseconds
+    #[arg(long, default_value = "25000")]
+    pub poll_max_wait_ms: u64,
+    /// Idle time after which gRPC subscriptions get a heartbeat message so proxies keep them
+    /// open, in milliseconds; 0 disables heartbeats
+    #[arg(long, default_value = "15000")]
+    pub heartbeat_interval_ms: u64,
     #[command(flatten)]
     pub db_args: DbArgs,
 }
 
+/// Tasks run instead of the indexer
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print the CREATE TABLE statements of a config without a database
+    PrintSchema(PrintSchemaArgs),
+}
+
 impl DubheIndexerArgs {
+    /// Read the Dubhe config as JSON; `.toml`, `.yaml` and `.yml` files are converted
     pub fn get_config_json(&self) -> Result<Value> {
-        let content = fs::read_to_string(self.config_json.clone())?;
-        let json: Value = serde_json::from_str(&content)?;
-        Ok(json)
+        DubheConfig::read_config_value(&self.config_json)
+    }
+
+    /// Settings of every Sui client built from these args
+    pub fn sui_client_options(&self) -> SuiClientOptions {
+        SuiClientOptions::default()
+            .with_request_timeout(Duration::from_millis(self.rpc_timeout_ms))
+            .with_max_concurrent_requests(self.rpc_max_concurrent_requests)
     }
 
     pub async fn get_sui_client(&self) -> Result<SuiClient> {
-        let sui_client = SuiClientBuilder::default().build(&self.rpc_url).await?;
+        let sui_client = build_sui_client(&self.rpc_url, &self.sui_client_options()).await?;
         Ok(sui_client)
     }
 
@@ -3,8 +3,9 @@ use anyhow::Result;
 use serde::Deserialize;
 use std::fs;
 use std::path::PathBuf;
+use dubhe_db::{build_sui_client, SuiClientOptions};
+use std::time::Duration;
 use sui_sdk::SuiClient;
-use sui_sdk::SuiClientBuilder;
 use url::Url;
 
 #[derive(Clone, Debug, Deserialize)]
@@ -24,6 +25,24 @@ pub struct SuiConfig {
     pub origin_package_id: String,
     pub start_checkpoint: u64,
     pub progress_file_path: Option<String>,
+    /// Longest time a single RPC request may take, in milliseconds
+    pub rpc_timeout_ms: Option<u64>,
+    /// Most RPC requests the client keeps in flight at once
+    pub rpc_max_concurrent_requests: Option<usize>,
+}
+
+impl SuiConfig {
+    /// Client settings, the [SuiClientOptions] defaults for those not configured
+    pub fn sui_client_options(&self) -> SuiClientOptions {
+        let mut options = SuiClientOptions::default();
+        if let Some(timeout_ms) = self.rpc_timeout_ms {
+            options = options.with_request_timeout(Duration::from_millis(timeout_ms));
+        }
+        if let Some(max_concurrent_requests) = self.rpc_max_concurrent_requests {
+            options = options.with_max_concurrent_requests(max_concurrent_requests);
+        }
+        options
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -86,7 +105,7 @@ impl DubheConfig {
     }
 
     pub async fn get_sui_client(&self) -> Result<SuiClient> {
-        let sui_client = SuiClientBuilder::default().build(&self.sui.rpc_url).await?;
+        let sui_client = build_sui_client(&self.sui.rpc_url, &self.sui.sui_client_options()).await?;
         Ok(sui_client)
     }
 
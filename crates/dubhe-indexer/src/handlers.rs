@@ -4,11 +4,15 @@
 // docs::#processordeps
 use anyhow::Result;
 use dubhe_common::DBData;
+use dubhe_common::{
+    DeadLetter, DeadLetterPolicy, IndexedStatement, UnknownTableError, UnknownTablePolicy,
+};
 use dubhe_common::DubheConfig;
 use dubhe_common::Event;
 use dubhe_common::PostgresStorage;
 use dubhe_common::{
-    Database, EventParser, StoreDeleteRecord, StoreSetField, StoreSetRecord, TableMetadata,
+    Database, Dialect, EventParser, SqlWriteBuffer, StoreDeleteRecord, StoreSetField, StoreSetRecord,
+    TableMetadata,
 };
 use dubhe_indexer_graphql::TableChange;
 use dubhe_indexer_grpc::types::TableChange as GrpcTableChange;
@@ -17,7 +21,7 @@ use std::collections::BTreeMap;
 use std::collections::HashMap;  
 
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use sui_indexer_alt_framework::{
     pipeline::Processor, types::full_checkpoint_content::CheckpointData,
 };
@@ -25,15 +29,37 @@ use sui_types::base_types::ObjectID;
 use tokio::sync::mpsc;
 use tokio::sync::RwLock;
 use prost_types::Value;
+use crate::status::IndexerStatus;
+use crate::reload::RuntimeConfig;
+use crate::throttle::PushThrottle;
 
 pub type GrpcSubscribers =
     Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<GrpcTableChange>>>>>;
 pub type GraphQLSubscribers = Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>;
 
 pub struct DubheEventHandler {
-    pub dubhe_config: DubheConfig,
+    /// Gains the tables inferred under the `auto_create` unknown table policy
+    pub dubhe_config: std::sync::RwLock<DubheConfig>,
     pub grpc_subscribers: GrpcSubscribers,
     pub graphql_subscribers: GraphQLSubscribers,
+    pub status: Arc<IndexerStatus>,
+    pub dead_letter_policy: DeadLetterPolicy,
+    /// Dialect of the database the generated statements run on
+    pub dialect: Dialect,
+    /// Push intervals reloaded on SIGHUP override the configured ones
+    pub runtime_config: Arc<RuntimeConfig>,
+    push_throttle: PushThrottle,
+}
+
+static SQL_WRITE_BUFFER: OnceLock<SqlWriteBuffer> = OnceLock::new();
+
+/// Have the pipeline's commit hand its batches to `buffer` and wait for them to be written,
+/// instead of executing them itself. `Handler::commit` has no access to the handler, so the
+/// buffer is process-wide.
+pub fn install_sql_write_buffer(buffer: SqlWriteBuffer) -> Result<()> {
+    SQL_WRITE_BUFFER
+        .set(buffer)
+        .map_err(|_| anyhow::anyhow!("SQL write buffer already installed"))
 }
 
 impl DubheEventHandler {
@@ -43,217 +69,339 @@ impl DubheEventHandler {
         graphql_subscribers: GraphQLSubscribers,
     ) -> Self {
         Self {
-            dubhe_config,
+            dubhe_config: std::sync::RwLock::new(dubhe_config),
+            push_throttle: PushThrottle::new(grpc_subscribers.clone()),
             grpc_subscribers,
             graphql_subscribers,
+            status: Arc::new(IndexerStatus::new()),
+            dead_letter_policy: DeadLetterPolicy::default(),
+            dialect: Dialect::default(),
+            runtime_config: Arc::new(RuntimeConfig::default()),
         }
     }
+
+    /// Choose whether events that fail conversion or execution halt indexing
+    pub fn with_dead_letter_policy(mut self, policy: DeadLetterPolicy) -> Self {
+        self.dead_letter_policy = policy;
+        self
+    }
+
+    /// Generate statements for `dialect` instead of Postgres
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    fn skips_failed_events(&self) -> bool {
+        self.dead_letter_policy == DeadLetterPolicy::Skip
+    }
+
+    /// Halt with the dead letter's error, or queue it for the dead letter table
+    fn reject_event(
+        &self,
+        dead_letter: DeadLetter,
+        parsed_events: &mut Vec<IndexedStatement>,
+    ) -> Result<()> {
+        if !self.skips_failed_events() {
+            return Err(anyhow::anyhow!(
+                "Failed to index {} in tx {}: {}",
+                dead_letter.event_name,
+                dead_letter.digest,
+                dead_letter.error
+            ));
+        }
+        log::warn!(
+            "⚠️ Dead-lettering {} in tx {}: {}",
+            dead_letter.event_name,
+            dead_letter.digest,
+            dead_letter.error
+        );
+        parsed_events.push(IndexedStatement {
+            sql: dead_letter.insert_sql(),
+            dead_letter: None,
+        });
+        Ok(())
+    }
+
+    /// Register the table of an event missing from the config under the `auto_create` policy,
+    /// or fail with `error` under any other policy. Returns the CREATE TABLE statement, or
+    /// `None` when another checkpoint registered the table first.
+    ///
+    /// The table is kept for the events that follow. The pipeline commits checkpoints in order
+    /// and retries a failed commit, so the statement is committed before any of theirs.
+    fn infer_table(&self, event: &Event, error: anyhow::Error) -> Result<Option<String>> {
+        let mut config = self.dubhe_config.write().unwrap();
+        if config.on_unknown_table != UnknownTablePolicy::AutoCreate {
+            return Err(error);
+        }
+        match config.can_convert_event_to_sql(event) {
+            Err(e) if e.is::<UnknownTableError>() => config.infer_table(event).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Report processed checkpoints to `status`
+    pub fn with_status(mut self, status: Arc<IndexerStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Take subscriber push intervals from `runtime_config` where it sets them
+    pub fn with_runtime_config(mut self, runtime_config: Arc<RuntimeConfig>) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
+
+    /// Convert one store event into SQL and notify gRPC subscribers. Events that cannot be
+    /// converted are handled according to the dead letter policy.
+    fn process_event(
+        &self,
+        event: &sui_types::event::Event,
+        checkpoint_timestamp_ms: u64,
+        current_digest: &str,
+        parsed_events: &mut Vec<IndexedStatement>,
+    ) -> Result<()> {
+        let event_name = event.type_.name.as_str();
+        if event_name != "Dubhe_Store_SetRecord"
+            && event_name != "Dubhe_Store_SetField"
+            && event_name != "Dubhe_Store_DeleteRecord"
+        {
+            return Ok(());
+        }
+        let current_digest = current_digest.to_string();
+        let dead_letter = |error: anyhow::Error| {
+            DeadLetter::new(event_name, &event.contents, error)
+                .with_transaction(&current_digest, checkpoint_timestamp_ms)
+        };
+        let parsed_event = match Event::from_bytes(event_name, event.contents.as_slice()) {
+            Ok(parsed_event) => parsed_event,
+            Err(e) => return self.reject_event(dead_letter(e), parsed_events),
+        };
+        let accepted = self.dubhe_config.read().unwrap().can_convert_event_to_sql(&parsed_event);
+        match accepted {
+            Ok(true) => {}
+            Err(e) if e.is::<UnknownTableError>() => match self.infer_table(&parsed_event, e) {
+                Ok(Some(create_sql)) => parsed_events.push(IndexedStatement {
+                    sql: create_sql,
+                    dead_letter: None,
+                }),
+                Ok(None) => {}
+                Err(e) => return self.reject_event(dead_letter(e), parsed_events),
+            },
+            _ => {
+                let config = self.dubhe_config.read().unwrap();
+                dubhe_common::record_skipped_event(&config, &parsed_event);
+                return Ok(());
+            }
+        }
+        let config = self.dubhe_config.read().unwrap();
+        let table_name = parsed_event.table_id().to_string();
+
+        println!("================ table_name: {:?}", table_name);
+        println!("================ parsed_event: {:?}", parsed_event);
+
+        if table_name != "storage_submit" {
+            let mut proto_struct = match config.convert_event_to_proto_struct(&parsed_event) {
+                Ok(proto_struct) => proto_struct,
+                Err(e) => return self.reject_event(dead_letter(e), parsed_events),
+            };
+
+            // proto_struct append updated_at_timestamp_ms, last_update_digest and is_deleted
+            proto_struct.fields.insert(
+                "updated_at_timestamp_ms".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::StringValue(
+                        checkpoint_timestamp_ms.to_string(),
+                    )),
+                },
+            );
+            proto_struct.fields.insert(
+                "last_update_digest".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::StringValue(
+                        current_digest.clone(),
+                    )),
+                },
+            );
+            // Deletions are soft, subscribers still get the row flagged as deleted
+            let is_deleted = matches!(parsed_event, Event::StoreDeleteRecord(_));
+            proto_struct.fields.insert(
+                "is_deleted".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::BoolValue(is_deleted)),
+                },
+            );
+
+            // Only notify subscribers of events that made it into SQL
+            let key_tuple = parsed_event.key_tuple().clone();
+            let sql = match config.convert_event_to_sql(
+                parsed_event,
+                checkpoint_timestamp_ms,
+                current_digest.clone(),
+                self.dialect,
+            ) {
+                Ok(sql) => sql,
+                Err(e) => return self.reject_event(dead_letter(e), parsed_events),
+            };
+            parsed_events.push(IndexedStatement {
+                sql,
+                dead_letter: self.skips_failed_events().then(|| {
+                    dead_letter(anyhow::anyhow!("SQL execution failed"))
+                }),
+            });
+
+            // Send without blocking, coalesced per key for tables with push_min_interval_ms
+            let min_interval = self
+                .runtime_config
+                .push_min_interval(&table_name, config.push_min_interval(&table_name));
+            self.push_throttle.push(
+                key_tuple,
+                GrpcTableChange {
+                    table_id: table_name,
+                    data: Some(proto_struct),
+                    trace_id: String::new(),
+                    cursor: 0,
+                    end_of_stream: false,
+                    heartbeat: false,
+                },
+                min_interval,
+            );
+        } else {
+            let table_name = parsed_event.table_id().to_string();
+
+            println!("================ table_name: {:?}", table_name);
+            println!("================ parsed_event: {:?}", parsed_event);
+            let mut proto_struct = prost_types::Struct { fields: BTreeMap::new() };
+
+
+            let (dapp_key, keys, values, fee) = match decode_storage_submit(&parsed_event) {
+                Ok(decoded) => decoded,
+                Err(e) => return self.reject_event(dead_letter(e), parsed_events),
+            };
+
+            println!("================ dapp_key: {:?}", dapp_key);
+            println!("================ keys: {:?}", keys);
+            println!("================ values: {:?}", values);
+            println!("================ fee: {:?}", fee);
+
+            proto_struct.fields.insert(
+                "dapp_key".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::StringValue(
+                        dapp_key.clone(),
+                    )),
+                },
+            );
+
+            proto_struct.fields.insert(
+                "key_tuple".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue {
+                        values: keys.clone()
+                            .iter()
+                            .map(|v| prost_types::Value {
+                                kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue {
+                                    values: v
+                                        .iter()
+                                        .map(|v| prost_types::Value {
+                                            kind: Some(prost_types::value::Kind::NumberValue(
+                                                *v as f64,
+                                            )),
+                                        })
+                                        .collect(),
+                                })),
+                            })
+                            .collect(),
+                    })),
+                },
+            );
+
+            proto_struct.fields.insert(
+                "value_tuple".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue { 
+                        values: values.clone()
+                            .iter()
+                            .map(|v| prost_types::Value {
+                                kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue {
+                                    values: v
+                                        .iter()
+                                        .map(|v| prost_types::Value {
+                                            kind: Some(prost_types::value::Kind::NumberValue(
+                                                *v as f64,
+                                            )),
+                                        })
+                                        .collect(),
+                                })),
+                            })
+                            .collect() })),
+                },
+            );
+            proto_struct.fields.insert(
+                "fee".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::StringValue(
+                        fee.to_string(),
+                    )),
+                },
+            );
+            proto_struct.fields.insert(
+                "last_update_digest".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::StringValue(
+                        current_digest.clone(),
+                    )),
+                },
+            );
+            proto_struct.fields.insert(
+                "is_deleted".to_string(),
+                Value {
+                    kind: Some(prost_types::value::Kind::BoolValue(false)),
+                },
+            );
+
+            // Published through the change archive like every other change, so subscribers
+            // resuming from a cursor get it too
+            self.push_throttle.push(
+                keys,
+                GrpcTableChange {
+                    table_id: table_name,
+                    data: Some(proto_struct),
+                    trace_id: String::new(),
+                    cursor: 0,
+                    end_of_stream: false,
+                    heartbeat: false,
+                },
+                None,
+            );
+        }
+        Ok(())
+    }
 }
 
 // docs::#processor
 impl Processor for DubheEventHandler {
     const NAME: &'static str = "dubhe_event_handler";
 
-    type Value = String;
+    type Value = IndexedStatement;
 
     fn process(&self, checkpoint: &Arc<CheckpointData>) -> Result<Vec<Self::Value>> {
         let current_checkpoint = checkpoint.checkpoint_summary.sequence_number;
         println!("current_checkpoint: {:?}", current_checkpoint);
-        let current_checkpoint = checkpoint.checkpoint_summary.timestamp_ms;
+        let checkpoint_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms;
         let mut parsed_events = Vec::new();
-        for transaction in &checkpoint.transactions {
-            let current_digest = transaction.transaction.digest().base58_encode().clone();
+        let mut checkpoint_events = Vec::new();
+        for (tx_index, transaction) in checkpoint.transactions.iter().enumerate() {
+            let current_digest = dubhe_common::digest_string(transaction.transaction.digest());
             let maybe_events = &transaction.events;
             if let Some(events) = maybe_events {
-                for event in &events.data {
-                    if event.type_.name.to_string() == "Dubhe_Store_SetRecord"
-                        || event.type_.name.to_string() == "Dubhe_Store_SetField"
-                        || event.type_.name.to_string() == "Dubhe_Store_DeleteRecord"
-                    {
-                        let parsed_event = Event::from_bytes(
-                            event.type_.name.as_str(),
-                            event.contents.as_slice(),
-                        )?;
-                        if self
-                            .dubhe_config
-                            .can_convert_event_to_sql(&parsed_event)
-                            .is_ok()
-                        {
-                            let table_name = parsed_event.table_id().to_string();
-
-                            println!("================ table_name: {:?}", table_name);
-                            println!("================ parsed_event: {:?}", parsed_event);
-
-                            if table_name != "storage_submit" {
-                                let mut proto_struct = self
-                                    .dubhe_config
-                                    .convert_event_to_proto_struct(&parsed_event)?;
-
-                                // proto_struct append updated_at_timestamp_ms, last_update_digest and is_deleted
-                                proto_struct.fields.insert(
-                                    "updated_at_timestamp_ms".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::StringValue(
-                                            current_checkpoint.to_string(),
-                                        )),
-                                    },
-                                );
-                                proto_struct.fields.insert(
-                                    "last_update_digest".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::StringValue(
-                                            current_digest.clone(),
-                                        )),
-                                    },
-                                );
-                                proto_struct.fields.insert(
-                                    "is_deleted".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::BoolValue(false)),
-                                    },
-                                );
-
-                                // Spawn async task to send update without blocking
-                                let subscribers = self.grpc_subscribers.clone();
-                                tokio::spawn(async move {
-                                    let table_change = dubhe_indexer_grpc::types::TableChange {
-                                        table_id: table_name.clone(),
-                                        data: Some(proto_struct),
-                                    };
-
-                                    // Send to GRPC subscribers
-                                    let subscribers = subscribers.read().await;
-                                    if let Some(senders) = subscribers.get(&table_name) {
-                                        for sender in senders {
-                                            let _ = sender.send(table_change.clone());
-                                        }
-                                    }
-                                });
-
-                                let sql = self.dubhe_config.convert_event_to_sql(
-                                    parsed_event,
-                                    current_checkpoint,
-                                    current_digest.clone(),
-                                )?;
-                                parsed_events.push(sql);
-                            } else {
-                                let table_name = parsed_event.table_id().to_string();
-
-                                println!("================ table_name: {:?}", table_name);
-                                println!("================ parsed_event: {:?}", parsed_event);
-                                let mut proto_struct = prost_types::Struct { fields: BTreeMap::new() };
-
-
-                                let dapp_key_bsc_bytes = parsed_event.value_tuple().clone()[0].clone();
-                                let keys_bcs_bytes = parsed_event.value_tuple().clone()[1].clone();
-                                let values_bcs_bytes = parsed_event.value_tuple().clone()[2].clone();
-                                let fee_bcs_bytes = parsed_event.value_tuple().clone()[3].clone();
-
-                                let dapp_key: String = bcs::from_bytes(&dapp_key_bsc_bytes).unwrap();
-                                let keys: Vec<Vec<u8>> = bcs::from_bytes(&keys_bcs_bytes).unwrap();
-                                let values: Vec<Vec<u8>> = bcs::from_bytes(&values_bcs_bytes).unwrap();
-                                let fee: U256 = bcs::from_bytes(&fee_bcs_bytes).unwrap();
-
-                                println!("================ dapp_key: {:?}", dapp_key);
-                                println!("================ keys: {:?}", keys);
-                                println!("================ values: {:?}", values);
-                                println!("================ fee: {:?}", fee);
-
-                                proto_struct.fields.insert(
-                                    "dapp_key".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::StringValue(
-                                            dapp_key.clone(),
-                                        )),
-                                    },
-                                );
-
-                                proto_struct.fields.insert(
-                                    "key_tuple".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue {
-                                            values: keys.clone()
-                                                .iter()
-                                                .map(|v| prost_types::Value {
-                                                    kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue {
-                                                        values: v
-                                                            .iter()
-                                                            .map(|v| prost_types::Value {
-                                                                kind: Some(prost_types::value::Kind::NumberValue(
-                                                                    *v as f64,
-                                                                )),
-                                                            })
-                                                            .collect(),
-                                                    })),
-                                                })
-                                                .collect(),
-                                        })),
-                                    },
-                                );
-
-                                proto_struct.fields.insert(
-                                    "value_tuple".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue { 
-                                            values: values.clone()
-                                                .iter()
-                                                .map(|v| prost_types::Value {
-                                                    kind: Some(prost_types::value::Kind::ListValue(prost_types::ListValue {
-                                                        values: v
-                                                            .iter()
-                                                            .map(|v| prost_types::Value {
-                                                                kind: Some(prost_types::value::Kind::NumberValue(
-                                                                    *v as f64,
-                                                                )),
-                                                            })
-                                                            .collect(),
-                                                    })),
-                                                })
-                                                .collect() })),
-                                    },
-                                );
-                                proto_struct.fields.insert(
-                                    "fee".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::StringValue(
-                                            fee.to_string(),
-                                        )),
-                                    },
-                                );
-                                proto_struct.fields.insert(
-                                    "last_update_digest".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::StringValue(
-                                            current_digest.clone(),
-                                        )),
-                                    },
-                                );
-                                proto_struct.fields.insert(
-                                    "is_deleted".to_string(),
-                                    Value {
-                                        kind: Some(prost_types::value::Kind::BoolValue(false)),
-                                    },
-                                );
-
-                                // Spawn async task to send update without blocking
-                                let subscribers = self.grpc_subscribers.clone();
-                                tokio::spawn(async move {
-                                    let table_change = dubhe_indexer_grpc::types::TableChange {
-                                        table_id: table_name.clone(),
-                                        data: Some(proto_struct),
-                                    };
-
-                                    // Send to "position" table subscribers
-                                    let subscribers = subscribers.read().await;
-                                    if let Some(senders) = subscribers.get("position") {
-                                        for sender in senders {
-                                            let _ = sender.send(table_change.clone());
-                                        }
-                                    }
-                                });
-                            }
-                        }
-                    }
+                for (event_index, event) in events.data.iter().enumerate() {
+                    checkpoint_events.push(CheckpointEvent {
+                        tx_index,
+                        event_index,
+                        digest: current_digest.clone(),
+                        event,
+                    });
                     // if event.type_.name.to_string() == "Dubhe_Store_SetRecord" {
                     //     let set_record: StoreSetRecord =
                     //         bcs::from_bytes(event.contents.as_slice())
@@ -359,10 +507,54 @@ impl Processor for DubheEventHandler {
                 }
             }
         }
+        // A delete emitted after a set of the same key has to be applied after it
+        sort_in_emission_order(&mut checkpoint_events);
+        for event in &checkpoint_events {
+            self.process_event(
+                event.event,
+                checkpoint_timestamp_ms,
+                &event.digest,
+                &mut parsed_events,
+            )?;
+        }
 
+        self.status
+            .record_checkpoint(checkpoint.checkpoint_summary.sequence_number);
         Ok(parsed_events)
     }
 }
+
+/// Dapp key, key tuple, value tuple and fee of a `storage_submit` record
+fn decode_storage_submit(event: &Event) -> Result<(String, Vec<Vec<u8>>, Vec<Vec<u8>>, U256)> {
+    let Event::StoreSetRecord(record) = event else {
+        return Err(anyhow::anyhow!("storage_submit only handles whole records"));
+    };
+    let value_tuple = &record.value_tuple;
+    let field = |index: usize| {
+        value_tuple
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("storage_submit record has no field {}", index))
+    };
+    Ok((
+        bcs::from_bytes(field(0)?)?,
+        bcs::from_bytes(field(1)?)?,
+        bcs::from_bytes(field(2)?)?,
+        bcs::from_bytes(field(3)?)?,
+    ))
+}
+
+/// An event of a checkpoint with its position on chain
+struct CheckpointEvent<'a> {
+    tx_index: usize,
+    event_index: usize,
+    digest: String,
+    event: &'a sui_types::event::Event,
+}
+
+/// Order events by (transaction index, event index), the order they were emitted in
+fn sort_in_emission_order(events: &mut [CheckpointEvent<'_>]) {
+    events.sort_by_key(|event| (event.tx_index, event.event_index));
+}
 // docs::/#processor
 // docs::#handler
 use diesel_async::RunQueryDsl;
@@ -374,17 +566,331 @@ use sui_indexer_alt_framework::{
 #[async_trait::async_trait]
 impl Handler for DubheEventHandler {
     type Store = Db;
-    type Batch = Vec<Self::Value>;
+    /// Statements of each checkpoint, so the SQL writer can count checkpoints per commit
+    type Batch = Vec<Vec<Self::Value>>;
 
     fn batch(batch: &mut Self::Batch, values: Vec<Self::Value>) {
-        batch.extend(values);
+        if !values.is_empty() {
+            batch.push(values);
+        }
     }
 
     async fn commit<'a>(batch: &Self::Batch, conn: &mut Connection<'a>) -> Result<usize> {
-        for sql in batch {
-            println!("🔄 Executing SQL: {}", sql);
-            diesel::sql_query(sql).execute(conn).await?;
+        // Returns once the writer has committed the batch, the pipeline records the
+        // watermark after this so it never gets ahead of the data
+        if let Some(buffer) = SQL_WRITE_BUFFER.get() {
+            buffer.write(batch.clone()).await?;
+            return Ok(0);
+        }
+        for statement in batch.iter().flatten() {
+            println!("🔄 Executing SQL: {}", statement.sql);
+            let Some(dead_letter) = &statement.dead_letter else {
+                diesel::sql_query(&statement.sql).execute(conn).await?;
+                continue;
+            };
+            // The savepoint keeps the commit transaction usable after a failed statement
+            diesel::sql_query("SAVEPOINT dubhe_event").execute(conn).await?;
+            match diesel::sql_query(&statement.sql).execute(conn).await {
+                Ok(_) => {
+                    diesel::sql_query("RELEASE SAVEPOINT dubhe_event").execute(conn).await?;
+                }
+                Err(e) => {
+                    diesel::sql_query("ROLLBACK TO SAVEPOINT dubhe_event").execute(conn).await?;
+                    log::warn!(
+                        "⚠️ Dead-lettering {} in tx {}: {}",
+                        dead_letter.event_name, dead_letter.digest, e
+                    );
+                    let mut dead_letter = dead_letter.clone();
+                    dead_letter.error = e.to_string();
+                    diesel::sql_query(dead_letter.insert_sql()).execute(conn).await?;
+                }
+            }
         }
         Ok(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::identifier::Identifier;
+    use move_core_types::language_storage::StructTag;
+    use serde_json::json;
+    use sui_types::base_types::SuiAddress;
+
+    fn handler(policy: DeadLetterPolicy) -> DubheEventHandler {
+        handler_for_unknown_tables(policy, "skip")
+    }
+
+    fn handler_for_unknown_tables(
+        policy: DeadLetterPolicy,
+        on_unknown_table: &str,
+    ) -> DubheEventHandler {
+        let config = DubheConfig::from_json(json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1",
+          "on_unknown_table": on_unknown_table
+        }))
+        .unwrap();
+        DubheEventHandler::new(
+            config,
+            Arc::new(RwLock::new(HashMap::new())),
+            Arc::new(RwLock::new(HashMap::new())),
+        )
+        .with_dead_letter_policy(policy)
+        .with_dialect(Dialect::Sqlite)
+    }
+
+    fn event(contents: Vec<u8>) -> sui_types::event::Event {
+        named_event("Dubhe_Store_SetRecord", contents)
+    }
+
+    fn named_event(name: &str, contents: Vec<u8>) -> sui_types::event::Event {
+        sui_types::event::Event {
+            package_id: ObjectID::ZERO,
+            transaction_module: Identifier::new("counter_system").unwrap(),
+            sender: SuiAddress::ZERO,
+            type_: StructTag {
+                address: move_core_types::account_address::AccountAddress::ONE,
+                module: Identifier::new("dubhe_events").unwrap(),
+                name: Identifier::new(name).unwrap(),
+                type_params: vec![],
+            },
+            contents,
+        }
+    }
+
+    fn set_counter() -> sui_types::event::Event {
+        event(
+            bcs::to_bytes(&StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter".to_string(),
+                key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+                value_tuple: vec![bcs::to_bytes(&5u32).unwrap()],
+            })
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_malformed_storage_submit_is_an_error() {
+        let record = |value_tuple| {
+            Event::StoreSetRecord(StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "storage_submit".to_string(),
+                key_tuple: vec![],
+                value_tuple,
+            })
+        };
+        let keys = vec![bcs::to_bytes(&1u64).unwrap()];
+        let decoded = decode_storage_submit(&record(vec![
+            bcs::to_bytes(&"0x1::dapp_key::DappKey".to_string()).unwrap(),
+            bcs::to_bytes(&keys).unwrap(),
+            bcs::to_bytes(&Vec::<Vec<u8>>::new()).unwrap(),
+            bcs::to_bytes(&U256::from(7u64)).unwrap(),
+        ]))
+        .unwrap();
+        assert_eq!(decoded.1, keys);
+        assert_eq!(decoded.3, U256::from(7u64));
+
+        assert!(decode_storage_submit(&record(vec![vec![0xff]])).is_err());
+        assert!(decode_storage_submit(&record(vec![])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_events_are_applied_in_emission_order() {
+        let handler = handler(DeadLetterPolicy::Halt);
+        let delete = named_event(
+            "Dubhe_Store_DeleteRecord",
+            bcs::to_bytes(&StoreDeleteRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "counter".to_string(),
+                key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+            })
+            .unwrap(),
+        );
+        let set = set_counter();
+        // The set of tx 0 is followed by a delete in tx 1 and another set later in tx 1
+        let at = |tx_index, event_index, event| CheckpointEvent {
+            tx_index,
+            event_index,
+            digest: format!("tx{}", tx_index),
+            event,
+        };
+        let mut events = vec![at(1, 1, &set), at(1, 0, &delete), at(0, 3, &set)];
+        sort_in_emission_order(&mut events);
+        let positions: Vec<_> = events.iter().map(|e| (e.tx_index, e.event_index)).collect();
+        assert_eq!(positions, vec![(0, 3), (1, 0), (1, 1)]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("order.db").display());
+        let database = Database::new(&url).await.unwrap();
+        for sql in handler.dubhe_config.read().unwrap().create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        let mut statements = Vec::new();
+        // The delete was collected first but follows the set on chain, so it wins
+        for event in &events[..2] {
+            handler
+                .process_event(event.event, 7, &event.digest, &mut statements)
+                .unwrap();
+        }
+        for statement in &statements {
+            database.execute(&statement.sql).await.unwrap();
+        }
+        let rows = database
+            .query("SELECT is_deleted, last_update_digest FROM store_counter")
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["is_deleted"], true);
+        assert_eq!(rows[0]["last_update_digest"], "tx1");
+    }
+
+    #[test]
+    fn test_package_mismatch_is_counted() {
+        let handler = handler(DeadLetterPolicy::Skip);
+        let wrong_package = event(
+            bcs::to_bytes(&StoreSetRecord {
+                dapp_key: "9::dapp_key::DappKey".to_string(),
+                table_id: "counter".to_string(),
+                key_tuple: vec![bcs::to_bytes(&1u64).unwrap()],
+                value_tuple: vec![bcs::to_bytes(&5u32).unwrap()],
+            })
+            .unwrap(),
+        );
+        let counter = dubhe_common::skipped_events().with_label_values(&["package_mismatch"]);
+        let before = counter.get();
+
+        let mut statements = Vec::new();
+        handler
+            .process_event(&wrong_package, 7, "digest", &mut statements)
+            .unwrap();
+        assert!(statements.is_empty());
+        assert_eq!(counter.get(), before + 1);
+        assert!(dubhe_common::encode_metrics()
+            .unwrap()
+            .contains("dubhe_skipped_events_total{reason=\"package_mismatch\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_event_is_dead_lettered_and_indexing_continues() {
+        let handler = handler(DeadLetterPolicy::Skip);
+        let mut statements = Vec::new();
+        handler
+            .process_event(&event(vec![0xff]), 7, "digest", &mut statements)
+            .unwrap();
+        handler
+            .process_event(&set_counter(), 7, "digest", &mut statements)
+            .unwrap();
+        assert_eq!(statements.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("dead_letters.db").display());
+        let database = Database::new(&url).await.unwrap();
+        database.execute(&DeadLetter::create_table_sql(Dialect::Sqlite)).await.unwrap();
+        for sql in handler.dubhe_config.read().unwrap().create_tables_sql() {
+            database.execute(&sql).await.unwrap();
+        }
+        for statement in &statements {
+            database.execute(&statement.sql).await.unwrap();
+        }
+
+        let dead_letters = database
+            .query("SELECT event_name, payload, digest FROM dubhe_dead_letters")
+            .await
+            .unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0]["event_name"], "Dubhe_Store_SetRecord");
+        assert_eq!(dead_letters[0]["payload"], "0xff");
+        assert_eq!(dead_letters[0]["digest"], "digest");
+        let rows = database.query("SELECT id FROM store_counter").await.unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_event_halts_indexing() {
+        let handler = handler(DeadLetterPolicy::Halt);
+        let mut statements = Vec::new();
+        assert!(handler
+            .process_event(&event(vec![0xff]), 7, "digest", &mut statements)
+            .is_err());
+        assert!(statements.is_empty());
+        handler
+            .process_event(&set_counter(), 7, "digest", &mut statements)
+            .unwrap();
+        assert!(statements[0].dead_letter.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auto_created_table_takes_later_events() {
+        let handler = handler_for_unknown_tables(DeadLetterPolicy::Halt, "auto_create");
+        let key_tuple = vec![bcs::to_bytes(&7u64).unwrap()];
+        let set_record = event(
+            bcs::to_bytes(&StoreSetRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "player".to_string(),
+                key_tuple: key_tuple.clone(),
+                value_tuple: vec![bcs::to_bytes(&"alice".to_string()).unwrap()],
+            })
+            .unwrap(),
+        );
+        let set_field = named_event(
+            "Dubhe_Store_SetField",
+            bcs::to_bytes(&StoreSetField {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "player".to_string(),
+                key_tuple: key_tuple.clone(),
+                field_index: 0,
+                value: bcs::to_bytes(&"bob".to_string()).unwrap(),
+            })
+            .unwrap(),
+        );
+        let delete_record = named_event(
+            "Dubhe_Store_DeleteRecord",
+            bcs::to_bytes(&StoreDeleteRecord {
+                dapp_key: "1::dapp_key::DappKey".to_string(),
+                table_id: "player".to_string(),
+                key_tuple,
+            })
+            .unwrap(),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("auto_create.db").display());
+        let database = Database::new(&url).await.unwrap();
+        let mut statements = Vec::new();
+        handler
+            .process_event(&set_record, 7, "tx0", &mut statements)
+            .unwrap();
+        // The table is created once, with the first event
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].sql.starts_with("CREATE TABLE"));
+        handler
+            .process_event(&set_field, 8, "tx1", &mut statements)
+            .unwrap();
+        handler
+            .process_event(&delete_record, 9, "tx2", &mut statements)
+            .unwrap();
+        assert_eq!(statements.len(), 4);
+        assert!(!statements[1..].iter().any(|s| s.sql.starts_with("CREATE TABLE")));
+        for statement in &statements {
+            database.execute(&statement.sql).await.unwrap();
+        }
+
+        let rows = database
+            .query("SELECT value_0, is_deleted, last_update_digest FROM store_player")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["value_0"], "0x03626f62");
+        assert_eq!(rows[0]["is_deleted"], true);
+        assert_eq!(rows[0]["last_update_digest"], "tx2");
+    }
+}
@@ -13,30 +13,58 @@ pub mod args;
 pub mod config;
 pub mod handlers;
 pub mod proxy;
+pub mod reload;
+pub mod schema;
+pub mod status;
+pub mod throttle;
 pub mod worker;
 
 // 重新导出常用类型
 pub use args::DubheIndexerArgs;
 pub use config::DubheConfig;
 pub use handlers::DubheEventHandler;
-pub use proxy::ProxyServer;
+pub use proxy::{BackendAddr, BackendTransport, ProxyServer};
+pub use reload::{RuntimeConfig, RuntimeSettings};
+pub use status::IndexerStatus;
 pub use worker::{DubheIndexerWorker, GrpcSubscribers};
 pub use dubhe_common::StoreSetRecord;
 use anyhow::Result;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, RwLock};
 pub use dubhe_indexer_graphql::TableChange;
 pub use dubhe_indexer_grpc::types::TableChange as GrpcTableChange;
-use dubhe_common::{Database, DubheConfig as DubheConfigCommon};
+use dubhe_common::{Database, DubheConfig as DubheConfigCommon, SqlWriteBuffer};
 use rand::Rng;
 use std::net::{SocketAddr, TcpListener};
 use url::Url;
 use sui_indexer_alt_framework::IndexerArgs as FrameworkIndexerArgs;
+use sui_indexer_alt_framework::pipeline::{sequential::SequentialConfig, CommitterConfig};
 
 /// 订阅者类型别名
 pub type GraphQLSubscribers = Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>;
 
+/// 解析配置中的 start_checkpoint，若已知链上最新 checkpoint，则拒绝超出该值的配置
+pub fn parse_start_checkpoint(start_checkpoint: &str, chain_tip: Option<u64>) -> Result<u64> {
+    let checkpoint = start_checkpoint.trim().parse::<u64>().map_err(|_| {
+        anyhow::anyhow!(
+            "start_checkpoint must be a non-negative integer, got '{}'",
+            start_checkpoint
+        )
+    })?;
+    if let Some(tip) = chain_tip {
+        if checkpoint > tip {
+            return Err(anyhow::anyhow!(
+                "start_checkpoint {} is beyond the latest chain checkpoint {}",
+                checkpoint,
+                tip
+            ));
+        }
+    }
+    Ok(checkpoint)
+}
+
 /// Indexer 核心功能结构体
 pub struct DubheIndexer {
     pub grpc_subscribers: GrpcSubscribers,
@@ -77,6 +105,10 @@ pub struct IndexerBuilder {
     graphql_subscribers: GraphQLSubscribers,
     config_json: Option<serde_json::Value>,
     dubhe_config: Option<DubheConfigCommon>,
+    status: Arc<IndexerStatus>,
+    runtime_config: Arc<RuntimeConfig>,
+    log_filter: Option<reload::LogFilterHandle>,
+    sql_writer: std::sync::Mutex<Option<(SqlWriteBuffer, tokio::task::JoinHandle<()>)>>,
 }
 
 impl IndexerBuilder {
@@ -89,9 +121,19 @@ impl IndexerBuilder {
             graphql_subscribers: Arc::new(RwLock::new(HashMap::new())),
             config_json: None,
             dubhe_config: None,
+            status: Arc::new(IndexerStatus::new()),
+            runtime_config: Arc::new(RuntimeConfig::default()),
+            log_filter: None,
+            sql_writer: std::sync::Mutex::new(None),
         }
     }
 
+    /// 由运行时配置控制已安装日志的级别
+    pub fn with_log_filter(mut self, log_filter: reload::LogFilterHandle) -> Self {
+        self.log_filter = Some(log_filter);
+        self
+    }
+
     /// 使用现有的订阅者
     pub fn with_subscribers(
         mut self,
@@ -108,6 +150,7 @@ impl IndexerBuilder {
         // 加载配置
         let config_json = self.args.get_config_json()?;
         let dubhe_config = DubheConfigCommon::from_json(config_json.clone())?;
+        log::info!("🔐 Config hash: {}", dubhe_config.config_hash());
 
         // 创建数据库连接
         let database = Arc::new(Database::new(&self.args.database_url).await?);
@@ -117,6 +160,18 @@ impl IndexerBuilder {
             database.clear().await?;
         }
 
+        // 加载可热更新的运行时配置，收到 SIGHUP 时重新加载
+        let mut runtime_config = RuntimeConfig::load(&self.args.config)?;
+        if let Some(log_filter) = self.log_filter.clone() {
+            runtime_config = runtime_config.with_log_filter(log_filter)?;
+        }
+        let runtime_config = Arc::new(runtime_config);
+        #[cfg(unix)]
+        runtime_config.watch_sighup()?;
+
+        self.status
+            .set_config_loaded(parse_start_checkpoint(&dubhe_config.start_checkpoint, None)?);
+        self.runtime_config = runtime_config;
         self.config_json = Some(config_json);
         self.dubhe_config = Some(dubhe_config);
         self.database = Some(database);
@@ -140,9 +195,18 @@ impl IndexerBuilder {
         };
 
         let mut cluster = if !database.is_empty().await? {
+            // 链上最新 checkpoint 仅用于校验，获取失败时跳过上限检查
+            let chain_tip = match self.args.get_sui_client().await {
+                Ok(client) => client.read_api().get_latest_checkpoint_sequence_number().await.ok(),
+                Err(_) => None,
+            };
+            let first_checkpoint = parse_start_checkpoint(&dubhe_config.start_checkpoint, chain_tip)?;
             database.create_tables(dubhe_config).await?;
+            // 表结构已变化，清除 GraphQL 的表缓存
+            dubhe_indexer_graphql::database::invalidate_table_cache(&self.args.database_url)
+                .await;
             let indexer_args = FrameworkIndexerArgs {
-                first_checkpoint: Some(dubhe_config.start_checkpoint.parse::<u64>().unwrap()),
+                first_checkpoint: Some(first_checkpoint),
                 ..Default::default()
             };
             println!("🔄 Starting from first checkpoint: {}", dubhe_config.start_checkpoint);
@@ -166,19 +230,60 @@ impl IndexerBuilder {
             dubhe_config.clone(),
             self.grpc_subscribers.clone(),
             self.graphql_subscribers.clone(),
-        );
+        )
+        .with_status(self.status.clone())
+        .with_runtime_config(self.runtime_config.clone())
+        .with_dead_letter_policy(self.args.dead_letter_policy)
+        .with_dialect(database.dialect());
+
+        // 数据库写入与 checkpoint 处理解耦，写入变慢时先填满缓冲区；
+        // 多个 checkpoint 合并到一个事务提交
+        let commit_batch_size = self.args.commit_batch_size.max(1);
+        if self.args.sql_buffer_size > 0 || commit_batch_size > 1 {
+            let capacity = self.args.sql_buffer_size.max(commit_batch_size);
+            let (buffer, writer) = SqlWriteBuffer::new(capacity);
+            let writer = writer.with_commit_batch(commit_batch_size);
+            handlers::install_sql_write_buffer(buffer.clone())?;
+            let database = database.clone();
+            let handle = tokio::spawn(async move {
+                if let Err(e) = writer.run(database).await {
+                    log::error!("❌ SQL writer stopped: {}", e);
+                }
+            });
+            *self.sql_writer.lock().unwrap() = Some((buffer, handle));
+            log::info!(
+                "🧺 SQL write buffer holds {} batches, committing {} checkpoint(s) at a time",
+                capacity,
+                commit_batch_size
+            );
+        }
 
-        // 注册 pipeline
+        // 注册 pipeline，--commit-flush-ms 控制每次提交前收集 checkpoint 的时间
+        let mut pipeline_config = SequentialConfig::default();
+        if let Some(commit_flush_ms) = self.args.commit_flush_ms {
+            pipeline_config.committer = CommitterConfig {
+                collect_interval_ms: commit_flush_ms,
+                ..pipeline_config.committer
+            };
+        }
         cluster
-            .sequential_pipeline(
-                dubhe_event_handler,
-                Default::default(),
-            )
+            .sequential_pipeline(dubhe_event_handler, pipeline_config)
             .await?;
 
         Ok(cluster)
     }
 
+    /// 关闭时提交 SQL writer 中尚未写入的批次
+    pub async fn flush_sql_writer(&self) -> Result<()> {
+        let Some((buffer, handle)) = self.sql_writer.lock().unwrap().take() else {
+            return Ok(());
+        };
+        log::info!("🧺 Flushing {} buffered batches...", buffer.len());
+        buffer.close();
+        handle.await?;
+        Ok(())
+    }
+
     /// 创建 ProxyServer
     pub async fn build_proxy_server(&self) -> Result<ProxyServer> {
         let config_json = self.config_json.as_ref()
@@ -204,14 +309,31 @@ impl IndexerBuilder {
         let server_addr = format!("0.0.0.0:{}", self.args.port)
             .parse::<SocketAddr>()?;
 
-        Ok(ProxyServer::new(
+        let proxy_server = ProxyServer::new(
             server_addr,
             Some(grpc_backend_addr),
             Some(graphql_backend_addr),
             self.grpc_subscribers.clone(),
             self.graphql_subscribers.clone(),
             Arc::new(config_json.clone()),
-        ))
+        )
+        .with_indexer_status(self.status.clone())
+        .with_runtime_config(self.runtime_config.clone())
+        .with_database_url(self.args.database_url.clone())
+        .with_poll_max_wait(Duration::from_millis(self.args.poll_max_wait_ms))
+        .with_heartbeat_interval(Duration::from_millis(self.args.heartbeat_interval_ms));
+
+        // 使用 Unix socket 连接后端服务
+        match &self.args.backend_socket_dir {
+            #[cfg(unix)]
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                Ok(proxy_server.with_backend_transport(BackendTransport::Unix(dir.clone())))
+            }
+            #[cfg(not(unix))]
+            Some(_) => Err(anyhow::anyhow!("--backend-socket-dir requires a Unix platform")),
+            None => Ok(proxy_server),
+        }
     }
 
     /// 打印启动信息
@@ -225,7 +347,13 @@ impl IndexerBuilder {
         println!("🏠 Welcome Page:     http://0.0.0.0:{}/welcome", self.args.port);
         println!("🎮 Playground:       http://0.0.0.0:{}/playground", self.args.port);
         println!("💚 Health Check:     http://0.0.0.0:{}/health", self.args.port);
+        println!("   Liveness:         http://0.0.0.0:{}/livez", self.args.port);
+        println!("   Readiness:        http://0.0.0.0:{}/readyz", self.args.port);
         println!("📋 Metadata:         http://0.0.0.0:{}/metadata", self.args.port);
+        println!("🏷️  Version:          http://0.0.0.0:{}/version", self.args.port);
+        if let Some(dubhe_config) = &self.dubhe_config {
+            println!("🔐 Config Hash:      {}", dubhe_config.config_hash());
+        }
         println!("\n💡 For gRPC clients, use: http://localhost:{}", grpc_port);
     }
 
@@ -239,6 +367,16 @@ impl IndexerBuilder {
         self.dubhe_config.clone()
     }
 
+    /// 获取 Indexer 进度，用于 readiness 检查
+    pub fn indexer_status(&self) -> Arc<IndexerStatus> {
+        self.status.clone()
+    }
+
+    /// 获取运行时配置
+    pub fn runtime_config(&self) -> Arc<RuntimeConfig> {
+        self.runtime_config.clone()
+    }
+
     /// 获取配置 JSON
     pub fn config_json(&self) -> Option<serde_json::Value> {
         self.config_json.clone()
@@ -255,3 +393,21 @@ impl IndexerBuilder {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_start_checkpoint() {
+        assert_eq!(parse_start_checkpoint("0", None).unwrap(), 0);
+        assert_eq!(parse_start_checkpoint("1024", Some(2048)).unwrap(), 1024);
+
+        let err = parse_start_checkpoint("abc", None).unwrap_err();
+        assert_eq!(err.to_string(), "start_checkpoint must be a non-negative integer, got 'abc'");
+        assert!(parse_start_checkpoint("", None).is_err());
+        assert!(parse_start_checkpoint("-1", None).is_err());
+
+        let err = parse_start_checkpoint("4096", Some(2048)).unwrap_err();
+        assert!(err.to_string().contains("beyond the latest chain checkpoint 2048"));
+    }
+}
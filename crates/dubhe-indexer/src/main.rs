@@ -3,15 +3,22 @@
 
 use anyhow::Result;
 use clap::Parser;
+use dubhe_indexer::args::Command;
 use dubhe_indexer::{DubheIndexerArgs, IndexerBuilder};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // 解析命令行参数
     let args = DubheIndexerArgs::parse();
+    if let Some(Command::PrintSchema(print_schema)) = &args.command {
+        return print_schema.run(&mut std::io::stdout());
+    }
+
+    // 初始化日志，日志级别可通过 SIGHUP 热更新
+    let log_filter = dubhe_indexer::reload::init_logging()?;
 
     // 创建 IndexerBuilder 并初始化
-    let mut builder = IndexerBuilder::new(args);
+    let mut builder = IndexerBuilder::new(args).with_log_filter(log_filter);
     builder.initialize().await?;
 
     // 构建 Cluster
@@ -49,7 +56,33 @@ async fn main() -> Result<()> {
                 Err(e) => log::error!("❌ Indexer executor task failed: {}", e),
             }
         }
+        _ = shutdown_signal() => log::info!("🛑 Shutdown signal received"),
     }
 
+    // 提交缓冲区中剩余的批次
+    builder.flush_sql_writer().await?;
+
     Ok(())
 }
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                log::warn!("⚠️ Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
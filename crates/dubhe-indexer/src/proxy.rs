@@ -1,6 +1,8 @@
+use crate::reload::RuntimeConfig;
+use crate::status::IndexerStatus;
 use crate::worker::GrpcSubscribers;
 use anyhow::Result;
-use dubhe_common::Database;
+use dubhe_common::{Database, DubheConfig};
 use dubhe_indexer_graphql::TableChange;
 use http::header::{CONTENT_TYPE, USER_AGENT};
 use hyper::server::conn::AddrStream;
@@ -9,13 +11,46 @@ use hyper::{Body, Method, Request, Response, Server, StatusCode, Version};
 use serde_json::json;
 use std::collections::HashMap;
 use std::convert::Infallible;
+use std::fmt;
 use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex, RwLock};
 
 // Channel 路由处理函数类型
 pub type ChannelHandler = Arc<dyn Fn(Request<Body>) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Response<Body>, Infallible>> + Send>> + Send + Sync>;
 
+/// How the proxy reaches its gRPC and GraphQL backends
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BackendTransport {
+    /// Backends listen on localhost TCP ports
+    #[default]
+    Tcp,
+    /// Backends listen on `grpc.sock` / `graphql.sock` in this directory
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Address a backend service listens on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl fmt::Display for BackendAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendAddr::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            BackendAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
 /// Main Proxy Server following Torii architecture pattern
 /// Routes requests to independent GraphQL and gRPC services based on content type and path
 pub struct ProxyServer {
@@ -29,8 +64,30 @@ pub struct ProxyServer {
     config_json: Arc<serde_json::Value>,
     // Channel 特殊路由处理器
     channel_handlers: Arc<RwLock<HashMap<String, ChannelHandler>>>,
+    backend_transport: BackendTransport,
+    indexer_status: Arc<IndexerStatus>,
+    count_separator: char,
+    runtime_config: Arc<RuntimeConfig>,
+    /// Database the indexer writes to, queried by the GraphQL backend
+    database_url: String,
+    /// Longest time a `/poll` request waits for a change before answering 204
+    poll_max_wait: Duration,
+    /// Idle time after which subscriptions get a keepalive, zero disables them
+    heartbeat_interval: Duration,
 }
 
+/// How long the welcome page reuses row counts before querying the database again
+const TABLE_STATS_TTL: Duration = Duration::from_secs(5);
+
+/// How long a `/poll` request waits for a change unless configured
+pub const DEFAULT_POLL_MAX_WAIT: Duration = Duration::from_secs(25);
+
+/// How often a waiting `/poll` request checks the database
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Most rows a `/poll` response carries, also the default `limit`
+const MAX_POLL_ROWS: usize = 1000;
+
 impl ProxyServer {
     /// Create a new proxy server with separate backend service addresses
     pub fn new(
@@ -50,9 +107,70 @@ impl ProxyServer {
             grpc_subscribers,
             graphql_subscribers,
             shutdown_tx,
-            version: "1.2.0".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
             config_json,
             channel_handlers: Arc::new(RwLock::new(HashMap::new())),
+            backend_transport: BackendTransport::default(),
+            indexer_status: Arc::new(IndexerStatus::new()),
+            count_separator: ',',
+            runtime_config: Arc::new(RuntimeConfig::default()),
+            database_url: String::new(),
+            poll_max_wait: DEFAULT_POLL_MAX_WAIT,
+            heartbeat_interval: dubhe_indexer_grpc::grpc::DEFAULT_HEARTBEAT_INTERVAL,
+        }
+    }
+
+    /// Database the indexer writes to; required when the GraphQL backend is enabled
+    pub fn with_database_url(mut self, database_url: impl Into<String>) -> Self {
+        self.database_url = database_url.into();
+        self
+    }
+
+    /// Settings reloaded at runtime, such as the readiness timeout
+    pub fn with_runtime_config(mut self, runtime_config: Arc<RuntimeConfig>) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
+
+    /// Longest time a `GET /poll/{table}` request waits for a change before answering 204,
+    /// whatever `timeout_ms` the client asks for
+    pub fn with_poll_max_wait(mut self, max_wait: Duration) -> Self {
+        self.poll_max_wait = max_wait;
+        self
+    }
+
+    /// Keep idle gRPC subscriptions open behind proxies by sending a heartbeat after
+    /// `interval` without changes; also passed to the GraphQL backend. Zero disables it.
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Thousands separator of the row counts on the welcome page
+    pub fn with_count_separator(mut self, separator: char) -> Self {
+        self.count_separator = separator;
+        self
+    }
+
+    /// Indexer progress reported by `/readyz` and `/health`
+    pub fn with_indexer_status(mut self, status: Arc<IndexerStatus>) -> Self {
+        self.indexer_status = status;
+        self
+    }
+
+    /// Reach the backends over `transport` instead of localhost TCP
+    pub fn with_backend_transport(mut self, transport: BackendTransport) -> Self {
+        self.backend_transport = transport;
+        self
+    }
+
+    /// Address the named backend listens on under the configured transport
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    fn backend_addr(&self, tcp_addr: SocketAddr, name: &str) -> BackendAddr {
+        match &self.backend_transport {
+            BackendTransport::Tcp => BackendAddr::Tcp(tcp_addr),
+            #[cfg(unix)]
+            BackendTransport::Unix(dir) => BackendAddr::Unix(dir.join(format!("{}.sock", name))),
         }
     }
 
@@ -67,63 +185,56 @@ impl ProxyServer {
     pub async fn start(&self, database: Arc<Database>) -> Result<()> {
         log::info!("🚀 Starting Dubhe Proxy Server on {}", self.addr);
 
-        // Start independent gRPC service if address is provided
-        if let Some(grpc_addr) = self.grpc_addr {
-            let grpc_subscribers = self.grpc_subscribers.clone();
-            let shutdown_rx = self.shutdown_tx.subscribe();
-            let config_json = self.config_json.clone();
-
-            tokio::spawn(async move {
-                if let Err(e) =
-                    start_grpc_service(grpc_addr, grpc_subscribers, database, config_json, shutdown_rx).await
-                {
-                    log::error!("❌ gRPC service failed: {}", e);
-                }
-            });
-
-            log::info!("🔌 gRPC service starting on {}", grpc_addr);
+        if self.graphql_addr.is_some() && self.database_url.is_empty() {
+            return Err(anyhow::anyhow!(
+                "GraphQL service enabled without a database_url, it would not see indexed data"
+            ));
         }
 
-        // Start independent GraphQL service if address is provided
-        if let Some(graphql_addr) = self.graphql_addr {
-            let graphql_subscribers = self.graphql_subscribers.clone();
-            let shutdown_rx = self.shutdown_tx.subscribe();
-
-            tokio::spawn(async move {
-                if let Err(e) =
-                    start_graphql_service(graphql_addr, graphql_subscribers, shutdown_rx).await
-                {
-                    log::error!("❌ GraphQL service failed: {}", e);
-                }
-            });
-
-            log::info!("📊 GraphQL service starting on {}", graphql_addr);
-        }
+        // Only backends that confirmed they are listening are routed to
+        let (grpc_addr, graphql_addr) = self.start_backends(database.clone()).await;
 
         // Start the main proxy server
-        let grpc_addr = self.grpc_addr;
-        let graphql_addr = self.graphql_addr;
         let version = self.version.clone();
         let config_json = self.config_json.clone();
+        let config_hash = config_hash(&self.config_json);
         let channel_handlers = self.channel_handlers.clone();
+        let table_stats = TableStats::new(Some(database.clone()), &self.config_json)
+            .with_separator(self.count_separator);
+        let poller = ChangePoller::new(Some(database.clone()), &self.config_json)
+            .with_max_wait(self.poll_max_wait)
+            .with_runtime_config(self.runtime_config.clone());
+        let readiness = ReadinessProbe {
+            database: Some(database),
+            status: self.indexer_status.clone(),
+            runtime_config: self.runtime_config.clone(),
+        };
 
         let make_svc = make_service_fn(move |conn: &AddrStream| {
             let remote_addr = conn.remote_addr().ip();
-            let grpc_addr = grpc_addr;
-            let graphql_addr = graphql_addr;
+            let grpc_addr = grpc_addr.clone();
+            let graphql_addr = graphql_addr.clone();
             let version = version.clone();
             let config_json = config_json.clone();
+            let config_hash = config_hash.clone();
             let channel_handlers = channel_handlers.clone();
+            let readiness = readiness.clone();
+            let table_stats = table_stats.clone();
+            let poller = poller.clone();
 
             async move {
                 Ok::<_, Infallible>(service_fn(move |req| {
-                    let grpc_addr = grpc_addr;
-                    let graphql_addr = graphql_addr;
+                    let grpc_addr = grpc_addr.clone();
+                    let graphql_addr = graphql_addr.clone();
                     let version = version.clone();
                     let config_json = config_json.clone();
+                    let config_hash = config_hash.clone();
                     let channel_handlers = channel_handlers.clone();
+                    let readiness = readiness.clone();
+                    let table_stats = table_stats.clone();
+                    let poller = poller.clone();
                     async move {
-                        handle_request(remote_addr, req, grpc_addr, graphql_addr, version, config_json, channel_handlers).await
+                        handle_request(remote_addr, req, grpc_addr, graphql_addr, version, config_json, config_hash, channel_handlers, readiness, table_stats, poller).await
                     }
                 }))
             }
@@ -135,6 +246,85 @@ impl ProxyServer {
         server.await.map_err(anyhow::Error::from)
     }
 
+    /// Start the configured gRPC and GraphQL backends and wait until each reports whether it
+    /// bound its address. Returns the addresses of the backends that are up; a backend that
+    /// failed to start is treated as unconfigured instead of being proxied to a dead port.
+    async fn start_backends(
+        &self,
+        database: Arc<Database>,
+    ) -> (Option<BackendAddr>, Option<BackendAddr>) {
+        let mut grpc_ready = None;
+        if let Some(grpc_addr) = self.grpc_addr {
+            let grpc_addr = self.backend_addr(grpc_addr, "grpc");
+            let grpc_subscribers = self.grpc_subscribers.clone();
+            let shutdown_rx = self.shutdown_tx.subscribe();
+            let config_json = self.config_json.clone();
+            let heartbeat_interval = self.heartbeat_interval;
+            let (ready_tx, ready_rx) = oneshot::channel();
+
+            let backend_addr = grpc_addr.clone();
+            tokio::spawn(async move {
+                if let Err(e) = start_grpc_service(
+                    backend_addr,
+                    grpc_subscribers,
+                    database,
+                    config_json,
+                    heartbeat_interval,
+                    shutdown_rx,
+                    ready_tx,
+                )
+                .await
+                {
+                    log::error!("❌ gRPC service failed: {}", e);
+                }
+            });
+
+            log::info!("🔌 gRPC service starting on {}", grpc_addr);
+            grpc_ready = Some((grpc_addr, ready_rx));
+        }
+
+        let mut graphql_ready = None;
+        if let Some(graphql_addr) = self.graphql_addr {
+            let graphql_addr = self.backend_addr(graphql_addr, "graphql");
+            let graphql_subscribers = self.graphql_subscribers.clone();
+            let shutdown_rx = self.shutdown_tx.subscribe();
+            let config_json = self.config_json.clone();
+            let database_url = self.database_url.clone();
+            let heartbeat_interval = self.heartbeat_interval;
+            let (ready_tx, ready_rx) = oneshot::channel();
+
+            let backend_addr = graphql_addr.clone();
+            tokio::spawn(async move {
+                if let Err(e) = start_graphql_service(
+                    backend_addr,
+                    graphql_subscribers,
+                    config_json,
+                    database_url,
+                    heartbeat_interval,
+                    shutdown_rx,
+                    ready_tx,
+                )
+                .await
+                {
+                    log::error!("❌ GraphQL service failed: {}", e);
+                }
+            });
+
+            log::info!("📊 GraphQL service starting on {}", graphql_addr);
+            graphql_ready = Some((graphql_addr, ready_rx));
+        }
+
+        let grpc_addr = match grpc_ready {
+            Some((addr, ready_rx)) => wait_until_ready("gRPC", addr, ready_rx).await,
+            None => None,
+        };
+        let graphql_addr = match graphql_ready {
+            Some((addr, ready_rx)) => wait_until_ready("GraphQL", addr, ready_rx).await,
+            None => None,
+        };
+        (grpc_addr, graphql_addr)
+    }
+
     /// Graceful shutdown
     pub async fn shutdown(&self) -> Result<()> {
         log::info!("🛑 Shutting down Dubhe Proxy Server...");
@@ -143,15 +333,548 @@ impl ProxyServer {
     }
 }
 
+/// Successful answer of a channel route, sent as `{"success", "message", "data"}`
+#[derive(Debug, Clone)]
+pub struct ChannelReply {
+    pub success: bool,
+    pub message: String,
+    pub data: serde_json::Value,
+}
+
+impl ChannelReply {
+    pub fn ok(message: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            data,
+        }
+    }
+}
+
+/// Failure of a channel route, sent as `{"success": false, "message", "data"}` with `status`
+#[derive(Debug, Clone)]
+pub struct ChannelError {
+    pub status: StatusCode,
+    pub message: String,
+    pub data: serde_json::Value,
+}
+
+impl ChannelError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            data: serde_json::Value::Null,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+}
+
+type RouteFuture = std::pin::Pin<
+    Box<dyn std::future::Future<Output = Result<ChannelReply, ChannelError>> + Send>,
+>;
+type RouteHandler<S> = Arc<dyn Fn(S, Request<Body>) -> RouteFuture + Send + Sync>;
+
+/// Builds [`ChannelHandler`]s from route handlers that get a clone of the shared state and
+/// return a [`ChannelReply`] or [`ChannelError`]. The router answers CORS preflights and
+/// unsupported methods, and formats every reply as JSON.
+pub struct ChannelRouter<S> {
+    state: S,
+    /// Handlers by path, then by method
+    routes: Vec<(String, Vec<(Method, RouteHandler<S>)>)>,
+}
+
+impl<S: Clone + Send + Sync + 'static> ChannelRouter<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Handle `method` requests to paths starting with `path`
+    pub fn route<F, Fut>(mut self, path: &str, method: Method, handler: F) -> Self
+    where
+        F: Fn(S, Request<Body>) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<ChannelReply, ChannelError>> + Send + 'static,
+    {
+        let handler: RouteHandler<S> =
+            Arc::new(move |state: S, req: Request<Body>| -> RouteFuture {
+                Box::pin(handler(state, req))
+            });
+        match self.routes.iter_mut().find(|(route_path, _)| route_path == path) {
+            Some((_, methods)) => methods.push((method, handler)),
+            None => self.routes.push((path.to_string(), vec![(method, handler)])),
+        }
+        self
+    }
+
+    /// One handler per path, dispatching on the request method
+    pub fn handlers(&self) -> Vec<(String, ChannelHandler)> {
+        self.routes
+            .iter()
+            .map(|(path, methods)| {
+                let state = self.state.clone();
+                let methods = Arc::new(methods.clone());
+                let handler: ChannelHandler = Arc::new(move |req| {
+                    let state = state.clone();
+                    let methods = methods.clone();
+                    Box::pin(async move { Ok(dispatch_channel_route(state, &methods, req).await) })
+                });
+                (path.clone(), handler)
+            })
+            .collect()
+    }
+
+    /// Register every route on `proxy`
+    pub async fn register(self, proxy: &ProxyServer) {
+        for (path, handler) in self.handlers() {
+            proxy.register_channel_handler(path, handler).await;
+        }
+    }
+}
+
+async fn dispatch_channel_route<S>(
+    state: S,
+    methods: &[(Method, RouteHandler<S>)],
+    req: Request<Body>,
+) -> Response<Body> {
+    let allowed: Vec<&str> = methods.iter().map(|(method, _)| method.as_str()).collect();
+    if req.method() == Method::OPTIONS {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Access-Control-Allow-Methods", format!("{}, OPTIONS", allowed.join(", ")))
+            .header("Access-Control-Allow-Headers", "Content-Type, Authorization, X-Trace-Id")
+            .header("Access-Control-Max-Age", "3600")
+            .body(Body::empty())
+            .unwrap();
+    }
+    let handler = methods
+        .iter()
+        .find(|(method, _)| method == req.method())
+        .map(|(_, handler)| handler.clone());
+    let (status, body) = match handler {
+        Some(handler) => match handler(state, req).await {
+            Ok(reply) => (
+                StatusCode::OK,
+                json!({ "success": reply.success, "message": reply.message, "data": reply.data }),
+            ),
+            Err(error) => (
+                error.status,
+                json!({ "success": false, "message": error.message, "data": error.data }),
+            ),
+        },
+        None => (
+            StatusCode::METHOD_NOT_ALLOWED,
+            json!({
+                "success": false,
+                "message": format!("Method not allowed. Only {} is supported", allowed.join(", ")),
+                "data": null
+            }),
+        ),
+    };
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Wait for a backend's ready signal, returning its address only if it bound successfully
+async fn wait_until_ready(
+    name: &str,
+    addr: BackendAddr,
+    ready_rx: oneshot::Receiver<Result<()>>,
+) -> Option<BackendAddr> {
+    match ready_rx.await {
+        Ok(Ok(())) => {
+            log::info!("✅ {} service ready on {}", name, addr);
+            Some(addr)
+        }
+        Ok(Err(e)) => {
+            log::error!("❌ {} service unavailable on {}: {}", name, addr, e);
+            None
+        }
+        Err(_) => {
+            log::error!("❌ {} service on {} exited before becoming ready", name, addr);
+            None
+        }
+    }
+}
+
+/// State consulted by the readiness probe
+#[derive(Clone)]
+struct ReadinessProbe {
+    database: Option<Arc<Database>>,
+    status: Arc<IndexerStatus>,
+    runtime_config: Arc<RuntimeConfig>,
+}
+
+impl ReadinessProbe {
+    /// Ready once the database answers and the indexer has reached its start checkpoint
+    async fn check(&self) -> serde_json::Value {
+        let database_reachable = match &self.database {
+            Some(database) => matches!(
+                tokio::time::timeout(self.runtime_config.readiness_timeout(), database.ping())
+                    .await,
+                Ok(Ok(()))
+            ),
+            None => false,
+        };
+        json!({
+            "ready": database_reachable && self.status.caught_up(),
+            "database": { "reachable": database_reachable },
+            "indexer": self.status.to_json(),
+        })
+    }
+}
+
+/// A storage table listed on the welcome page
+#[derive(Debug, Clone)]
+struct WelcomeTable {
+    name: String,
+    store_table: String,
+    key_fields: Vec<String>,
+    value_fields: Vec<String>,
+}
+
+/// Row counts of the configured storage tables, cached for [`TABLE_STATS_TTL`]
+#[derive(Clone)]
+struct TableStats {
+    database: Option<Arc<Database>>,
+    tables: Arc<Vec<WelcomeTable>>,
+    separator: char,
+    /// Counts by storage table, `None` when the count query failed
+    cache: Arc<Mutex<Option<(Instant, HashMap<String, Option<u64>>)>>>,
+}
+
+impl TableStats {
+    fn new(database: Option<Arc<Database>>, config_json: &serde_json::Value) -> Self {
+        let tables = match DubheConfig::from_json(config_json.clone()) {
+            Ok(config) => config
+                .tables
+                .iter()
+                .filter(|table| !config.is_excluded_table(&table.name))
+                .map(|table| {
+                    let fields = config.fields.iter().filter(|field| field.table == table.name);
+                    WelcomeTable {
+                        name: table.name.clone(),
+                        store_table: config
+                            .store_table_name(Some(&config.original_package_id), &table.name),
+                        key_fields: config.field_names_by_table_and_primary_key(&table.name),
+                        value_fields: fields
+                            .filter(|field| !field.primary_key)
+                            .map(|field| field.column_name())
+                            .collect(),
+                    }
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        Self {
+            database,
+            tables: Arc::new(tables),
+            separator: ',',
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn with_separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Row count of every table, querying the database only when the cache has expired
+    async fn row_counts(&self) -> HashMap<String, Option<u64>> {
+        // Holding the lock while counting keeps concurrent page loads from all hitting the DB
+        let mut cache = self.cache.lock().await;
+        if let Some((fetched_at, counts)) = cache.as_ref() {
+            if fetched_at.elapsed() < TABLE_STATS_TTL {
+                return counts.clone();
+            }
+        }
+        let mut counts = HashMap::new();
+        for table in self.tables.iter() {
+            let count = match &self.database {
+                Some(database) => database.count_rows(&table.store_table, "").await.ok(),
+                None => None,
+            };
+            counts.insert(table.store_table.clone(), count);
+        }
+        *cache = Some((Instant::now(), counts.clone()));
+        counts
+    }
+
+    /// Welcome page section listing every table with its fields and row count
+    async fn render(&self) -> String {
+        if self.tables.is_empty() {
+            return "<p>No tables configured.</p>".to_string();
+        }
+        let counts = self.row_counts().await;
+        self.tables
+            .iter()
+            .map(|table| {
+                let rows = match counts.get(&table.store_table).copied().flatten() {
+                    Some(count) => format_count(count, self.separator),
+                    None => "—".to_string(),
+                };
+                format!(
+                    r#"
+        <div class="table-info">
+            <h3>📊 {}</h3>
+            <div class="fields">
+                <div><strong>Rows:</strong> {}</div>
+                <div><strong>Key Fields:</strong> {}</div>
+                <div><strong>Value Fields:</strong> {}</div>
+            </div>
+        </div>"#,
+                    table.name,
+                    rows,
+                    table.key_fields.join(", "),
+                    table.value_fields.join(", ")
+                )
+            })
+            .collect()
+    }
+}
+
+/// Format `count` with `separator` between groups of three digits
+fn format_count(count: u64, separator: char) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(separator);
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Long-poll fallback for clients whose network blocks the WebSocket and HTTP/2 streams
+/// used by GraphQL and gRPC subscriptions
+#[derive(Clone)]
+struct ChangePoller {
+    database: Option<Arc<Database>>,
+    /// Storage table of every configured table, by table name
+    store_tables: Arc<HashMap<String, String>>,
+    /// `push_min_interval_ms` of the throttled tables, by table name
+    push_min_intervals: Arc<HashMap<String, Duration>>,
+    /// Push intervals reloaded on SIGHUP override the configured ones
+    runtime_config: Arc<RuntimeConfig>,
+    max_wait: Duration,
+}
+
+impl ChangePoller {
+    fn new(database: Option<Arc<Database>>, config_json: &serde_json::Value) -> Self {
+        let mut store_tables = HashMap::new();
+        let mut push_min_intervals = HashMap::new();
+        if let Ok(config) = DubheConfig::from_json(config_json.clone()) {
+            for table in &config.tables {
+                if config.is_excluded_table(&table.name) {
+                    continue;
+                }
+                let store_table =
+                    config.store_table_name(Some(&config.original_package_id), &table.name);
+                store_tables.insert(table.name.clone(), store_table);
+                if let Some(interval) = config.push_min_interval(&table.name) {
+                    push_min_intervals.insert(table.name.clone(), interval);
+                }
+            }
+        }
+        Self {
+            database,
+            store_tables: Arc::new(store_tables),
+            push_min_intervals: Arc::new(push_min_intervals),
+            runtime_config: Arc::new(RuntimeConfig::default()),
+            max_wait: DEFAULT_POLL_MAX_WAIT,
+        }
+    }
+
+    fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    fn with_runtime_config(mut self, runtime_config: Arc<RuntimeConfig>) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
+
+    /// Serve `GET /poll/{table}?since_ts=N[&timeout_ms=M][&limit=L]`: the rows of `table`
+    /// updated after `since_ts`, oldest first, deleted rows included with `is_deleted` set.
+    /// Waits up to `timeout_ms` (capped at the configured maximum) for a change and answers
+    /// 204 if none came. At most `limit` rows are returned; `has_more` tells the client to
+    /// poll again right away with `since_ts` set to `next_since_ts`. For a table with a push
+    /// interval the answer comes one interval after the first change, with the latest state
+    /// of every row changed meanwhile, like the subscriber pushes.
+    async fn serve(&self, req: &Request<Body>) -> Response<Body> {
+        let json_response = |status: StatusCode, body: serde_json::Value| {
+            Response::builder()
+                .status(status)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let bad_request = |message: &str| {
+            json_response(
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "Bad Request", "message": message }),
+            )
+        };
+
+        let table = req.uri().path().trim_start_matches("/poll/");
+        let Some(store_table) = self.store_tables.get(table) else {
+            return json_response(
+                StatusCode::NOT_FOUND,
+                json!({ "error": "Not Found", "message": format!("Unknown table: {}", table) }),
+            );
+        };
+        let Some(database) = &self.database else {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                json!({ "error": "Service Unavailable", "message": "No database connected" }),
+            );
+        };
+
+        let params: HashMap<String, String> =
+            url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+                .into_owned()
+                .collect();
+        let since_ts = match params.get("since_ts").map(|value| value.parse::<u64>()) {
+            Some(Ok(since_ts)) => since_ts,
+            Some(Err(_)) => return bad_request("since_ts must be a timestamp in milliseconds"),
+            None => return bad_request("Missing since_ts"),
+        };
+        let wait = match params.get("timeout_ms").map(|value| value.parse::<u64>()) {
+            Some(Ok(timeout_ms)) => Duration::from_millis(timeout_ms).min(self.max_wait),
+            Some(Err(_)) => return bad_request("timeout_ms must be a number of milliseconds"),
+            None => self.max_wait,
+        };
+        let limit = match params.get("limit").map(|value| value.parse::<usize>()) {
+            Some(Ok(limit)) if limit > 0 => limit.min(MAX_POLL_ROWS),
+            Some(_) => return bad_request("limit must be a positive number of rows"),
+            None => MAX_POLL_ROWS,
+        };
+
+        // One row past the limit shows whether there are more
+        let sql = format!(
+            "SELECT * FROM {} WHERE updated_at_timestamp_ms > {} \
+             ORDER BY updated_at_timestamp_ms LIMIT {}",
+            store_table,
+            since_ts,
+            limit + 1
+        );
+        let configured = self.push_min_intervals.get(table).copied();
+        let mut throttle = self.runtime_config.push_min_interval(table, configured);
+        let deadline = Instant::now() + wait;
+        loop {
+            let rows = database.query(&sql).await;
+            if let (Ok(rows), Some(interval)) = (&rows, throttle) {
+                if !rows.is_empty() {
+                    throttle = None;
+                    tokio::time::sleep(interval).await;
+                    continue;
+                }
+            }
+            let page = match rows {
+                Ok(rows) if rows.is_empty() => None,
+                Ok(rows) => Some(page_rows(database, store_table, rows, limit).await),
+                Err(e) => Some(Err(e)),
+            };
+            match page {
+                Some(Ok((rows, has_more))) => {
+                    let next_since_ts = rows.last().map_or(since_ts, row_timestamp);
+                    return json_response(
+                        StatusCode::OK,
+                        json!({
+                            "table": table,
+                            "rows": rows,
+                            "next_since_ts": next_since_ts,
+                            "has_more": has_more,
+                        }),
+                    );
+                }
+                Some(Err(e)) => {
+                    log::error!("❌ Poll query on {} failed: {}", store_table, e);
+                    return json_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        json!({ "error": "Internal Server Error", "message": e.to_string() }),
+                    );
+                }
+                None => {}
+            }
+            if Instant::now() >= deadline {
+                return Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::empty())
+                    .unwrap();
+            }
+            tokio::time::sleep(POLL_INTERVAL.min(deadline - Instant::now())).await;
+        }
+    }
+}
+
+fn row_timestamp(row: &serde_json::Value) -> u64 {
+    row["updated_at_timestamp_ms"].as_u64().unwrap_or_default()
+}
+
+/// Cut `rows`, ordered by timestamp and at most `limit + 1` of them, to a page of at most
+/// `limit` rows, and tell whether more follow. A page never ends inside a group of rows
+/// sharing a timestamp, as the next poll starts after it; a group larger than `limit` is
+/// returned whole.
+async fn page_rows(
+    database: &Database,
+    store_table: &str,
+    mut rows: Vec<serde_json::Value>,
+    limit: usize,
+) -> Result<(Vec<serde_json::Value>, bool)> {
+    if rows.len() <= limit {
+        return Ok((rows, false));
+    }
+    let boundary = row_timestamp(&rows[limit]);
+    rows.truncate(limit);
+    if let Some(last) = rows.iter().rposition(|row| row_timestamp(row) != boundary) {
+        rows.truncate(last + 1);
+        return Ok((rows, true));
+    }
+    let rows = database
+        .query(&format!(
+            "SELECT * FROM {} WHERE updated_at_timestamp_ms = {}",
+            store_table, boundary
+        ))
+        .await?;
+    Ok((rows, true))
+}
+
 /// Core request handling and routing logic
+#[allow(clippy::too_many_arguments)]
 async fn handle_request(
     client_addr: IpAddr,
     req: Request<Body>,
-    grpc_addr: Option<SocketAddr>,
-    graphql_addr: Option<SocketAddr>,
+    grpc_addr: Option<BackendAddr>,
+    graphql_addr: Option<BackendAddr>,
     _version: String,
     config_json: Arc<serde_json::Value>,
+    config_hash: Option<String>,
     channel_handlers: Arc<RwLock<HashMap<String, ChannelHandler>>>,
+    readiness: ReadinessProbe,
+    table_stats: TableStats,
+    poller: ChangePoller,
 ) -> Result<Response<Body>, Infallible> {
     let path = req.uri().path();
     let method = req.method();
@@ -200,19 +923,50 @@ async fn handle_request(
         return Ok(serve_graphql_playground());
     }
 
+    // Liveness: the process is up and serving requests
+    if path == "/livez" {
+        return Ok(serve_liveness());
+    }
+
+    // Readiness: the database and the indexer can serve traffic
+    if path == "/readyz" {
+        return Ok(serve_readiness(readiness.check().await));
+    }
+
     // Handle health check
     if path.starts_with("/health") {
-        return Ok(serve_health_check(grpc_addr, graphql_addr));
+        return Ok(serve_health_check(
+            grpc_addr,
+            graphql_addr,
+            config_hash,
+            readiness.check().await,
+        ));
+    }
+
+    // Crate version and config hash, to compare replicas
+    if path == "/version" {
+        return Ok(serve_version(config_hash));
     }
 
     // Handle welcome page
     if path.starts_with("/welcome") {
-        return Ok(serve_welcome_page());
+        return Ok(serve_welcome_page(&table_stats).await);
     }
 
     // Handle metadata endpoint
     if path.starts_with("/metadata") {
-        return Ok(serve_metadata(config_json));
+        let db_type = readiness.database.as_ref().map(|database| database.db_type());
+        return Ok(serve_metadata(&config_json, db_type));
+    }
+
+    // Prometheus metrics, e.g. events skipped because of a config mismatch
+    if path == "/metrics" {
+        return Ok(serve_metrics());
+    }
+
+    // Plain HTTP/1.1 fallback for subscriptions
+    if path.starts_with("/poll/") && method == Method::GET {
+        return Ok(poller.serve(&req).await);
     }
 
     // Default 404 response
@@ -224,7 +978,7 @@ async fn handle_request(
             json!({
                 "error": "Not Found",
                 "message": format!("No handler for {} {}", method, path),
-                "available_endpoints": ["/", "/health", "/graphql", "/playground", "/metadata"]
+                "available_endpoints": ["/", "/health", "/livez", "/readyz", "/graphql", "/playground", "/version", "/metadata", "/metrics", "/poll/{table}"]
             })
             .to_string(),
         ))
@@ -300,7 +1054,7 @@ fn is_grpc_request(req: &Request<Body>) -> bool {
 /// Forward request to gRPC backend service
 async fn handle_grpc_request(
     req: Request<Body>,
-    grpc_addr: Option<SocketAddr>,
+    grpc_addr: Option<BackendAddr>,
 ) -> Result<Response<Body>, Infallible> {
     let Some(grpc_addr) = grpc_addr else {
         log::error!("❌ gRPC service not available");
@@ -313,23 +1067,13 @@ async fn handle_grpc_request(
             .unwrap());
     };
 
-    // Create HTTP client that supports both HTTP/1.1 (for gRPC-Web) and HTTP/2 (for native gRPC)
-    let client = hyper::Client::builder().build_http();
-
-    let grpc_url = format!("http://{}", grpc_addr);
-    let target_uri = format!(
-        "{}{}",
-        grpc_url,
-        req.uri().path_and_query().map(|x| x.as_str()).unwrap_or("")
-    );
-
-    match target_uri.parse::<hyper::Uri>() {
+    match backend_uri(&grpc_addr, &req) {
         Ok(parsed_uri) => {
             let (mut parts, body) = req.into_parts();
             parts.uri = parsed_uri;
             let forwarded_req = Request::from_parts(parts, body);
 
-            match client.request(forwarded_req).await {
+            match send_to_backend(&grpc_addr, forwarded_req).await {
                 Ok(response) => {
                     log::debug!("✅ gRPC request forwarded successfully");
                     Ok(response)
@@ -359,10 +1103,48 @@ async fn handle_grpc_request(
     }
 }
 
+/// Target URI of `req` on the backend. Unix socket backends ignore the authority.
+fn backend_uri(
+    backend: &BackendAddr,
+    req: &Request<Body>,
+) -> Result<hyper::Uri, http::uri::InvalidUri> {
+    let path_and_query = req.uri().path_and_query().map(|x| x.as_str()).unwrap_or("");
+    match backend {
+        BackendAddr::Tcp(addr) => format!("http://{}{}", addr, path_and_query).parse(),
+        #[cfg(unix)]
+        BackendAddr::Unix(_) => format!("http://localhost{}", path_and_query).parse(),
+    }
+}
+
+/// Send a request to a backend over its transport
+async fn send_to_backend(backend: &BackendAddr, req: Request<Body>) -> Result<Response<Body>> {
+    match backend {
+        BackendAddr::Tcp(_) => {
+            // Supports both HTTP/1.1 (for gRPC-Web) and HTTP/2 (for native gRPC)
+            let client = hyper::Client::builder().build_http();
+            Ok(client.request(req).await?)
+        }
+        #[cfg(unix)]
+        BackendAddr::Unix(path) => {
+            let stream = tokio::net::UnixStream::connect(path).await?;
+            let (mut sender, connection) = hyper::client::conn::Builder::new()
+                .http2_only(req.version() == Version::HTTP_2)
+                .handshake(stream)
+                .await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    log::debug!("🔌 Unix socket backend connection closed: {}", e);
+                }
+            });
+            Ok(sender.send_request(req).await?)
+        }
+    }
+}
+
 /// Forward request to GraphQL backend service
 async fn handle_graphql_request(
     req: Request<Body>,
-    graphql_addr: Option<SocketAddr>,
+    graphql_addr: Option<BackendAddr>,
 ) -> Result<Response<Body>, Infallible> {
     let Some(graphql_addr) = graphql_addr else {
         log::error!("❌ GraphQL service not available");
@@ -378,21 +1160,13 @@ async fn handle_graphql_request(
             .unwrap());
     };
 
-    let client = hyper::Client::new();
-    let graphql_url = format!("http://{}", graphql_addr);
-    let target_uri = format!(
-        "{}{}",
-        graphql_url,
-        req.uri().path_and_query().map(|x| x.as_str()).unwrap_or("")
-    );
-
-    match target_uri.parse::<hyper::Uri>() {
+    match backend_uri(&graphql_addr, &req) {
         Ok(parsed_uri) => {
             let (mut parts, body) = req.into_parts();
             parts.uri = parsed_uri;
             let forwarded_req = Request::from_parts(parts, body);
 
-            match client.request(forwarded_req).await {
+            match send_to_backend(&graphql_addr, forwarded_req).await {
                 Ok(response) => {
                     log::debug!("✅ GraphQL request forwarded successfully");
                     Ok(response)
@@ -471,26 +1245,53 @@ fn serve_graphql_playground() -> Response<Body> {
         .unwrap()
 }
 
-/// Serve health check endpoint
+/// Serve the liveness probe, which only needs the process to be running
+fn serve_liveness() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "status": "alive" }).to_string()))
+        .unwrap()
+}
+
+/// Serve the readiness probe: 200 when `readiness` reports ready, 503 otherwise
+fn serve_readiness(readiness: serde_json::Value) -> Response<Body> {
+    let status = if readiness["ready"] == true {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(readiness.to_string()))
+        .unwrap()
+}
+
+/// Serve health check endpoint, a summary of backends and readiness
 fn serve_health_check(
-    grpc_addr: Option<SocketAddr>,
-    graphql_addr: Option<SocketAddr>,
+    grpc_addr: Option<BackendAddr>,
+    graphql_addr: Option<BackendAddr>,
+    config_hash: Option<String>,
+    readiness: serde_json::Value,
 ) -> Response<Body> {
     let health_status = json!({
-        "status": "healthy",
+        "status": if readiness["ready"] == true { "healthy" } else { "degraded" },
         "service": "dubhe-indexer",
-        "version": "1.2.0",
+        "version": env!("CARGO_PKG_VERSION"),
+        "config_hash": config_hash,
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "services": {
             "grpc": {
                 "configured": grpc_addr.is_some(),
-                "address": grpc_addr.map(|a| a.to_string())
+                "address": grpc_addr.as_ref().map(|a| a.to_string())
             },
             "graphql": {
                 "configured": graphql_addr.is_some(),
-                "address": graphql_addr.map(|a| a.to_string())
+                "address": graphql_addr.as_ref().map(|a| a.to_string())
             }
-        }
+        },
+        "readiness": readiness
     });
 
     Response::builder()
@@ -500,6 +1301,29 @@ fn serve_health_check(
         .unwrap()
 }
 
+/// Hash of the loaded config, see [`DubheConfig::config_hash`]; `None` if it does not parse
+fn config_hash(config_json: &serde_json::Value) -> Option<String> {
+    DubheConfig::from_json(config_json.clone())
+        .ok()
+        .map(|config| config.config_hash())
+}
+
+/// Serve the crate version and config hash, so config drift across replicas shows up
+fn serve_version(config_hash: Option<String>) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "service": "dubhe-indexer",
+                "version": env!("CARGO_PKG_VERSION"),
+                "config_hash": config_hash,
+            })
+            .to_string(),
+        ))
+        .unwrap()
+}
+
 /// Serve service information at root endpoint
 fn serve_service_info(version: String) -> Response<Body> {
     let service_info = json!({
@@ -517,7 +1341,12 @@ fn serve_service_info(version: String) -> Response<Body> {
                 "endpoint": "/ (with Content-Type: application/grpc)",
                 "description": "gRPC API for real-time subscriptions and queries"
             },
-            "health": "/health"
+            "health": "/health",
+            "liveness": "/livez",
+            "readiness": "/readyz",
+            "version": "/version",
+            "metrics": "/metrics",
+            "poll": "/poll/{table}?since_ts=N"
         },
         "documentation": "https://github.com/0xobelisk/dubhe",
         "timestamp": chrono::Utc::now().to_rfc3339()
@@ -530,42 +1359,106 @@ fn serve_service_info(version: String) -> Response<Body> {
         .unwrap()
 }
 
+/// Discovery document for SDKs: the package, its tables and enums, and the database type
+fn metadata(config: &DubheConfig, db_type: Option<&str>) -> serde_json::Value {
+    let tables: Vec<_> = config
+        .tables
+        .iter()
+        .filter(|table| !config.is_excluded_table(&table.name))
+        .map(|table| {
+            let fields: Vec<_> = config
+                .fields
+                .iter()
+                .filter(|field| field.table == table.name)
+                .map(|field| {
+                    json!({
+                        "name": field.column_name(),
+                        "type": field.move_type,
+                        "primary_key": field.primary_key,
+                    })
+                })
+                .collect();
+            let keys: Vec<_> = config
+                .field_names_by_table_and_primary_key(&table.name)
+                .iter()
+                .map(|key| key.trim_matches('"').to_string())
+                .collect();
+            json!({
+                "name": table.name,
+                "type": if table.component { "component" } else { "resource" },
+                "offchain": table.offchain,
+                "keys": keys,
+                "fields": fields,
+            })
+        })
+        .collect();
+
+    // Variants in discriminant order
+    let mut enums: Vec<(&str, Vec<(u64, &str)>)> = Vec::new();
+    for variant in &config.enums {
+        let position = match enums.iter().position(|(name, _)| *name == variant.name) {
+            Some(position) => position,
+            None => {
+                enums.push((&variant.name, Vec::new()));
+                enums.len() - 1
+            }
+        };
+        enums[position].1.push((variant.index, &variant.value));
+    }
+    let enums: Vec<_> = enums
+        .into_iter()
+        .map(|(name, mut variants)| {
+            variants.sort();
+            let values: Vec<_> = variants.into_iter().map(|(_, value)| value).collect();
+            json!({ "name": name, "values": values })
+        })
+        .collect();
+
+    json!({
+        "package_id": config.original_package_id,
+        "dubhe_object_id": config.dubhe_object_id,
+        "start_checkpoint": config.start_checkpoint,
+        "database_type": db_type,
+        "tables": tables,
+        "enums": enums,
+    })
+}
+
 /// Serve metadata endpoint
-fn serve_metadata(config_json: Arc<serde_json::Value>) -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::OK)
-        .header(CONTENT_TYPE, "application/json")
-        .body(Body::from(config_json.to_string()))
-        .unwrap()
+fn serve_metadata(config_json: &serde_json::Value, db_type: Option<&str>) -> Response<Body> {
+    match DubheConfig::from_json(config_json.clone()) {
+        Ok(config) => Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(metadata(&config, db_type).to_string()))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "error": e.to_string() }).to_string()))
+            .unwrap(),
+    }
+}
+
+/// Serve the metrics of the default prometheus registry in the text format
+fn serve_metrics() -> Response<Body> {
+    match dubhe_common::encode_metrics() {
+        Ok(metrics) => Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(metrics))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "error": e.to_string() }).to_string()))
+            .unwrap(),
+    }
 }
 
 /// Serve welcome page
-fn serve_welcome_page() -> Response<Body> {
-    // Generate a simplified welcome page without database connection
-    // In a real implementation, this would fetch table info from GraphQL service
-    let table_list = r#"
-        <div class="table-info">
-            <h3>📊 events</h3>
-            <div class="fields">
-                <div><strong>Key Fields:</strong> id, checkpoint</div>
-                <div><strong>Value Fields:</strong> transaction_digest, event_sequence, event_type, event_data</div>
-            </div>
-        </div>
-        <div class="table-info">
-            <h3>📊 checkpoints</h3>
-            <div class="fields">
-                <div><strong>Key Fields:</strong> sequence_number</div>
-                <div><strong>Value Fields:</strong> digest, timestamp_ms, network_total_transactions, previous_digest</div>
-            </div>
-        </div>
-        <div class="table-info">
-            <h3>📊 transactions</h3>
-            <div class="fields">
-                <div><strong>Key Fields:</strong> digest</div>
-                <div><strong>Value Fields:</strong> checkpoint_sequence_number, timestamp_ms, sender, gas_used, gas_budget</div>
-            </div>
-        </div>
-    "#;
+async fn serve_welcome_page(table_stats: &TableStats) -> Response<Body> {
+    let table_list = table_stats.render().await;
 
     let welcome_html = format!(
         r#"
@@ -674,7 +1567,7 @@ fn serve_welcome_page() -> Response<Body> {
                 <div class="container">
                     <h1>🚀 Dubhe Indexer API Gateway</h1>
                     <p class="subtitle">Unified GraphQL and gRPC API Gateway for Sui Indexer</p>
-                    <p class="status">● Server Status: Running Normally | Available <span class="highlight">3</span> core tables</p>
+                    <p class="status">● Server Status: Running Normally | Available <span class="highlight">{}</span> tables</p>
                     
                     <div class="center">
                         <a href="/graphql" class="link">📊 GraphQL API</a>
@@ -726,6 +1619,7 @@ fn serve_welcome_page() -> Response<Body> {
             </body>
         </html>
     "#,
+        table_stats.tables.len(),
         table_list
     );
 
@@ -738,71 +1632,142 @@ fn serve_welcome_page() -> Response<Body> {
 
 /// Start independent gRPC service
 async fn start_grpc_service(
-    addr: SocketAddr,
+    addr: BackendAddr,
     subscribers: GrpcSubscribers,
     database: Arc<Database>,
     config_json: Arc<serde_json::Value>,
+    heartbeat_interval: Duration,
     mut shutdown_rx: broadcast::Receiver<()>,
+    ready_tx: oneshot::Sender<Result<()>>,
 ) -> Result<()> {
-    use dubhe_indexer_grpc::grpc::DubheGrpcService;
+    use dubhe_indexer_grpc::grpc::{
+        DubheGrpcService, SubscriptionDrain, SUBSCRIPTION_DRAIN_TIMEOUT,
+    };
     use dubhe_indexer_grpc::types::dubhe_grpc_server::DubheGrpcServer;
     use tonic::transport::Server;
     use dubhe_common::DubheConfig;
 
+    // Bind before reporting ready so a port conflict is seen by the proxy
+    let listener = match bind_backend(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            let _ = ready_tx.send(Err(anyhow::anyhow!("failed to bind {}: {}", addr, e)));
+            return Err(e.into());
+        }
+    };
+
     // Parse DubheConfig from JSON
-    let dubhe_config = Arc::new(DubheConfig::from_json(config_json.as_ref().clone())?);
+    let dubhe_config = match DubheConfig::from_json(config_json.as_ref().clone()) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            let _ = ready_tx.send(Err(anyhow::anyhow!("invalid config: {}", e)));
+            return Err(e);
+        }
+    };
 
-    let grpc_service = DubheGrpcService::new(subscribers, database, dubhe_config);
+    let drain = SubscriptionDrain::new();
+    let grpc_service = DubheGrpcService::new(subscribers, database, dubhe_config)
+        .with_subscription_drain(drain.clone())
+        .with_heartbeat_interval(heartbeat_interval);
     let grpc_server = DubheGrpcServer::new(grpc_service);
 
     log::info!(
         "🔌 gRPC service listening on {} (with gRPC-Web support)",
         addr
     );
+    let _ = ready_tx.send(Ok(()));
 
-    Server::builder()
+    let router = Server::builder()
         .accept_http1(true) // Enable HTTP/1.1 for gRPC-Web
-        .add_service(tonic_web::enable(grpc_server)) // Enable gRPC-Web
-        .serve_with_shutdown(addr, async {
-            shutdown_rx.recv().await.ok();
-            log::info!("🛑 gRPC service shutting down");
-        })
-        .await
-        .map_err(anyhow::Error::from)
+        .add_service(tonic_web::enable(grpc_server)); // Enable gRPC-Web
+    let shutdown = async move {
+        shutdown_rx.recv().await.ok();
+        log::info!("🛑 gRPC service shutting down");
+        // Let subscribers receive the changes already queued for them before closing
+        if !drain.drain(SUBSCRIPTION_DRAIN_TIMEOUT).await {
+            log::warn!(
+                "⚠️ Subscriptions still flushing after {:?}, closing them",
+                SUBSCRIPTION_DRAIN_TIMEOUT
+            );
+        }
+    };
+    match listener {
+        BackendListener::Tcp(listener) => {
+            router
+                .serve_with_incoming_shutdown(
+                    tokio_stream::wrappers::TcpListenerStream::new(listener),
+                    shutdown,
+                )
+                .await
+        }
+        #[cfg(unix)]
+        BackendListener::Unix(listener) => {
+            router
+                .serve_with_incoming_shutdown(
+                    tokio_stream::wrappers::UnixListenerStream::new(listener),
+                    shutdown,
+                )
+                .await
+        }
+    }
+    .map_err(anyhow::Error::from)
+}
+
+/// Listener bound for a backend service
+enum BackendListener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+async fn bind_backend(addr: &BackendAddr) -> std::io::Result<BackendListener> {
+    match addr {
+        BackendAddr::Tcp(addr) => Ok(BackendListener::Tcp(tokio::net::TcpListener::bind(addr).await?)),
+        #[cfg(unix)]
+        BackendAddr::Unix(path) => {
+            // A socket file left by a previous run would make bind fail
+            let _ = std::fs::remove_file(path);
+            Ok(BackendListener::Unix(tokio::net::UnixListener::bind(path)?))
+        }
+    }
 }
 
 /// Start independent GraphQL service  
 async fn start_graphql_service(
-    addr: SocketAddr,
+    addr: BackendAddr,
     subscribers: Arc<RwLock<HashMap<String, Vec<mpsc::UnboundedSender<TableChange>>>>>,
+    config_json: Arc<serde_json::Value>,
+    database_url: String,
+    heartbeat_interval: Duration,
     mut shutdown_rx: broadcast::Receiver<()>,
+    ready_tx: oneshot::Sender<Result<()>>,
 ) -> Result<()> {
-    use dubhe_indexer_graphql::{GraphQLConfig, GraphQLServerManager};
+    use dubhe_common::DubheConfig;
+    use dubhe_indexer_graphql::GraphQLServerManager;
 
     log::info!("📊 Starting independent GraphQL service on {}", addr);
 
-    // Create GraphQL configuration
-    let config = GraphQLConfig {
-        port: addr.port(),
-        database_url: "sqlite://data.db".to_string(), // This should come from actual config
-        schema: "public".to_string(),
-        endpoint: "/graphql".to_string(),
-        cors: true,
-        subscriptions: true,
-        env: "development".to_string(),
-        debug: true,
-        query_timeout: 30,
-        max_connections: 100,
-        heartbeat_interval: 10,
-        enable_metrics: false,
-        enable_live_queries: true,
-        enable_pg_subscriptions: false,
-        enable_native_websocket: true,
-        realtime_port: None,
-    };
+    // The GraphQL server binds internally, so probe the port first to catch a conflict
+    // before the proxy starts routing to it
+    match bind_backend(&addr).await {
+        Ok(probe) => drop(probe),
+        Err(e) => {
+            let _ = ready_tx.send(Err(anyhow::anyhow!("failed to bind {}: {}", addr, e)));
+            return Err(e.into());
+        }
+    }
+    let _ = ready_tx.send(Ok(()));
+
+    let config = graphql_config(&addr, database_url, heartbeat_interval);
 
     // Create and start GraphQL server manager
     let mut graphql_manager = GraphQLServerManager::new(config, subscribers);
+    match DubheConfig::from_json(config_json.as_ref().clone()) {
+        Ok(dubhe_config) => {
+            graphql_manager = graphql_manager.with_dubhe_config(Arc::new(dubhe_config));
+        }
+        Err(e) => log::warn!("⚠️ GraphQL tableSchema disabled, invalid config: {}", e),
+    }
 
     // Start GraphQL server in a separate task
     let graphql_handle = tokio::spawn(async move {
@@ -826,3 +1791,516 @@ async fn start_graphql_service(
 
     Ok(())
 }
+
+/// GraphQL backend settings; `database_url` must be the database the indexer writes to
+fn graphql_config(
+    addr: &BackendAddr,
+    database_url: String,
+    heartbeat_interval: Duration,
+) -> dubhe_indexer_graphql::GraphQLConfig {
+    use dubhe_indexer_graphql::GraphQLConfig;
+
+    let (port, unix_socket) = match addr {
+        BackendAddr::Tcp(addr) => (addr.port(), None),
+        #[cfg(unix)]
+        BackendAddr::Unix(path) => (0, Some(path.display().to_string())),
+    };
+    GraphQLConfig {
+        port,
+        database_url,
+        schema: "public".to_string(),
+        endpoint: "/graphql".to_string(),
+        cors: true,
+        subscriptions: true,
+        env: "development".to_string(),
+        debug: true,
+        query_timeout: 30,
+        max_connections: 100,
+        heartbeat_interval: heartbeat_interval.as_millis() as u64,
+        enable_metrics: false,
+        enable_live_queries: true,
+        enable_pg_subscriptions: false,
+        enable_native_websocket: true,
+        realtime_port: None,
+        unix_socket,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_graphql_uses_indexer_database_url() {
+        let database_url = "postgres://indexer@localhost/dubhe";
+        let addr = BackendAddr::Tcp("127.0.0.1:4000".parse().unwrap());
+        let config = graphql_config(&addr, database_url.to_string(), Duration::from_secs(15));
+        assert_eq!(config.database_url, database_url);
+        assert_eq!(config.port, 4000);
+
+        // Without the indexer's URL the proxy refuses to start the GraphQL backend
+        let proxy = ProxyServer::new(
+            "127.0.0.1:0".parse().unwrap(),
+            None,
+            Some("127.0.0.1:0".parse().unwrap()),
+            Arc::new(RwLock::new(HashMap::new())),
+            Arc::new(RwLock::new(HashMap::new())),
+            Arc::new(json!({})),
+        );
+        let database = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let err = proxy.start(database).await.unwrap_err();
+        assert!(err.to_string().contains("database_url"));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_unavailable_when_port_in_use() {
+        // Hold the port so the gRPC backend cannot bind it
+        let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let grpc_addr = occupied.local_addr().unwrap();
+
+        let proxy = ProxyServer::new(
+            "127.0.0.1:0".parse().unwrap(),
+            Some(grpc_addr),
+            None,
+            Arc::new(RwLock::new(HashMap::new())),
+            Arc::new(RwLock::new(HashMap::new())),
+            Arc::new(json!({})),
+        );
+        let database = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let (grpc, graphql) = proxy.start_backends(database).await;
+        assert_eq!(grpc, None);
+        assert_eq!(graphql, None);
+
+        let response = serve_health_check(grpc, graphql, None, json!({ "ready": false }));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["services"]["grpc"]["configured"], false);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_graphql_routed_over_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("graphql.sock");
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(hyper::server::conn::Http::new().serve_connection(
+                    stream,
+                    service_fn(|req: Request<Body>| async move {
+                        let body = format!("backend saw {}", req.uri().path());
+                        Ok::<_, Infallible>(Response::new(Body::from(body)))
+                    }),
+                ));
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/graphql")
+            .body(Body::from(r#"{"query":"{ __typename }"}"#))
+            .unwrap();
+        let response = handle_request(
+            "127.0.0.1".parse().unwrap(),
+            req,
+            None,
+            Some(BackendAddr::Unix(socket_path)),
+            "test".to_string(),
+            Arc::new(json!({})),
+            None,
+            Arc::new(RwLock::new(HashMap::new())),
+            ReadinessProbe {
+                database: None,
+                status: Arc::new(IndexerStatus::new()),
+                runtime_config: Arc::new(RuntimeConfig::default()),
+            },
+            TableStats::new(None, &json!({})),
+            ChangePoller::new(None, &json!({})),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "backend saw /graphql");
+    }
+
+    async fn probe(path: &str, readiness: ReadinessProbe) -> Response<Body> {
+        let req = Request::builder().uri(path).body(Body::empty()).unwrap();
+        handle_request(
+            "127.0.0.1".parse().unwrap(),
+            req,
+            None,
+            None,
+            "test".to_string(),
+            Arc::new(json!({})),
+            None,
+            Arc::new(RwLock::new(HashMap::new())),
+            readiness,
+            TableStats::new(None, &json!({})),
+            ChangePoller::new(None, &json!({})),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_liveness_and_readiness_probes() {
+        let status = Arc::new(IndexerStatus::new());
+        status.set_config_loaded(10);
+        status.record_checkpoint(12);
+        let database = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let readiness = ReadinessProbe {
+            database: Some(database.clone()),
+            status: status.clone(),
+            runtime_config: Arc::new(RuntimeConfig::default()),
+        };
+
+        assert_eq!(probe("/livez", readiness.clone()).await.status(), StatusCode::OK);
+        assert_eq!(probe("/readyz", readiness.clone()).await.status(), StatusCode::OK);
+
+        // With the database down the process stays live but stops taking traffic
+        database.close().await;
+        assert_eq!(probe("/livez", readiness.clone()).await.status(), StatusCode::OK);
+        let response = probe("/readyz", readiness.clone()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report["database"]["reachable"], false);
+        assert_eq!(report["indexer"]["caught_up"], true);
+
+        // /health stays 200 and summarizes the same state
+        let response = probe("/health", readiness).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["readiness"]["ready"], false);
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_before_start_checkpoint() {
+        let status = Arc::new(IndexerStatus::new());
+        let readiness = ReadinessProbe {
+            database: Some(Arc::new(Database::new("sqlite::memory:").await.unwrap())),
+            status: status.clone(),
+            runtime_config: Arc::new(RuntimeConfig::default()),
+        };
+        // Config not loaded yet
+        let response = probe("/readyz", readiness.clone()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        status.set_config_loaded(100);
+        status.record_checkpoint(99);
+        let response = probe("/readyz", readiness.clone()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        status.record_checkpoint(100);
+        assert_eq!(probe("/readyz", readiness).await.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0, ','), "0");
+        assert_eq!(format_count(999, ','), "999");
+        assert_eq!(format_count(1234, ','), "1,234");
+        assert_eq!(format_count(1234567, '.'), "1.234.567");
+    }
+
+    #[tokio::test]
+    async fn test_welcome_page_shows_row_counts() {
+        let config_json = json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false } },
+            { "missing": { "fields": [{ "id": "u64" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("welcome.db").display());
+        let database = Arc::new(Database::new(&url).await.unwrap());
+        let config = DubheConfig::from_json(config_json.clone()).unwrap();
+        // store_missing is configured but never created, so its count fails
+        database
+            .execute(&config.create_table_sql("counter").unwrap())
+            .await
+            .unwrap();
+        database
+            .execute(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1234) \
+                 INSERT INTO store_counter (id, value) SELECT i, 0 FROM n",
+            )
+            .await
+            .unwrap();
+
+        let table_stats = TableStats::new(Some(database.clone()), &config_json);
+        let body = |response: Response<Body>| async move {
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+        let html = body(serve_welcome_page(&table_stats).await).await;
+        assert!(html.contains("<h3>📊 counter</h3>"));
+        assert!(html.contains("<strong>Rows:</strong> 1,234"));
+        assert!(html.contains("<strong>Rows:</strong> —"));
+
+        // Counts are served from the cache until it expires
+        database.execute("DELETE FROM store_counter").await.unwrap();
+        let html = body(serve_welcome_page(&table_stats).await).await;
+        assert!(html.contains("<strong>Rows:</strong> 1,234"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_returns_rows_changed_since() {
+        let config_json = json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("poll.db").display());
+        let database = Arc::new(Database::new(&url).await.unwrap());
+        let config = DubheConfig::from_json(config_json.clone()).unwrap();
+        database
+            .execute(&config.create_table_sql("counter").unwrap())
+            .await
+            .unwrap();
+        database
+            .execute(
+                "INSERT INTO store_counter (id, value, updated_at_timestamp_ms) VALUES (1, 5, 100)",
+            )
+            .await
+            .unwrap();
+
+        let poller = ChangePoller::new(Some(database), &config_json)
+            .with_max_wait(Duration::from_millis(100));
+        let poll = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = poller.serve(&poll("/poll/counter?since_ts=50")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let changes: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(changes["rows"].as_array().unwrap().len(), 1);
+        assert_eq!(changes["rows"][0]["value"], 5);
+        assert_eq!(changes["next_since_ts"], 100);
+        assert_eq!(changes["has_more"], false);
+
+        // Nothing newer: the request waits at most the configured maximum
+        let started = Instant::now();
+        let response = poller.serve(&poll("/poll/counter?since_ts=100&timeout_ms=60000")).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let response = poller.serve(&poll("/poll/missing?since_ts=0")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = poller.serve(&poll("/poll/counter")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = poller.serve(&poll("/poll/counter?since_ts=0&limit=0")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_poll_pages_through_rows() {
+        let config_json = json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("poll.db").display());
+        let database = Arc::new(Database::new(&url).await.unwrap());
+        let config = DubheConfig::from_json(config_json.clone()).unwrap();
+        database
+            .execute(&config.create_table_sql("counter").unwrap())
+            .await
+            .unwrap();
+        // Rows 2 and 3 were written in the same checkpoint
+        for (id, ts) in [(1, 100), (2, 200), (3, 200), (4, 300)] {
+            database
+                .execute(&format!(
+                    "INSERT INTO store_counter (id, value, updated_at_timestamp_ms) \
+                     VALUES ({}, 0, {})",
+                    id, ts
+                ))
+                .await
+                .unwrap();
+        }
+
+        let poller = ChangePoller::new(Some(database), &config_json)
+            .with_max_wait(Duration::from_millis(100));
+        let poller = &poller;
+        let poll = |since_ts: u64, limit: usize| async move {
+            let uri = format!("/poll/counter?since_ts={}&limit={}", since_ts, limit);
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = poller.serve(&request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let changes: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let ids: Vec<u64> = changes["rows"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|row| row["id"].as_u64().unwrap())
+                .collect();
+            (ids, changes["next_since_ts"].as_u64().unwrap(), changes["has_more"] == true)
+        };
+
+        // The page stops before the rows sharing a timestamp rather than split them
+        assert_eq!(poll(0, 2).await, (vec![1], 100, true));
+        // A group larger than the limit comes whole
+        let (mut ids, next, has_more) = poll(100, 1).await;
+        ids.sort();
+        assert_eq!((ids, next, has_more), (vec![2, 3], 200, true));
+        assert_eq!(poll(200, 2).await, (vec![4], 300, false));
+    }
+
+    #[tokio::test]
+    async fn test_poll_coalesces_changes_of_throttled_tables() {
+        let config_json = json!({
+          "components": [
+            { "counter": { "fields": [{ "id": "u64" }, { "value": "u32" }], "keys": ["id"], "offchain": false, "push_min_interval_ms": 300 } }
+          ],
+          "resources": [],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}", dir.path().join("poll.db").display());
+        let database = Arc::new(Database::new(&url).await.unwrap());
+        let config = DubheConfig::from_json(config_json.clone()).unwrap();
+        database
+            .execute(&config.create_table_sql("counter").unwrap())
+            .await
+            .unwrap();
+        let set = |value: u32, ts: u64| {
+            format!(
+                "INSERT INTO store_counter (id, value, updated_at_timestamp_ms) VALUES (1, {}, {}) \
+                 ON CONFLICT (id) DO UPDATE SET value = {}, updated_at_timestamp_ms = {}",
+                value, ts, value, ts
+            )
+        };
+        database.execute(&set(1, 100)).await.unwrap();
+
+        let poller = ChangePoller::new(Some(database.clone()), &config_json)
+            .with_max_wait(Duration::from_secs(5));
+        let request = Request::builder().uri("/poll/counter?since_ts=0").body(Body::empty());
+        let started = Instant::now();
+        let response = tokio::spawn(async move { poller.serve(&request.unwrap()).await });
+        // A change within the interval replaces the first one in the same answer
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        database.execute(&set(2, 200)).await.unwrap();
+
+        let response = response.await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let changes: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(changes["rows"].as_array().unwrap().len(), 1);
+        assert_eq!(changes["rows"][0]["value"], 2);
+        assert_eq!(changes["next_since_ts"], 200);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_lists_configured_tables() {
+        let config_json = json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "x": "u64" }, { "y": "u64" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [
+            { "message": { "fields": [{ "text": "String" }], "keys": [], "offchain": true } }
+          ],
+          "enums": [{ "Direction": ["North", "East", "South"] }],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "7"
+        });
+
+        let response = serve_metadata(&config_json, Some("sqlite"));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let metadata: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(metadata["package_id"], "0x1");
+        assert_eq!(metadata["start_checkpoint"], "7");
+        assert_eq!(metadata["database_type"], "sqlite");
+
+        let tables = metadata["tables"].as_array().unwrap();
+        let mut names: Vec<_> = tables.iter().map(|table| table["name"].as_str().unwrap()).collect();
+        names.sort();
+        assert_eq!(names, vec!["message", "position"]);
+        let position = tables.iter().find(|table| table["name"] == "position").unwrap();
+        assert_eq!(position["type"], "component");
+        assert_eq!(position["offchain"], false);
+        assert_eq!(position["keys"], json!(["player"]));
+        assert_eq!(position["fields"][1], json!({ "name": "x", "type": "u64", "primary_key": false }));
+        let message = tables.iter().find(|table| table["name"] == "message").unwrap();
+        assert_eq!(message["type"], "resource");
+        assert_eq!(message["offchain"], true);
+
+        assert_eq!(
+            metadata["enums"],
+            json!([{ "name": "Direction", "values": ["North", "East", "South"] }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_channel_router_formats_replies() {
+        let router = ChannelRouter::new(Arc::new(std::sync::atomic::AtomicU32::new(0)))
+            .route("/counter", Method::POST, |counter, _req| async move {
+                let value = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                Ok(ChannelReply::ok("Incremented", json!({ "value": value })))
+            })
+            .route("/counter", Method::DELETE, |_counter, _req| async move {
+                Err(ChannelError::bad_request("Counter cannot be deleted")
+                    .with_data(json!({ "value": 1 })))
+            });
+        let handlers = router.handlers();
+        assert_eq!(handlers.len(), 1);
+        let (path, handler) = &handlers[0];
+        assert_eq!(path, "/counter");
+
+        let call = |method: Method| {
+            let req = Request::builder().method(method).uri("/counter").body(Body::empty()).unwrap();
+            async move {
+                let response = handler(req).await.unwrap();
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+
+        let (status, body) = call(Method::POST).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "success": true, "message": "Incremented", "data": { "value": 1 } }));
+
+        let (status, body) = call(Method::DELETE).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["success"], false);
+        assert_eq!(body["message"], "Counter cannot be deleted");
+        assert_eq!(body["data"]["value"], 1);
+
+        let (status, body) = call(Method::GET).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(body["message"], "Method not allowed. Only POST, DELETE is supported");
+
+        let (status, _) = call(Method::OPTIONS).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// How long `/readyz` waits for the database to answer unless configured
+pub const DEFAULT_READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Handle to the filter of the installed logger, see [`init_logging`]
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Install the logger: `RUST_LOG`, or `info`, until the config file sets a level. `log`
+/// records are forwarded to `tracing`. The returned handle lets [`RuntimeConfig`] change
+/// the filter while the indexer runs.
+pub fn init_logging() -> Result<LogFilterHandle> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("Failed to install the logger: {}", e))?;
+    Ok(handle)
+}
+
+/// Settings that are safe to change while the indexer runs. Schema, package and database
+/// settings are only read at startup and need a restart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeSettings {
+    /// `None` keeps the level the logger was started with
+    pub log_level: Option<log::LevelFilter>,
+    /// How long `/readyz` waits for the database to answer
+    pub readiness_timeout: Duration,
+    /// Subscriber push intervals by table, replacing the `push_min_interval_ms` of the
+    /// Dubhe config. Zero pushes every change.
+    pub push_min_intervals: BTreeMap<String, Duration>,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            log_level: None,
+            readiness_timeout: DEFAULT_READINESS_TIMEOUT,
+            push_min_intervals: BTreeMap::new(),
+        }
+    }
+}
+
+/// The reloadable subset of the `--config` file; every other section is ignored
+#[derive(Debug, Default, Deserialize)]
+struct RuntimeConfigFile {
+    #[serde(default)]
+    logging: LoggingSection,
+    #[serde(default)]
+    server: ServerSection,
+    #[serde(default)]
+    throttle: ThrottleSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LoggingSection {
+    level: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ServerSection {
+    readiness_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ThrottleSection {
+    #[serde(default)]
+    push_min_interval_ms: BTreeMap<String, u64>,
+}
+
+impl RuntimeSettings {
+    /// Read and validate the runtime settings of a `.toml`, `.yaml` or `.yml` config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e)
+        })?;
+        let file: RuntimeConfigFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("YAML parsing error: {}", e))?,
+            Some("toml") => toml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("TOML parsing error: {}", e))?,
+            _ => return Err(anyhow::anyhow!("Unsupported config file format")),
+        };
+
+        let mut settings = Self::default();
+        if let Some(level) = file.logging.level {
+            settings.log_level = Some(
+                log::LevelFilter::from_str(&level)
+                    .map_err(|_| anyhow::anyhow!("Invalid log level: {}", level))?,
+            );
+        }
+        if let Some(timeout_ms) = file.server.readiness_timeout_ms {
+            if timeout_ms == 0 {
+                return Err(anyhow::anyhow!("readiness_timeout_ms must be greater than 0"));
+            }
+            settings.readiness_timeout = Duration::from_millis(timeout_ms);
+        }
+        settings.push_min_intervals = file
+            .throttle
+            .push_min_interval_ms
+            .into_iter()
+            .map(|(table, interval_ms)| (table, Duration::from_millis(interval_ms)))
+            .collect();
+        Ok(settings)
+    }
+
+    /// Set the log level, both on the logger's filter and on the `log` facade, whose own
+    /// maximum would otherwise still drop records below the level the logger started with
+    fn apply(&self, log_filter: Option<&LogFilterHandle>) -> Result<()> {
+        let Some(level) = self.log_level else {
+            return Ok(());
+        };
+        if let Some(log_filter) = log_filter {
+            log_filter
+                .reload(EnvFilter::new(level.as_str().to_lowercase()))
+                .map_err(|e| anyhow::anyhow!("Failed to change the log level: {}", e))?;
+        }
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+/// Runtime settings shared by the running services, reloaded from the config file on SIGHUP
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    path: PathBuf,
+    settings: RwLock<RuntimeSettings>,
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl RuntimeConfig {
+    /// Load the settings from `path`, falling back to the defaults if the file does not exist
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let settings = if path.exists() {
+            RuntimeSettings::from_file(&path)?
+        } else {
+            log::info!(
+                "Config file {} not found, using default runtime settings",
+                path.display()
+            );
+            RuntimeSettings::default()
+        };
+        settings.apply(None)?;
+        Ok(Self {
+            path,
+            settings: RwLock::new(settings),
+            log_filter: None,
+        })
+    }
+
+    /// Change the level of the logger behind `log_filter` along with the settings
+    pub fn with_log_filter(mut self, log_filter: LogFilterHandle) -> Result<Self> {
+        self.settings().apply(Some(&log_filter))?;
+        self.log_filter = Some(log_filter);
+        Ok(self)
+    }
+
+    pub fn settings(&self) -> RuntimeSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn readiness_timeout(&self) -> Duration {
+        self.settings.read().unwrap().readiness_timeout
+    }
+
+    /// Subscriber push interval of `table_id`: the config file's `[throttle]` entry if it has
+    /// one, `configured` otherwise
+    pub fn push_min_interval(
+        &self,
+        table_id: &str,
+        configured: Option<Duration>,
+    ) -> Option<Duration> {
+        match self.settings.read().unwrap().push_min_intervals.get(table_id) {
+            Some(interval) if interval.is_zero() => None,
+            Some(interval) => Some(*interval),
+            None => configured,
+        }
+    }
+
+    /// Re-read the config file and apply it. An invalid file leaves the running settings
+    /// untouched.
+    pub fn reload(&self) -> Result<RuntimeSettings> {
+        let settings = RuntimeSettings::from_file(&self.path)?;
+        settings.apply(self.log_filter.as_ref())?;
+        *self.settings.write().unwrap() = settings.clone();
+        Ok(settings)
+    }
+
+    /// Reload the settings every time the process receives SIGHUP
+    #[cfg(unix)]
+    pub fn watch_sighup(self: &Arc<Self>) -> Result<tokio::task::JoinHandle<()>> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        let runtime = self.clone();
+        Ok(tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                match runtime.reload() {
+                    Ok(settings) => log::info!("🔄 Reloaded runtime settings: {:?}", settings),
+                    Err(e) => log::error!("❌ Keeping runtime settings, reload failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_reload_keeps_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[server]\nreadiness_timeout_ms = 1500\n").unwrap();
+        let runtime = RuntimeConfig::load(&path).unwrap();
+        assert_eq!(runtime.readiness_timeout(), Duration::from_millis(1500));
+
+        std::fs::write(&path, "[logging]\nlevel = \"loud\"\n").unwrap();
+        assert!(runtime.reload().is_err());
+        std::fs::write(&path, "[server]\nreadiness_timeout_ms = 0\n").unwrap();
+        assert!(runtime.reload().is_err());
+        assert_eq!(runtime.readiness_timeout(), Duration::from_millis(1500));
+
+        let runtime = RuntimeConfig::load(dir.path().join("missing.toml")).unwrap();
+        assert_eq!(runtime.settings(), RuntimeSettings::default());
+    }
+
+    #[test]
+    fn test_reload_applies_timeout_throttle_and_log_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "[sui]\nstart_checkpoint = 10\n\n[server]\nreadiness_timeout_ms = 2000\n",
+        )
+        .unwrap();
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let _subscriber = tracing_subscriber::registry().with(filter);
+        let runtime = RuntimeConfig::load(&path)
+            .unwrap()
+            .with_log_filter(handle.clone())
+            .unwrap();
+        let configured = Some(Duration::from_millis(100));
+        assert_eq!(runtime.push_min_interval("price_feed", configured), configured);
+
+        // Schema settings may change in the file too, they are simply not reloaded
+        std::fs::write(
+            &path,
+            "[sui]\nstart_checkpoint = 20\n\n[server]\nreadiness_timeout_ms = 500\n\n\
+             [logging]\nlevel = \"debug\"\n\n\
+             [throttle.push_min_interval_ms]\nprice_feed = 250\nposition = 0\n",
+        )
+        .unwrap();
+        runtime.reload().unwrap();
+        assert_eq!(runtime.readiness_timeout(), Duration::from_millis(500));
+        assert_eq!(
+            runtime.push_min_interval("price_feed", configured),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(runtime.push_min_interval("position", configured), None);
+        assert_eq!(runtime.push_min_interval("counter", configured), configured);
+        // The level goes up past the one the logger started with
+        assert_eq!(handle.clone_current().unwrap().to_string(), "debug");
+        assert_eq!(log::max_level(), log::LevelFilter::Debug);
+    }
+}
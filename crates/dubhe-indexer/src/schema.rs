@@ -0,0 +1,107 @@
+use anyhow::Result;
+use clap::Args;
+pub use dubhe_common::Dialect;
+use dubhe_common::{DeadLetter, DubheConfig};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Print the DDL of a config without connecting to a database
+#[derive(Args, Debug, Clone)]
+pub struct PrintSchemaArgs {
+    /// Configuration file path (.json, .toml, .yaml or .yml)
+    #[arg(long)]
+    pub config: PathBuf,
+    #[arg(long, value_enum, default_value_t = Dialect::Postgres)]
+    pub dialect: Dialect,
+}
+
+impl PrintSchemaArgs {
+    /// Load the config and write one statement per line to `out`
+    pub fn run(&self, out: &mut impl Write) -> Result<()> {
+        let config = DubheConfig::from_path(&self.config)?;
+        for sql in schema_statements(&config, self.dialect) {
+            writeln!(out, "{}", sql)?;
+        }
+        Ok(())
+    }
+}
+
+/// The statements the indexer runs to create its tables, starting with
+/// [`dubhe_common::DEAD_LETTERS_TABLE`], which keeps both failed events and failed submitted
+/// statements. SQLite has no array columns, so vector fields are declared as TEXT there.
+pub fn schema_statements(config: &DubheConfig, dialect: Dialect) -> Vec<String> {
+    let mut config = config.clone();
+    if dialect == Dialect::Sqlite {
+        for field in config.fields.iter_mut() {
+            if field.db_type.ends_with("[]") {
+                field.db_type = "TEXT".to_string();
+            }
+        }
+    }
+    let mut statements = vec![DeadLetter::create_table_sql(dialect)];
+    statements.extend(config.create_tables_sql());
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{Command, DubheIndexerArgs};
+    use clap::Parser;
+    use dubhe_common::DEAD_LETTERS_TABLE;
+    use serde_json::json;
+
+    #[test]
+    fn test_print_schema_creates_every_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dubhe.config.json");
+        let config = json!({
+          "components": [
+            { "position": { "fields": [{ "player": "address" }, { "path": "vector<u64>" }], "keys": ["player"], "offchain": false } }
+          ],
+          "resources": [
+            { "counter": { "fields": [{ "value": "u32" }], "keys": [], "offchain": false } }
+          ],
+          "enums": [],
+          "original_package_id": "0x1",
+          "dubhe_object_id": "0x2",
+          "original_dubhe_package_id": "0x3",
+          "start_checkpoint": "1"
+        });
+        std::fs::write(&path, config.to_string()).unwrap();
+
+        let args = DubheIndexerArgs::try_parse_from([
+            "dubhe-indexer",
+            "print-schema",
+            "--config",
+            path.to_str().unwrap(),
+            "--dialect",
+            "sqlite",
+        ])
+        .unwrap();
+        let Some(Command::PrintSchema(print_schema)) = args.command else {
+            panic!("expected the print-schema subcommand");
+        };
+        assert_eq!(print_schema.dialect, Dialect::Sqlite);
+
+        let mut out = Vec::new();
+        print_schema.run(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for table in [DEAD_LETTERS_TABLE, "store_position", "store_counter"] {
+            assert!(
+                out.contains(&format!("CREATE TABLE IF NOT EXISTS {} (", table)),
+                "{}",
+                out
+            );
+        }
+        assert!(!out.contains("[]"));
+
+        let postgres = PrintSchemaArgs {
+            config: path,
+            dialect: Dialect::Postgres,
+        };
+        let mut out = Vec::new();
+        postgres.run(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\"path\" BIGINT[]"));
+    }
+}
@@ -0,0 +1,69 @@
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Indexer progress shared between the event handler and the proxy's readiness probe
+#[derive(Debug)]
+pub struct IndexerStatus {
+    config_loaded: AtomicBool,
+    start_checkpoint: AtomicU64,
+    /// One past the highest processed checkpoint, 0 before the first one
+    next_checkpoint: AtomicU64,
+}
+
+impl IndexerStatus {
+    pub fn new() -> Self {
+        Self {
+            config_loaded: AtomicBool::new(false),
+            start_checkpoint: AtomicU64::new(0),
+            next_checkpoint: AtomicU64::new(0),
+        }
+    }
+
+    /// Mark the Dubhe config as loaded, indexing from `start_checkpoint`
+    pub fn set_config_loaded(&self, start_checkpoint: u64) {
+        self.start_checkpoint.store(start_checkpoint, Ordering::SeqCst);
+        self.config_loaded.store(true, Ordering::SeqCst);
+    }
+
+    /// Record that `checkpoint` has been processed
+    pub fn record_checkpoint(&self, checkpoint: u64) {
+        self.next_checkpoint
+            .fetch_max(checkpoint.saturating_add(1), Ordering::SeqCst);
+    }
+
+    pub fn config_loaded(&self) -> bool {
+        self.config_loaded.load(Ordering::SeqCst)
+    }
+
+    pub fn start_checkpoint(&self) -> u64 {
+        self.start_checkpoint.load(Ordering::SeqCst)
+    }
+
+    /// Highest checkpoint processed so far
+    pub fn last_checkpoint(&self) -> Option<u64> {
+        self.next_checkpoint.load(Ordering::SeqCst).checked_sub(1)
+    }
+
+    /// Whether the indexer has processed `start_checkpoint` or a later checkpoint
+    pub fn caught_up(&self) -> bool {
+        self.config_loaded()
+            && self
+                .last_checkpoint()
+                .is_some_and(|checkpoint| checkpoint >= self.start_checkpoint())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "config_loaded": self.config_loaded(),
+            "start_checkpoint": self.start_checkpoint(),
+            "last_checkpoint": self.last_checkpoint(),
+            "caught_up": self.caught_up(),
+        })
+    }
+}
+
+impl Default for IndexerStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,101 @@
+use crate::handlers::GrpcSubscribers;
+use dubhe_common::Debounce;
+use dubhe_indexer_grpc::change_archive;
+use dubhe_indexer_grpc::types::TableChange;
+use std::time::Duration;
+
+/// Pushes table changes to gRPC subscribers. Changes of throttled tables are coalesced per key:
+/// the first change of a key schedules a push after the table's interval, and every change
+/// arriving before it replaces the pending state, so subscribers only get the latest one.
+#[derive(Clone)]
+pub struct PushThrottle {
+    subscribers: GrpcSubscribers,
+    /// Latest unsent change by table and key tuple
+    pending: Debounce<(String, Vec<Vec<u8>>), TableChange>,
+}
+
+impl PushThrottle {
+    pub fn new(subscribers: GrpcSubscribers) -> Self {
+        Self {
+            subscribers,
+            pending: Debounce::new(),
+        }
+    }
+
+    /// Push `change` of the row identified by `key_tuple`, right away when `min_interval` is
+    /// `None` and at most once per `min_interval` otherwise
+    pub fn push(
+        &self,
+        key_tuple: Vec<Vec<u8>>,
+        change: TableChange,
+        min_interval: Option<Duration>,
+    ) {
+        let subscribers = self.subscribers.clone();
+        let Some(min_interval) = min_interval else {
+            tokio::spawn(send(subscribers, change));
+            return;
+        };
+        let key = (change.table_id.clone(), key_tuple);
+        self.pending
+            .push(key, change, min_interval, move |change| send(subscribers, change));
+    }
+}
+
+async fn send(subscribers: GrpcSubscribers, change: TableChange) {
+    let subscribers = subscribers.read().await;
+    change_archive().publish(&subscribers, change);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::value::Kind;
+    use prost_types::{Struct, Value};
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, RwLock};
+
+    fn price_change(price: f64) -> TableChange {
+        TableChange {
+            table_id: "price_feed".to_string(),
+            data: Some(Struct {
+                fields: BTreeMap::from([(
+                    "price".to_string(),
+                    Value {
+                        kind: Some(Kind::NumberValue(price)),
+                    },
+                )]),
+            }),
+            trace_id: String::new(),
+            cursor: 0,
+            end_of_stream: false,
+            heartbeat: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changes_within_interval_are_coalesced() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let subscribers: GrpcSubscribers = Arc::new(RwLock::new(HashMap::from([(
+            "price_feed".to_string(),
+            vec![sender],
+        )])));
+        let throttle = PushThrottle::new(subscribers);
+
+        let key_tuple = vec![vec![1u8]];
+        for price in 1..=10 {
+            throttle.push(
+                key_tuple.clone(),
+                price_change(price as f64),
+                Some(Duration::from_millis(200)),
+            );
+        }
+        assert!(receiver.try_recv().is_err());
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let change = receiver.try_recv().unwrap();
+        assert!(change.cursor > 0);
+        assert_eq!(TableChange { cursor: 0, ..change }, price_change(10.0));
+        assert!(receiver.try_recv().is_err());
+    }
+}
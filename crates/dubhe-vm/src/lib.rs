@@ -1,2 +1,4 @@
+pub mod object_json;
 pub mod ptb_runner;
-pub use ptb_runner::{execute_single_ptb, execute_single_ptb_with_store_set_record};
+pub use object_json::move_object_fields;
+pub use ptb_runner::{execute_ptb, execute_single_ptb, execute_single_ptb_with_store_set_record, PtbExecutionResult};
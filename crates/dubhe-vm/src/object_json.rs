@@ -0,0 +1,44 @@
+use anyhow::anyhow;
+use move_core_types::language_storage::StructTag;
+use move_vm_runtime::move_vm::MoveVM;
+use sui_adapter_latest::adapter::new_move_vm;
+use sui_adapter_latest::type_layout_resolver::TypeLayoutResolver;
+use sui_json_rpc_types::SuiMoveValue;
+use sui_move_natives_latest::all_natives;
+use sui_protocol_config::ProtocolConfig;
+use sui_types::layout_resolver::LayoutResolver;
+use sui_types::object::bounded_visitor::BoundedVisitor;
+use sui_types::object::Object;
+use sui_types::storage::BackingPackageStore;
+use std::sync::OnceLock;
+
+static MOVE_VM: OnceLock<MoveVM> = OnceLock::new();
+
+/// The VM layouts are resolved with, built on first use
+fn move_vm() -> anyhow::Result<&'static MoveVM> {
+    if let Some(vm) = MOVE_VM.get() {
+        return Ok(vm);
+    }
+    let protocol_config = ProtocolConfig::get_for_max_version_UNSAFE();
+    let vm = new_move_vm(all_natives(true, &protocol_config), &protocol_config)?;
+    Ok(MOVE_VM.get_or_init(|| vm))
+}
+
+/// Fields of a Move object as JSON, in the format of the RPC's parsed object content. The
+/// layout of the object's type is resolved from the packages in `store`. `None` for packages.
+pub fn move_object_fields<S: BackingPackageStore>(
+    object: &Object,
+    store: &S,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    let Some(move_object) = object.data.try_as_move() else {
+        return Ok(None);
+    };
+    let mut resolver = TypeLayoutResolver::new(move_vm()?, Box::new(store));
+    let struct_tag: StructTag = move_object.type_().clone().into();
+    let layout = resolver
+        .get_annotated_layout(&struct_tag)
+        .map_err(|e| anyhow!("Failed to resolve the layout of {}: {}", struct_tag, e))?
+        .into_layout();
+    let value = BoundedVisitor::deserialize_value(move_object.contents(), &layout)?;
+    Ok(Some(SuiMoveValue::from(value).to_json_value()))
+}
@@ -13,6 +13,7 @@ use sui_move_natives_latest::all_natives;
 use sui_protocol_config::ProtocolConfig;
 use sui_types::base_types::{SuiAddress, TransactionDigest, TxContext};
 use sui_types::committee::EpochId;
+use sui_types::effects::TransactionEffectsAPI;
 use sui_types::metrics::LimitsMetrics;
 use sui_types::storage::{BackingPackageStore, ObjectStore};
 use sui_types::transaction::{ProgrammableTransaction, CheckedInputObjects, GasData, TransactionKind};
@@ -51,7 +52,7 @@ pub fn test_execute_single_ptb<DB: DatabaseRef + ObjectStore + BackingPackageSto
     db: &mut DB,
     sender: SuiAddress,
 ) -> anyhow::Result<(Vec<sui_types::event::Event>, u64, String)> {
-    println!("      🔧 开始执行 PTB...");
+    tracing::info!("      🔧 开始执行 PTB...");
     
     // 创建 Move VM
     let protocol_config = ProtocolConfig::get_for_max_version_UNSAFE();
@@ -73,7 +74,7 @@ pub fn test_execute_single_ptb<DB: DatabaseRef + ObjectStore + BackingPackageSto
     let epoch_timestamp_ms = now as u64;
     
     // 🔑 关键修复：从 PTB inputs 中构建真正的输入对象列表
-    println!("        📦 构建输入对象列表...");
+    tracing::info!("        📦 构建输入对象列表...");
     let mut input_objects_vec = Vec::new();
 
     let input_objects = ptb.input_objects().unwrap();
@@ -89,7 +90,7 @@ pub fn test_execute_single_ptb<DB: DatabaseRef + ObjectStore + BackingPackageSto
     
     let input_count = input_objects_vec.len();
     let input_objects = CheckedInputObjects::new_for_genesis(input_objects_vec);
-    println!("        ✅ 构建了 {} 个输入对象", input_count);
+    tracing::info!("        ✅ 构建了 {} 个输入对象", input_count);
     
     // 准备 gas 数据（空）
     let gas_data = GasData {
@@ -109,7 +110,7 @@ pub fn test_execute_single_ptb<DB: DatabaseRef + ObjectStore + BackingPackageSto
     let mut trace_builder_opt = None;
     
     // 执行 PTB
-    println!("        🚀 调用 Sui PTB 执行引擎...");
+    tracing::info!("        🚀 调用 Sui PTB 执行引擎...");
     let (_temp_store, _final_gas_status, _effects, _timings, execution_result) = execute_transaction_to_effects::<DevInspect<true>>(
         db,                      // store: &dyn BackingStore
         input_objects,              // input_objects: CheckedInputObjects
@@ -130,25 +131,37 @@ pub fn test_execute_single_ptb<DB: DatabaseRef + ObjectStore + BackingPackageSto
     
     match execution_result {
         Ok(_execution_results) => {
-            println!("execution_result: {:?}", _execution_results.clone());
-            println!("_effects: {:?}", _effects);
-            println!("execution_result: {:?}", _temp_store.written);
+            tracing::debug!("execution_result: {:?}", _execution_results.clone());
+            tracing::debug!("_effects: {:?}", _effects);
+            tracing::debug!("execution_result: {:?}", _temp_store.written);
             _temp_store.written.iter().for_each(|(id, object)| {
-                println!("id: {:?}", id);
-                println!("object: {:?}", object);
+                tracing::debug!("id: {:?}", id);
+                tracing::debug!("object: {:?}", object);
                 // state.objects.insert(id.clone(), object.clone());
             });
 
-            Ok((_temp_store.events.data, epoch_timestamp_ms, tx_digest.to_string()))
+            Ok((_temp_store.events.data, epoch_timestamp_ms, dubhe_common::digest_string(&tx_digest)))
         }
         Err(e) => {
-            println!("          ❌ PTB 执行失败: {}", e);
+            tracing::error!("          ❌ PTB 执行失败: {}", e);
             Err(anyhow::anyhow!("PTB execution failed: {}", e))
         }
 }
 }
 
 
+/// Structured outcome of executing a PTB against the VM
+#[derive(Debug, Clone)]
+pub struct PtbExecutionResult {
+    /// `Dubhe_Store_SetRecord` events emitted by the transaction
+    pub records: Vec<dubhe_common::Event>,
+    /// All `Dubhe_Store_*` events in emission order
+    pub events: Vec<dubhe_common::SequencedEvent>,
+    pub timestamp_ms: u64,
+    pub digest: String,
+    pub gas_used: u64,
+}
+
 /// 执行单个 PTB
 pub fn execute_single_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPackageStore + ChildObjectResolver + ParentSync>(
     ptb: &ProgrammableTransaction,
@@ -156,7 +169,18 @@ pub fn execute_single_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPack
     sender: SuiAddress,
     tx_digest: TransactionDigest,
 ) -> anyhow::Result<(Vec<sui_types::event::Event>, u64, String)> {
-    println!("      🔧 开始执行 PTB...");
+    let (events, timestamp_ms, digest, _gas_used) = run_ptb(ptb, state, sender, tx_digest)?;
+    Ok((events, timestamp_ms, digest))
+}
+
+/// 执行 PTB 并返回原始事件、时间戳、摘要和 gas 消耗
+fn run_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPackageStore + ChildObjectResolver + ParentSync>(
+    ptb: &ProgrammableTransaction,
+    state: &mut DB,
+    sender: SuiAddress,
+    tx_digest: TransactionDigest,
+) -> anyhow::Result<(Vec<sui_types::event::Event>, u64, String, u64)> {
+    tracing::info!("      🔧 开始执行 PTB...");
     
     // 创建 Move VM
     let protocol_config = ProtocolConfig::get_for_max_version_UNSAFE();
@@ -179,7 +203,7 @@ pub fn execute_single_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPack
     let epoch_timestamp_ms = now as u64;
     
     // 🔑 关键修复：从 PTB inputs 中构建真正的输入对象列表
-    println!("        📦 构建输入对象列表...");
+    tracing::info!("        📦 构建输入对象列表...");
     let mut input_objects_vec = Vec::new();
 
     let input_objects = ptb.input_objects().unwrap();
@@ -195,7 +219,7 @@ pub fn execute_single_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPack
     
     let input_count = input_objects_vec.len();
     let input_objects = CheckedInputObjects::new_for_genesis(input_objects_vec);
-    println!("        ✅ 构建了 {} 个输入对象", input_count);
+    tracing::info!("        ✅ 构建了 {} 个输入对象", input_count);
     
     // 准备 gas 数据（空）
     let gas_data = GasData {
@@ -215,7 +239,7 @@ pub fn execute_single_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPack
     let mut trace_builder_opt = None;
     
     // 执行 PTB
-    println!("        🚀 调用 Sui PTB 执行引擎...");
+    tracing::info!("        🚀 调用 Sui PTB 执行引擎...");
     let (_temp_store, _final_gas_status, _effects, _timings, execution_result) = execute_transaction_to_effects::<DevInspect<true>>(
         state,                      // store: &dyn BackingStore
         input_objects,              // input_objects: CheckedInputObjects
@@ -236,43 +260,80 @@ pub fn execute_single_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPack
     
     match execution_result {
         Ok(_execution_results) => {
-            println!("execution_result: {:?}", _execution_results.clone());
-            println!("_effects: {:?}", _effects);
-            println!("execution_result: {:?}", _temp_store.written);
+            tracing::debug!("execution_result: {:?}", _execution_results.clone());
+            tracing::debug!("_effects: {:?}", _effects);
+            tracing::debug!("execution_result: {:?}", _temp_store.written);
             _temp_store.written.iter().for_each(|(_id, object)| {
-                println!("id: {:?}", _id);
-                println!("object: {:?}", object);
+                tracing::debug!("id: {:?}", _id);
+                tracing::debug!("object: {:?}", object);
                 // Now insert_object accepts Object directly
                 let _ = state.insert_object(object.clone());
             });
 
-            Ok((_temp_store.events.data, epoch_timestamp_ms, tx_digest.to_string()))
+            let gas_used = _effects.gas_cost_summary().gas_used();
+            Ok((_temp_store.events.data, epoch_timestamp_ms, dubhe_common::digest_string(&tx_digest), gas_used))
         }
         Err(e) => {
-            println!("          ❌ PTB 执行失败: {}", e);
+            tracing::error!("          ❌ PTB 执行失败: {}", e);
             Err(anyhow::anyhow!("PTB execution failed: {}", e))
         }
 }
 }
 
+/// Execute a PTB and return its `StoreSetRecord` events together with execution metadata
+pub fn execute_ptb<DB: Database + DatabaseRef + ObjectStore + BackingPackageStore + ChildObjectResolver + ParentSync>(
+    ptb: &ProgrammableTransaction,
+    state: &mut DB,
+    sender: SuiAddress,
+    tx_digest: TransactionDigest,
+) -> anyhow::Result<PtbExecutionResult> {
+    let (events, timestamp_ms, digest, gas_used) = run_ptb(ptb, state, sender, tx_digest)?;
+    Ok(PtbExecutionResult {
+        records: store_set_records(&events),
+        events: store_events(&events),
+        timestamp_ms,
+        digest,
+        gas_used,
+    })
+}
+
+/// Only parse the StoreSetRecord events, skipping anything that does not decode
+pub fn store_set_records(events: &[sui_types::event::Event]) -> Vec<dubhe_common::Event> {
+    events
+        .iter()
+        .filter(|event| event.type_.name.to_string() == "Dubhe_Store_SetRecord")
+        .filter_map(|event| bcs::from_bytes::<StoreSetRecord>(event.contents.as_slice()).ok())
+        .map(dubhe_common::Event::StoreSetRecord)
+        .collect()
+}
+
+/// Parse the SetRecord, SetField and DeleteRecord events, tagged with their position among the
+/// transaction's events and sorted by it
+pub fn store_events(events: &[sui_types::event::Event]) -> Vec<dubhe_common::SequencedEvent> {
+    let mut store_events: Vec<_> = events
+        .iter()
+        .enumerate()
+        .filter_map(|(event_seq, event)| {
+            dubhe_common::Event::from_bytes(event.type_.name.as_str(), event.contents.as_slice())
+                .ok()
+                .map(|event| dubhe_common::SequencedEvent {
+                    event_seq: event_seq as u64,
+                    event,
+                })
+        })
+        .collect();
+    dubhe_common::sort_by_event_sequence(&mut store_events);
+    store_events
+}
+
 pub fn execute_single_ptb_with_store_set_record<DB: Database + DatabaseRef + ObjectStore + BackingPackageStore + ChildObjectResolver + ParentSync>(
     ptb: &ProgrammableTransaction,
     state: &mut DB,
     sender: SuiAddress,
     tx_digest: TransactionDigest,
 ) -> anyhow::Result<(Vec<dubhe_common::Event>, u64, String)> {
-    let (events, current_checkpoint_timestamp_ms, current_digest) = execute_single_ptb(ptb, state, sender, tx_digest)?;
-    // Only parse the StoreSetRecord event
-    let mut store_set_records = Vec::new();
-    events.iter().filter(|event| event.type_.name.to_string() == "Dubhe_Store_SetRecord").for_each(|event| {
-        match bcs::from_bytes::<StoreSetRecord>(event.contents.as_slice()) {
-            Ok(record) => {
-                store_set_records.push(dubhe_common::Event::StoreSetRecord(record.clone()));
-            },
-            Err(e) => { }
-        }
-    });
-    Ok((store_set_records, current_checkpoint_timestamp_ms, current_digest))
+    let result = execute_ptb(ptb, state, sender, tx_digest)?;
+    Ok((result.records, result.timestamp_ms, result.digest))
 }
 
 #[cfg(test)]
@@ -304,8 +365,9 @@ mod tests {
             &client, 
             "0x86c8925b708ecd5570d70f3ccbc30035f9fa65480b546a563afdc046da98d103", 
             "0xa337791835d15223727ace33cce17ea0901c094c8cfbe34d089c1a18c2df7a15", 
-            "0x4c3f65fa8562679d00076350b51c1c3f2d966d83a4a6609a13f4fb04561d1140"
-        ).await;
+            "0x4c3f65fa8562679d00076350b51c1c3f2d966d83a4a6609a13f4fb04561d1140",
+            &dubhe_db::CacheInitOptions::default(),
+        ).await.unwrap();
 
         // cache hash map length
         println!("cache hash map length: {:?}", cache_db.cache.read().unwrap().sui_object_data.len());
@@ -366,4 +428,52 @@ mod tests {
          let dynamic_field_object = client.read_api().get_dynamic_field_object(parent_object_id, name).await.unwrap();
          println!("dynamic_field_object: {:?}", dynamic_field_object);
     }
+
+    #[test]
+    fn test_move_position_yields_store_set_records() {
+        let position = StoreSetRecord {
+            dapp_key: "0x4c3f65fa8562679d00076350b51c1c3f2d966d83a4a6609a13f4fb04561d1140::dapp_key::DappKey".to_string(),
+            table_id: "position".to_string(),
+            key_tuple: vec![bcs::to_bytes(&SuiAddress::ZERO).unwrap()],
+            value_tuple: vec![bcs::to_bytes(&1u64).unwrap(), bcs::to_bytes(&2u64).unwrap()],
+        };
+        let event = |name: &str, contents: Vec<u8>| sui_types::event::Event {
+            package_id: ObjectID::from_hex_literal("0xa337791835d15223727ace33cce17ea0901c094c8cfbe34d089c1a18c2df7a15").unwrap(),
+            transaction_module: Identifier::new("map_system").unwrap(),
+            sender: SuiAddress::ZERO,
+            type_: StructTag {
+                address: move_core_types::account_address::AccountAddress::ONE,
+                module: Identifier::new("dubhe_events").unwrap(),
+                name: Identifier::new(name).unwrap(),
+                type_params: vec![],
+            },
+            contents,
+        };
+        // move_position emits the new position plus unrelated events
+        let events = vec![
+            event("Dubhe_Store_SetRecord", bcs::to_bytes(&position).unwrap()),
+            event("Moved", vec![1, 2, 3]),
+            event("Dubhe_Store_SetRecord", vec![0xff]),
+        ];
+
+        assert_eq!(
+            store_set_records(&events),
+            vec![dubhe_common::Event::StoreSetRecord(position.clone())]
+        );
+
+        // A delete keeps its place after the set it follows
+        let delete = dubhe_common::StoreDeleteRecord {
+            dapp_key: position.dapp_key.clone(),
+            table_id: position.table_id.clone(),
+            key_tuple: position.key_tuple.clone(),
+        };
+        let mut events = events;
+        events.push(event("Dubhe_Store_DeleteRecord", bcs::to_bytes(&delete).unwrap()));
+        let sequenced = store_events(&events);
+        assert_eq!(
+            sequenced.iter().map(|event| event.event_seq).collect::<Vec<_>>(),
+            vec![0, 3]
+        );
+        assert_eq!(sequenced[1].event, dubhe_common::Event::StoreDeleteRecord(delete));
+    }
 }